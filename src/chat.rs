@@ -13,6 +13,9 @@ pub enum Chat {
     Translation(TranslationComponent),
     Keybind(KeybindComponent),
     Score(ScoreComponent),
+    Selector(SelectorComponent),
+    // boxed since it's much bigger than the other kinds and rarely sent
+    Nbt(Box<NbtComponent>),
 }
 
 impl Chat {
@@ -24,6 +27,21 @@ impl Chat {
             Translation(body) => &body.base,
             Keybind(body) => &body.base,
             Score(body) => &body.base,
+            Selector(body) => &body.base,
+            Nbt(body) => &body.base,
+        }
+    }
+
+    pub fn base_mut(&mut self) -> &mut BaseComponent {
+        use Chat::*;
+
+        match self {
+            Text(body) => &mut body.base,
+            Translation(body) => &mut body.base,
+            Keybind(body) => &mut body.base,
+            Score(body) => &mut body.base,
+            Selector(body) => &mut body.base,
+            Nbt(body) => &mut body.base,
         }
     }
 
@@ -42,8 +60,176 @@ impl Chat {
         })
     }
 
+    pub fn from_translation(key: &str, with: Vec<Chat>) -> Chat {
+        Chat::Translation(TranslationComponent {
+            translate: key.to_owned(),
+            with: with.into_iter().map(Chat::boxed).collect(),
+            base: BaseComponent::default(),
+        })
+    }
+
     pub fn from_traditional(orig: &str, translate_colorcodes: bool) -> Chat {
-        TraditionalParser::new(orig, translate_colorcodes).parse()
+        TraditionalParser::new(orig, if translate_colorcodes { Some('&') } else { None }).parse()
+    }
+
+    // Splits a string with § formatting codes into components. Like vanilla, a color code clears
+    // the formats before it and §r clears everything. A § which isn't followed by a known code is
+    // kept as text.
+    pub fn from_legacy(orig: &str) -> Chat {
+        TraditionalParser::new(orig, None).parse()
+    }
+
+    // like from_legacy, but also accepts an alternate code character such as '&'
+    pub fn from_legacy_with(orig: &str, alternate: char) -> Chat {
+        TraditionalParser::new(orig, Some(alternate)).parse()
+    }
+
+    // flattens the component tree into § formatting codes, children inherit the style of their
+    // parent. Components are written like to_plain_text does, with each translation argument
+    // keeping its own style.
+    pub fn to_legacy(&self) -> String {
+        self.to_legacy_with(SECTION_SYMBOL)
+    }
+
+    pub fn to_legacy_with(&self, code_char: char) -> String {
+        let mut out = String::new();
+        let mut current = TextStyle::default();
+        self.walk_styled(TextStyle::default(), &mut |style, text| {
+            let style = TextStyle {
+                color: style.color.map(move |color| ChatColor::Named(color.nearest_named())),
+                ..*style
+            };
+            style.write_transition(&mut out, code_char, &mut current);
+            out.push_str(text);
+        });
+        out
+    }
+
+    // writes the tree with ANSI escape codes for terminals, named colors use the 16 standard colors
+    // and rgb colors use 24-bit color. The style is reset at the end. Obfuscated text is written as is.
+    pub fn to_ansi(&self) -> String {
+        let mut out = String::new();
+        let mut current = TextStyle::default();
+        self.walk_styled(TextStyle::default(), &mut |style, text| {
+            let style = TextStyle { obfuscated: false, ..*style };
+            if style != current {
+                if current != TextStyle::default() {
+                    out.push_str(ANSI_RESET);
+                }
+                style.write_ansi(&mut out);
+                current = style;
+            }
+            out.push_str(text);
+        });
+
+        if current != TextStyle::default() {
+            out.push_str(ANSI_RESET);
+        }
+        out
+    }
+
+    // for terminals (or users) which don't want colors, without them this is just to_plain_text
+    pub fn to_ansi_with(&self, colors: bool) -> String {
+        if colors {
+            self.to_ansi()
+        } else {
+            self.to_plain_text()
+        }
+    }
+
+    // calls `f` with each piece of text in the tree and the style it inherits, empty pieces are skipped
+    fn walk_styled(&self, parent: TextStyle, f: &mut dyn FnMut(&TextStyle, &str)) {
+        let base = self.base();
+        let style = TextStyle {
+            color: base.color.or(parent.color),
+            bold: base.bold || parent.bold,
+            italic: base.italic || parent.italic,
+            underlined: base.underlined || parent.underlined,
+            strikethrough: base.strikethrough || parent.strikethrough,
+            obfuscated: base.obfuscated || parent.obfuscated,
+        };
+        let text = move |f: &mut dyn FnMut(&TextStyle, &str), text: &str| if !text.is_empty() {
+            f(&style, text);
+        };
+
+        match self {
+            Chat::Text(body) => text(f, body.text.as_str()),
+            Chat::Translation(body) => {
+                text(f, body.translate.as_str());
+                if !body.with.is_empty() {
+                    text(f, "[");
+                    for (i, arg) in body.with.iter().enumerate() {
+                        if i > 0 {
+                            text(f, ", ");
+                        }
+                        arg.walk_styled(style, f);
+                    }
+                    text(f, "]");
+                }
+            }
+            Chat::Keybind(body) => text(f, body.keybind.as_str()),
+            Chat::Score(body) => text(f, body.score.value.as_deref().unwrap_or("")),
+            Chat::Selector(body) => text(f, body.selector.as_str()),
+            Chat::Nbt(_) => {}
+        }
+
+        for child in &base.extra {
+            child.walk_styled(style, f);
+        }
+    }
+
+    // the text of the whole tree without any formatting. Translations have no language loaded, so
+    // they are written as their key followed by the arguments, like "chat.type.text[Steve, hi]"
+    pub fn to_plain_text(&self) -> String {
+        self.to_plain_text_with(|_| None)
+    }
+
+    // like to_plain_text, but translation keys are looked up with `lookup` first. The format
+    // strings use the vanilla placeholders ("%s", "%1$s" and "%%")
+    pub fn to_plain_text_with<F>(&self, lookup: F) -> String where F: Fn(&str) -> Option<String> {
+        let mut out = String::new();
+        self.write_plain_text(&mut out, &lookup);
+        out
+    }
+
+    fn write_plain_text<F>(&self, out: &mut String, lookup: &F) where F: Fn(&str) -> Option<String> {
+        use Chat::*;
+
+        match self {
+            Text(body) => out.push_str(body.text.as_str()),
+            Translation(body) => {
+                let args: Vec<String> = body.with.iter()
+                    .map(move |arg| {
+                        let mut text = String::new();
+                        arg.write_plain_text(&mut text, lookup);
+                        text
+                    })
+                    .collect();
+                match lookup(body.translate.as_str()) {
+                    Some(format) => write_translation(out, format.as_str(), args.as_slice()),
+                    None => {
+                        out.push_str(body.translate.as_str());
+                        if !args.is_empty() {
+                            out.push('[');
+                            out.push_str(args.join(", ").as_str());
+                            out.push(']');
+                        }
+                    }
+                }
+            }
+            Keybind(body) => out.push_str(body.keybind.as_str()),
+            Score(body) => if let Some(value) = &body.score.value {
+                out.push_str(value.as_str());
+            },
+            // the server replaces these with what they point at, so one which is still here has
+            // nothing to show except the selector itself
+            Selector(body) => out.push_str(body.selector.as_str()),
+            Nbt(_) => {}
+        }
+
+        for child in &self.base().extra {
+            child.write_plain_text(out, lookup);
+        }
     }
 
     pub fn to_traditional(&self) -> Option<String> {
@@ -56,10 +242,113 @@ impl Chat {
     }
 }
 
+// substitutes arguments into a translation format string, placeholders past the end of the
+// arguments are dropped and a '%' which doesn't start a placeholder is kept as is
+fn write_translation(out: &mut String, format: &str, args: &[String]) {
+    let mut next_arg = 0;
+    let mut rest = format;
+    while let Some(at) = rest.find('%') {
+        out.push_str(&rest[..at]);
+        rest = &rest[at + 1..];
+
+        if let Some(after) = rest.strip_prefix('%') {
+            out.push('%');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('s') {
+            if let Some(arg) = args.get(next_arg) {
+                out.push_str(arg.as_str());
+            }
+            next_arg += 1;
+            rest = after;
+        } else {
+            let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+            match (rest[..digits].parse::<usize>(), rest[digits..].strip_prefix("$s")) {
+                (Ok(index), Some(after)) if index > 0 => {
+                    if let Some(arg) = args.get(index - 1) {
+                        out.push_str(arg.as_str());
+                    }
+                    rest = after;
+                }
+                _ => out.push('%'),
+            }
+        }
+    }
+    out.push_str(rest);
+}
+
+const ANSI_RESET: &str = "\x1b[0m";
+
+#[derive(Clone, Copy, Default, PartialEq)]
+struct TextStyle {
+    color: Option<ChatColor>,
+    bold: bool,
+    italic: bool,
+    underlined: bool,
+    strikethrough: bool,
+    obfuscated: bool,
+}
+
+impl TextStyle {
+    // writes the codes which turn the current style into this one
+    fn write_transition(&self, out: &mut String, code_char: char, current: &mut TextStyle) {
+        if self == current {
+            return;
+        }
+
+        let mut push_code = |code: char| {
+            out.push(code_char);
+            out.push(code);
+        };
+
+        // there is no code to turn a single format off, so start over from a color (or reset)
+        let flags = [
+            (self.bold, current.bold, Formatter::Bold),
+            (self.italic, current.italic, Formatter::Italic),
+            (self.strikethrough, current.strikethrough, Formatter::Strikethrough),
+            (self.underlined, current.underlined, Formatter::Underline),
+            (self.obfuscated, current.obfuscated, Formatter::Obfuscated),
+        ];
+        let restart = self.color != current.color || flags.iter().any(move |(want, have, _)| *have && !*want);
+        if restart {
+            push_code(self.color.map(move |color| color.nearest_named().code()).unwrap_or('r'));
+        }
+        for (want, have, formatter) in flags.iter() {
+            if *want && (restart || !*have) {
+                push_code(formatter.code());
+            }
+        }
+
+        *current = *self;
+    }
+
+    fn write_ansi(&self, out: &mut String) {
+        let mut codes = Vec::new();
+        for (set, code) in [(self.bold, "1"), (self.italic, "3"), (self.underlined, "4"), (self.strikethrough, "9")] {
+            if set {
+                codes.push(code.to_owned());
+            }
+        }
+        match self.color {
+            Some(ChatColor::Named(code)) => codes.push(code.ansi_code().to_string()),
+            Some(ChatColor::Rgb(rgb)) => {
+                let rgb = rgb.rgb();
+                codes.push(format!("38;2;{};{};{}", (rgb >> 16) & 0xFF, (rgb >> 8) & 0xFF, rgb & 0xFF))
+            }
+            None => {}
+        }
+
+        if !codes.is_empty() {
+            out.push_str("\x1b[");
+            out.push_str(codes.join(";").as_str());
+            out.push('m');
+        }
+    }
+}
+
 struct TraditionalParser {
     source: Vec<char>,
     at: usize,
-    translate_colorcodes: bool,
+    alternate: Option<char>,
 
     // state
     text: String,
@@ -76,11 +365,11 @@ struct TraditionalParser {
 
 impl TraditionalParser {
 
-    fn new(source: &str, translate_colorcodes: bool) -> Self {
+    fn new(source: &str, alternate: Option<char>) -> Self {
         Self {
             source: source.chars().collect(),
             at: 0,
-            translate_colorcodes,
+            alternate,
 
             text: String::new(),
             color: None,
@@ -109,13 +398,11 @@ impl TraditionalParser {
     fn handle_formatter(&mut self, formatter: Formatter) {
         use Formatter::*;
 
-        if self.has_text() {
-            self.finish_current();
-        }
+        self.finish_current();
 
         match formatter {
             Color(color) => {
-                self.finish_current();
+                self.reset_style();
                 self.color = Some(color);
             }
             Obfuscated => self.obfuscated = true,
@@ -123,7 +410,7 @@ impl TraditionalParser {
             Strikethrough => self.strikethrough = true,
             Underline => self.underlined = true,
             Italic => self.italic = true,
-            _ => {}
+            Reset => self.reset_style(),
         }
     }
 
@@ -136,7 +423,7 @@ impl TraditionalParser {
             let current = TextComponent {
                 text: self.text.clone(),
                 base: BaseComponent {
-                    color: self.color.clone(),
+                    color: self.color.map(ChatColor::Named),
                     bold: self.bold,
                     italic: self.italic,
                     underlined: self.underlined,
@@ -151,8 +438,6 @@ impl TraditionalParser {
             self.text.clear();
             self.done.push(current);
         }
-
-        self.reset_style();
     }
 
     fn reset_style(&mut self) {
@@ -171,7 +456,7 @@ impl TraditionalParser {
     fn is_on_formatter(&self) -> bool {
         self.source.get(self.at).map(move |c| {
             let c = *c;
-            c == SECTION_SYMBOL || (self.translate_colorcodes && c == '&')
+            c == SECTION_SYMBOL || self.alternate == Some(c)
         }).unwrap_or(false)
     }
 
@@ -184,16 +469,12 @@ impl TraditionalParser {
         }
     }
 
+    // leaves the code character to be read as text unless a known code follows it
     fn consume_formatter(&mut self) -> Option<Formatter> {
         if self.is_on_formatter() {
-            self.consume_char()?;
-            let c = self.consume_char()?;
-            let out = Formatter::from_code(&c);
-            if out.is_none() {
-                self.at -= 1;
-            }
-
-            out
+            let out = Formatter::from_code(self.source.get(self.at + 1)?)?;
+            self.at += 2;
+            Some(out)
         } else {
             None
         }
@@ -260,12 +541,14 @@ pub struct BaseComponent {
     #[serde(skip_serializing_if = "should_skip_flag_field")]
     pub obfuscated: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub color: Option<ColorCode>,
+    pub color: Option<ChatColor>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub insertion: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "clickEvent")]
     pub click_event: Option<ChatClickEvent>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "hoverEvent")]
     pub hover_event: Option<ChatHoverEvent>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub extra: Vec<BoxedChat>,
@@ -311,7 +594,7 @@ struct JsonComponentBase {
     pub underlined: Option<bool>,
     pub strikethrough: Option<bool>,
     pub obfuscated: Option<bool>,
-    pub color: Option<ColorCode>,
+    pub color: Option<ChatColor>,
     pub insertion: Option<String>,
     #[serde(rename = "clickEvent")]
     pub click_event: Option<ChatClickEvent>,
@@ -391,7 +674,7 @@ impl TextComponent {
 
         if let Some(c) = b.color {
             buf.push(SECTION_SYMBOL);
-            buf.push(c.code());
+            buf.push(c.nearest_named().code());
         }
 
         let mut apply_formatter = |b: bool, formatter: Formatter| {
@@ -449,6 +732,53 @@ pub struct ScoreComponent {
     pub base: BaseComponent
 }
 
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct SelectorComponent {
+    pub selector: String,
+    // 1.17 and later only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub separator: Option<BoxedChat>,
+
+    #[serde(flatten)]
+    #[serde(skip_deserializing)]
+    pub base: BaseComponent
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct NbtComponent {
+    pub nbt: String,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "should_skip_flag_field")]
+    pub interpret: bool,
+    #[serde(flatten)]
+    pub source: NbtComponentSource,
+    // 1.17 and later only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub separator: Option<BoxedChat>,
+
+    #[serde(flatten)]
+    #[serde(skip_deserializing)]
+    pub base: BaseComponent
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum NbtComponentSource {
+    // the coordinates of a block entity, which may be relative like "~ ~-1 ~"
+    Block(String),
+    Entity(String),
+    Storage(String),
+}
+
+fn read_separator<E: de::Error>(additional: &mut BTreeMap<String, Value>) -> Result<Option<BoxedChat>, E> {
+    match additional.remove("separator") {
+        Some(raw) => Chat::deserialize(raw.into_deserializer())
+            .map(move |separator| Some(separator.boxed()))
+            .map_err(move |err| E::custom(format!("bad component separator :: {:?}", err))),
+        None => Ok(None),
+    }
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 pub struct ScoreComponentObjective {
     pub name: String,
@@ -463,22 +793,31 @@ pub enum ChatClickEvent {
     OpenUrl(String),
     RunCommand(String),
     SuggestCommand(String),
-    ChangePage(i32)
+    ChangePage(i32),
+    CopyToClipboard(String),
+    // the whole event object for an action we don't know, kept so it's written back out unchanged
+    Unknown(Value),
 }
 
 impl Serialize for ChatClickEvent {
     fn serialize<S>(&self, serializer: S) -> Result<<S as Serializer>::Ok, <S as Serializer>::Error> where
         S: Serializer
     {
-        let mut m = serializer.serialize_map(Some(2))?;
-
         use ChatClickEvent::*;
 
+        if let Unknown(raw) = self {
+            return raw.serialize(serializer);
+        }
+
+        let mut m = serializer.serialize_map(Some(2))?;
+
         m.serialize_entry("action", match self {
             OpenUrl(_) => "open_url",
             RunCommand(_) => "run_command",
             SuggestCommand(_) => "suggest_command",
             ChangePage(_) => "change_page",
+            CopyToClipboard(_) => "copy_to_clipboard",
+            Unknown(_) => unreachable!("written above"),
         })?;
 
         m.serialize_key("value")?;
@@ -488,6 +827,8 @@ impl Serialize for ChatClickEvent {
             RunCommand(body) => m.serialize_value(body),
             SuggestCommand(body) => m.serialize_value(body),
             ChangePage(body) => m.serialize_value(body),
+            CopyToClipboard(body) => m.serialize_value(body),
+            Unknown(_) => unreachable!("written above"),
         }?;
 
         m.end()
@@ -510,10 +851,14 @@ impl<'de> Deserialize<'de> for ChatClickEvent {
             fn visit_map<A>(self, mut map: A) -> Result<Self::Value, <A as MapAccess<'de>>::Error> where
                 A: MapAccess<'de>
             {
-                let (action, value) = read_event(&mut map)?;
+                let event = read_event(&mut map)?;
+                if event.has_unknown_keys {
+                    return Ok(ChatClickEvent::Unknown(event.raw));
+                }
 
+                let value = event.body;
                 use ChatClickEvent::*;
-                match action {
+                match event.action.as_str() {
                     "open_url" => match value.as_str() {
                         Some(url) => Ok(OpenUrl(url.to_owned())),
                         None => Err(A::Error::custom(format!("open_url requires string body, got {}", value)))
@@ -526,11 +871,16 @@ impl<'de> Deserialize<'de> for ChatClickEvent {
                         Some(cmd) => Ok(SuggestCommand(cmd.to_owned())),
                         None => Err(A::Error::custom(format!("suggest_command requires string body, got {}", value)))
                     },
-                    "change_page" => match value.as_i64() {
+                    // vanilla writes the page as a string
+                    "change_page" => match value.as_i64().or_else(|| value.as_str().and_then(|v| v.parse().ok())) {
                         Some(v) => Ok(ChangePage(v as i32)),
                         None => Err(A::Error::custom(format!("change_page requires integer body, got {}", value)))
                     },
-                    other => Err(A::Error::custom(format!("invalid click action kind {}", other)))
+                    "copy_to_clipboard" => match value.as_str() {
+                        Some(text) => Ok(CopyToClipboard(text.to_owned())),
+                        None => Err(A::Error::custom(format!("copy_to_clipboard requires string body, got {}", value)))
+                    },
+                    _ => Ok(Unknown(event.raw)),
                 }
             }
         }
@@ -542,30 +892,131 @@ impl<'de> Deserialize<'de> for ChatClickEvent {
 #[derive(Clone, Debug, PartialEq)]
 pub enum ChatHoverEvent {
     ShowText(BoxedChat),
-    ShowItem(Value),
-    ShowEntity(Value)
+    ShowItem(Box<ChatHoverItem>),
+    ShowEntity(Box<ChatHoverEntity>),
+    // the whole event object for an action we don't know (or a show_entity we can't read), kept
+    // so it's written back out unchanged
+    Unknown(Value),
+}
+
+// The body of a show_item hover event, kept as it was sent so it's written back out unchanged
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChatHoverItem {
+    // `value`, before 1.16. Vanilla sends a text component holding the item as SNBT, like
+    // {"text":"{id:\"minecraft:diamond_sword\",Count:1b}"}, but a plain string works too.
+    Value(Value),
+    // `contents`, from 1.16. An object with the item id and, optionally, Count and tag.
+    Contents(Value),
+}
+
+impl ChatHoverItem {
+    // the `value` vanilla would send for the item
+    pub fn from_snbt(snbt: &str) -> Self {
+        let mut wrapper = serde_json::Map::new();
+        wrapper.insert("text".to_owned(), Value::String(snbt.to_owned()));
+        ChatHoverItem::Value(Value::Object(wrapper))
+    }
+
+    // the SNBT held by a `value`, there is none for `contents`
+    pub fn snbt(&self) -> Option<String> {
+        match self {
+            ChatHoverItem::Value(value) => Chat::deserialize(value).ok().map(move |chat| chat.to_plain_text()),
+            ChatHoverItem::Contents(_) => None,
+        }
+    }
+
+    pub fn item_id(&self) -> Option<String> {
+        match self {
+            ChatHoverItem::Value(_) => crate::nbt::parse_snbt(self.snbt()?.as_str()).ok()?.get_str("id").map(move |id| id.into()),
+            ChatHoverItem::Contents(contents) => contents.get("id")?.as_str().map(move |id| id.to_owned()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChatHoverEntity {
+    pub entity_type: String,
+    pub id: String,
+    pub name: Option<BoxedChat>,
+}
+
+impl ChatHoverEntity {
+    // the vanilla value is a compound in SNBT, with the name as a JSON chat component
+    fn from_snbt(raw: &str) -> Option<Self> {
+        let tag = crate::nbt::parse_snbt(raw).ok()?;
+        let name = match tag.get_str("name") {
+            Some(name) => Some(serde_json::from_str::<Chat>(name).ok()?.boxed()),
+            None => None,
+        };
+
+        Some(Self {
+            entity_type: tag.get_str("type")?.into(),
+            id: tag.get_str("id")?.into(),
+            name,
+        })
+    }
+
+    // from 1.16 an object with the type, the uuid as a string and the name as a chat component
+    fn from_contents(contents: &Value) -> Option<Self> {
+        let name = match contents.get("name") {
+            Some(name) => Some(Chat::deserialize(name).ok()?.boxed()),
+            None => None,
+        };
+
+        Some(Self {
+            entity_type: contents.get("type")?.as_str()?.to_owned(),
+            id: contents.get("id")?.as_str()?.to_owned(),
+            name,
+        })
+    }
+
+    fn to_snbt(&self) -> Result<String, serde_json::Error> {
+        use crate::nbt::Tag;
+
+        let mut tag = Tag::Compound(Vec::new());
+        tag.insert("type", Tag::String(self.entity_type.clone()));
+        tag.insert("id", Tag::String(self.id.clone()));
+        if let Some(name) = &self.name {
+            tag.insert("name", Tag::String(serde_json::to_string(name)?));
+        }
+        Ok(tag.to_snbt())
+    }
 }
 
 impl Serialize for ChatHoverEvent {
     fn serialize<S>(&self, serializer: S) -> Result<<S as Serializer>::Ok, <S as Serializer>::Error> where
         S: Serializer
     {
-        let mut m = serializer.serialize_map(Some(2))?;
-
         use ChatHoverEvent::*;
 
+        if let Unknown(raw) = self {
+            return raw.serialize(serializer);
+        }
+
+        let mut m = serializer.serialize_map(Some(2))?;
+
         m.serialize_entry("action", match self {
             ShowText(_) => "show_text",
             ShowItem(_) => "show_item",
             ShowEntity(_) => "show_entity",
+            Unknown(_) => unreachable!("written above"),
         })?;
 
-        m.serialize_key("value")?;
+        m.serialize_key(match self {
+            ShowItem(item) if matches!(**item, ChatHoverItem::Contents(_)) => "contents",
+            _ => "value",
+        })?;
 
         match self {
             ShowText(body) => m.serialize_value(body),
-            ShowItem(body) => m.serialize_value(body),
-            ShowEntity(body) => m.serialize_value(body),
+            ShowItem(item) => match &**item {
+                ChatHoverItem::Value(body) | ChatHoverItem::Contents(body) => m.serialize_value(body),
+            },
+            ShowEntity(body) => m.serialize_value(body.to_snbt()
+                .map_err(move |err| <S::Error as serde::ser::Error>::custom(
+                    format!("error serializing entity name {:?}", err)))?
+                .as_str()),
+            Unknown(_) => unreachable!("written above"),
         }?;
 
         m.end()
@@ -582,25 +1033,49 @@ impl<'de> Deserialize<'de> for ChatHoverEvent {
             type Value = ChatHoverEvent;
 
             fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-                write!(f, "an event object for ChatClickEvent")
+                write!(f, "an event object for ChatHoverEvent")
             }
 
             //noinspection ALL
             fn visit_map<A>(self, mut map: A) -> Result<Self::Value, <A as MapAccess<'de>>::Error> where
                 A: MapAccess<'de>
             {
-                let (action, value) = read_event(&mut map)?;
+                let event = read_event(&mut map)?;
+                if event.has_unknown_keys {
+                    return Ok(ChatHoverEvent::Unknown(event.raw));
+                }
 
+                let value = event.body;
                 use ChatHoverEvent::*;
-                match action {
+                match event.action.as_str() {
                     "show_text" => Ok(ShowText(
                         Chat::deserialize(value.into_deserializer())
                             .map_err(move |err| A::Error::custom(
                                 format!("error deserializing text to show {:?}", err)))?
                             .boxed())),
-                    "show_item" => Ok(ShowItem(value)),
-                    "show_entity" => Ok(ShowEntity(value)),
-                    other => Err(A::Error::custom(format!("invalid hover action kind {}", other)))
+                    "show_item" if event.contents => Ok(match value.get("id").map(Value::is_string) {
+                        Some(true) => ShowItem(Box::new(ChatHoverItem::Contents(value))),
+                        _ => Unknown(event.raw),
+                    }),
+                    "show_item" => {
+                        Chat::deserialize(&value)
+                            .map_err(move |err| A::Error::custom(
+                                format!("error deserializing item to show {:?}", err)))?;
+                        Ok(ShowItem(Box::new(ChatHoverItem::Value(value))))
+                    }
+                    "show_entity" => {
+                        let entity = if event.contents {
+                            ChatHoverEntity::from_contents(&value)
+                        } else {
+                            Chat::deserialize(&value).ok()
+                                .and_then(move |raw| ChatHoverEntity::from_snbt(raw.to_plain_text().as_str()))
+                        };
+                        Ok(match entity {
+                            Some(entity) => ShowEntity(Box::new(entity)),
+                            None => Unknown(event.raw),
+                        })
+                    }
+                    _ => Ok(Unknown(event.raw)),
                 }
             }
         }
@@ -719,6 +1194,58 @@ impl ColorCode {
     }
 }
 
+impl ColorCode {
+    // the SGR foreground code of the matching standard terminal color
+    pub fn ansi_code(&self) -> u8 {
+        match self {
+            ColorCode::Black => 30,
+            ColorCode::DarkBlue => 34,
+            ColorCode::DarkGreen => 32,
+            ColorCode::DarkAqua => 36,
+            ColorCode::DarkRed => 31,
+            ColorCode::DarkPurple => 35,
+            ColorCode::Gold => 33,
+            ColorCode::Gray => 37,
+            ColorCode::DarkGray => 90,
+            ColorCode::Blue => 94,
+            ColorCode::Green => 92,
+            ColorCode::Aqua => 96,
+            ColorCode::Red => 91,
+            ColorCode::LightPurple => 95,
+            ColorCode::Yellow => 93,
+            ColorCode::White => 97,
+        }
+    }
+
+    pub fn to_rgb(&self) -> u32 {
+        match self {
+            ColorCode::Black => 0x000000,
+            ColorCode::DarkBlue => 0x0000AA,
+            ColorCode::DarkGreen => 0x00AA00,
+            ColorCode::DarkAqua => 0x00AAAA,
+            ColorCode::DarkRed => 0xAA0000,
+            ColorCode::DarkPurple => 0xAA00AA,
+            ColorCode::Gold => 0xFFAA00,
+            ColorCode::Gray => 0xAAAAAA,
+            ColorCode::DarkGray => 0x555555,
+            ColorCode::Blue => 0x5555FF,
+            ColorCode::Green => 0x55FF55,
+            ColorCode::Aqua => 0x55FFFF,
+            ColorCode::Red => 0xFF5555,
+            ColorCode::LightPurple => 0xFF55FF,
+            ColorCode::Yellow => 0xFFFF55,
+            ColorCode::White => 0xFFFFFF,
+        }
+    }
+}
+
+const NAMED_COLORS: [ColorCode; 16] = [
+    ColorCode::Black, ColorCode::DarkBlue, ColorCode::DarkGreen, ColorCode::DarkAqua,
+    ColorCode::DarkRed, ColorCode::DarkPurple, ColorCode::Gold, ColorCode::Gray,
+    ColorCode::DarkGray, ColorCode::Blue, ColorCode::Green, ColorCode::Aqua,
+    ColorCode::Red, ColorCode::LightPurple, ColorCode::Yellow, ColorCode::White,
+];
+
 impl fmt::Display for ColorCode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}{}", SECTION_SYMBOL, self.code())
@@ -760,6 +1287,140 @@ impl<'de> Deserialize<'de> for ColorCode {
     }
 }
 
+// The color of a component. 1.16 added "#rrggbb" colors next to the named ones, which are kept in
+// whichever form they were parsed from.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum ChatColor {
+    Named(ColorCode),
+    Rgb(RgbColor),
+}
+
+// A "#rrggbb" color, which keeps the hex digits as they were written so it serializes back to the
+// same string. Colors compare equal by value, whatever the case of their digits.
+#[derive(Debug, Copy, Clone)]
+pub struct RgbColor {
+    hex: [u8; 6],
+}
+
+impl RgbColor {
+    pub fn new(rgb: u32) -> Self {
+        let mut hex = [0u8; 6];
+        for (i, digit) in hex.iter_mut().enumerate() {
+            *digit = b"0123456789ABCDEF"[((rgb >> (20 - 4 * i)) & 0xF) as usize];
+        }
+        Self { hex }
+    }
+
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        if hex.len() != 6 || !hex.chars().all(move |c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        let mut digits = [0u8; 6];
+        digits.copy_from_slice(hex.as_bytes());
+        Some(Self { hex: digits })
+    }
+
+    pub fn rgb(&self) -> u32 {
+        u32::from_str_radix(self.hex(), 16).expect("hex digits are valid")
+    }
+
+    // the six hex digits, without the '#'
+    pub fn hex(&self) -> &str {
+        core::str::from_utf8(&self.hex).expect("hex digits are ascii")
+    }
+}
+
+impl PartialEq for RgbColor {
+    fn eq(&self, other: &Self) -> bool {
+        self.hex.eq_ignore_ascii_case(&other.hex)
+    }
+}
+
+impl Eq for RgbColor {}
+
+impl ChatColor {
+    pub fn rgb(rgb: u32) -> Self {
+        ChatColor::Rgb(RgbColor::new(rgb))
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        if let Some(hex) = name.strip_prefix('#') {
+            return RgbColor::from_hex(hex).map(ChatColor::Rgb);
+        }
+
+        ColorCode::from_name(name).map(ChatColor::Named)
+    }
+
+    pub fn to_rgb(&self) -> u32 {
+        match self {
+            ChatColor::Named(code) => code.to_rgb(),
+            ChatColor::Rgb(rgb) => rgb.rgb(),
+        }
+    }
+
+    // the named color closest to this one, for clients (and § codes) which only know those
+    pub fn nearest_named(&self) -> ColorCode {
+        let rgb = match self {
+            ChatColor::Named(code) => return *code,
+            ChatColor::Rgb(rgb) => rgb.rgb(),
+        };
+
+        let channels = move |rgb: u32| [(rgb >> 16) as i32 & 0xFF, (rgb >> 8) as i32 & 0xFF, rgb as i32 & 0xFF];
+        let target = channels(rgb);
+        *NAMED_COLORS.iter()
+            .min_by_key(move |code| {
+                let named = channels(code.to_rgb());
+                (0..3).map(move |i| (named[i] - target[i]).pow(2)).sum::<i32>()
+            })
+            .expect("there are named colors")
+    }
+}
+
+impl From<ColorCode> for ChatColor {
+    fn from(code: ColorCode) -> Self {
+        ChatColor::Named(code)
+    }
+}
+
+impl Serialize for ChatColor {
+    fn serialize<S>(&self, serializer: S) -> Result<<S as Serializer>::Ok, <S as Serializer>::Error> where
+        S: Serializer {
+        match self {
+            ChatColor::Named(code) => code.serialize(serializer),
+            ChatColor::Rgb(rgb) => serializer.serialize_str(format!("#{}", rgb.hex()).as_str()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ChatColor {
+    fn deserialize<D>(deserializer: D) -> Result<Self, <D as Deserializer<'de>>::Error> where
+        D: Deserializer<'de>
+    {
+        struct V;
+
+        impl<'de> Visitor<'de> for V {
+            type Value = ChatColor;
+
+            fn expecting(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(fmt, "a color name or a #rrggbb color")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> where
+                E: de::Error,
+            {
+                if let Some(color) = ChatColor::from_name(v) {
+                    Ok(color)
+                } else {
+                    Err(E::custom(format!("invalid color {}", v)))
+                }
+            }
+        }
+
+        deserializer.deserialize_str(V)
+    }
+}
+
 #[derive(PartialOrd, PartialEq, Debug, Copy, Clone)]
 pub enum Formatter {
     Color(ColorCode),
@@ -838,7 +1499,7 @@ impl<'de> Deserialize<'de> for Chat {
             type Value = Chat;
 
             fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
-                write!(formatter, "any primitive or a JSON object specifying the component")
+                write!(formatter, "any primitive, a JSON object specifying the component or an array of components")
             }
 
             fn visit_bool<E>(self, value: bool) -> Result<Self::Value, E> where E: de::Error {
@@ -860,6 +1521,16 @@ impl<'de> Deserialize<'de> for Chat {
                 }))
             }
 
+            // like vanilla, the first component of an array gets the rest as extras
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error> where A: de::SeqAccess<'de> {
+                let mut first: Chat = seq.next_element()?
+                    .ok_or_else(move || A::Error::custom("chat component array is empty"))?;
+                while let Some(next) = seq.next_element::<Chat>()? {
+                    first.base_mut().extra.push(next.boxed());
+                }
+                Ok(first)
+            }
+
             fn visit_map<M>(self, map: M) -> Result<Self::Value, M::Error> where M: de::MapAccess<'de> {
                 let mut base: JsonComponentBase = de::Deserialize::deserialize(de::value::MapAccessDeserializer::new(map))?;
                 let additional = &mut base._additional;
@@ -934,7 +1605,48 @@ impl<'de> Deserialize<'de> for Chat {
                     }));
                 }
 
-                // selector (SKIP)
+                // selector
+                if let Some(raw_selector) = additional.remove("selector") {
+                    return if let Some(selector) = raw_selector.as_str() {
+                        Ok(Chat::Selector(SelectorComponent {
+                            selector: selector.to_owned(),
+                            separator: read_separator::<M::Error>(additional)?,
+                            base: base.into(),
+                        }))
+                    } else {
+                        Err(M::Error::custom(format!("have selector but it's not a string! {:?}", raw_selector)))
+                    }
+                }
+
+                // nbt
+                if let Some(raw_nbt) = additional.remove("nbt") {
+                    let nbt = match raw_nbt.as_str() {
+                        Some(nbt) => nbt.to_owned(),
+                        None => return Err(M::Error::custom(format!("have nbt but it's not a string! {:?}", raw_nbt))),
+                    };
+                    let interpret = match additional.remove("interpret") {
+                        Some(raw) => raw.as_bool()
+                            .ok_or_else(move || M::Error::custom(format!("nbt interpret is not a bool! {:?}", raw)))?,
+                        None => false,
+                    };
+                    let source = ["block", "entity", "storage"].iter()
+                        .find_map(|key| Some((*key, additional.remove(*key)?)))
+                        .ok_or_else(move || M::Error::custom("nbt component needs a block, entity or storage"))?;
+                    let source = match source {
+                        ("block", Value::String(pos)) => NbtComponentSource::Block(pos),
+                        ("entity", Value::String(selector)) => NbtComponentSource::Entity(selector),
+                        ("storage", Value::String(id)) => NbtComponentSource::Storage(id),
+                        (key, other) => return Err(M::Error::custom(format!("nbt {} is not a string! {:?}", key, other))),
+                    };
+
+                    return Ok(Chat::Nbt(Box::new(NbtComponent {
+                        nbt,
+                        interpret,
+                        source,
+                        separator: read_separator::<M::Error>(additional)?,
+                        base: base.into(),
+                    })));
+                }
 
                 Err(M::Error::custom("not able to parse chat component, not a valid chat component kind"))
             }
@@ -951,10 +1663,14 @@ impl Serialize for Chat {
         use Chat::*;
 
         match self {
+            // text without any style or extras is written as a plain string
+            Text(body) if body.base == BaseComponent::default() => serializer.serialize_str(body.text.as_str()),
             Text(body) => body.serialize(serializer),
             Translation(body) => body.serialize(serializer),
             Keybind(body) => body.serialize(serializer),
-            Score(body) => body.serialize(serializer)
+            Score(body) => body.serialize(serializer),
+            Selector(body) => body.serialize(serializer),
+            Nbt(body) => body.serialize(serializer),
         }
     }
 }
@@ -963,14 +1679,14 @@ impl super::Serialize for Chat {
     fn mc_serialize<S: super::Serializer>(&self, to: &mut S) -> SerializeResult {
         serde_json::to_string(self)
             .map_err(move |err| super::SerializeErr::FailedJsonEncode(
-                format!("error while encoding chat :: {:?} -> {:?}", self, err)))?
-            .mc_serialize(to)
+                format!("error while encoding chat :: {:?} -> {:?}", self, err)))
+            .and_then(move |raw| crate::types::serialize_string_limited(raw.as_str(), crate::types::CHAT_MAX_LENGTH, to))
     }
 }
 
 impl super::Deserialize for Chat {
     fn mc_deserialize(data: &[u8]) -> DeserializeResult<'_, Self> {
-        String::mc_deserialize(data)?.try_map(move |raw| {
+        crate::types::deserialize_string_limited(data, crate::types::CHAT_MAX_LENGTH)?.try_map(move |raw| {
             serde_json::from_str(raw.as_str()).map_err(move |err|
                 super::DeserializeErr::FailedJsonDeserialize(format!(
                     "failed to deserialize chat from JSON '{}' :: {:?}", raw, err
@@ -979,10 +1695,10 @@ impl super::Deserialize for Chat {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 use super::protocol::TestRandom;
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for Chat {
     fn test_gen_random() -> Self {
         let str = String::test_gen_random();
@@ -990,38 +1706,42 @@ impl TestRandom for Chat {
     }
 }
 
-fn read_event<'de, A>(
-    access: &mut A,
-) -> Result<(&'de str, Value), <A as MapAccess<'de>>::Error>
-    where A: MapAccess<'de>
-{
-    let mut action: Option<&str> = None;
-    let mut value: Option<Value> = None;
-    while action.is_none() || value.is_none() {
-        if let Some(key) = access.next_key()? {
-            match key {
-                "action" => {
-                    action = access.next_value()?;
-                    if action.is_none() {
-                        return Err(A::Error::custom("none for value key=action"));
-                    }
-                },
-                "value" => {
-                    value = access.next_value()?;
-                    if value.is_none() {
-                        return Err(A::Error::custom("none for value key=value"));
-                    }
-                },
-                other => {
-                    return Err(A::Error::custom(format!("unexpected key in event {}", other)));
-                }
-            }
-        } else {
-            return Err(A::Error::custom(format!("event needs action and value")));
-        }
+// An event object. The body is `value`, or `contents` which 1.16 added for hover events. Events we
+// can't represent, like ones with keys we don't know, are kept as the raw object.
+struct RawEvent {
+    action: String,
+    body: Value,
+    contents: bool,
+    has_unknown_keys: bool,
+    raw: Value,
+}
+
+fn read_event<'de, A>(access: &mut A) -> Result<RawEvent, <A as MapAccess<'de>>::Error> where A: MapAccess<'de> {
+    let mut raw = serde_json::Map::new();
+    while let Some(key) = access.next_key::<String>()? {
+        let value: Value = access.next_value()?;
+        raw.insert(key, value);
     }
 
-    Ok((action.expect("checked"), value.expect("checked")))
+    let action = match raw.get("action") {
+        Some(Value::String(action)) => action.clone(),
+        Some(other) => return Err(A::Error::custom(format!("event action must be a string, got {}", other))),
+        None => return Err(A::Error::custom("event needs an action")),
+    };
+
+    let (body, contents) = match (raw.get("contents"), raw.get("value")) {
+        (Some(contents), _) => (contents.clone(), true),
+        (None, Some(value)) => (value.clone(), false),
+        (None, None) => return Err(A::Error::custom("event needs a value or contents")),
+    };
+
+    Ok(RawEvent {
+        action,
+        body,
+        contents,
+        has_unknown_keys: raw.len() > 2,
+        raw: Value::Object(raw),
+    })
 }
 
 #[cfg(test)]
@@ -1029,6 +1749,414 @@ pub mod tests {
 
     use super::*;
 
+    fn raw_event(action: &str, value: Value) -> Value {
+        let mut raw = serde_json::Map::new();
+        raw.insert("action".to_owned(), Value::String(action.to_owned()));
+        raw.insert("value".to_owned(), value);
+        Value::Object(raw)
+    }
+
+    #[test]
+    fn test_from_legacy_keeps_color_for_formats() {
+        let out = Chat::from_legacy("§ahi §lthere");
+        let parts = out.siblings();
+        assert_eq!(out.base().extra.len(), 2);
+        assert_eq!(parts[0], Chat::Text(TextComponent {
+            text: "hi ".to_owned(),
+            base: BaseComponent { color: Some(ColorCode::Green.into()), ..BaseComponent::default() },
+        }).boxed());
+        assert_eq!(parts[1], Chat::Text(TextComponent {
+            text: "there".to_owned(),
+            base: BaseComponent { color: Some(ColorCode::Green.into()), bold: true, ..BaseComponent::default() },
+        }).boxed());
+        assert_eq!(out.to_legacy(), "§ahi §lthere");
+    }
+
+    #[test]
+    fn test_legacy_round_trips() {
+        for legacy in &[
+            "plain text",
+            "§cred §lbold red§r plain",
+            "§4§l§ndark red bold underlined §9blue",
+            "§e§lyellow §oitalic too §r§kmagic",
+            "50% off §x unknown and trailing §",
+            "",
+        ] {
+            assert_eq!(Chat::from_legacy(legacy).to_legacy().as_str(), *legacy);
+        }
+
+        let out = Chat::from_legacy("§l§cred");
+        assert_eq!(out, Chat::Text(TextComponent {
+            text: "red".to_owned(),
+            base: BaseComponent { color: Some(ColorCode::Red.into()), ..BaseComponent::default() },
+        }));
+
+        let alternate = Chat::from_legacy_with("&6gold & §lbold", '&');
+        assert_eq!(alternate.to_legacy(), "§6gold & §lbold");
+        assert_eq!(alternate.to_legacy_with('&'), "&6gold & &lbold");
+        assert_eq!(Chat::from_legacy("&6not a code").to_legacy(), "&6not a code");
+    }
+
+    #[test]
+    fn test_to_legacy_inherits_parent_style() {
+        let mut parent = TextComponent {
+            text: "parent ".to_owned(),
+            base: BaseComponent { color: Some(ColorCode::Aqua.into()), italic: true, ..BaseComponent::default() },
+        };
+        parent.base.extra.push(Chat::Text(TextComponent {
+            text: "child".to_owned(),
+            base: BaseComponent { bold: true, ..BaseComponent::default() },
+        }).boxed());
+        parent.base.extra.push(Chat::from_text(" again").boxed());
+
+        assert_eq!(Chat::Text(parent).to_legacy(), "§b§oparent §lchild§b§o again");
+    }
+
+    #[test]
+    fn test_to_plain_text_nested_extras() {
+        let mut inner = TextComponent {
+            text: "world".to_owned(),
+            base: BaseComponent { bold: true, ..BaseComponent::default() },
+        };
+        inner.base.extra.push(Chat::from_text("!").boxed());
+
+        let mut root = TextComponent {
+            text: "hello ".to_owned(),
+            base: BaseComponent { color: Some(ColorCode::Red.into()), ..BaseComponent::default() },
+        };
+        root.base.extra.push(Chat::Text(inner).boxed());
+        root.base.extra.push(Chat::from_legacy(" §aand §lmore").boxed());
+
+        assert_eq!(Chat::Text(root).to_plain_text(), "hello world! and more");
+    }
+
+    #[test]
+    fn test_to_plain_text_translation() {
+        let message = Chat::Translation(TranslationComponent {
+            translate: "chat.type.text".to_owned(),
+            with: alloc::vec![
+                Chat::from_legacy("§eSteve").boxed(),
+                Chat::Translation(TranslationComponent {
+                    translate: "item.minecraft.diamond".to_owned(),
+                    with: Vec::new(),
+                    base: BaseComponent::default(),
+                }).boxed(),
+            ],
+            base: BaseComponent::default(),
+        });
+
+        assert_eq!(message.to_plain_text(), "chat.type.text[Steve, item.minecraft.diamond]");
+        assert_eq!(message.to_plain_text_with(|key| match key {
+            "chat.type.text" => Some("<%s> %s".to_owned()),
+            "item.minecraft.diamond" => Some("Diamond".to_owned()),
+            _ => None,
+        }), "<Steve> Diamond");
+        assert_eq!(message.to_plain_text_with(|key| if key == "chat.type.text" {
+            Some("%2$s from %1$s, 100%% %s%s%s %q".to_owned())
+        } else {
+            None
+        }),
+                   "item.minecraft.diamond from Steve, 100% Steveitem.minecraft.diamond %q");
+    }
+
+    #[test]
+    fn test_vanilla_death_message() {
+        // sent by a 1.15.2 server when one player kills another
+        let raw = r#"{"translate":"death.attack.player","with":[{"insertion":"Steve","clickEvent":{"action":"suggest_command","value":"/tell Steve "},"hoverEvent":{"action":"show_entity","value":{"text":"{name:\"{\\\"text\\\":\\\"Steve\\\"}\",id:\"8667ba71-b85a-4004-af54-457a9734eed7\",type:\"minecraft:player\"}"}},"text":"Steve"},{"insertion":"Alex","clickEvent":{"action":"suggest_command","value":"/tell Alex "},"hoverEvent":{"action":"show_entity","value":{"text":"{name:\"{\\\"text\\\":\\\"Alex\\\"}\",id:\"ec561538-f3fd-461d-aff5-086b22154bce\",type:\"minecraft:player\"}"}},"text":"Alex"}]}"#;
+        let message: Chat = serde_json::from_str(raw).expect("deserializes");
+
+        let body = match &message {
+            Chat::Translation(body) => body,
+            other => panic!("expected a translation, got {:?}", other),
+        };
+        assert_eq!(body.translate, "death.attack.player");
+        assert_eq!(body.with.len(), 2);
+        let killer = body.with[1].base();
+        assert_eq!(killer.insertion.as_deref(), Some("Alex"));
+        assert_eq!(killer.click_event, Some(ChatClickEvent::SuggestCommand("/tell Alex ".to_owned())));
+        match &killer.hover_event {
+            Some(ChatHoverEvent::ShowEntity(entity)) => {
+                assert_eq!(entity.entity_type, "minecraft:player");
+                assert_eq!(entity.id, "ec561538-f3fd-461d-aff5-086b22154bce");
+            }
+            other => panic!("expected show_entity, got {:?}", other),
+        }
+
+        assert_eq!(message.to_plain_text(), "death.attack.player[Steve, Alex]");
+        assert_eq!(message.to_plain_text_with(|key| match key {
+            "death.attack.player" => Some("%1$s was slain by %2$s".to_owned()),
+            _ => None,
+        }), "Steve was slain by Alex");
+
+        let json = serde_json::to_string(&message).expect("serializes");
+        assert_eq!(serde_json::from_str::<Chat>(json.as_str()).expect("deserializes"), message);
+    }
+
+    #[test]
+    fn test_translation_builder_and_legacy() {
+        let mut player = TextComponent {
+            text: "Steve".to_owned(),
+            base: BaseComponent { color: Some(ColorCode::Yellow.into()), ..BaseComponent::default() },
+        };
+        player.base.extra.push(Chat::from_text("!").boxed());
+        let message = Chat::from_translation("multiplayer.player.joined", alloc::vec![Chat::Text(player)]);
+
+        assert_eq!(message, Chat::Translation(TranslationComponent {
+            translate: "multiplayer.player.joined".to_owned(),
+            with: alloc::vec![Chat::Text(TextComponent {
+                text: "Steve".to_owned(),
+                base: BaseComponent {
+                    color: Some(ColorCode::Yellow.into()),
+                    extra: alloc::vec![Chat::from_text("!").boxed()],
+                    ..BaseComponent::default()
+                },
+            }).boxed()],
+            base: BaseComponent::default(),
+        }));
+        assert_eq!(message.to_legacy(), "multiplayer.player.joined[§eSteve!§r]");
+        assert_eq!(Chat::from_translation("gui.done", Vec::new()).to_legacy(), "gui.done");
+    }
+
+    #[test]
+    fn test_chat_json_shapes() {
+        let bare: Chat = serde_json::from_str(r#""hello""#).expect("deserializes");
+        assert_eq!(bare, Chat::from_text("hello"));
+        assert_eq!(serde_json::to_string(&bare).expect("serializes"), r#""hello""#);
+
+        let object: Chat = serde_json::from_str(r#"{"text":"hello","bold":true}"#).expect("deserializes");
+        assert!(object.base().bold);
+        assert_eq!(serde_json::to_string(&object).expect("serializes"), r#"{"text":"hello","bold":true}"#);
+
+        let array: Chat = serde_json::from_str(r#"[{"text":"a","color":"red"},"b",["c","d"]]"#).expect("deserializes");
+        let mut c = Chat::from_text("c");
+        c.base_mut().extra.push(Chat::from_text("d").boxed());
+        assert_eq!(array, Chat::Text(TextComponent {
+            text: "a".to_owned(),
+            base: BaseComponent {
+                color: Some(ColorCode::Red.into()),
+                extra: alloc::vec![Chat::from_text("b").boxed(), c.boxed()],
+                ..BaseComponent::default()
+            },
+        }));
+        assert_eq!(array.to_plain_text(), "abcd");
+        let json = serde_json::to_string(&array).expect("serializes");
+        assert_eq!(json, r#"{"text":"a","color":"red","extra":["b",{"text":"c","extra":["d"]}]}"#);
+        assert_eq!(serde_json::from_str::<Chat>(json.as_str()).expect("deserializes"), array);
+
+        let nested: Chat = serde_json::from_str(r#"{"text":"","extra":[["x","y"]]}"#).expect("deserializes");
+        assert_eq!(nested.to_plain_text(), "xy");
+        assert!(serde_json::from_str::<Chat>("[]").is_err());
+    }
+
+    #[test]
+    fn test_status_with_bare_string_description() {
+        // returned by a Paper server with a plain motd
+        let raw = r#"{"description":"A Minecraft Server","players":{"max":20,"online":0},"version":{"name":"Paper 1.15.2","protocol":578}}"#;
+        let status: crate::status::StatusSpec = serde_json::from_str(raw).expect("deserializes");
+        assert_eq!(status.description, Chat::from_text("A Minecraft Server"));
+        assert_eq!(status.players.max, 20);
+        assert_eq!(status.version.as_ref().map(|version| version.protocol), Some(578));
+
+        let json = serde_json::to_string(&status).expect("serializes");
+        assert!(json.contains(r#""description":"A Minecraft Server""#));
+    }
+
+    #[test]
+    fn test_to_ansi() {
+        let message = Chat::from_legacy("§c§lHello §9§lWorld");
+        assert_eq!(message.to_ansi(), "\x1b[1;91mHello \x1b[0m\x1b[1;94mWorld\x1b[0m");
+        assert_eq!(message.to_ansi_with(false), "Hello World");
+
+        let mut rgb = TextComponent {
+            text: "purple".to_owned(),
+            base: BaseComponent { color: Some(ChatColor::rgb(0xAA00FF)), italic: true, ..BaseComponent::default() },
+        };
+        rgb.base.extra.push(Chat::Text(TextComponent {
+            text: " struck".to_owned(),
+            base: BaseComponent { strikethrough: true, underlined: true, ..BaseComponent::default() },
+        }).boxed());
+        assert_eq!(Chat::Text(rgb).to_ansi(), "\x1b[3;38;2;170;0;255mpurple\x1b[0m\x1b[3;4;9;38;2;170;0;255m struck\x1b[0m");
+
+        assert_eq!(Chat::from_text("plain").to_ansi(), "plain");
+        assert_eq!(Chat::from_legacy("§kmagic").to_ansi(), "magic");
+    }
+
+    #[test]
+    fn test_other_component_kinds_round_trip() {
+        for raw in &[
+            r#"{"keybind":"key.inventory","color":"gold"}"#,
+            r#"{"score":{"name":"Steve","objective":"kills","value":"12"}}"#,
+            r#"{"score":{"name":"*","objective":"deaths"},"bold":true}"#,
+            r#"{"selector":"@a[distance=..10]"}"#,
+            r#"{"selector":"@e[type=cow]","separator":{"text":" | ","color":"gray"}}"#,
+            r#"{"nbt":"Inventory[0].id","entity":"@p"}"#,
+            r#"{"nbt":"Items","block":"~ ~-1 ~","extra":[" items"]}"#,
+            r#"{"nbt":"data.msg","interpret":true,"storage":"minecraft:motd"}"#,
+        ] {
+            let chat: Chat = serde_json::from_str(raw).expect("deserializes");
+            assert_eq!(&serde_json::to_string(&chat).expect("serializes").as_str(), raw);
+        }
+
+        let parse = |raw: &str| serde_json::from_str::<Chat>(raw).expect("deserializes");
+        assert_eq!(parse(r#"{"keybind":"key.jump"}"#).to_plain_text(), "key.jump");
+        assert_eq!(parse(r#"{"score":{"name":"Steve","objective":"kills","value":"12"}}"#).to_plain_text(), "12");
+        assert_eq!(parse(r#"{"score":{"name":"Steve","objective":"kills"}}"#).to_plain_text(), "");
+        assert_eq!(parse(r#"{"selector":"@p"}"#).to_plain_text(), "@p");
+        let nbt = parse(r#"{"nbt":"Items","block":"1 64 -3","extra":[" items"]}"#);
+        assert_eq!(nbt.to_plain_text(), " items");
+        match nbt {
+            Chat::Nbt(body) => {
+                assert_eq!(body.source, NbtComponentSource::Block("1 64 -3".to_owned()));
+                assert!(!body.interpret);
+            }
+            other => panic!("expected an nbt component, got {:?}", other),
+        }
+
+        // a tab header built from a scoreboard
+        let header = parse(r#"{"text":"","extra":[{"text":"Kills: ","color":"gray"},{"score":{"name":"Steve","objective":"kills","value":"7"},"color":"red"}]}"#);
+        assert_eq!(header.to_plain_text(), "Kills: 7");
+        assert_eq!(header.to_legacy(), "§7Kills: §c7");
+
+        assert!(serde_json::from_str::<Chat>(r#"{"nbt":"Items"}"#).is_err());
+        assert!(serde_json::from_str::<Chat>(r#"{"selector":3}"#).is_err());
+    }
+
+    #[test]
+    fn test_rgb_colors() {
+        let named: Chat = serde_json::from_str(r#"{"text":"red","color":"red"}"#).expect("deserializes");
+        assert_eq!(named.base().color, Some(ChatColor::Named(ColorCode::Red)));
+        assert_eq!(serde_json::to_string(&named).expect("serializes"), r#"{"text":"red","color":"red"}"#);
+
+        let rgb: Chat = serde_json::from_str(r##"{"text":"purple","color":"#aa00ff"}"##).expect("deserializes");
+        assert_eq!(rgb.base().color, Some(ChatColor::rgb(0xAA00FF)));
+        assert_eq!(serde_json::to_string(&rgb).expect("serializes"), r##"{"text":"purple","color":"#aa00ff"}"##);
+
+        let mixed: Chat = serde_json::from_str(r##"{"text":"purple","color":"#aA00Ff"}"##).expect("deserializes");
+        assert_eq!(mixed.base().color, rgb.base().color);
+        assert_eq!(serde_json::to_string(&mixed).expect("serializes"), r##"{"text":"purple","color":"#aA00Ff"}"##);
+
+        let built = Chat::Text(TextComponent {
+            text: "purple".to_owned(),
+            base: BaseComponent { color: Some(ChatColor::rgb(0xAA00FF)), ..BaseComponent::default() },
+        });
+        assert_eq!(serde_json::to_string(&built).expect("serializes"), r##"{"text":"purple","color":"#AA00FF"}"##);
+        assert_eq!(ChatColor::rgb(0x0000AB), ChatColor::from_name("#0000ab").expect("valid color"));
+
+        for bad in &["#12345", "#1234567", "#gg0000", "purple"] {
+            assert_eq!(ChatColor::from_name(bad), None);
+        }
+        assert!(serde_json::from_str::<Chat>(r##"{"text":"x","color":"#12"}"##).is_err());
+
+        assert_eq!(ColorCode::Gold.to_rgb(), 0xFFAA00);
+        assert_eq!(ChatColor::Named(ColorCode::Gold).to_rgb(), 0xFFAA00);
+        assert_eq!(ChatColor::rgb(0xFFAA00).nearest_named(), ColorCode::Gold);
+        assert_eq!(ChatColor::rgb(0xAA00FF).nearest_named(), ColorCode::DarkPurple);
+        assert_eq!(ChatColor::rgb(0x101010).nearest_named(), ColorCode::Black);
+        assert_eq!(ChatColor::rgb(0xF0F0F0).nearest_named(), ColorCode::White);
+        assert_eq!(ChatColor::Named(ColorCode::Blue).nearest_named(), ColorCode::Blue);
+        assert_eq!(rgb.to_legacy(), "§5purple");
+    }
+
+    #[test]
+    fn test_chat_events_round_trip() {
+        let mut body = TextComponent {
+            text: "click me".to_owned(),
+            base: BaseComponent::default(),
+        };
+        body.base.click_event = Some(ChatClickEvent::OpenUrl("https://example.com/".to_owned()));
+        body.base.hover_event = Some(ChatHoverEvent::ShowText(Chat::from_legacy("§aopens a link").boxed()));
+        let message = Chat::Text(body);
+
+        let json = serde_json::to_string(&message).expect("serializes");
+        assert!(json.contains(r#""clickEvent":{"action":"open_url","value":"https://example.com/"}"#));
+        assert!(json.contains(r#""hoverEvent":{"action":"show_text""#));
+        assert_eq!(serde_json::from_str::<Chat>(json.as_str()).expect("deserializes"), message);
+
+        for event in &[
+            ChatClickEvent::CopyToClipboard("copied".to_owned()),
+            ChatClickEvent::ChangePage(3),
+            ChatClickEvent::Unknown(raw_event("open_file", Value::from("screenshots/a.png"))),
+        ] {
+            let json = serde_json::to_string(event).expect("serializes");
+            assert_eq!(&serde_json::from_str::<ChatClickEvent>(json.as_str()).expect("deserializes"), event);
+        }
+        assert_eq!(serde_json::from_str::<ChatClickEvent>(r#"{"action":"change_page","value":"7"}"#).expect("deserializes"),
+                   ChatClickEvent::ChangePage(7));
+    }
+
+    #[test]
+    fn test_hover_event_item_and_entity() {
+        let item: ChatHoverEvent = serde_json::from_str(
+            r#"{"action":"show_item","value":{"text":"{id:\"minecraft:diamond\",Count:1b}"}}"#).expect("deserializes");
+        assert_eq!(item, ChatHoverEvent::ShowItem(Box::new(ChatHoverItem::from_snbt("{id:\"minecraft:diamond\",Count:1b}"))));
+        let json = serde_json::to_string(&item).expect("serializes");
+        assert_eq!(json, r#"{"action":"show_item","value":{"text":"{id:\"minecraft:diamond\",Count:1b}"}}"#);
+        match &item {
+            ChatHoverEvent::ShowItem(shown) => {
+                assert_eq!(shown.snbt().as_deref(), Some("{id:\"minecraft:diamond\",Count:1b}"));
+                assert_eq!(shown.item_id().as_deref(), Some("minecraft:diamond"));
+            }
+            other => panic!("expected show_item, got {:?}", other),
+        }
+
+        let plain_json = r#"{"action":"show_item","value":"{id:\"minecraft:stone\"}"}"#;
+        let plain: ChatHoverEvent = serde_json::from_str(plain_json).expect("deserializes");
+        assert_eq!(serde_json::to_string(&plain).expect("serializes"), plain_json);
+
+        let contents_json = r#"{"action":"show_item","contents":{"Count":3,"id":"minecraft:apple"}}"#;
+        let contents: ChatHoverEvent = serde_json::from_str(contents_json).expect("deserializes");
+        match &contents {
+            ChatHoverEvent::ShowItem(shown) => {
+                assert_eq!(shown.item_id().as_deref(), Some("minecraft:apple"));
+                assert_eq!(shown.snbt(), None);
+            }
+            other => panic!("expected show_item, got {:?}", other),
+        }
+        assert_eq!(serde_json::to_string(&contents).expect("serializes"), contents_json);
+
+        let text: ChatHoverEvent = serde_json::from_str(r#"{"action":"show_text","contents":"hi"}"#).expect("deserializes");
+        assert_eq!(text, ChatHoverEvent::ShowText(Chat::from_text("hi").boxed()));
+
+        let contents_entity: ChatHoverEvent = serde_json::from_str(
+            r#"{"action":"show_entity","contents":{"type":"minecraft:pig","id":"0b4d0e39-4c94-4ba4-9ec6-0abe5ba5df8d","name":{"text":"Bob"}}}"#,
+        ).expect("deserializes");
+        assert_eq!(contents_entity, ChatHoverEvent::ShowEntity(Box::new(ChatHoverEntity {
+            entity_type: "minecraft:pig".to_owned(),
+            id: "0b4d0e39-4c94-4ba4-9ec6-0abe5ba5df8d".to_owned(),
+            name: Some(Chat::from_text("Bob").boxed()),
+        })));
+
+        // keys we don't know are kept by keeping the whole event
+        let extra_json = r#"{"action":"show_text","extra":1,"value":"hi"}"#;
+        let extra: ChatHoverEvent = serde_json::from_str(extra_json).expect("deserializes");
+        assert!(matches!(extra, ChatHoverEvent::Unknown(_)));
+        assert_eq!(serde_json::to_string(&extra).expect("serializes"), extra_json);
+        let click_json = r#"{"action":"open_url","source":"sign","value":"https://example.com/"}"#;
+        let click: ChatClickEvent = serde_json::from_str(click_json).expect("deserializes");
+        assert_eq!(serde_json::to_string(&click).expect("serializes"), click_json);
+
+        assert!(serde_json::from_str::<ChatHoverEvent>(r#"{"action":"show_text"}"#).is_err());
+        assert!(serde_json::from_str::<ChatHoverEvent>(r#"{"value":"hi"}"#).is_err());
+
+        let entity: ChatHoverEvent = serde_json::from_str(
+            r#"{"action":"show_entity","value":{"text":"{name:\"{\\\"text\\\":\\\"Bob\\\"}\",id:\"0b4d0e39-4c94-4ba4-9ec6-0abe5ba5df8d\",type:\"minecraft:pig\"}"}}"#,
+        ).expect("deserializes");
+        assert_eq!(entity, ChatHoverEvent::ShowEntity(Box::new(ChatHoverEntity {
+            entity_type: "minecraft:pig".to_owned(),
+            id: "0b4d0e39-4c94-4ba4-9ec6-0abe5ba5df8d".to_owned(),
+            name: Some(Chat::from_text("Bob").boxed()),
+        })));
+        let json = serde_json::to_string(&entity).expect("serializes");
+        assert_eq!(serde_json::from_str::<ChatHoverEvent>(json.as_str()).expect("deserializes"), entity);
+
+        let unknown_json = r#"{"action":"show_achievement","value":"achievement.openInventory"}"#;
+        let unknown: ChatHoverEvent = serde_json::from_str(unknown_json).expect("deserializes");
+        assert_eq!(serde_json::to_string(&unknown).expect("serializes"), unknown_json);
+
+        let broken: ChatHoverEvent = serde_json::from_str(r#"{"action":"show_entity","value":"{type:"}"#).expect("deserializes");
+        assert_eq!(broken, ChatHoverEvent::Unknown(raw_event("show_entity", Value::from("{type:"))));
+    }
+
     #[test]
     fn test_from_traditional_simple() {
         let out = Chat::from_traditional("&cthis &cis red, and &rthis is &e&lyellow", true);
@@ -1041,7 +2169,7 @@ pub mod tests {
                         text: "this is red, and ".to_owned(),
                         base: {
                             let mut b = BaseComponent::default();
-                            b.color = Some(ColorCode::Red);
+                            b.color = Some(ColorCode::Red.into());
                             b
                         },
                     }).boxed(),
@@ -1053,7 +2181,7 @@ pub mod tests {
                         text: "yellow".to_owned(),
                         base: {
                             let mut b = BaseComponent::default();
-                            b.color = Some(ColorCode::Yellow);
+                            b.color = Some(ColorCode::Yellow.into());
                             b.bold = true;
                             b
                         }
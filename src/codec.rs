@@ -0,0 +1,459 @@
+use crate::{Deserialize, DeserializeErr, Deserialized, Serialize, SerializeErr};
+use crate::connection::{ConnectionErr, DEFAULT_MAX_FRAME_SIZE};
+use crate::protocol::{Id, PacketDirection, PacketErr, RawPacket, State};
+use crate::types::{mc_serialize_byte_len, VarInt};
+use alloc::fmt;
+use bytes::{Buf, Bytes, BytesMut};
+
+pub type FramedMinecraftConn<S> = tokio_util::codec::Framed<S, MinecraftFrameCodec>;
+
+// one decoded frame: the packet id and the packet data which follows it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub id: i32,
+    pub body: Bytes,
+}
+
+impl Frame {
+    // the id on the wire doesn't say which state or direction it belongs to, the caller tracks that
+    pub fn raw_packet<'a, P>(&'a self, state: State, direction: PacketDirection) -> Result<P, PacketErr>
+        where P: RawPacket<'a>
+    {
+        P::create(Id { id: self.id, state, direction }, self.body.as_ref())
+    }
+
+    pub fn packet<'a, P>(&'a self, state: State, direction: PacketDirection) -> Result<P::Packet, PacketErr>
+        where P: RawPacket<'a>
+    {
+        self.raw_packet::<P>(state, direction)?.deserialize()
+    }
+}
+
+pub enum CodecErr {
+    Io(std::io::Error),
+    Connection(ConnectionErr),
+    SerializeFailed(SerializeErr),
+    BadPacketId(DeserializeErr),
+    BadDataLength(DeserializeErr),
+    UncompressedTooLarge { size: usize, max: usize },
+    UncompressedBelowThreshold { size: usize, threshold: usize },
+    UncompressedLengthMismatch { declared: usize, actual: usize },
+}
+
+impl fmt::Display for CodecErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use CodecErr::*;
+        match self {
+            Io(err) => f.write_fmt(format_args!("io error: {}", err)),
+            Connection(err) => f.write_fmt(format_args!("{}", err)),
+            SerializeFailed(err) => f.write_fmt(format_args!("failed to serialize frame: {}", err)),
+            BadPacketId(err) => f.write_fmt(format_args!("bad packet id: {}", err)),
+            BadDataLength(err) => f.write_fmt(format_args!("bad uncompressed data length: {}", err)),
+            UncompressedTooLarge { size, max } => f.write_fmt(format_args!(
+                "packet of {} bytes uncompressed exceeds the limit of {} bytes", size, max
+            )),
+            UncompressedBelowThreshold { size, threshold } => f.write_fmt(format_args!(
+                "compressed packet of {} bytes is below the compression threshold of {} bytes", size, threshold
+            )),
+            UncompressedLengthMismatch { declared, actual } => f.write_fmt(format_args!(
+                "packet declared {} bytes uncompressed but inflated to {} bytes", declared, actual
+            )),
+        }
+    }
+}
+
+impl fmt::Debug for CodecErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        <dyn fmt::Display>::fmt(self, f)
+    }
+}
+
+impl std::error::Error for CodecErr {}
+
+impl From<std::io::Error> for CodecErr {
+    fn from(err: std::io::Error) -> Self {
+        CodecErr::Io(err)
+    }
+}
+
+impl From<ConnectionErr> for CodecErr {
+    fn from(err: ConnectionErr) -> Self {
+        CodecErr::Connection(err)
+    }
+}
+
+impl From<SerializeErr> for CodecErr {
+    fn from(err: SerializeErr) -> Self {
+        CodecErr::SerializeFailed(err)
+    }
+}
+
+// Length prefixed framing for tokio. Writes raw packets as VarInt(length) + VarInt(id) + data, and
+// reads frames back without copying their body out of the read buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MinecraftFrameCodec {
+    max_frame_size: usize,
+}
+
+impl Default for MinecraftFrameCodec {
+    fn default() -> Self {
+        Self::with_max_frame_size(DEFAULT_MAX_FRAME_SIZE)
+    }
+}
+
+impl MinecraftFrameCodec {
+    pub fn with_max_frame_size(max_frame_size: usize) -> Self {
+        Self { max_frame_size }
+    }
+
+    pub fn max_frame_size(&self) -> usize {
+        self.max_frame_size
+    }
+
+    // frames a packet id and the packet data which follows it
+    pub fn encode_parts(&self, id: i32, data: &[u8], dst: &mut BytesMut) -> Result<(), CodecErr> {
+        let id = VarInt(id);
+        let size = mc_serialize_byte_len(&id)? + data.len();
+        write_frame_header(size, self.max_frame_size, dst)?;
+        id.mc_serialize(dst)?;
+        dst.extend_from_slice(data);
+        Ok(())
+    }
+}
+
+impl<'a, P> tokio_util::codec::Encoder<P> for MinecraftFrameCodec where P: RawPacket<'a> {
+    type Error = CodecErr;
+
+    fn encode(&mut self, packet: P, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.encode_parts(packet.id().id, packet.data(), dst)
+    }
+}
+
+impl tokio_util::codec::Decoder for MinecraftFrameCodec {
+    type Item = Frame;
+    type Error = CodecErr;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match split_frame(src, self.max_frame_size)? {
+            Some(frame) => split_packet_id(frame).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+fn write_frame_header(size: usize, max_frame_size: usize, dst: &mut BytesMut) -> Result<(), CodecErr> {
+    if size > max_frame_size {
+        return Err(ConnectionErr::FrameTooLarge { size, max: max_frame_size }.into());
+    }
+
+    dst.reserve(5 + size);
+    VarInt(size as i32).mc_serialize(dst)?;
+    Ok(())
+}
+
+// takes one complete frame (without its length prefix) off the front of the buffer
+fn split_frame(src: &mut BytesMut, max_frame_size: usize) -> Result<Option<Bytes>, CodecErr> {
+    let (length, header_size) = match VarInt::mc_deserialize(src.as_ref()) {
+        Ok(Deserialized { value, data }) => (value, src.len() - data.len()),
+        Err(DeserializeErr::Eof) => return Ok(None),
+        Err(err) => return Err(ConnectionErr::BadFrameHeader(err).into()),
+    };
+
+    if length.0 < 0 {
+        return Err(ConnectionErr::NegativeFrameLength(length).into());
+    }
+
+    let size = length.0 as usize;
+    if size > max_frame_size {
+        return Err(ConnectionErr::FrameTooLarge { size, max: max_frame_size }.into());
+    }
+
+    let frame_end = header_size + size;
+    if src.len() < frame_end {
+        src.reserve(frame_end - src.len());
+        return Ok(None);
+    }
+
+    src.advance(header_size);
+    Ok(Some(src.split_to(size).freeze()))
+}
+
+fn split_packet_id(packet: Bytes) -> Result<Frame, CodecErr> {
+    let (id, id_size) = match VarInt::mc_deserialize(packet.as_ref()) {
+        Ok(Deserialized { value, data }) => (value, packet.len() - data.len()),
+        Err(err) => return Err(CodecErr::BadPacketId(err)),
+    };
+
+    Ok(Frame {
+        id: id.0,
+        body: packet.slice(id_size..),
+    })
+}
+
+// vanilla refuses to inflate packets larger than this
+#[cfg(feature = "compression")]
+pub const DEFAULT_MAX_UNCOMPRESSED_SIZE: usize = 1 << 23;
+
+// The framing used after LoginSetCompression. Every frame carries the uncompressed length of the
+// packet after its own length, packets at least `threshold` bytes long are zlib compressed and
+// shorter ones are sent as is with an uncompressed length of 0. A negative threshold turns
+// compression off, which makes the frames identical to MinecraftFrameCodec's.
+#[cfg(feature = "compression")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CompressedPacketCodec {
+    threshold: i32,
+    max_frame_size: usize,
+    max_uncompressed_size: usize,
+}
+
+#[cfg(feature = "compression")]
+impl CompressedPacketCodec {
+    pub fn new(threshold: i32) -> Self {
+        Self {
+            threshold,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            max_uncompressed_size: DEFAULT_MAX_UNCOMPRESSED_SIZE,
+        }
+    }
+
+    pub fn with_limits(threshold: i32, max_frame_size: usize, max_uncompressed_size: usize) -> Self {
+        Self { threshold, max_frame_size, max_uncompressed_size }
+    }
+
+    pub fn threshold(&self) -> i32 {
+        self.threshold
+    }
+
+    pub fn set_threshold(&mut self, threshold: i32) {
+        self.threshold = threshold;
+    }
+
+    fn compression_threshold(&self) -> Option<usize> {
+        if self.threshold < 0 {
+            None
+        } else {
+            Some(self.threshold as usize)
+        }
+    }
+}
+
+#[cfg(feature = "compression")]
+impl<'a, P> tokio_util::codec::Encoder<P> for CompressedPacketCodec where P: RawPacket<'a> {
+    type Error = CodecErr;
+
+    fn encode(&mut self, packet: P, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.encode_parts(packet.id().id, packet.data(), dst)
+    }
+}
+
+#[cfg(feature = "compression")]
+impl CompressedPacketCodec {
+    // frames (and compresses, when over the threshold) a packet id and the packet data which follows it
+    pub fn encode_parts(&self, id: i32, data: &[u8], dst: &mut BytesMut) -> Result<(), CodecErr> {
+        use std::io::Write;
+
+        let threshold = match self.compression_threshold() {
+            Some(threshold) => threshold,
+            None => return MinecraftFrameCodec::with_max_frame_size(self.max_frame_size).encode_parts(id, data, dst),
+        };
+
+        let id = VarInt(id);
+        let uncompressed_size = mc_serialize_byte_len(&id)? + data.len();
+
+        if uncompressed_size > self.max_uncompressed_size {
+            return Err(CodecErr::UncompressedTooLarge { size: uncompressed_size, max: self.max_uncompressed_size });
+        }
+
+        if uncompressed_size < threshold {
+            write_frame_header(1 + uncompressed_size, self.max_frame_size, dst)?;
+            VarInt(0).mc_serialize(dst)?;
+            id.mc_serialize(dst)?;
+            dst.extend_from_slice(data);
+            return Ok(());
+        }
+
+        let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(id.mc_serialize_to_vec()?.as_slice())?;
+        encoder.write_all(data)?;
+        let compressed = encoder.finish()?;
+
+        let data_length = VarInt(uncompressed_size as i32);
+        write_frame_header(mc_serialize_byte_len(&data_length)? + compressed.len(), self.max_frame_size, dst)?;
+        data_length.mc_serialize(dst)?;
+        dst.extend_from_slice(compressed.as_slice());
+        Ok(())
+    }
+}
+
+#[cfg(feature = "compression")]
+impl tokio_util::codec::Decoder for CompressedPacketCodec {
+    type Item = Frame;
+    type Error = CodecErr;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        use std::io::Read;
+
+        let frame = match split_frame(src, self.max_frame_size)? {
+            Some(frame) => frame,
+            None => return Ok(None),
+        };
+
+        let threshold = match self.compression_threshold() {
+            Some(threshold) => threshold,
+            None => return split_packet_id(frame).map(Some),
+        };
+
+        let (data_length, header_size) = match VarInt::mc_deserialize(frame.as_ref()) {
+            Ok(Deserialized { value, data }) => (value, frame.len() - data.len()),
+            Err(err) => return Err(CodecErr::BadDataLength(err)),
+        };
+
+        if data_length.0 < 0 {
+            return Err(CodecErr::BadDataLength(DeserializeErr::NegativeLength(data_length)));
+        }
+
+        let declared = data_length.0 as usize;
+        if declared == 0 {
+            return split_packet_id(frame.slice(header_size..)).map(Some);
+        }
+
+        if declared > self.max_uncompressed_size {
+            return Err(CodecErr::UncompressedTooLarge { size: declared, max: self.max_uncompressed_size });
+        }
+
+        if declared < threshold {
+            return Err(CodecErr::UncompressedBelowThreshold { size: declared, threshold });
+        }
+
+        // read at most one byte more than declared, which is enough to see that the length is wrong
+        let mut inflated = Vec::with_capacity(declared);
+        flate2::read::ZlibDecoder::new(&frame[header_size..])
+            .take(declared as u64 + 1)
+            .read_to_end(&mut inflated)?;
+        if inflated.len() != declared {
+            return Err(CodecErr::UncompressedLengthMismatch { declared, actual: inflated.len() });
+        }
+
+        split_packet_id(inflated.into()).map(Some)
+    }
+}
+
+#[cfg(all(test, feature = "v1_15_2"))]
+mod tests {
+    use super::*;
+    use crate::v1_15_2::*;
+    use tokio_util::codec::{Decoder, Encoder};
+
+    #[test]
+    fn test_frame_round_trip() {
+        let mut codec = MinecraftFrameCodec::default();
+        let mut buffer = BytesMut::new();
+
+        let keep_alive = PlayServerKeepAliveSpec { id: 0x1122334455667788 }.mc_serialize_to_vec().expect("serializes");
+        let raw = RawPacket578::create(Id { id: 0x21, state: State::Play, direction: PacketDirection::ClientBound }, keep_alive.as_slice())
+            .expect("valid id");
+        codec.encode(raw, &mut buffer).expect("encodes");
+        assert_eq!(&buffer[..2], &[9, 0x21]);
+
+        // feed the frame one byte at a time
+        let encoded = buffer.split();
+        let mut frame = None;
+        for byte in encoded.iter() {
+            assert!(frame.is_none());
+            buffer.extend_from_slice(&[*byte]);
+            frame = codec.decode(&mut buffer).expect("decodes");
+        }
+
+        let frame = frame.expect("frame is complete");
+        assert!(buffer.is_empty());
+        assert_eq!(frame.id, 0x21);
+        assert_eq!(frame.body.as_ref(), keep_alive.as_slice());
+        let raw: RawPacket578 = frame.raw_packet(State::Play, PacketDirection::ClientBound).expect("valid id");
+        assert_eq!(raw.deserialize().expect("deserializes"), Packet578::PlayServerKeepAlive(PlayServerKeepAliveSpec {
+            id: 0x1122334455667788,
+        }));
+    }
+
+    #[test]
+    fn test_frame_limits() {
+        let mut codec = MinecraftFrameCodec::with_max_frame_size(16);
+        let mut buffer = BytesMut::new();
+        VarInt(17).mc_serialize(&mut buffer).expect("serializes");
+        assert!(matches!(
+            codec.decode(&mut buffer),
+            Err(CodecErr::Connection(ConnectionErr::FrameTooLarge { size: 17, max: 16 }))
+        ));
+
+        let mut buffer = BytesMut::from(&[0xFFu8, 0xFF, 0xFF, 0xFF, 0x0F][..]);
+        assert!(matches!(codec.decode(&mut buffer), Err(CodecErr::Connection(ConnectionErr::NegativeFrameLength(_)))));
+
+        let data = [0u8; 16];
+        let raw = RawPacket578::create(Id { id: 0x21, state: State::Play, direction: PacketDirection::ClientBound }, &data)
+            .expect("valid id");
+        let mut out = BytesMut::new();
+        assert!(matches!(codec.encode(raw, &mut out), Err(CodecErr::Connection(ConnectionErr::FrameTooLarge { size: 17, max: 16 }))));
+        assert!(out.is_empty());
+    }
+
+    #[cfg(feature = "compression")]
+    fn raw_keep_alive(data: &[u8]) -> RawPacket578<'_> {
+        RawPacket578::create(Id { id: 0x21, state: State::Play, direction: PacketDirection::ClientBound }, data)
+            .expect("valid id")
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_compressed_round_trip() {
+        let mut codec = CompressedPacketCodec::new(256);
+        for (body_size, compressed) in [(100, false), (255, true), (256, true), (1024, true)] {
+            let data: Vec<u8> = (0..body_size).map(|i| (i % 7) as u8).collect();
+            let mut buffer = BytesMut::new();
+            codec.encode(raw_keep_alive(data.as_slice()), &mut buffer).expect("encodes");
+
+            let Deserialized { value: frame_length, data: rest } = VarInt::mc_deserialize(buffer.as_ref()).expect("has length");
+            assert_eq!(frame_length.0 as usize, rest.len());
+            let data_length = VarInt::mc_deserialize(rest).expect("has data length").value;
+            if compressed {
+                assert_eq!(data_length.0, body_size + 1, "body of {} bytes", body_size);
+                assert!(rest.len() < body_size as usize);
+            } else {
+                assert_eq!(data_length.0, 0, "body of {} bytes", body_size);
+                assert_eq!(&rest[1..], [&[0x21u8][..], data.as_slice()].concat().as_slice());
+            }
+
+            let frame = codec.decode(&mut buffer).expect("decodes").expect("frame is complete");
+            assert!(buffer.is_empty());
+            assert_eq!(frame.id, 0x21);
+            assert_eq!(frame.body.as_ref(), data.as_slice());
+        }
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_compression_threshold_switch() {
+        let data = [7u8; 300];
+        let mut codec = CompressedPacketCodec::new(-1);
+        let mut plain = BytesMut::new();
+        let mut compressed = BytesMut::new();
+        codec.encode(raw_keep_alive(&data), &mut compressed).expect("encodes");
+        MinecraftFrameCodec::default().encode(raw_keep_alive(&data), &mut plain).expect("encodes");
+        assert_eq!(compressed, plain);
+
+        // LoginSetCompression arrives
+        codec.set_threshold(64);
+        codec.encode(raw_keep_alive(&data), &mut compressed).expect("encodes");
+        let first = MinecraftFrameCodec::default().decode(&mut compressed).expect("decodes").expect("complete");
+        assert_eq!(first.body.as_ref(), &data[..]);
+        let second = codec.decode(&mut compressed).expect("decodes").expect("complete");
+        assert_eq!(second.body.as_ref(), &data[..]);
+
+        // a frame claiming to be compressed but shorter than the threshold
+        let mut lenient = CompressedPacketCodec::new(0);
+        let mut small = BytesMut::new();
+        lenient.encode(raw_keep_alive(&data[..8]), &mut small).expect("encodes");
+        assert!(matches!(
+            codec.decode(&mut small),
+            Err(CodecErr::UncompressedBelowThreshold { size: 9, threshold: 64 })
+        ));
+    }
+}
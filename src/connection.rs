@@ -0,0 +1,491 @@
+use crate::{Deserialize, DeserializeErr, Deserialized};
+use crate::types::VarInt;
+use alloc::{vec::Vec, fmt};
+
+// the vanilla server refuses frames whose length doesn't fit in a 3 byte VarInt
+pub const DEFAULT_MAX_FRAME_SIZE: usize = (1 << 21) - 1;
+pub const DEFAULT_MAX_PACKETS_PER_READ: usize = 128;
+
+const MAX_FRAME_HEADER_SIZE: usize = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConnectionLimits {
+    pub max_frame_size: usize,
+    pub max_buffered_bytes: usize,
+    pub max_packets_per_read: usize,
+}
+
+impl Default for ConnectionLimits {
+    fn default() -> Self {
+        Self {
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            max_buffered_bytes: DEFAULT_MAX_FRAME_SIZE + MAX_FRAME_HEADER_SIZE,
+            max_packets_per_read: DEFAULT_MAX_PACKETS_PER_READ,
+        }
+    }
+}
+
+pub enum ConnectionErr {
+    FrameTooLarge { size: usize, max: usize },
+    NegativeFrameLength(VarInt),
+    BadFrameHeader(DeserializeErr),
+}
+
+impl fmt::Display for ConnectionErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ConnectionErr::*;
+        match self {
+            FrameTooLarge { size, max } => {
+                f.write_fmt(format_args!("frame of {} bytes exceeds the limit of {} bytes", size, max))
+            }
+            NegativeFrameLength(length) => {
+                f.write_fmt(format_args!("negative frame length {:?}", length))
+            }
+            BadFrameHeader(err) => f.write_fmt(format_args!("bad frame header: {:?}", err)),
+        }
+    }
+}
+
+impl fmt::Debug for ConnectionErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        <dyn fmt::Display>::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ConnectionErr {}
+
+#[cfg(feature = "std")]
+pub enum ReadFrameErr {
+    Io(std::io::Error),
+    // the reader ended cleanly, between two frames
+    Eof,
+    // the reader ended part way through a frame
+    TruncatedFrame { expected: usize, read: usize },
+    Connection(ConnectionErr),
+    BadPacketId(DeserializeErr),
+    Packet(crate::protocol::PacketErr),
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for ReadFrameErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ReadFrameErr::*;
+        match self {
+            Io(err) => f.write_fmt(format_args!("io error: {}", err)),
+            Eof => f.write_str("end of stream"),
+            TruncatedFrame { expected, read } => f.write_fmt(format_args!(
+                "stream ended after {} of {} bytes of a frame", read, expected
+            )),
+            Connection(err) => f.write_fmt(format_args!("{}", err)),
+            BadPacketId(err) => f.write_fmt(format_args!("bad packet id: {}", err)),
+            Packet(err) => f.write_fmt(format_args!("{}", err)),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Debug for ReadFrameErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        <dyn fmt::Display>::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ReadFrameErr {}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for ReadFrameErr {
+    fn from(err: std::io::Error) -> Self {
+        ReadFrameErr::Io(err)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<ConnectionErr> for ReadFrameErr {
+    fn from(err: ConnectionErr) -> Self {
+        ReadFrameErr::Connection(err)
+    }
+}
+
+// fills as much of buf as the reader will give, stopping early only at the end of the stream
+#[cfg(feature = "std")]
+fn read_fully<R: std::io::Read>(r: &mut R, buf: &mut [u8]) -> Result<usize, std::io::Error> {
+    let mut read = 0;
+    while read < buf.len() {
+        match r.read(&mut buf[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(read)
+}
+
+// Blocking counterpart of `Connection::read_packet`, reads one frame and returns its body (the packet
+// id followed by the packet data). The length prefix is read a byte at a time so nothing past the
+// frame is consumed from the reader.
+#[cfg(feature = "std")]
+pub fn read_frame<R: std::io::Read>(r: &mut R, max_len: usize) -> Result<Vec<u8>, ReadFrameErr> {
+    let mut header = [0u8; MAX_FRAME_HEADER_SIZE];
+    let mut header_size = 0;
+    loop {
+        if read_fully(r, &mut header[header_size..=header_size])? == 0 {
+            return Err(if header_size == 0 {
+                ReadFrameErr::Eof
+            } else {
+                ReadFrameErr::TruncatedFrame { expected: header_size + 1, read: header_size }
+            });
+        }
+        header_size += 1;
+        if header[header_size - 1] & 0x80 == 0 || header_size == MAX_FRAME_HEADER_SIZE {
+            break;
+        }
+    }
+
+    let length = VarInt::mc_deserialize(&header[..header_size])
+        .map_err(ConnectionErr::BadFrameHeader)?
+        .value;
+    if length.0 < 0 {
+        return Err(ConnectionErr::NegativeFrameLength(length).into());
+    }
+
+    let size = length.0 as usize;
+    if size > max_len {
+        return Err(ConnectionErr::FrameTooLarge { size, max: max_len }.into());
+    }
+
+    let mut body = alloc::vec![0u8; size];
+    let read = read_fully(r, body.as_mut_slice())?;
+    if read < size {
+        return Err(ReadFrameErr::TruncatedFrame { expected: header_size + size, read: header_size + read });
+    }
+
+    Ok(body)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backpressure {
+    // the per-read decode budget is spent, more complete frames may still be buffered
+    DecodeLimitReached,
+    // the buffer is at capacity and no complete frame can be produced from it
+    BufferFull,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReadPacket {
+    // the body of one frame (packet id followed by the packet data)
+    Packet(Vec<u8>),
+    Incomplete,
+    Backpressure(Backpressure),
+}
+
+// Buffers bytes read from a peer and splits them into length-prefixed frames, without doing any IO.
+//
+// Each call to `receive` is one "read call": it accepts at most as many bytes as fit under
+// `max_buffered_bytes` (returning how many were taken, so the caller can hand the rest back later)
+// and refills the budget of packets `read_packet` will produce before signalling backpressure.
+#[derive(Clone, Debug)]
+pub struct Connection {
+    limits: ConnectionLimits,
+    buffer: Vec<u8>,
+    // frames before this offset have been read, they are dropped from the buffer once per `receive`
+    // rather than once per packet
+    read_at: usize,
+    remaining_reads: usize,
+}
+
+impl Default for Connection {
+    fn default() -> Self {
+        Self::with_limits(ConnectionLimits::default())
+    }
+}
+
+impl Connection {
+    pub fn with_limits(limits: ConnectionLimits) -> Self {
+        Self {
+            limits,
+            buffer: Vec::new(),
+            read_at: 0,
+            remaining_reads: limits.max_packets_per_read,
+        }
+    }
+
+    pub fn limits(&self) -> &ConnectionLimits {
+        &self.limits
+    }
+
+    pub fn buffered(&self) -> usize {
+        self.buffer.len() - self.read_at
+    }
+
+    pub fn receive(&mut self, data: &[u8]) -> usize {
+        self.buffer.drain(..self.read_at);
+        self.read_at = 0;
+        let available = self.limits.max_buffered_bytes.saturating_sub(self.buffer.len());
+        let accepted = core::cmp::min(available, data.len());
+        self.buffer.extend_from_slice(&data[..accepted]);
+        self.remaining_reads = self.limits.max_packets_per_read;
+        accepted
+    }
+
+    pub fn read_packet(&mut self) -> Result<ReadPacket, ConnectionErr> {
+        if self.remaining_reads == 0 {
+            return Ok(ReadPacket::Backpressure(Backpressure::DecodeLimitReached));
+        }
+
+        let unread = &self.buffer[self.read_at..];
+        let (length, header_size) = match VarInt::mc_deserialize(unread) {
+            Ok(Deserialized { value, data }) => (value, unread.len() - data.len()),
+            Err(DeserializeErr::Eof) => return Ok(self.incomplete()),
+            Err(err) => return Err(ConnectionErr::BadFrameHeader(err)),
+        };
+
+        if length.0 < 0 {
+            return Err(ConnectionErr::NegativeFrameLength(length));
+        }
+
+        let size = length.0 as usize;
+        if size > self.limits.max_frame_size {
+            return Err(ConnectionErr::FrameTooLarge { size, max: self.limits.max_frame_size });
+        }
+
+        let frame_end = header_size + size;
+        if unread.len() < frame_end {
+            return Ok(self.incomplete());
+        }
+
+        let body = unread[header_size..frame_end].to_vec();
+        self.read_at += frame_end;
+        self.remaining_reads -= 1;
+        Ok(ReadPacket::Packet(body))
+    }
+
+    fn incomplete(&self) -> ReadPacket {
+        if self.buffered() >= self.limits.max_buffered_bytes {
+            ReadPacket::Backpressure(Backpressure::BufferFull)
+        } else {
+            ReadPacket::Incomplete
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Serialize, types::BytesSerializer};
+
+    fn frame(body: &[u8]) -> Vec<u8> {
+        let mut out = BytesSerializer::default();
+        VarInt(body.len() as i32).mc_serialize(&mut out).expect("serialize succeeds");
+        let mut out = out.into_bytes();
+        out.extend_from_slice(body);
+        out
+    }
+
+    #[test]
+    fn test_rejects_huge_frame_header() {
+        let mut conn = Connection::default();
+        let mut out = BytesSerializer::default();
+        VarInt(10 * 1024 * 1024).mc_serialize(&mut out).expect("serialize succeeds");
+        let huge = out.into_bytes();
+
+        assert_eq!(conn.receive(huge.as_slice()), huge.len());
+        match conn.read_packet() {
+            Err(ConnectionErr::FrameTooLarge { size, max }) => {
+                assert_eq!(size, 10 * 1024 * 1024);
+                assert_eq!(max, DEFAULT_MAX_FRAME_SIZE);
+            }
+            other => panic!("expected frame too large, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_rejects_overlong_frame_header() {
+        let mut conn = Connection::default();
+        conn.receive(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x01]);
+        assert!(matches!(conn.read_packet(), Err(ConnectionErr::BadFrameHeader(DeserializeErr::VarNumTooLong(_)))));
+
+        let mut conn = Connection::default();
+        conn.receive(&[0x80, 0x80, 0x80, 0x80, 0x10]);
+        assert!(matches!(conn.read_packet(), Err(ConnectionErr::BadFrameHeader(DeserializeErr::VarNumTooLong(_)))));
+    }
+
+    // hands out at most one byte per read call, and interrupts every other one
+    #[cfg(feature = "std")]
+    struct TrickleReader<'a> {
+        data: &'a [u8],
+        interrupt: bool,
+    }
+
+    #[cfg(feature = "std")]
+    impl std::io::Read for TrickleReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.interrupt = !self.interrupt;
+            if self.interrupt {
+                return Err(std::io::ErrorKind::Interrupted.into());
+            }
+            if self.data.is_empty() || buf.is_empty() {
+                return Ok(0);
+            }
+            buf[0] = self.data[0];
+            self.data = &self.data[1..];
+            Ok(1)
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_read_frame() {
+        let body = alloc::vec![0x21u8; 300];
+        let mut stream = frame(body.as_slice());
+        stream.extend(frame(&[0x00]));
+        let mut cursor = std::io::Cursor::new(stream.clone());
+        assert_eq!(read_frame(&mut cursor, 1024).expect("reads"), body);
+        assert_eq!(read_frame(&mut cursor, 1024).expect("reads"), alloc::vec![0x00]);
+        assert!(matches!(read_frame(&mut cursor, 1024), Err(ReadFrameErr::Eof)));
+
+        let mut trickle = TrickleReader { data: stream.as_slice(), interrupt: false };
+        assert_eq!(read_frame(&mut trickle, 1024).expect("reads"), body);
+        assert_eq!(read_frame(&mut trickle, 1024).expect("reads"), alloc::vec![0x00]);
+        assert!(matches!(read_frame(&mut trickle, 1024), Err(ReadFrameErr::Eof)));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_read_frame_errors() {
+        let stream = frame(&[0x21u8; 300]);
+        let mut truncated = std::io::Cursor::new(&stream[..100]);
+        match read_frame(&mut truncated, 1024) {
+            Err(ReadFrameErr::TruncatedFrame { expected, read }) => {
+                assert_eq!(expected, stream.len());
+                assert_eq!(read, 100);
+            }
+            other => panic!("expected a truncated frame, got {:?}", other),
+        }
+
+        let mut truncated_header = std::io::Cursor::new(&stream[..1]);
+        assert!(matches!(read_frame(&mut truncated_header, 1024), Err(ReadFrameErr::TruncatedFrame { expected: 2, read: 1 })));
+
+        let mut oversized = std::io::Cursor::new(stream.as_slice());
+        assert!(matches!(read_frame(&mut oversized, 299),
+                         Err(ReadFrameErr::Connection(ConnectionErr::FrameTooLarge { size: 300, max: 299 }))));
+        assert_eq!(oversized.position(), 2);
+
+        let mut overlong = std::io::Cursor::new(&[0x80u8, 0x80, 0x80, 0x80, 0x80, 0x01]);
+        assert!(matches!(read_frame(&mut overlong, 1024),
+                         Err(ReadFrameErr::Connection(ConnectionErr::BadFrameHeader(DeserializeErr::VarNumTooLong(_))))));
+    }
+
+    #[cfg(all(feature = "std", feature = "v1_15_2"))]
+    #[test]
+    fn test_raw_packet_read_from() {
+        use crate::protocol::{HasPacketKind, PacketDirection, RawPacket, State};
+        use crate::v1_15_2::{Packet578Kind, RawPacket578};
+
+        let mut body = alloc::vec![0x21u8];
+        body.extend_from_slice(&7i64.to_be_bytes());
+        let mut stream = frame(body.as_slice());
+        stream.extend(frame(&[0x7F]));
+        let mut cursor = std::io::Cursor::new(stream);
+
+        let mut buf = Vec::new();
+        let packet = RawPacket578::read_from(&mut cursor, &mut buf, 1024, State::Play, PacketDirection::ClientBound)
+            .expect("reads");
+        assert_eq!(packet.kind(), Packet578Kind::PlayServerKeepAlive);
+        assert_eq!(packet.data(), &7i64.to_be_bytes());
+
+        let mut buf = Vec::new();
+        let unknown = RawPacket578::read_from(&mut cursor, &mut buf, 1024, State::Play, PacketDirection::ClientBound);
+        assert!(matches!(unknown, Err(ReadFrameErr::Packet(crate::protocol::PacketErr::UnknownId(_)))));
+    }
+
+    #[test]
+    fn test_partial_frames() {
+        let mut conn = Connection::default();
+        let bytes = frame(&[0x21, 1, 2, 3, 4, 5, 6, 7, 8]);
+        let (first, second) = bytes.split_at(4);
+
+        assert_eq!(conn.receive(first), first.len());
+        assert_eq!(conn.read_packet().expect("ok"), ReadPacket::Incomplete);
+        assert_eq!(conn.receive(second), second.len());
+        assert_eq!(conn.read_packet().expect("ok"), ReadPacket::Packet(bytes[1..].to_vec()));
+        assert_eq!(conn.read_packet().expect("ok"), ReadPacket::Incomplete);
+        assert_eq!(conn.buffered(), 0);
+    }
+
+    #[test]
+    fn test_read_frames_compact_on_receive() {
+        let mut conn = Connection::default();
+        let bytes = [frame(&[0x21, 1]), frame(&[0x21, 2]), frame(&[0x21, 3])].concat();
+        let (whole, partial) = bytes.split_at(bytes.len() - 2);
+
+        conn.receive(whole);
+        assert_eq!(conn.read_packet().expect("ok"), ReadPacket::Packet(alloc::vec![0x21, 1]));
+        assert_eq!(conn.read_packet().expect("ok"), ReadPacket::Packet(alloc::vec![0x21, 2]));
+        assert_eq!(conn.read_packet().expect("ok"), ReadPacket::Incomplete);
+        assert_eq!(conn.buffered(), 1);
+        assert_eq!(conn.buffer.len(), whole.len());
+
+        conn.receive(partial);
+        assert_eq!(conn.buffer.len(), 3);
+        assert_eq!(conn.read_packet().expect("ok"), ReadPacket::Packet(alloc::vec![0x21, 3]));
+        assert_eq!(conn.buffered(), 0);
+    }
+
+    #[test]
+    fn test_keep_alive_burst_with_limits() {
+        let limits = ConnectionLimits {
+            max_frame_size: 64,
+            max_buffered_bytes: 1024,
+            max_packets_per_read: 100,
+        };
+        let mut conn = Connection::with_limits(limits);
+
+        let mut stream = Vec::new();
+        for i in 0..1000i64 {
+            let mut body = alloc::vec![0x21u8];
+            body.extend_from_slice(&i.to_be_bytes());
+            stream.extend(frame(body.as_slice()));
+        }
+
+        let mut pending = stream.as_slice();
+        let mut received = Vec::new();
+        let mut saw_buffer_limit = false;
+        let mut saw_decode_limit = false;
+        while received.len() < 1000 {
+            let accepted = conn.receive(pending);
+            if accepted < pending.len() {
+                saw_buffer_limit = true;
+            }
+            pending = &pending[accepted..];
+            assert!(conn.buffered() <= limits.max_buffered_bytes);
+
+            let mut decoded = 0;
+            loop {
+                match conn.read_packet().expect("no fatal errors") {
+                    ReadPacket::Packet(body) => {
+                        decoded += 1;
+                        received.push(body);
+                    }
+                    ReadPacket::Incomplete => break,
+                    ReadPacket::Backpressure(Backpressure::DecodeLimitReached) => {
+                        saw_decode_limit = true;
+                        break;
+                    }
+                    ReadPacket::Backpressure(Backpressure::BufferFull) => panic!("frames fit in the buffer"),
+                }
+            }
+            assert!(decoded <= limits.max_packets_per_read);
+        }
+
+        assert!(saw_buffer_limit);
+        assert!(saw_decode_limit);
+        assert!(pending.is_empty());
+        for (i, body) in received.iter().enumerate() {
+            assert_eq!(body[0], 0x21);
+            assert_eq!(&body[1..], &(i as i64).to_be_bytes());
+        }
+    }
+}
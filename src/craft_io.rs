@@ -0,0 +1,355 @@
+use crate::codec::{CodecErr, Frame};
+use crate::protocol::{Packet, RawPacket};
+use crate::connection::DEFAULT_MAX_FRAME_SIZE;
+use bytes::BytesMut;
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio_util::codec::Decoder;
+
+#[cfg(feature = "encryption")]
+use crate::encryption::{Decryptor, Encryptor};
+
+// without compression support the frames are the same as CompressedPacketCodec's with compression off
+#[cfg(feature = "compression")]
+type FrameCodec = crate::codec::CompressedPacketCodec;
+#[cfg(not(feature = "compression"))]
+type FrameCodec = crate::codec::MinecraftFrameCodec;
+
+#[cfg(feature = "compression")]
+fn frame_codec(max_frame_size: usize) -> FrameCodec {
+    FrameCodec::with_limits(-1, max_frame_size, crate::codec::DEFAULT_MAX_UNCOMPRESSED_SIZE)
+}
+
+#[cfg(not(feature = "compression"))]
+fn frame_codec(max_frame_size: usize) -> FrameCodec {
+    FrameCodec::with_max_frame_size(max_frame_size)
+}
+
+// Reads frames from an AsyncRead, decrypting and inflating them once the connection turns those on.
+//
+// `read_frame` is cancel safe: bytes are only taken from the inner reader into the buffer kept here,
+// so dropping the future (like a losing `select!` branch does) loses nothing.
+pub struct AsyncCraftReader<R> {
+    inner: R,
+    buffer: BytesMut,
+    codec: FrameCodec,
+    #[cfg(feature = "encryption")]
+    decryptor: Option<Decryptor>,
+}
+
+impl<R> AsyncCraftReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self::with_max_frame_size(inner, DEFAULT_MAX_FRAME_SIZE)
+    }
+
+    pub fn with_max_frame_size(inner: R, max_frame_size: usize) -> Self {
+        Self {
+            inner,
+            buffer: BytesMut::new(),
+            codec: frame_codec(max_frame_size),
+            #[cfg(feature = "encryption")]
+            decryptor: None,
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    // bytes which were read but aren't part of a returned frame yet are lost
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    #[cfg(feature = "compression")]
+    pub fn set_compression_threshold(&mut self, threshold: i32) {
+        self.codec.set_threshold(threshold);
+    }
+
+    // everything after the last frame returned is encrypted, including anything already buffered
+    #[cfg(feature = "encryption")]
+    pub fn enable_encryption(&mut self, key: [u8; 16]) {
+        let mut decryptor = Decryptor::new(key);
+        decryptor.decrypt(self.buffer.as_mut());
+        self.decryptor = Some(decryptor);
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncCraftReader<R> {
+    // None when the stream ends cleanly between two frames
+    pub async fn read_frame(&mut self) -> Result<Option<Frame>, CodecErr> {
+        loop {
+            if let Some(frame) = self.codec.decode(&mut self.buffer)? {
+                return Ok(Some(frame));
+            }
+
+            let before = self.buffer.len();
+            if self.inner.read_buf(&mut self.buffer).await? == 0 {
+                return if self.buffer.is_empty() {
+                    Ok(None)
+                } else {
+                    Err(io::Error::from(io::ErrorKind::UnexpectedEof).into())
+                };
+            }
+
+            #[cfg(feature = "encryption")]
+            if let Some(decryptor) = &mut self.decryptor {
+                decryptor.decrypt(&mut self.buffer[before..]);
+            }
+            #[cfg(not(feature = "encryption"))]
+            let _ = before;
+        }
+    }
+}
+
+// Writes packets to an AsyncWrite, compressing and encrypting them once the connection turns those on.
+//
+// Packets are framed into a buffer kept here before any of them is written, so a cancelled write
+// leaves its remaining bytes to go out ahead of the next packet (or on `flush`). The cancelled packet
+// is still sent that way, so don't retry it: writing it again sends it twice.
+pub struct AsyncCraftWriter<W> {
+    inner: W,
+    pending: BytesMut,
+    codec: FrameCodec,
+    #[cfg(feature = "encryption")]
+    encryptor: Option<Encryptor>,
+}
+
+impl<W> AsyncCraftWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self::with_max_frame_size(inner, DEFAULT_MAX_FRAME_SIZE)
+    }
+
+    pub fn with_max_frame_size(inner: W, max_frame_size: usize) -> Self {
+        Self {
+            inner,
+            pending: BytesMut::new(),
+            codec: frame_codec(max_frame_size),
+            #[cfg(feature = "encryption")]
+            encryptor: None,
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    // bytes which haven't been written to the inner writer yet are lost
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    #[cfg(feature = "compression")]
+    pub fn set_compression_threshold(&mut self, threshold: i32) {
+        self.codec.set_threshold(threshold);
+    }
+
+    // packets written from now on are encrypted. Bytes still pending from a cancelled write were framed
+    // before this, so they are sent afterwards but unencrypted, ahead of the first encrypted packet.
+    #[cfg(feature = "encryption")]
+    pub fn enable_encryption(&mut self, key: [u8; 16]) {
+        self.encryptor = Some(Encryptor::new(key));
+    }
+
+    fn encode_parts(&mut self, id: i32, data: &[u8]) -> Result<(), CodecErr> {
+        let start = self.pending.len();
+        self.codec.encode_parts(id, data, &mut self.pending)?;
+        #[cfg(feature = "encryption")]
+        if let Some(encryptor) = &mut self.encryptor {
+            encryptor.encrypt(&mut self.pending[start..]);
+        }
+        #[cfg(not(feature = "encryption"))]
+        let _ = start;
+        Ok(())
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncCraftWriter<W> {
+    pub async fn write_packet<P: Packet>(&mut self, packet: &P) -> Result<(), CodecErr> {
+        let mut body = crate::types::BytesSerializer::default();
+        packet.mc_serialize_body(&mut body)?;
+        self.encode_parts(packet.id().id, body.into_bytes().as_slice())?;
+        self.write_pending().await
+    }
+
+    pub async fn write_raw_packet<'a, P: RawPacket<'a>>(&mut self, packet: &P) -> Result<(), CodecErr> {
+        self.encode_parts(packet.id().id, packet.data())?;
+        self.write_pending().await
+    }
+
+    pub async fn flush(&mut self) -> Result<(), CodecErr> {
+        self.write_pending().await?;
+        self.inner.flush().await?;
+        Ok(())
+    }
+
+    async fn write_pending(&mut self) -> Result<(), CodecErr> {
+        while !self.pending.is_empty() {
+            if self.inner.write_buf(&mut self.pending).await? == 0 {
+                return Err(io::Error::from(io::ErrorKind::WriteZero).into());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "v1_15_2"))]
+mod tests {
+    use super::*;
+    use crate::protocol::{PacketDirection, State};
+    use crate::status::{StatusPlayersSpec, StatusSpec, StatusVersionSpec};
+    use crate::types::Chat;
+    use crate::v1_15_2::*;
+
+    fn status() -> StatusSpec {
+        StatusSpec {
+            version: Some(StatusVersionSpec { name: "1.15.2".to_owned(), protocol: 578 }),
+            players: StatusPlayersSpec { max: 20, online: 1, sample: Vec::new() },
+            description: Chat::from_text("hello"),
+            favicon: None,
+        }
+    }
+
+    async fn next_packet<R: AsyncRead + Unpin>(reader: &mut AsyncCraftReader<R>) -> Packet578 {
+        let frame = reader.read_frame().await.expect("reads").expect("has a frame");
+        frame.packet::<RawPacket578>(State::Play, PacketDirection::ClientBound).expect("valid packet")
+    }
+
+    #[tokio::test]
+    async fn test_status_handshake() {
+        let (client, server) = tokio::io::duplex(64);
+        let (client_read, client_write) = tokio::io::split(client);
+        let (server_read, server_write) = tokio::io::split(server);
+        let mut client_reader = AsyncCraftReader::new(client_read);
+        let mut client_writer = AsyncCraftWriter::new(client_write);
+        let mut server_reader = AsyncCraftReader::new(server_read);
+        let mut server_writer = AsyncCraftWriter::new(server_write);
+
+        let client = async {
+            client_writer.write_packet(&Packet578::Handshake(HandshakeSpec {
+                version: 578.into(),
+                server_address: "localhost".to_owned(),
+                server_port: 25565,
+                next_state: HandshakeNextState::Status,
+            })).await.expect("writes");
+            client_writer.write_packet(&Packet578::StatusRequest(StatusRequestSpec {})).await.expect("writes");
+
+            let frame = client_reader.read_frame().await.expect("reads").expect("has a response");
+            let response = frame.packet::<RawPacket578>(State::Status, PacketDirection::ClientBound).expect("valid packet");
+            assert_eq!(response, Packet578::StatusResponse(StatusResponseSpec { response: status() }));
+
+            client_writer.write_packet(&Packet578::StatusPing(StatusPingSpec { payload: 42 })).await.expect("writes");
+            let frame = client_reader.read_frame().await.expect("reads").expect("has a pong");
+            let pong = frame.packet::<RawPacket578>(State::Status, PacketDirection::ClientBound).expect("valid packet");
+            assert_eq!(pong, Packet578::StatusPong(StatusPongSpec { payload: 42 }));
+            client_writer.get_mut().shutdown().await.expect("shuts down");
+        };
+
+        let server = async {
+            let frame = server_reader.read_frame().await.expect("reads").expect("has a handshake");
+            match frame.packet::<RawPacket578>(State::Handshaking, PacketDirection::ServerBound).expect("valid packet") {
+                Packet578::Handshake(body) => assert_eq!(body.next_state, HandshakeNextState::Status),
+                other => panic!("expected a handshake, got {:?}", other),
+            }
+
+            let frame = server_reader.read_frame().await.expect("reads").expect("has a request");
+            let request = frame.packet::<RawPacket578>(State::Status, PacketDirection::ServerBound).expect("valid packet");
+            assert_eq!(request, Packet578::StatusRequest(StatusRequestSpec {}));
+            server_writer.write_packet(&Packet578::StatusResponse(StatusResponseSpec { response: status() }))
+                .await.expect("writes");
+
+            let frame = server_reader.read_frame().await.expect("reads").expect("has a ping");
+            let raw: RawPacket578 = frame.raw_packet(State::Status, PacketDirection::ServerBound).expect("valid id");
+            server_writer.write_packet(&Packet578::StatusPong(StatusPongSpec {
+                payload: match raw.deserialize().expect("deserializes") {
+                    Packet578::StatusPing(ping) => ping.payload,
+                    other => panic!("expected a ping, got {:?}", other),
+                },
+            })).await.expect("writes");
+
+            assert!(server_reader.read_frame().await.expect("ends cleanly").is_none());
+        };
+
+        tokio::join!(client, server);
+    }
+
+    #[tokio::test]
+    async fn test_read_frame_cancel_safe() {
+        let (mut client, server) = tokio::io::duplex(64);
+        let mut reader = AsyncCraftReader::new(server);
+
+        let mut bytes = Vec::new();
+        let mut frames = AsyncCraftWriter::new(&mut bytes);
+        frames.write_packet(&Packet578::PlayServerKeepAlive(PlayServerKeepAliveSpec { id: 7 })).await.expect("writes");
+        let (first, second) = bytes.split_at(4);
+
+        client.write_all(first).await.expect("writes");
+        tokio::select! {
+            biased;
+            _ = reader.read_frame() => panic!("the frame isn't complete"),
+            _ = std::future::ready(()) => {}
+        }
+
+        client.write_all(second).await.expect("writes");
+        assert_eq!(next_packet(&mut reader).await, Packet578::PlayServerKeepAlive(PlayServerKeepAliveSpec { id: 7 }));
+
+        drop(client);
+        assert!(matches!(reader.read_frame().await, Ok(None)));
+    }
+
+    #[tokio::test]
+    async fn test_truncated_stream() {
+        let (mut client, server) = tokio::io::duplex(64);
+        let mut reader = AsyncCraftReader::new(server);
+        client.write_all(&[9, 0x21, 0, 0]).await.expect("writes");
+        drop(client);
+        assert!(matches!(reader.read_frame().await, Err(CodecErr::Io(err)) if err.kind() == io::ErrorKind::UnexpectedEof));
+    }
+
+    #[cfg(all(feature = "compression", feature = "encryption"))]
+    #[tokio::test]
+    async fn test_compressed_encrypted_session() {
+        let (client, server) = tokio::io::duplex(256);
+        let mut writer = AsyncCraftWriter::new(client);
+        let mut reader = AsyncCraftReader::new(server);
+        let key = [0x17; 16];
+        let message = Packet578::PlayServerPluginMessage(PlayServerPluginMessageSpec {
+            channel: "minecraft:brand".to_owned(),
+            data: alloc::vec![3u8; 4096].into(),
+        });
+        let keep_alive = Packet578::PlayServerKeepAlive(PlayServerKeepAliveSpec { id: -5 });
+
+        let write = async {
+            writer.write_packet(&keep_alive).await.expect("writes");
+            writer.enable_encryption(key);
+            writer.write_packet(&keep_alive).await.expect("writes");
+            writer.set_compression_threshold(256);
+            writer.write_packet(&message).await.expect("writes");
+            writer.write_packet(&keep_alive).await.expect("writes");
+            writer.flush().await.expect("flushes");
+            writer.get_mut().shutdown().await.expect("shuts down");
+        };
+
+        let read = async {
+            let mut packets = alloc::vec![next_packet(&mut reader).await];
+            reader.enable_encryption(key);
+            packets.push(next_packet(&mut reader).await);
+            reader.set_compression_threshold(256);
+            packets.push(next_packet(&mut reader).await);
+            packets.push(next_packet(&mut reader).await);
+            assert!(reader.read_frame().await.expect("ends cleanly").is_none());
+            packets
+        };
+
+        let ((), packets) = tokio::join!(write, read);
+        assert_eq!(packets, alloc::vec![keep_alive.clone(), keep_alive.clone(), message, keep_alive]);
+    }
+}
@@ -6,29 +6,40 @@ pub enum DeserializeErr {
     VarNumTooLong(Vec<u8>),
     NegativeLength(VarInt),
     BadStringEncoding(FromUtf8Error),
+    StringTooLong { length: usize, max: usize },
+    ArrayTooLarge { count: usize, limit: usize },
     InvalidBool(u8),
     NbtUnknownTagType(u8),
     NbtBadLength(isize),
     NbtInvalidStartTag(u8),
     CannotUnderstandValue(String),
     FailedJsonDeserialize(String),
+    ExtraData(Vec<u8>),
 }
 
 impl fmt::Display for DeserializeErr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use DeserializeErr::*;
         match self {
-            Eof => f.write_str("unexpected eof"),
+            Eof => f.write_str("unexpected end of input"),
             VarNumTooLong(data) => {
                 f.write_fmt(format_args!("var num is too long: data={:?}", data))
             }
             NegativeLength(data) => {
-                f.write_fmt(format_args!("negative length encountered {:?}", data))
+                f.write_fmt(format_args!("negative length prefix: {}", data))
             }
             BadStringEncoding(data) => f.write_fmt(format_args!(
                 "failed to decode string, utf error: {:?}",
                 data
             )),
+            StringTooLong { length, max } => f.write_fmt(format_args!(
+                "string of length {} exceeds the maximum length of {}",
+                length, max
+            )),
+            ArrayTooLarge { count, limit } => f.write_fmt(format_args!(
+                "array of {} elements exceeds the limit of {} elements",
+                count, limit
+            )),
             InvalidBool(value) => f.write_fmt(format_args!(
                 "could not decode boolean, unexpected byte: {:?}",
                 value
@@ -39,11 +50,14 @@ impl fmt::Display for DeserializeErr {
                 f.write_fmt(format_args!("nbt: unexpected start tag id: {:?}", data))
             }
             CannotUnderstandValue(data) => {
-                f.write_fmt(format_args!("cannot understand value: {:?}", data))
+                f.write_fmt(format_args!("cannot understand value: {}", data))
             }
             FailedJsonDeserialize(data) => {
                 f.write_fmt(format_args!("failed to deserialize json: {:?}", data))
             }
+            ExtraData(data) => {
+                f.write_fmt(format_args!("{} unexpected bytes after value: {:?}", data.len(), data))
+            }
         }
     }
 }
@@ -54,7 +68,14 @@ impl fmt::Debug for DeserializeErr {
 }
 
 #[cfg(feature = "std")]
-impl std::error::Error for DeserializeErr {}
+impl std::error::Error for DeserializeErr {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DeserializeErr::BadStringEncoding(err) => Some(err),
+            _ => None,
+        }
+    }
+}
 
 impl<'b, R> Into<DeserializeResult<'b, R>> for DeserializeErr {
     fn into(self) -> DeserializeResult<'b, R> {
@@ -131,4 +152,14 @@ pub type DeserializeResult<'b, R> = Result<Deserialized<'b, R>, DeserializeErr>;
 
 pub trait Deserialize: Sized {
     fn mc_deserialize(data: &[u8]) -> DeserializeResult<Self>;
+
+    // fails if anything is left over after the value
+    fn mc_deserialize_complete(data: &[u8]) -> Result<Self, DeserializeErr> {
+        let Deserialized { value, data: rest } = Self::mc_deserialize(data)?;
+        if rest.is_empty() {
+            Ok(value)
+        } else {
+            Err(DeserializeErr::ExtraData(rest.to_vec()))
+        }
+    }
 }
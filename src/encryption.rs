@@ -0,0 +1,234 @@
+use aes::Aes128;
+use aes::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit, generic_array::GenericArray};
+use alloc::vec::Vec;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use std::io;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+type Aes128Cfb8Enc = cfb8::Encryptor<Aes128>;
+type Aes128Cfb8Dec = cfb8::Decryptor<Aes128>;
+
+// One direction of the stream cipher, for readers and writers which keep their own buffers. The
+// protocol uses the shared secret as both the key and the IV.
+pub struct Encryptor {
+    cipher: Aes128Cfb8Enc,
+}
+
+impl Encryptor {
+    pub fn new(key: [u8; 16]) -> Self {
+        Self { cipher: Aes128Cfb8Enc::new(&key.into(), &key.into()) }
+    }
+
+    pub fn encrypt(&mut self, data: &mut [u8]) {
+        for byte in data.chunks_exact_mut(1) {
+            self.cipher.encrypt_block_mut(GenericArray::from_mut_slice(byte));
+        }
+    }
+}
+
+pub struct Decryptor {
+    cipher: Aes128Cfb8Dec,
+}
+
+impl Decryptor {
+    pub fn new(key: [u8; 16]) -> Self {
+        Self { cipher: Aes128Cfb8Dec::new(&key.into(), &key.into()) }
+    }
+
+    pub fn decrypt(&mut self, data: &mut [u8]) {
+        for byte in data.chunks_exact_mut(1) {
+            self.cipher.decrypt_block_mut(GenericArray::from_mut_slice(byte));
+        }
+    }
+}
+
+// Wraps a stream after the encryption handshake, all bytes in both directions go through
+// AES-128-CFB8 keyed with the shared secret, which the protocol also uses as the IV.
+//
+// Bytes accepted by `poll_write` are encrypted immediately (the cipher state moves forward), so
+// any the inner stream didn't take yet are kept here until a later write, flush or shutdown.
+pub struct EncryptedStream<S> {
+    inner: S,
+    encryptor: Encryptor,
+    decryptor: Decryptor,
+    pending_write: Vec<u8>,
+    pending_at: usize,
+}
+
+impl<S> EncryptedStream<S> {
+    pub fn new(inner: S, key: [u8; 16]) -> Self {
+        Self {
+            inner,
+            encryptor: Encryptor::new(key),
+            decryptor: Decryptor::new(key),
+            pending_write: Vec::new(),
+            pending_at: 0,
+        }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    // encrypted bytes which haven't been handed to the inner stream yet are lost
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: AsyncWrite + Unpin> EncryptedStream<S> {
+    fn poll_write_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.pending_at < self.pending_write.len() {
+            let written = match Pin::new(&mut self.inner).poll_write(cx, &self.pending_write[self.pending_at..]) {
+                Poll::Ready(Ok(written)) => written,
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            };
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.pending_at += written;
+        }
+
+        self.pending_write.clear();
+        self.pending_at = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for EncryptedStream<S> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let before = buf.filled().len();
+        match Pin::new(&mut this.inner).poll_read(cx, buf) {
+            Poll::Ready(Ok(())) => {}
+            other => return other,
+        }
+
+        this.decryptor.decrypt(&mut buf.filled_mut()[before..]);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for EncryptedStream<S> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        match this.poll_write_pending(cx) {
+            Poll::Ready(Ok(())) => {}
+            Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+            Poll::Pending => return Poll::Pending,
+        }
+
+        this.pending_write.extend_from_slice(buf);
+        this.encryptor.encrypt(this.pending_write.as_mut_slice());
+
+        // the data is accepted either way, a pending inner write is picked up by the next call
+        if let Poll::Ready(Err(err)) = this.poll_write_pending(cx) {
+            return Poll::Ready(Err(err));
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        match this.poll_write_pending(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.inner).poll_flush(cx),
+            other => other,
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        match this.poll_write_pending(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.inner).poll_shutdown(cx),
+            other => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aes::cipher::AsyncStreamCipher;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    const KEY: [u8; 16] = [0x42; 16];
+
+    fn encrypt(data: &[u8]) -> Vec<u8> {
+        let mut out = data.to_vec();
+        Aes128Cfb8Enc::new(&KEY.into(), &KEY.into()).encrypt(out.as_mut_slice());
+        out
+    }
+
+    #[tokio::test]
+    async fn test_encrypts_writes() {
+        let (client, mut server) = tokio::io::duplex(8);
+        let mut client = EncryptedStream::new(client, KEY);
+        let plaintext: Vec<u8> = (0..200u8).collect();
+
+        let writer = async {
+            for chunk in plaintext.chunks(7) {
+                client.write_all(chunk).await.expect("write succeeds");
+            }
+            client.shutdown().await.expect("shutdown succeeds");
+        };
+        let reader = async {
+            let mut received = Vec::new();
+            server.read_to_end(&mut received).await.expect("read succeeds");
+            received
+        };
+
+        let ((), received) = tokio::join!(writer, reader);
+        assert_eq!(received, encrypt(plaintext.as_slice()));
+    }
+
+    #[tokio::test]
+    async fn test_decrypts_reads() {
+        let (mut client, server) = tokio::io::duplex(16);
+        let mut server = EncryptedStream::new(server, KEY);
+        let plaintext: Vec<u8> = (0..200u8).rev().collect();
+        let ciphertext = encrypt(plaintext.as_slice());
+
+        let writer = async {
+            client.write_all(ciphertext.as_slice()).await.expect("write succeeds");
+            client.shutdown().await.expect("shutdown succeeds");
+        };
+        let reader = async {
+            let mut received = Vec::new();
+            let mut buf = [0u8; 5];
+            loop {
+                let n = server.read(&mut buf).await.expect("read succeeds");
+                if n == 0 {
+                    break received;
+                }
+                received.extend_from_slice(&buf[..n]);
+            }
+        };
+
+        let ((), received) = tokio::join!(writer, reader);
+        assert_eq!(received, plaintext);
+    }
+
+    #[tokio::test]
+    async fn test_both_ends_encrypted() {
+        let (client, server) = tokio::io::duplex(64);
+        let mut client = EncryptedStream::new(client, KEY);
+        let mut server = EncryptedStream::new(server, KEY);
+
+        client.write_all(b"hello").await.expect("write succeeds");
+        client.flush().await.expect("flush succeeds");
+        let mut buf = [0u8; 5];
+        server.read_exact(&mut buf).await.expect("read succeeds");
+        assert_eq!(&buf, b"hello");
+
+        server.write_all(b"world").await.expect("write succeeds");
+        server.flush().await.expect("flush succeeds");
+        client.read_exact(&mut buf).await.expect("read succeeds");
+        assert_eq!(&buf, b"world");
+    }
+}
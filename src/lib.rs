@@ -9,6 +9,13 @@ extern crate test;
 
 pub mod byte_order;
 mod chat;
+#[cfg(feature = "codec")]
+pub mod codec;
+pub mod connection;
+#[cfg(feature = "tokio")]
+pub mod craft_io;
+#[cfg(feature = "encryption")]
+pub mod encryption;
 mod deserialize;
 pub mod nbt;
 pub mod protocol;
@@ -2,7 +2,7 @@ use crate::utils::take;
 use crate::{DeserializeErr, DeserializeResult, Deserialized};
 use alloc::{string::{String, ToString}, borrow::ToOwned, fmt, vec::Vec, vec, format};
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 use crate::protocol::TestRandom;
 use crate::byte_order::{ProtoByteOrder, ByteOrder};
 
@@ -25,7 +25,29 @@ impl NamedTag {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+// in serde formats a tag is the base64 encoding of its binary NBT representation
+#[cfg(feature = "serde")]
+impl serde::Serialize for NamedTag {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(base64::encode(self.bytes()).as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for NamedTag {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+        let encoded = String::deserialize(deserializer)?;
+        let bytes = base64::decode(encoded.as_str()).map_err(D::Error::custom)?;
+        match read_named_tag(bytes.as_slice()) {
+            Ok(Deserialized { value, data: [] }) => Ok(value),
+            Ok(Deserialized { data, .. }) => Err(D::Error::custom(format!("{} extra bytes after nbt tag", data.len()))),
+            Err(err) => Err(D::Error::custom(err)),
+        }
+    }
+}
+
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for NamedTag {
     fn test_gen_random() -> Self {
         Self {
@@ -81,7 +103,168 @@ impl Tag {
         }
     }
 
-    pub fn tag_type_name(&self) -> &str {
+    // looks up an entry of a compound tag, returns None for every other kind of tag
+    pub fn get(&self, name: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(entries) => entries.iter()
+                .find(move |entry| entry.name == name)
+                .map(move |entry| &entry.payload),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Tag> {
+        match self {
+            Tag::Compound(entries) => entries.iter_mut()
+                .find(move |entry| entry.name == name)
+                .map(move |entry| &mut entry.payload),
+            _ => None,
+        }
+    }
+
+    // sets an entry of a compound tag, replacing any entry with the same name in place. Does nothing
+    // unless this is a compound tag.
+    pub fn insert(&mut self, name: &str, value: Tag) {
+        if let Tag::Compound(entries) = self {
+            match entries.iter_mut().find(|entry| entry.name == name) {
+                Some(entry) => entry.payload = value,
+                None => entries.push(value.with_name(name)),
+            }
+        }
+    }
+
+    // gets the entry of a compound tag, inserting the result of `default` if it's missing. Returns
+    // None unless this is a compound tag.
+    pub fn get_or_insert_with<F>(&mut self, name: &str, default: F) -> Option<&mut Tag> where F: FnOnce() -> Tag {
+        match self {
+            Tag::Compound(entries) => {
+                let idx = match entries.iter().position(|entry| entry.name == name) {
+                    Some(idx) => idx,
+                    None => {
+                        entries.push(default().with_name(name));
+                        entries.len() - 1
+                    }
+                };
+                Some(&mut entries[idx].payload)
+            }
+            _ => None,
+        }
+    }
+
+    // the entries of a compound tag in order, every other kind of tag has none
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Tag)> + '_ {
+        self.entries().iter().map(move |entry| (entry.name.as_str(), &entry.payload))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> + '_ {
+        self.entries().iter().map(move |entry| entry.name.as_str())
+    }
+
+    fn entries(&self) -> &[NamedTag] {
+        match self {
+            Tag::Compound(entries) => entries.as_slice(),
+            _ => &[],
+        }
+    }
+
+    // typed lookups of compound entries, None if the entry is missing or holds another kind of tag
+
+    pub fn get_i8(&self, name: &str) -> Option<i8> {
+        match self.get(name)? {
+            Tag::Byte(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn get_i16(&self, name: &str) -> Option<i16> {
+        match self.get(name)? {
+            Tag::Short(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn get_i32(&self, name: &str) -> Option<i32> {
+        match self.get(name)? {
+            Tag::Int(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn get_i64(&self, name: &str) -> Option<i64> {
+        match self.get(name)? {
+            Tag::Long(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn get_f32(&self, name: &str) -> Option<f32> {
+        match self.get(name)? {
+            Tag::Float(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn get_f64(&self, name: &str) -> Option<f64> {
+        match self.get(name)? {
+            Tag::Double(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        match self.get(name)? {
+            Tag::String(v) => Some(v.as_str()),
+            _ => None,
+        }
+    }
+
+    // returns the compound tag itself, so lookups can be chained
+    pub fn get_compound(&self, name: &str) -> Option<&Tag> {
+        match self.get(name)? {
+            compound @ Tag::Compound(_) => Some(compound),
+            _ => None,
+        }
+    }
+
+    pub fn get_list(&self, name: &str) -> Option<&[Tag]> {
+        match self.get(name)? {
+            Tag::List(v) => Some(v.as_slice()),
+            _ => None,
+        }
+    }
+
+    pub fn get_byte_array(&self, name: &str) -> Option<&[u8]> {
+        match self.get(name)? {
+            Tag::ByteArray(v) => Some(v.as_slice()),
+            _ => None,
+        }
+    }
+
+    pub fn get_int_array(&self, name: &str) -> Option<&[i32]> {
+        match self.get(name)? {
+            Tag::IntArray(v) => Some(v.as_slice()),
+            _ => None,
+        }
+    }
+
+    pub fn get_long_array(&self, name: &str) -> Option<&[i64]> {
+        match self.get(name)? {
+            Tag::LongArray(v) => Some(v.as_slice()),
+            _ => None,
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Tag> {
+        match self {
+            Tag::Compound(entries) => {
+                let idx = entries.iter().position(move |entry| entry.name == name)?;
+                Some(entries.remove(idx).payload)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn tag_type_name(&self) -> &'static str {
         match self {
             Tag::Byte(_) => "Byte",
             Tag::Short(_) => "Short",
@@ -124,10 +307,10 @@ impl Tag {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for Tag {
     fn test_gen_random() -> Self {
-        let random_idx = rand::random::<usize>() % 8;
+        let random_idx = (crate::protocol::test_random::<u32>() % 8) as usize;
         match random_idx {
             0 => Tag::Byte(i8::test_gen_random()),
             1 => Tag::Short(i16::test_gen_random()),
@@ -137,9 +320,9 @@ impl TestRandom for Tag {
             5 => Tag::Double(f64::test_gen_random()),
             6 => Tag::String(String::test_gen_random()),
             7 => Tag::List({
-                let count = rand::random::<usize>() % 256;
+                let count = (crate::protocol::test_random::<u32>() % 256) as usize;
                 let mut out = Vec::with_capacity(count);
-                let random_idx = rand::random::<usize>() % 6;
+                let random_idx = (crate::protocol::test_random::<u32>() % 6) as usize;
                 for _ in 0..count {
                     out.push(match random_idx {
                         0 => Tag::Byte(i8::test_gen_random()),
@@ -156,7 +339,7 @@ impl TestRandom for Tag {
                 out
             }),
             8 => Tag::Compound({
-                let count = rand::random::<usize>() % 256;
+                let count = (crate::protocol::test_random::<u32>() % 256) as usize;
                 let mut out = Vec::with_capacity(count);
                 for _ in 0..count {
                     out.push(NamedTag::test_gen_random());
@@ -268,10 +451,18 @@ fn read_tag_string(data: &[u8]) -> DeserializeResult<Tag> {
 fn read_tag_list(data: &[u8]) -> DeserializeResult<Tag> {
     let Deserialized { value: contents_tag_type_id, data } = ProtoByteOrder::read_ubyte(data)?;
     let Deserialized { value: list_length, data } = ProtoByteOrder::read_int(data)?;
-    if list_length == 0 {
+    if list_length < 0 {
+        Err(DeserializeErr::NbtBadLength(list_length as isize))
+    } else if list_length == 0 {
         Deserialized::ok(Tag::List(vec![]), data)
+    } else if contents_tag_type_id == 0x00 {
+        // end tags take no bytes, so this could claim billions of them for free. vanilla refuses these
+        Err(DeserializeErr::CannotUnderstandValue(format!(
+            "nbt list of {} end tags", list_length
+        )))
     } else {
-        let mut out_vec = Vec::with_capacity(list_length as usize);
+        // every element takes at least one byte
+        let mut out_vec = Vec::with_capacity(core::cmp::min(list_length as usize, data.len()));
         let mut remaining_data = data;
         for _ in 0..list_length {
             let Deserialized { value: element, data: rest } =
@@ -324,7 +515,7 @@ fn read_array_tag<'a, R, F, M>(
     if count < 0 {
         Err(DeserializeErr::NbtBadLength(count as isize))
     } else {
-        let mut out = Vec::with_capacity(count as usize);
+        let mut out = Vec::with_capacity(core::cmp::min(count as usize, data.len()));
         let mut data_remaining = data;
         for _ in 0..count {
             let Deserialized {
@@ -476,6 +667,643 @@ impl Tag {
     }
 }
 
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct NbtTypeErr {
+    pub expected: &'static str,
+    pub found: &'static str,
+}
+
+impl fmt::Display for NbtTypeErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!("expected {} tag but found {}", self.expected, self.found))
+    }
+}
+
+impl fmt::Debug for NbtTypeErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        <dyn fmt::Display>::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NbtTypeErr {}
+
+macro_rules! tag_try_into {
+    ($($fnam: ident, $variant: ident, $typ: ty);* $(;)?) => {
+        impl Tag {
+            $(pub fn $fnam(self) -> Result<$typ, NbtTypeErr> {
+                match self {
+                    Tag::$variant(v) => Ok(v),
+                    other => Err(NbtTypeErr { expected: stringify!($variant), found: other.tag_type_name() }),
+                }
+            })*
+        }
+    }
+}
+
+tag_try_into!(
+    try_into_i8, Byte, i8;
+    try_into_i16, Short, i16;
+    try_into_i32, Int, i32;
+    try_into_i64, Long, i64;
+    try_into_f32, Float, f32;
+    try_into_f64, Double, f64;
+    try_into_string, String, String;
+    try_into_list, List, Vec<Tag>;
+    try_into_compound, Compound, Vec<NamedTag>;
+    try_into_byte_array, ByteArray, Vec<u8>;
+    try_into_int_array, IntArray, Vec<i32>;
+    try_into_long_array, LongArray, Vec<i64>;
+);
+
+// paths like "tag.display.Lore[0]", a dot separated list of compound keys where each key may be
+// followed by list indices
+
+#[derive(Clone, PartialEq, Eq)]
+pub enum NbtPathErr {
+    BadPath { offset: usize },
+    MissingKey { at: String, key: String },
+    NotCompound { at: String, found: String },
+    NotList { at: String, found: String },
+    IndexOutOfBounds { at: String, index: usize, len: usize },
+}
+
+impl fmt::Display for NbtPathErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use NbtPathErr::*;
+        match self {
+            BadPath { offset } => f.write_fmt(format_args!("invalid nbt path at offset {}", offset)),
+            MissingKey { at, key } => f.write_fmt(format_args!(
+                "no key {:?} in compound at {}", key, display_nbt_path(at)
+            )),
+            NotCompound { at, found } => f.write_fmt(format_args!(
+                "expected a compound at {} but found {}", display_nbt_path(at), found
+            )),
+            NotList { at, found } => f.write_fmt(format_args!(
+                "expected a list at {} but found {}", display_nbt_path(at), found
+            )),
+            IndexOutOfBounds { at, index, len } => f.write_fmt(format_args!(
+                "index {} is out of bounds for list of length {} at {}", index, len, display_nbt_path(at)
+            )),
+        }
+    }
+}
+
+impl fmt::Debug for NbtPathErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        <dyn fmt::Display>::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NbtPathErr {}
+
+fn display_nbt_path(at: &str) -> &str {
+    if at.is_empty() {
+        "<root>"
+    } else {
+        at
+    }
+}
+
+enum NbtPathStep<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+// each step comes with the part of the path which leads up to it, for error messages
+fn parse_nbt_path(path: &str) -> Result<Vec<(&str, NbtPathStep<'_>)>, NbtPathErr> {
+    let bytes = path.as_bytes();
+    let mut steps = Vec::new();
+    let mut at = 0;
+    while at < bytes.len() {
+        if bytes[at] != b'[' {
+            let start = at;
+            while at < bytes.len() && bytes[at] != b'.' && bytes[at] != b'[' {
+                at += 1;
+            }
+            if at == start {
+                return Err(NbtPathErr::BadPath { offset: start });
+            }
+            steps.push((&path[..start.saturating_sub(1)], NbtPathStep::Key(&path[start..at])));
+        }
+
+        while at < bytes.len() && bytes[at] == b'[' {
+            let start = at;
+            let close = path[start..].find(']').map(move |idx| start + idx)
+                .ok_or(NbtPathErr::BadPath { offset: bytes.len() })?;
+            let index = path[start + 1..close].parse()
+                .map_err(move |_| NbtPathErr::BadPath { offset: start + 1 })?;
+            steps.push((&path[..start], NbtPathStep::Index(index)));
+            at = close + 1;
+        }
+
+        if at < bytes.len() {
+            if bytes[at] != b'.' || at + 1 == bytes.len() {
+                return Err(NbtPathErr::BadPath { offset: at });
+            }
+            at += 1;
+            if bytes[at] == b'.' || bytes[at] == b'[' {
+                return Err(NbtPathErr::BadPath { offset: at });
+            }
+        }
+    }
+
+    Ok(steps)
+}
+
+impl Tag {
+    pub fn get_path(&self, path: &str) -> Result<&Tag, NbtPathErr> {
+        let mut current = self;
+        for (at, step) in parse_nbt_path(path)? {
+            current = match (step, current) {
+                (NbtPathStep::Key(key), Tag::Compound(entries)) => entries.iter()
+                    .find(move |entry| entry.name == key)
+                    .map(move |entry| &entry.payload)
+                    .ok_or_else(move || NbtPathErr::MissingKey { at: at.to_owned(), key: key.to_owned() })?,
+                (NbtPathStep::Index(index), Tag::List(elems)) => elems.get(index)
+                    .ok_or_else(move || NbtPathErr::IndexOutOfBounds { at: at.to_owned(), index, len: elems.len() })?,
+                (step, other) => return Err(nbt_path_wrong_type(at, step, other)),
+            };
+        }
+
+        Ok(current)
+    }
+
+    pub fn get_path_mut(&mut self, path: &str) -> Result<&mut Tag, NbtPathErr> {
+        let mut current = self;
+        for (at, step) in parse_nbt_path(path)? {
+            current = match (step, current) {
+                (NbtPathStep::Key(key), Tag::Compound(entries)) => entries.iter_mut()
+                    .find(move |entry| entry.name == key)
+                    .map(move |entry| &mut entry.payload)
+                    .ok_or_else(move || NbtPathErr::MissingKey { at: at.to_owned(), key: key.to_owned() })?,
+                (NbtPathStep::Index(index), Tag::List(elems)) => {
+                    let len = elems.len();
+                    elems.get_mut(index)
+                        .ok_or_else(move || NbtPathErr::IndexOutOfBounds { at: at.to_owned(), index, len })?
+                }
+                (step, other) => return Err(nbt_path_wrong_type(at, step, other)),
+            };
+        }
+
+        Ok(current)
+    }
+}
+
+fn nbt_path_wrong_type(at: &str, step: NbtPathStep<'_>, found: &Tag) -> NbtPathErr {
+    let at = at.to_owned();
+    let found = found.tag_type_name().to_owned();
+    match step {
+        NbtPathStep::Key(_) => NbtPathErr::NotCompound { at, found },
+        NbtPathStep::Index(_) => NbtPathErr::NotList { at, found },
+    }
+}
+
+// SNBT, the stringified form of NBT used in commands
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SnbtErr {
+    UnexpectedEnd { offset: usize },
+    UnexpectedChar { offset: usize, found: char, expected: &'static str },
+    BadEscape { offset: usize, found: char },
+    MixedList { offset: usize },
+    NotCompound { offset: usize },
+    TrailingData { offset: usize },
+}
+
+impl SnbtErr {
+    pub fn offset(&self) -> usize {
+        use SnbtErr::*;
+        match self {
+            UnexpectedEnd { offset } => *offset,
+            UnexpectedChar { offset, .. } => *offset,
+            BadEscape { offset, .. } => *offset,
+            MixedList { offset } => *offset,
+            NotCompound { offset } => *offset,
+            TrailingData { offset } => *offset,
+        }
+    }
+}
+
+impl fmt::Display for SnbtErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use SnbtErr::*;
+        match self {
+            UnexpectedEnd { offset } => f.write_fmt(format_args!("unexpected end of snbt at offset {}", offset)),
+            UnexpectedChar { offset, found, expected } => f.write_fmt(format_args!(
+                "unexpected {:?} at offset {}, expected {}", found, offset, expected
+            )),
+            BadEscape { offset, found } => f.write_fmt(format_args!("invalid escape \\{} at offset {}", found, offset)),
+            MixedList { offset } => f.write_fmt(format_args!("list element at offset {} has a different type", offset)),
+            NotCompound { offset } => f.write_fmt(format_args!("value at offset {} is not a compound", offset)),
+            TrailingData { offset } => f.write_fmt(format_args!("unexpected data after snbt value at offset {}", offset)),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SnbtErr {}
+
+impl Tag {
+    pub fn to_snbt(&self) -> String {
+        let mut out = String::new();
+        self.write_snbt(&mut out);
+        out
+    }
+
+    fn write_snbt(&self, out: &mut String) {
+        match self {
+            Tag::Byte(v) => out.push_str(format!("{}b", v).as_str()),
+            Tag::Short(v) => out.push_str(format!("{}s", v).as_str()),
+            Tag::Int(v) => out.push_str(format!("{}", v).as_str()),
+            Tag::Long(v) => out.push_str(format!("{}L", v).as_str()),
+            Tag::Float(v) => out.push_str(format!("{}f", v).as_str()),
+            Tag::Double(v) => out.push_str(format!("{}d", v).as_str()),
+            Tag::ByteArray(v) => write_snbt_array(out, 'B', v.iter().map(move |b| format!("{}b", *b as i8))),
+            Tag::String(v) => write_snbt_string(out, v.as_str()),
+            Tag::List(v) => {
+                out.push('[');
+                for (i, elem) in v.iter().enumerate() {
+                    if i != 0 {
+                        out.push(',');
+                    }
+                    elem.write_snbt(out);
+                }
+                out.push(']');
+            }
+            Tag::Compound(v) => {
+                out.push('{');
+                for (i, elem) in v.iter().enumerate() {
+                    if i != 0 {
+                        out.push(',');
+                    }
+                    write_snbt_key(out, elem.name.as_str());
+                    out.push(':');
+                    elem.payload.write_snbt(out);
+                }
+                out.push('}');
+            }
+            Tag::IntArray(v) => write_snbt_array(out, 'I', v.iter().map(move |i| format!("{}", i))),
+            Tag::LongArray(v) => write_snbt_array(out, 'L', v.iter().map(move |l| format!("{}L", l))),
+            Tag::End => {}
+        }
+    }
+}
+
+// arrays longer than this are cut short when pretty printing
+pub const PRETTY_ARRAY_LIMIT: usize = 16;
+
+const DEFAULT_PRETTY_INDENT: usize = 2;
+
+impl Tag {
+    // SNBT-like rendering for humans, which can't necessarily be parsed back. The alternate flag
+    // ({:#}) puts compound entries and nested lists on their own lines, indented by the width if
+    // one is given ({:#4}) or two spaces otherwise.
+    pub fn fmt_pretty(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let indent = if f.alternate() {
+            Some(f.width().unwrap_or(DEFAULT_PRETTY_INDENT))
+        } else {
+            None
+        };
+
+        let mut out = String::new();
+        self.write_pretty(&mut out, indent, 0);
+        f.write_str(out.as_str())
+    }
+
+    fn write_pretty(&self, out: &mut String, indent: Option<usize>, depth: usize) {
+        match self {
+            Tag::ByteArray(v) => write_pretty_array(out, 'B', v.iter().map(move |b| format!("{}b", *b as i8)), v.len()),
+            Tag::IntArray(v) => write_pretty_array(out, 'I', v.iter().map(move |i| format!("{}", i)), v.len()),
+            Tag::LongArray(v) => write_pretty_array(out, 'L', v.iter().map(move |l| format!("{}L", l)), v.len()),
+            Tag::List(v) => {
+                // lists of plain values stay on one line
+                let nested = v.iter().any(move |elem| matches!(elem, Tag::List(_) | Tag::Compound(_)));
+                let indent = if nested { indent } else { None };
+                write_pretty_seq(out, ('[', ']'), v.iter(), indent, depth, move |out, elem, depth| {
+                    elem.write_pretty(out, indent, depth);
+                });
+            }
+            Tag::Compound(v) => {
+                write_pretty_seq(out, ('{', '}'), v.iter(), indent, depth, move |out, elem, depth| {
+                    write_snbt_key(out, elem.name.as_str());
+                    out.push_str(": ");
+                    elem.payload.write_pretty(out, indent, depth);
+                });
+            }
+            other => other.write_snbt(out),
+        }
+    }
+}
+
+fn write_pretty_seq<T, I, F>(out: &mut String, brackets: (char, char), elems: I, indent: Option<usize>, depth: usize, mut write_elem: F)
+    where I: ExactSizeIterator<Item = T>, F: FnMut(&mut String, T, usize)
+{
+    let (open, close) = brackets;
+    out.push(open);
+    if elems.len() == 0 {
+        out.push(close);
+        return;
+    }
+
+    for (i, elem) in elems.enumerate() {
+        if i != 0 {
+            out.push(',');
+        }
+        match indent {
+            Some(indent) => {
+                out.push('\n');
+                push_spaces(out, indent * (depth + 1));
+            }
+            None if i != 0 => out.push(' '),
+            None => {}
+        }
+        write_elem(out, elem, depth + 1);
+    }
+
+    if let Some(indent) = indent {
+        out.push('\n');
+        push_spaces(out, indent * depth);
+    }
+    out.push(close);
+}
+
+fn write_pretty_array<I>(out: &mut String, kind: char, elems: I, len: usize) where I: Iterator<Item = String> {
+    out.push('[');
+    out.push(kind);
+    out.push(';');
+    for (i, elem) in elems.take(PRETTY_ARRAY_LIMIT).enumerate() {
+        out.push_str(if i == 0 { " " } else { ", " });
+        out.push_str(elem.as_str());
+    }
+    if len > PRETTY_ARRAY_LIMIT {
+        out.push_str(format!(", ... ({} more)", len - PRETTY_ARRAY_LIMIT).as_str());
+    }
+    out.push(']');
+}
+
+fn push_spaces(out: &mut String, n: usize) {
+    for _ in 0..n {
+        out.push(' ');
+    }
+}
+
+impl NamedTag {
+    pub fn get_path(&self, path: &str) -> Result<&Tag, NbtPathErr> {
+        self.payload.get_path(path)
+    }
+
+    pub fn get_path_mut(&mut self, path: &str) -> Result<&mut Tag, NbtPathErr> {
+        self.payload.get_path_mut(path)
+    }
+}
+
+impl NamedTag {
+    // SNBT has no notion of names, so only the payload is written
+    pub fn to_snbt(&self) -> String {
+        self.payload.to_snbt()
+    }
+}
+
+fn write_snbt_array<I>(out: &mut String, kind: char, elems: I) where I: Iterator<Item = String> {
+    out.push('[');
+    out.push(kind);
+    out.push(';');
+    for (i, elem) in elems.enumerate() {
+        if i != 0 {
+            out.push(',');
+        }
+        out.push_str(elem.as_str());
+    }
+    out.push(']');
+}
+
+fn write_snbt_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        if c == '"' || c == '\\' {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('"');
+}
+
+fn write_snbt_key(out: &mut String, name: &str) {
+    if !name.is_empty() && name.chars().all(is_snbt_unquoted_char) {
+        out.push_str(name);
+    } else {
+        write_snbt_string(out, name);
+    }
+}
+
+fn is_snbt_unquoted_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.' || c == '+'
+}
+
+pub fn parse_snbt(input: &str) -> Result<Tag, SnbtErr> {
+    let mut parser = SnbtParser { chars: input.chars().collect(), at: 0 };
+    let tag = parser.value()?;
+    parser.skip_whitespace();
+    if parser.at < parser.chars.len() {
+        return Err(SnbtErr::TrailingData { offset: parser.at });
+    }
+
+    Ok(tag)
+}
+
+struct SnbtParser {
+    chars: Vec<char>,
+    at: usize,
+}
+
+impl SnbtParser {
+    fn skip_whitespace(&mut self) {
+        while self.chars.get(self.at).map(|c| c.is_whitespace()).unwrap_or(false) {
+            self.at += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Result<char, SnbtErr> {
+        self.skip_whitespace();
+        self.chars.get(self.at).copied().ok_or(SnbtErr::UnexpectedEnd { offset: self.at })
+    }
+
+    fn expect(&mut self, c: char, expected: &'static str) -> Result<(), SnbtErr> {
+        let found = self.peek()?;
+        if found != c {
+            return Err(SnbtErr::UnexpectedChar { offset: self.at, found, expected });
+        }
+
+        self.at += 1;
+        Ok(())
+    }
+
+    // consumes the separator before the next element, returns false at the end of the sequence
+    fn next_elem(&mut self, first: bool, close: char, expected: &'static str) -> Result<bool, SnbtErr> {
+        let c = self.peek()?;
+        if c == close {
+            self.at += 1;
+            return Ok(false);
+        }
+
+        if !first {
+            self.expect(',', expected)?;
+        }
+        Ok(true)
+    }
+
+    fn value(&mut self) -> Result<Tag, SnbtErr> {
+        match self.peek()? {
+            '{' => self.compound(),
+            '[' => self.list_or_array(),
+            '"' | '\'' => Ok(Tag::String(self.quoted()?)),
+            _ => self.unquoted_value(),
+        }
+    }
+
+    fn compound(&mut self) -> Result<Tag, SnbtErr> {
+        self.expect('{', "'{'")?;
+        let mut entries = Vec::new();
+        while self.next_elem(entries.is_empty(), '}', "',' or '}'")? {
+            let name = match self.peek()? {
+                '"' | '\'' => self.quoted()?,
+                _ => self.unquoted()?,
+            };
+            self.expect(':', "':'")?;
+            entries.push(NamedTag { name, payload: self.value()? });
+        }
+
+        Ok(Tag::Compound(entries))
+    }
+
+    fn list_or_array(&mut self) -> Result<Tag, SnbtErr> {
+        self.expect('[', "'['")?;
+        let kind = self.chars.get(self.at).copied();
+        if self.chars.get(self.at + 1) == Some(&';') {
+            if let Some(kind @ ('B' | 'I' | 'L')) = kind {
+                self.at += 2;
+                return self.array(kind);
+            }
+        }
+
+        let mut elems: Vec<Tag> = Vec::new();
+        while self.next_elem(elems.is_empty(), ']', "',' or ']'")? {
+            self.skip_whitespace();
+            let offset = self.at;
+            let elem = self.value()?;
+            if elems.first().map(|first| first.id() != elem.id()).unwrap_or(false) {
+                return Err(SnbtErr::MixedList { offset });
+            }
+            elems.push(elem);
+        }
+
+        Ok(Tag::List(elems))
+    }
+
+    fn array(&mut self, kind: char) -> Result<Tag, SnbtErr> {
+        let mut bytes = Vec::new();
+        let mut ints = Vec::new();
+        let mut longs = Vec::new();
+        let mut first = true;
+        while self.next_elem(first, ']', "',' or ']'")? {
+            first = false;
+            self.skip_whitespace();
+            let offset = self.at;
+            match (kind, self.value()?) {
+                ('B', Tag::Byte(b)) => bytes.push(b as u8),
+                ('I', Tag::Int(i)) => ints.push(i),
+                ('L', Tag::Long(l)) => longs.push(l),
+                _ => return Err(SnbtErr::MixedList { offset }),
+            }
+        }
+
+        Ok(match kind {
+            'B' => Tag::ByteArray(bytes),
+            'I' => Tag::IntArray(ints),
+            _ => Tag::LongArray(longs),
+        })
+    }
+
+    fn quoted(&mut self) -> Result<String, SnbtErr> {
+        let quote = self.peek()?;
+        self.at += 1;
+        let mut out = String::new();
+        loop {
+            let c = *self.chars.get(self.at).ok_or(SnbtErr::UnexpectedEnd { offset: self.at })?;
+            self.at += 1;
+            if c == quote {
+                return Ok(out);
+            }
+
+            if c == '\\' {
+                let escaped = *self.chars.get(self.at).ok_or(SnbtErr::UnexpectedEnd { offset: self.at })?;
+                if escaped != '\\' && escaped != '"' && escaped != '\'' {
+                    return Err(SnbtErr::BadEscape { offset: self.at - 1, found: escaped });
+                }
+                self.at += 1;
+                out.push(escaped);
+            } else {
+                out.push(c);
+            }
+        }
+    }
+
+    fn unquoted(&mut self) -> Result<String, SnbtErr> {
+        let start = self.at;
+        while self.chars.get(self.at).copied().map(is_snbt_unquoted_char).unwrap_or(false) {
+            self.at += 1;
+        }
+
+        if self.at == start {
+            return Err(match self.chars.get(self.at) {
+                Some(found) => SnbtErr::UnexpectedChar { offset: self.at, found: *found, expected: "a value" },
+                None => SnbtErr::UnexpectedEnd { offset: self.at },
+            });
+        }
+
+        Ok(self.chars[start..self.at].iter().collect())
+    }
+
+    // like vanilla, anything that doesn't look like a number is a string
+    fn unquoted_value(&mut self) -> Result<Tag, SnbtErr> {
+        let token = self.unquoted()?;
+        Ok(parse_snbt_number(token.as_str()).unwrap_or(Tag::String(token)))
+    }
+}
+
+fn parse_snbt_number(token: &str) -> Option<Tag> {
+    match token {
+        "true" => return Some(Tag::Byte(1)),
+        "false" => return Some(Tag::Byte(0)),
+        _ => {}
+    }
+
+    let is_integer = |s: &str| {
+        let digits = s.strip_prefix(['-', '+']).unwrap_or(s);
+        !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+    };
+    let is_decimal = |s: &str| {
+        let mantissa = s.split(['e', 'E']).next().unwrap_or(s);
+        s.chars().any(|c| c.is_ascii_digit())
+            && mantissa.chars().all(|c| c.is_ascii_digit() || c == '.' || c == '-' || c == '+')
+            && !s.ends_with(['e', 'E'])
+    };
+
+    let (body, suffix) = token.split_at(token.len() - 1);
+    match suffix {
+        "b" | "B" if is_integer(body) => body.parse().ok().map(Tag::Byte),
+        "s" | "S" if is_integer(body) => body.parse().ok().map(Tag::Short),
+        "l" | "L" if is_integer(body) => body.parse().ok().map(Tag::Long),
+        "f" | "F" if is_decimal(body) => body.parse().ok().map(Tag::Float),
+        "d" | "D" if is_decimal(body) => body.parse().ok().map(Tag::Double),
+        _ if is_integer(token) => token.parse().ok().map(Tag::Int),
+        _ if is_decimal(token) => token.parse().ok().map(Tag::Double),
+        _ => None,
+    }
+}
+
 // test
 #[cfg(test)]
 mod tests {
@@ -541,6 +1369,32 @@ mod tests {
         assert_eq!(unserialized, result);
     }
 
+    #[test]
+    fn test_hostile_lengths() {
+        let header = |tag_type: u8, length: i32| {
+            let mut data = alloc::vec![tag_type];
+            data.extend_from_slice(&length.to_be_bytes());
+            data.extend_from_slice(&[1, 2, 3]);
+            data
+        };
+
+        // list of ints, then list of end tags
+        assert!(matches!(read_tag(0x09, header(0x03, i32::MAX).as_slice()), Err(DeserializeErr::Eof)));
+        assert!(matches!(read_tag(0x09, header(0x03, -1).as_slice()), Err(DeserializeErr::NbtBadLength(-1))));
+        assert!(matches!(read_tag(0x09, header(0x00, i32::MAX).as_slice()), Err(DeserializeErr::CannotUnderstandValue(_))));
+        assert!(matches!(read_tag(0x09, header(0x00, 0).as_slice()).map(|d| d.value), Ok(Tag::List(list)) if list.is_empty()));
+
+        let array = |length: i32| {
+            let mut data = length.to_be_bytes().to_vec();
+            data.extend_from_slice(&[1, 2, 3]);
+            data
+        };
+        for tag_type in [0x07, 0x0B, 0x0C].iter() {
+            assert!(read_tag(*tag_type, array(i32::MAX).as_slice()).is_err());
+            assert!(read_tag(*tag_type, array(-5).as_slice()).is_err());
+        }
+    }
+
     #[test]
     fn test_int_array() {
         let original = Tag::Compound(vec![Tag::IntArray(vec![
@@ -583,6 +1437,216 @@ mod tests {
         assert_eq!(original, unserialized);
     }
 
+    #[test]
+    fn test_heightmap_compound() {
+        // 256 columns of 9 bit heights, packed 7 to a long
+        let motion_blocking: Vec<i64> = (0..37).map(move |i: i64| i * 0x0101_0101_0101).collect();
+        let mut heightmaps = Tag::Compound(vec![]);
+        heightmaps.insert("MOTION_BLOCKING", Tag::LongArray(motion_blocking.clone()));
+        heightmaps.get_or_insert_with("WORLD_SURFACE", move || Tag::LongArray(vec![0; 37]));
+        if let Some(Tag::LongArray(surface)) = heightmaps.get_or_insert_with("WORLD_SURFACE", move || Tag::Int(0)) {
+            surface[0] = -1;
+        }
+
+        let original = heightmaps.with_name("");
+        let bytes = original.bytes();
+        let Deserialized { value: read, data: rest } = NamedTag::root_compound_tag_from_bytes(bytes.as_slice())
+            .expect("deserialize heightmaps");
+        assert!(rest.is_empty());
+        assert_eq!(read, original);
+        assert_eq!(read.bytes(), bytes);
+
+        let tag = &read.payload;
+        assert_eq!(tag.keys().collect::<Vec<_>>(), vec!["MOTION_BLOCKING", "WORLD_SURFACE"]);
+        assert_eq!(tag.get_long_array("MOTION_BLOCKING"), Some(motion_blocking.as_slice()));
+        assert_eq!(tag.get_long_array("WORLD_SURFACE").map(|v| (v.len(), v[0])), Some((37, -1)));
+        assert_eq!(tag.get_int_array("MOTION_BLOCKING"), None);
+        assert_eq!(tag.get_long_array("OCEAN_FLOOR"), None);
+        assert_eq!(tag.iter().count(), 2);
+        assert_eq!(Tag::Int(1).keys().count(), 0);
+        assert!(Tag::Int(1).get_or_insert_with("x", move || Tag::Int(2)).is_none());
+
+        let arrays = Tag::Compound(vec![
+            Tag::ByteArray(vec![1, 2]).with_name("bytes"),
+            Tag::IntArray(vec![3, 4]).with_name("ints"),
+        ]);
+        assert_eq!(arrays.get_byte_array("bytes"), Some(&[1u8, 2][..]));
+        assert_eq!(arrays.get_int_array("ints"), Some(&[3, 4][..]));
+    }
+
+    #[test]
+    fn test_typed_getters() {
+        let tag = parse_snbt(r#"{b:1b,s:2s,i:3,l:4L,f:5.5f,d:6.5d,str:"x",c:{inner:7},list:[1,2],ba:[B;1b],ia:[I;2],la:[L;3L]}"#)
+            .expect("valid snbt");
+
+        assert_eq!(tag.get_i8("b"), Some(1));
+        assert_eq!(tag.get_i16("s"), Some(2));
+        assert_eq!(tag.get_i32("i"), Some(3));
+        assert_eq!(tag.get_i64("l"), Some(4));
+        assert_eq!(tag.get_f32("f"), Some(5.5));
+        assert_eq!(tag.get_f64("d"), Some(6.5));
+        assert_eq!(tag.get_str("str"), Some("x"));
+        assert_eq!(tag.get_compound("c").and_then(|c| c.get_i32("inner")), Some(7));
+        assert_eq!(tag.get_list("list"), Some(&[Tag::Int(1), Tag::Int(2)][..]));
+        assert_eq!(tag.get_byte_array("ba"), Some(&[1u8][..]));
+        assert_eq!(tag.get_int_array("ia"), Some(&[2][..]));
+        assert_eq!(tag.get_long_array("la"), Some(&[3i64][..]));
+
+        assert_eq!(tag.get_i8("s"), None);
+        assert_eq!(tag.get_i16("i"), None);
+        assert_eq!(tag.get_i32("l"), None);
+        assert_eq!(tag.get_i64("i"), None);
+        assert_eq!(tag.get_f32("d"), None);
+        assert_eq!(tag.get_f64("f"), None);
+        assert_eq!(tag.get_str("c"), None);
+        assert_eq!(tag.get_compound("str"), None);
+        assert_eq!(tag.get_list("la"), None);
+        assert_eq!(tag.get_byte_array("list"), None);
+        assert_eq!(tag.get_int_array("la"), None);
+        assert_eq!(tag.get_long_array("ia"), None);
+        assert_eq!(tag.get_i32("missing"), None);
+        assert_eq!(Tag::Int(1).get_i32("i"), None);
+    }
+
+    #[test]
+    fn test_try_into() {
+        assert_eq!(Tag::Byte(1).try_into_i8(), Ok(1));
+        assert_eq!(Tag::Short(2).try_into_i16(), Ok(2));
+        assert_eq!(Tag::Int(3).try_into_i32(), Ok(3));
+        assert_eq!(Tag::Long(4).try_into_i64(), Ok(4));
+        assert_eq!(Tag::Float(5.0).try_into_f32(), Ok(5.0));
+        assert_eq!(Tag::Double(6.0).try_into_f64(), Ok(6.0));
+        assert_eq!(Tag::String("x".to_owned()).try_into_string(), Ok("x".to_owned()));
+        assert_eq!(Tag::List(vec![Tag::End]).try_into_list(), Ok(vec![Tag::End]));
+        assert_eq!(Tag::Compound(vec![]).try_into_compound(), Ok(vec![]));
+        assert_eq!(Tag::ByteArray(vec![1]).try_into_byte_array(), Ok(vec![1]));
+        assert_eq!(Tag::IntArray(vec![2]).try_into_int_array(), Ok(vec![2]));
+        assert_eq!(Tag::LongArray(vec![3]).try_into_long_array(), Ok(vec![3]));
+
+        let err = Tag::Short(2).try_into_i32().unwrap_err();
+        assert_eq!(err, NbtTypeErr { expected: "Int", found: "Short" });
+        assert_eq!(format!("{}", err), "expected Int tag but found Short");
+        assert_eq!(Tag::Int(1).try_into_string().unwrap_err().found, "Int");
+        assert_eq!(Tag::IntArray(vec![]).try_into_long_array().unwrap_err().found, "Int_Array");
+    }
+
+    fn path_test_tag() -> Tag {
+        parse_snbt(r#"{tag:{display:{Name:"sword",Lore:["a","b"]}},pages:["one","two","three"],grid:[[1,2],[3]],n:5}"#)
+            .expect("valid snbt")
+    }
+
+    #[test]
+    fn test_get_path() {
+        let tag = path_test_tag();
+        assert_eq!(tag.get_path("tag.display.Name"), Ok(&Tag::String("sword".to_owned())));
+        assert_eq!(tag.get_path("tag.display.Lore[1]"), Ok(&Tag::String("b".to_owned())));
+        assert_eq!(tag.get_path("pages[2]"), Ok(&Tag::String("three".to_owned())));
+        assert_eq!(tag.get_path("grid[0][1]"), Ok(&Tag::Int(2)));
+        assert_eq!(tag.get_path(""), Ok(&tag));
+        assert_eq!(Tag::List(vec![tag.clone()]).get_path("[0].n"), Ok(&Tag::Int(5)));
+
+        let named = NamedTag { name: "".to_owned(), payload: tag };
+        assert_eq!(named.get_path("n"), Ok(&Tag::Int(5)));
+    }
+
+    #[test]
+    fn test_get_path_mut() {
+        let mut tag = path_test_tag();
+        *tag.get_path_mut("tag.display.Lore[0]").expect("exists") = Tag::String("edited".to_owned());
+        tag.get_path_mut("tag.display").expect("exists").insert("color", Tag::Int(3));
+
+        assert_eq!(tag.get_path("tag.display.Lore[0]"), Ok(&Tag::String("edited".to_owned())));
+        assert_eq!(tag.get_path("tag.display.color"), Ok(&Tag::Int(3)));
+        assert_eq!(
+            tag.get_path_mut("grid[1][4]"),
+            Err(NbtPathErr::IndexOutOfBounds { at: "grid[1]".to_owned(), index: 4, len: 1 })
+        );
+    }
+
+    #[test]
+    fn test_get_path_errors() {
+        let tag = path_test_tag();
+        let message = |path: &str| format!("{}", tag.get_path(path).unwrap_err());
+
+        assert_eq!(message("tag.display.Nmae"), "no key \"Nmae\" in compound at tag.display");
+        assert_eq!(message("missing"), "no key \"missing\" in compound at <root>");
+        assert_eq!(message("n.x"), "expected a compound at n but found Int");
+        assert_eq!(message("tag[0]"), "expected a list at tag but found Compound");
+        assert_eq!(message("pages[3]"), "index 3 is out of bounds for list of length 3 at pages");
+        assert_eq!(message("pages[x]"), "invalid nbt path at offset 6");
+        assert_eq!(message("tag..display"), "invalid nbt path at offset 4");
+        assert_eq!(message("tag."), "invalid nbt path at offset 3");
+        assert_eq!(message("pages[0"), "invalid nbt path at offset 7");
+    }
+
+    #[test]
+    fn test_snbt_round_trip_all_tags() {
+        let tag = Tag::Compound(vec![
+            Tag::Byte(-3).with_name("byte"),
+            Tag::Short(300).with_name("short"),
+            Tag::Int(-70000).with_name("int"),
+            Tag::Long(1 << 40).with_name("long"),
+            Tag::Float(0.1).with_name("float"),
+            Tag::Double(-2.5e-10).with_name("double"),
+            Tag::ByteArray(vec![0, 1, 255]).with_name("byte array"),
+            Tag::String("say \"hi\" \\ bye".to_owned()).with_name("string"),
+            Tag::List(vec![Tag::List(vec![Tag::Int(1)]), Tag::List(vec![])]).with_name("nested"),
+            Tag::List(vec![Tag::Compound(vec![]), Tag::Compound(vec![Tag::Int(1).with_name("x")])]).with_name("compounds"),
+            Tag::IntArray(vec![1, -2, 3]).with_name("ints"),
+            Tag::LongArray(vec![i64::MIN, i64::MAX]).with_name("longs"),
+        ]);
+
+        let snbt = tag.to_snbt();
+        assert_eq!(parse_snbt(snbt.as_str()).expect("parses"), tag);
+        assert!(snbt.contains("byte:-3b"));
+        assert!(snbt.contains("\"byte array\":[B;0b,1b,-1b]"));
+        assert!(snbt.contains("longs:[L;-9223372036854775808L,9223372036854775807L]"));
+    }
+
+    #[test]
+    fn test_snbt_parses_item_nbt() {
+        let snbt = r#"{Damage:12,Unbreakable:1b,display:{Name:'{"text":"It\'s \\ sharp","color":"gold"}',Lore:["{\"text\":\"line\"}"]},
+            Enchantments:[{id:"minecraft:sharpness",lvl:5s},{id:'minecraft:looting',lvl:3S}],
+            AttributeModifiers:[{Amount:1.5D,Operation:0,UUID:[I;1,-2,3,4],"Attribute Name":generic.attack_damage}],
+            CustomModelData:7L,HideFlags:false,Scale:2.0F,Flag:true}"#;
+        let tag = parse_snbt(snbt).expect("parses");
+        assert_eq!(
+            tag.get("display").and_then(|d| d.get("Name")),
+            Some(&Tag::String(r#"{"text":"It's \ sharp","color":"gold"}"#.to_owned()))
+        );
+        let enchantments = match tag.get("Enchantments") {
+            Some(Tag::List(v)) => v,
+            other => panic!("expected list, got {:?}", other),
+        };
+        assert_eq!(enchantments[1].get("id"), Some(&Tag::String("minecraft:looting".to_owned())));
+        assert_eq!(enchantments[1].get("lvl"), Some(&Tag::Short(3)));
+        assert_eq!(tag.get("HideFlags"), Some(&Tag::Byte(0)));
+        assert_eq!(tag.get("Flag"), Some(&Tag::Byte(1)));
+        assert_eq!(tag.get("Scale"), Some(&Tag::Float(2.0)));
+        assert_eq!(tag.get("CustomModelData"), Some(&Tag::Long(7)));
+
+        assert_eq!(parse_snbt(tag.to_snbt().as_str()).expect("round trips"), tag);
+    }
+
+    #[test]
+    fn test_snbt_errors_have_offsets() {
+        assert_eq!(parse_snbt("{a:1,b:[1,2b]}"), Err(SnbtErr::MixedList { offset: 10 }));
+        assert_eq!(parse_snbt("{a:1 b:2}"), Err(SnbtErr::UnexpectedChar { offset: 5, found: 'b', expected: "',' or '}'" }));
+        assert_eq!(parse_snbt("{a:\"\\n\"}"), Err(SnbtErr::BadEscape { offset: 4, found: 'n' }));
+        assert_eq!(parse_snbt("[I;1,2"), Err(SnbtErr::UnexpectedEnd { offset: 6 }));
+        assert_eq!(parse_snbt("{} x"), Err(SnbtErr::TrailingData { offset: 3 }));
+        assert_eq!(parse_snbt("[I;1,2L]"), Err(SnbtErr::MixedList { offset: 5 }));
+        assert!(format!("{}", parse_snbt("{a:}").unwrap_err()).contains("offset 3"));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_snbt_round_trip_bigtest() {
+        let bigtest = read_bigtest();
+        let parsed = parse_snbt(bigtest.to_snbt().as_str()).expect("parses");
+        assert_eq!(parsed, bigtest.payload);
+    }
+
     #[cfg(feature = "std")]
     #[test]
     fn test_display() {
@@ -87,6 +87,123 @@ pub struct ProtocolPacketField {
     pub kind: String,
 }
 
+// One entry of the static packet table each protocol has (`Packet578::packets()` for example),
+// which unlike `describe()` can be listed without allocating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PacketDescription {
+    pub id: Id,
+    pub name: &'static str,
+    pub body_struct: &'static str,
+}
+
+impl PacketDescription {
+    pub fn body_struct_name(&self) -> &'static str {
+        self.body_struct
+    }
+}
+
+// Per packet hashes of the field layout and of the encoding of a fixed-seed random body. A change
+// in any of them means the wire format of that packet changed.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ProtocolFingerprint {
+    pub packets: alloc::collections::BTreeMap<String, u64>,
+}
+
+impl ProtocolFingerprint {
+    pub fn to_fixture(&self) -> String {
+        let mut out = String::new();
+        for (name, hash) in &self.packets {
+            out.push_str(alloc::format!("{} {:016x}\n", name, hash).as_str());
+        }
+        out
+    }
+
+    pub fn from_fixture(fixture: &str) -> Option<Self> {
+        let mut packets = alloc::collections::BTreeMap::new();
+        for line in fixture.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let mut parts = line.split_whitespace();
+            let name = parts.next()?;
+            let hash = u64::from_str_radix(parts.next()?, 16).ok()?;
+            if parts.next().is_some() {
+                return None;
+            }
+            packets.insert(name.to_owned(), hash);
+        }
+
+        Some(Self { packets })
+    }
+
+    pub fn diff(&self, expected: &Self) -> Vec<String> {
+        let mut out = Vec::new();
+        for (name, hash) in &self.packets {
+            match expected.packets.get(name) {
+                Some(expected_hash) if expected_hash == hash => {}
+                Some(expected_hash) => out.push(alloc::format!(
+                    "{}: fingerprint changed from {:016x} to {:016x}",
+                    name, expected_hash, hash
+                )),
+                None => out.push(alloc::format!("{}: new packet with fingerprint {:016x}", name, hash)),
+            }
+        }
+
+        for name in expected.packets.keys() {
+            if !self.packets.contains_key(name) {
+                out.push(alloc::format!("{}: packet was removed", name));
+            }
+        }
+
+        out
+    }
+}
+
+// 64-bit FNV-1a, used for fingerprints because, unlike the standard library's hashers, its output
+// is fixed. The random bodies behind a fingerprint are only reproducible because they come from
+// SeededRng and are generated with fixed-width integers, never usize.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FingerprintHasher {
+    state: u64,
+}
+
+impl Default for FingerprintHasher {
+    fn default() -> Self {
+        Self { state: 0xcbf29ce484222325 }
+    }
+}
+
+impl FingerprintHasher {
+    pub fn write(&mut self, data: &[u8]) {
+        for byte in data {
+            self.state ^= *byte as u64;
+            self.state = self.state.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    // hashes the string with its whitespace removed, followed by a separator
+    pub fn write_str(&mut self, data: &str) {
+        for part in data.split_whitespace() {
+            self.write(part.as_bytes());
+        }
+        self.write(&[0xFF]);
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.state
+    }
+}
+
+impl Serializer for FingerprintHasher {
+    fn serialize_bytes(&mut self, data: &[u8]) -> SerializeResult {
+        self.write(data);
+        Ok(())
+    }
+}
+
+pub fn fingerprint_hash(data: &[u8]) -> u64 {
+    let mut hasher = FingerprintHasher::default();
+    hasher.write(data);
+    hasher.finish()
+}
+
 pub trait HasPacketKind {
     type Kind: PacketKind;
 
@@ -114,6 +231,12 @@ pub trait HasPacketId {
 pub trait HasPacketBody {
 
     fn mc_serialize_body<S>(&self, to: &mut S) -> SerializeResult where S: Serializer;
+
+    fn mc_body_size(&self) -> usize {
+        let mut counter = crate::types::CountingSerializer::default();
+        let _ = self.mc_serialize_body(&mut counter);
+        counter.count
+    }
 }
 
 pub trait RawPacket<'a>: HasPacketId + Sized {
@@ -125,9 +248,50 @@ pub trait RawPacket<'a>: HasPacketId + Sized {
     fn data(&self) -> &'a [u8];
 
     fn deserialize(&self) -> Result<Self::Packet, PacketErr>;
+
+    // reads one frame into `buf` and creates the raw packet over it, the state and direction aren't
+    // on the wire so the caller has to say which ones it expects
+    #[cfg(feature = "std")]
+    fn read_from<R: std::io::Read>(
+        r: &mut R,
+        buf: &'a mut Vec<u8>,
+        max_len: usize,
+        state: State,
+        direction: PacketDirection,
+    ) -> Result<Self, crate::connection::ReadFrameErr> {
+        use crate::connection::{read_frame, ReadFrameErr};
+
+        *buf = read_frame(r, max_len)?;
+        let frame: &'a Vec<u8> = buf;
+        let crate::Deserialized { value: id, data } = crate::types::VarInt::mc_deserialize(frame.as_slice())
+            .map_err(ReadFrameErr::BadPacketId)?;
+        Self::create(Id { id: id.0, state, direction }, data).map_err(ReadFrameErr::Packet)
+    }
+}
+
+pub trait Packet: HasPacketId + HasPacketBody + Sized {
+
+    // the packet id followed by the body, without the frame length prefix
+    fn serialized_size(&self) -> usize {
+        crate::types::VarInt(self.id().id).len() + self.mc_body_size()
+    }
 }
 
-pub trait Packet: HasPacketId + HasPacketBody + Sized {}
+// version agnostic view of a typed packet, so middleware can be written once for every protocol
+pub trait MinecraftPacket {
+
+    fn protocol_version() -> i32 where Self: Sized;
+
+    fn direction(&self) -> PacketDirection;
+
+    fn state(&self) -> State;
+
+    fn packet_id(&self) -> i32;
+
+    fn serialize_body(&self) -> Result<Vec<u8>, crate::SerializeErr>;
+
+    fn packet_name(&self) -> &'static str;
+}
 
 pub enum PacketErr {
     UnknownId(Id),
@@ -164,11 +328,119 @@ pub trait ProtocolType: Serialize + Deserialize {}
 
 impl<T: Serialize + Deserialize> ProtocolType for T {}
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 pub trait TestRandom {
     fn test_gen_random() -> Self;
 }
 
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
+std::thread_local! {
+    static TEST_SEEDED_RNG: core::cell::RefCell<Option<SeededRng>> = const { core::cell::RefCell::new(None) };
+}
+
+// SplitMix64. Seeded values, and so fingerprints, only depend on this and on rand's distributions,
+// which rand keeps value-stable within a minor version. rand's StdRng makes no such promise.
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
+#[derive(Debug, Clone)]
+pub struct SeededRng {
+    state: u64,
+}
+
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+}
+
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
+impl rand::RngCore for SeededRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+// All TestRandom implementations draw from this rng. It is the thread rng unless a closure is running
+// under `with_test_seed`, in which case values are reproducible for the given seed.
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
+pub struct TestRng;
+
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
+impl rand::RngCore for TestRng {
+    fn next_u32(&mut self) -> u32 {
+        Self::with(move |rng| rng.next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        Self::with(move |rng| rng.next_u64())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        Self::with(move |rng| rng.fill_bytes(dest))
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        Self::with(move |rng| rng.try_fill_bytes(dest))
+    }
+}
+
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
+impl TestRng {
+    fn with<F, R>(f: F) -> R where F: FnOnce(&mut dyn rand::RngCore) -> R {
+        TEST_SEEDED_RNG.with(move |seeded| match seeded.borrow_mut().as_mut() {
+            Some(rng) => f(rng),
+            None => f(&mut rand::thread_rng()),
+        })
+    }
+}
+
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
+pub fn test_rng() -> TestRng {
+    TestRng
+}
+
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
+pub fn test_random<T>() -> T where rand::distributions::Standard: rand::distributions::Distribution<T> {
+    use rand::Rng;
+    test_rng().gen()
+}
+
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
+pub fn with_test_seed<F, R>(seed: u64, f: F) -> R where F: FnOnce() -> R {
+    // puts the previous rng back even if f panics
+    struct RestoreSeed(Option<SeededRng>);
+
+    impl Drop for RestoreSeed {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            TEST_SEEDED_RNG.with(move |seeded| seeded.replace(previous));
+        }
+    }
+
+    let _restore = RestoreSeed(TEST_SEEDED_RNG.with(move |seeded| seeded.replace(Some(SeededRng::new(seed)))));
+    f()
+}
+
 #[macro_export]
 macro_rules! as_item {
     ($i:item) => {
@@ -180,12 +452,17 @@ macro_rules! as_item {
 macro_rules! proto_struct {
     ($bodyt: ident { }) => {
         #[derive(Debug, Clone, PartialEq, Default)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
         pub struct $bodyt;
 
         impl Serialize for $bodyt {
             fn mc_serialize<S: Serializer>(&self, _: &mut S) -> SerializeResult {
                 Ok(())
             }
+
+            fn mc_size(&self) -> usize {
+                0
+            }
         }
 
         impl Deserialize for $bodyt {
@@ -194,7 +471,7 @@ macro_rules! proto_struct {
             }
         }
 
-        #[cfg(all(test, feature = "std"))]
+        #[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
         impl TestRandom for $bodyt {
             fn test_gen_random() -> Self {
                 Self::default()
@@ -202,12 +479,13 @@ macro_rules! proto_struct {
         }
     };
     ($bodyt: ident $(<$($g: ident),*>)? {
-        $($fname: ident: $ftyp: ty ),+
+        $($(#[$fmeta: meta])* $fname: ident: $ftyp: ty ),+
     }) => {
         $crate::as_item! {
             #[derive(Debug, Clone, PartialEq)]
+            #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
             pub struct $bodyt$(<$($g),*> where $($g: alloc::fmt::Debug + Clone + PartialEq),*)? {
-               $(pub $fname: $ftyp),+
+               $($(#[$fmeta])* pub $fname: $ftyp),+
             }
         }
 
@@ -218,6 +496,10 @@ macro_rules! proto_struct {
                 )+
                 Ok(())
             }
+
+            fn mc_size(&self) -> usize {
+                0 $(+ self.$fname.mc_size())+
+            }
         }
 
         impl$(<$($g),*>)? Deserialize for $bodyt$(<$($g),*> where $($g: Deserialize + alloc::fmt::Debug + Clone + PartialEq),*)? {
@@ -243,7 +525,7 @@ macro_rules! proto_struct {
             }
         }
 
-        #[cfg(all(test, feature = "std"))]
+        #[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
         impl$(<$($g),*>)? TestRandom for $bodyt$(<$($g),*> where $($g: TestRandom + alloc::fmt::Debug + Clone + PartialEq),*)? {
             fn test_gen_random() -> Self {
                 Self{ $($fname: <$ftyp>::test_gen_random()),+ }
@@ -256,11 +538,12 @@ macro_rules! proto_struct {
 macro_rules! define_protocol {
     ($version: literal, $packett: ident, $rawpackett: ident, $rawdt: ident, $kindt: ident => {
         $($nam: ident, $id: literal, $state: ident, $direction: ident => $body: ident {
-            $($fnam: ident: $ftyp: ty),* }),*
+            $($(#[$fmeta: meta])* $fnam: ident: $ftyp: ty),* }),*
         }
     ) => {
         $crate::as_item! {
             #[derive(Debug, PartialEq, Clone)]
+            #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
             pub enum $packett {
                 $($nam($body)),*,
             }
@@ -275,6 +558,7 @@ macro_rules! define_protocol {
 
         $crate::as_item! {
             #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+            #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
             pub enum $kindt {
                 $($nam),*,
             }
@@ -307,10 +591,45 @@ macro_rules! define_protocol {
                     $($nam(body) => to.serialize_other(body)),+
                 }
             }
+
+            fn mc_body_size(&self) -> usize {
+                use self::$packett::*;
+                match self {
+                    $($nam(body) => crate::Serialize::mc_size(body)),+
+                }
+            }
         }
 
         impl crate::protocol::Packet for $packett {}
 
+        impl crate::protocol::MinecraftPacket for $packett {
+            fn protocol_version() -> i32 {
+                $version
+            }
+
+            fn direction(&self) -> crate::protocol::PacketDirection {
+                crate::protocol::HasPacketId::id(self).direction
+            }
+
+            fn state(&self) -> crate::protocol::State {
+                crate::protocol::HasPacketId::id(self).state
+            }
+
+            fn packet_id(&self) -> i32 {
+                crate::protocol::HasPacketId::id(self).id
+            }
+
+            fn serialize_body(&self) -> Result<alloc::vec::Vec<u8>, crate::SerializeErr> {
+                let mut out = crate::types::BytesSerializer::default();
+                crate::protocol::HasPacketBody::mc_serialize_body(self, &mut out)?;
+                Ok(out.into_bytes())
+            }
+
+            fn packet_name(&self) -> &'static str {
+                crate::protocol::HasPacketKind::kind(self).name()
+            }
+        }
+
         impl $packett {
             pub fn describe() -> crate::protocol::ProtocolSpec {
                 crate::protocol::ProtocolSpec {
@@ -332,6 +651,59 @@ macro_rules! define_protocol {
                     )
                 }
             }
+
+            pub fn packets() -> &'static [crate::protocol::PacketDescription] {
+                static PACKETS: &[crate::protocol::PacketDescription] = &[
+                    $(crate::protocol::PacketDescription {
+                        id: crate::protocol::Id {
+                            id: $id,
+                            state: crate::protocol::State::$state,
+                            direction: crate::protocol::PacketDirection::$direction,
+                        },
+                        name: stringify!($nam),
+                        body_struct: stringify!($body),
+                    }),*
+                ];
+
+                PACKETS
+            }
+
+            pub fn client_bound_packets() -> impl Iterator<Item = &'static crate::protocol::PacketDescription> {
+                Self::packets().iter()
+                    .filter(|packet| packet.id.direction == crate::protocol::PacketDirection::ClientBound)
+            }
+
+            pub fn server_bound_packets() -> impl Iterator<Item = &'static crate::protocol::PacketDescription> {
+                Self::packets().iter()
+                    .filter(|packet| packet.id.direction == crate::protocol::PacketDirection::ServerBound)
+            }
+
+            pub fn packet_count() -> usize {
+                Self::packets().len()
+            }
+
+            #[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
+            pub fn fingerprint() -> crate::protocol::ProtocolFingerprint {
+                use crate::protocol::{FingerprintHasher, TestRandom, fingerprint_hash, with_test_seed};
+                let mut packets = alloc::collections::BTreeMap::new();
+                $({
+                    let mut hasher = FingerprintHasher::default();
+                    hasher.write_str(stringify!($state));
+                    hasher.write_str(stringify!($direction));
+                    hasher.write(&($id as i32).to_be_bytes());
+                    hasher.write_str(stringify!($body));
+                    $(
+                        hasher.write_str(stringify!($fnam));
+                        hasher.write_str(stringify!($ftyp));
+                    )*
+                    let seed = fingerprint_hash(stringify!($nam).as_bytes());
+                    let body: $body = with_test_seed(seed, <$body as TestRandom>::test_gen_random);
+                    body.mc_serialize(&mut hasher).expect("fingerprint body serializes");
+                    packets.insert(stringify!($nam).to_owned(), hasher.finish());
+                })*
+
+                crate::protocol::ProtocolFingerprint { packets }
+            }
         }
 
         impl<'a> crate::protocol::HasPacketKind for $rawpackett<'a> {
@@ -354,6 +726,26 @@ macro_rules! define_protocol {
             }
         }
 
+        impl<'a> $rawpackett<'a> {
+            // raw packets can only be created for known ids, so the name is always available
+            pub fn packet_name(&self) -> &'static str {
+                crate::protocol::HasPacketKind::kind(self).name()
+            }
+
+            pub fn direction_and_state(&self) -> (crate::protocol::PacketDirection, crate::protocol::State) {
+                let id = crate::protocol::HasPacketId::id(self);
+                (id.direction, id.state)
+            }
+
+            pub fn is_play_packet(&self) -> bool {
+                crate::protocol::HasPacketId::id(self).state == crate::protocol::State::Play
+            }
+
+            pub fn is_login_packet(&self) -> bool {
+                crate::protocol::HasPacketId::id(self).state == crate::protocol::State::Login
+            }
+        }
+
         impl<'a> crate::protocol::RawPacket<'a> for $rawpackett<'a> {
 
             type Packet = $packett;
@@ -453,6 +845,12 @@ macro_rules! define_protocol {
         }
 
         impl $kindt {
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $($kindt::$nam => stringify!($nam)),*,
+                }
+            }
+
             fn with_body_data_inner<'a>(self, data: &'a [u8]) -> $rawpackett<'a> {
                 match self {
                     $($kindt::$nam => $rawpackett::$nam($rawdt{
@@ -463,7 +861,7 @@ macro_rules! define_protocol {
             }
         }
 
-        $($crate::proto_struct!($body { $($fnam: $ftyp),* });)*
+        $($crate::proto_struct!($body { $($(#[$fmeta])* $fnam: $ftyp),* });)*
     };
 }
 
@@ -496,6 +894,7 @@ macro_rules! proto_enum_with_type {
     ($typ: ty, $typname: ident, $(($bval: literal, $nam: ident $(($bod: ty))?)),*) => {
         $crate::as_item! {
             #[derive(PartialEq, Clone, Debug)]
+            #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
             pub enum $typname {
                 $($nam $(($bod))?),*
             }
@@ -556,13 +955,13 @@ macro_rules! proto_enum_with_type {
             }
         }
 
-        #[cfg(all(test, feature = "std"))]
+        #[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
         impl TestRandom for $typname {
             fn test_gen_random() -> Self {
-                let mut rng = rand::thread_rng();
+                let mut rng = $crate::protocol::test_rng();
                 use rand::distributions::Distribution;
-                let distr = rand::distributions::Uniform::new(1, Self::variant_count() + 1);
-                let mut idx: usize = distr.sample(&mut rng);
+                let distr = rand::distributions::Uniform::new(1u32, Self::variant_count() as u32 + 1);
+                let mut idx = distr.sample(&mut rng) as usize;
                 $(
                     idx -= 1;
                     if idx == 0 {
@@ -601,6 +1000,7 @@ macro_rules! proto_str_enum {
     ($typname: ident, $($sval: literal :: $nam: ident $(($bod: ident))?),*) => {
         crate::as_item! {
             #[derive(PartialEq, Clone, Debug)]
+            #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
             pub enum $typname {
                 $($nam $(($bod))?),*
             }
@@ -627,7 +1027,7 @@ macro_rules! proto_str_enum {
                 crate::strip_plus!($(+ crate::instead_of_ident!($sval, 1))+)
             }
 
-            pub fn name(&self) -> &str {
+            pub fn name(&self) -> &'static str {
                 match self {
                     $($typname::$nam$((instead_of_ident!($bod, _)))? => $sval),+,
                 }
@@ -667,13 +1067,13 @@ macro_rules! proto_str_enum {
             }
         }
 
-        #[cfg(all(test, feature = "std"))]
+        #[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
         impl TestRandom for $typname {
             fn test_gen_random() -> Self {
-                let mut rng = rand::thread_rng();
+                let mut rng = $crate::protocol::test_rng();
                 use rand::distributions::Distribution;
-                let distr = rand::distributions::Uniform::new(1, Self::variant_count() + 1);
-                let mut idx: usize = distr.sample(&mut rng);
+                let distr = rand::distributions::Uniform::new(1u32, Self::variant_count() as u32 + 1);
+                let mut idx = distr.sample(&mut rng) as usize;
                 $(
                     idx -= 1;
                     if idx == 0 {
@@ -688,11 +1088,20 @@ macro_rules! proto_str_enum {
 
 #[macro_export]
 macro_rules! proto_byte_flag {
-    ($typname: ident, $($bval: literal :: $isnam: ident $setnam: ident),*) => {
+    ($typname: ident, $($bval: literal :: $isnam: ident $setnam: ident $withnam: ident),*) => {
         #[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
         pub struct $typname(pub u8);
 
         impl $typname {
+            pub fn none() -> Self {
+                $typname(0)
+            }
+
+            pub fn all() -> Self {
+                $typname(0 $(| $bval)*)
+            }
+
             $(pub fn $isnam(&self) -> bool {
                 self.0 & $bval != 0
             }
@@ -703,6 +1112,11 @@ macro_rules! proto_byte_flag {
                 } else {
                     self.0 ^= $bval & self.0;
                 }
+            }
+
+            pub fn $withnam(mut self, value: bool) -> Self {
+                self.$setnam(value);
+                self
             })+
         }
 
@@ -718,15 +1132,241 @@ macro_rules! proto_byte_flag {
             }
         }
 
-        #[cfg(all(test, feature = "std"))]
+        #[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
         impl TestRandom for $typname {
             fn test_gen_random() -> Self {
                 let mut out = <$typname>::default();
                 $(
-                    out.$setnam(rand::random::<bool>());
+                    out.$setnam($crate::protocol::test_random::<bool>());
                 )+
                 out
             }
         }
     }
-}
\ No newline at end of file
+}
+
+// The EntityMetadata helpers which are the same in every protocol version here: lookups and edits
+// by index, applying and computing deltas, and the index constants, flag types and typed accessors
+// for the fields every entity (and every living entity) has. Expanded in the module of a version,
+// next to its EntityMetadata, EntityMetadataFieldData and EntityPose.
+#[macro_export]
+macro_rules! entity_metadata_helpers {
+    () => {
+        impl EntityMetadata {
+            pub fn set(&mut self, index: u8, data: EntityMetadataFieldData) {
+                for field in &mut self.fields {
+                    if field.index == index {
+                        field.data = data;
+                        return;
+                    }
+                }
+
+                self.fields.push(EntityMetadataField{
+                    index,
+                    data,
+                })
+            }
+
+            pub fn with_field(mut self, index: u8, data: EntityMetadataFieldData) -> Self {
+                self.set(index, data);
+                self
+            }
+
+            pub fn remove(&mut self, index: u8) -> Option<EntityMetadataFieldData> {
+                let i = self.fields.iter().position(move |field| field.index == index)?;
+                Some(self.fields.remove(i).data)
+            }
+
+            pub fn contains(&self, index: u8) -> bool {
+                self.fields.iter().any(move |field| field.index == index)
+            }
+
+            pub fn iter(&self) -> impl Iterator<Item = &EntityMetadataField> {
+                self.fields.iter()
+            }
+
+            pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut EntityMetadataField> {
+                self.fields.iter_mut()
+            }
+
+            pub fn get(&self, index: u8) -> Option<&EntityMetadataFieldData> {
+                for field in &self.fields {
+                    if field.index == index {
+                        return Some(&field.data);
+                    }
+                }
+
+                None
+            }
+
+            pub fn get_mut(&mut self, index: u8) -> Option<&mut EntityMetadataFieldData> {
+                for field in &mut self.fields {
+                    if field.index == index {
+                        return Some(&mut field.data);
+                    }
+                }
+
+                None
+            }
+        }
+
+        // metadata indices shared by every entity, and by every living entity
+        pub const METADATA_ENTITY_FLAGS: u8 = 0;
+        pub const METADATA_AIR_TICKS: u8 = 1;
+        pub const METADATA_CUSTOM_NAME: u8 = 2;
+        pub const METADATA_CUSTOM_NAME_VISIBLE: u8 = 3;
+        pub const METADATA_SILENT: u8 = 4;
+        pub const METADATA_NO_GRAVITY: u8 = 5;
+        pub const METADATA_POSE: u8 = 6;
+        pub const METADATA_HAND_STATES: u8 = 7;
+        pub const METADATA_HEALTH: u8 = 8;
+
+        $crate::proto_byte_flag!(EntityFlags,
+            0x01 :: is_on_fire set_on_fire with_on_fire,
+            0x02 :: is_crouching set_crouching with_crouching,
+            0x08 :: is_sprinting set_sprinting with_sprinting,
+            0x10 :: is_swimming set_swimming with_swimming,
+            0x20 :: is_invisible set_invisible with_invisible,
+            0x40 :: is_glowing set_glowing with_glowing,
+            0x80 :: is_elytra_flying set_elytra_flying with_elytra_flying
+        );
+
+        $crate::proto_byte_flag!(LivingEntityHandStates,
+            0x01 :: is_hand_active set_hand_active with_hand_active,
+            0x02 :: is_offhand_active set_offhand_active with_offhand_active,
+            0x04 :: is_riptide_spin_attack set_riptide_spin_attack with_riptide_spin_attack
+        );
+
+        pub enum EntityMetadataErr {
+            Absent(u8),
+            WrongType { index: u8, expected: &'static str, found: String },
+        }
+
+        impl fmt::Display for EntityMetadataErr {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                use EntityMetadataErr::*;
+                match self {
+                    Absent(index) => f.write_fmt(format_args!("metadata index {} is not present", index)),
+                    WrongType { index, expected, found } => f.write_fmt(format_args!(
+                        "metadata index {} should be {} but is {}",
+                        index, expected, found
+                    )),
+                }
+            }
+        }
+
+        impl fmt::Debug for EntityMetadataErr {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                <dyn fmt::Display>::fmt(self, f)
+            }
+        }
+
+        #[cfg(feature = "std")]
+        impl std::error::Error for EntityMetadataErr {}
+
+        impl EntityMetadata {
+            pub fn flags(&self) -> Result<EntityFlags, EntityMetadataErr> {
+                self.typed(METADATA_ENTITY_FLAGS, "Byte", move |data| match data {
+                    EntityMetadataFieldData::Byte(flags) => Some(EntityFlags(*flags as u8)),
+                    _ => None,
+                })
+            }
+
+            pub fn set_flags(&mut self, flags: EntityFlags) {
+                self.set(METADATA_ENTITY_FLAGS, EntityMetadataFieldData::Byte(flags.0 as i8));
+            }
+
+            pub fn air_ticks(&self) -> Result<VarInt, EntityMetadataErr> {
+                self.typed(METADATA_AIR_TICKS, "VarInt", move |data| match data {
+                    EntityMetadataFieldData::VarInt(ticks) => Some(*ticks),
+                    _ => None,
+                })
+            }
+
+            pub fn set_air_ticks(&mut self, ticks: VarInt) {
+                self.set(METADATA_AIR_TICKS, EntityMetadataFieldData::VarInt(ticks));
+            }
+
+            pub fn custom_name(&self) -> Result<Option<&Chat>, EntityMetadataErr> {
+                self.typed(METADATA_CUSTOM_NAME, "OptChat", move |data| match data {
+                    EntityMetadataFieldData::OptChat(name) => Some(name.as_ref()),
+                    _ => None,
+                })
+            }
+
+            pub fn set_custom_name(&mut self, name: Option<Chat>) {
+                self.set(METADATA_CUSTOM_NAME, EntityMetadataFieldData::OptChat(name));
+            }
+
+            pub fn is_custom_name_visible(&self) -> Result<bool, EntityMetadataErr> {
+                self.typed(METADATA_CUSTOM_NAME_VISIBLE, "Boolean", move |data| match data {
+                    EntityMetadataFieldData::Boolean(visible) => Some(*visible),
+                    _ => None,
+                })
+            }
+
+            pub fn set_custom_name_visible(&mut self, visible: bool) {
+                self.set(METADATA_CUSTOM_NAME_VISIBLE, EntityMetadataFieldData::Boolean(visible));
+            }
+
+            pub fn pose(&self) -> Result<EntityPose, EntityMetadataErr> {
+                self.typed(METADATA_POSE, "Pose", move |data| match data {
+                    EntityMetadataFieldData::Pose(pose) => Some(pose.clone()),
+                    _ => None,
+                })
+            }
+
+            pub fn set_pose(&mut self, pose: EntityPose) {
+                self.set(METADATA_POSE, EntityMetadataFieldData::Pose(pose));
+            }
+
+            pub fn hand_states(&self) -> Result<LivingEntityHandStates, EntityMetadataErr> {
+                self.typed(METADATA_HAND_STATES, "Byte", move |data| match data {
+                    EntityMetadataFieldData::Byte(states) => Some(LivingEntityHandStates(*states as u8)),
+                    _ => None,
+                })
+            }
+
+            pub fn set_hand_states(&mut self, states: LivingEntityHandStates) {
+                self.set(METADATA_HAND_STATES, EntityMetadataFieldData::Byte(states.0 as i8));
+            }
+
+            pub fn health(&self) -> Result<f32, EntityMetadataErr> {
+                self.typed(METADATA_HEALTH, "Float", move |data| match data {
+                    EntityMetadataFieldData::Float(health) => Some(*health),
+                    _ => None,
+                })
+            }
+
+            pub fn set_health(&mut self, health: f32) {
+                self.set(METADATA_HEALTH, EntityMetadataFieldData::Float(health));
+            }
+
+            fn typed<'a, T, F>(&'a self, index: u8, expected: &'static str, f: F) -> Result<T, EntityMetadataErr>
+                where F: FnOnce(&'a EntityMetadataFieldData) -> Option<T>
+            {
+                let data = self.get(index).ok_or(EntityMetadataErr::Absent(index))?;
+                f(data).ok_or_else(move || EntityMetadataErr::WrongType { index, expected, found: data.name().to_owned() })
+            }
+        }
+
+        impl EntityMetadata {
+            pub fn apply(&mut self, delta: &EntityMetadata) {
+                for field in &delta.fields {
+                    self.set(field.index, field.data.clone());
+                }
+            }
+
+            // the fields of newer which are missing or different here. Metadata can't remove fields, so
+            // fields only present here are not part of the delta.
+            pub fn diff(&self, newer: &EntityMetadata) -> EntityMetadata {
+                EntityMetadata {
+                    fields: newer.fields.iter()
+                        .filter(|field| self.get(field.index) != Some(&field.data))
+                        .cloned()
+                        .collect(),
+                }
+            }
+        }
+    }
+}
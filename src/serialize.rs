@@ -1,8 +1,9 @@
-use alloc::{string::String, fmt};
+use alloc::{string::String, vec::Vec, fmt};
 
 pub enum SerializeErr {
     FailedJsonEncode(String),
     CannotSerialize(String),
+    BufferFull { needed: usize, remaining: usize },
 }
 
 impl fmt::Display for SerializeErr {
@@ -15,6 +16,9 @@ impl fmt::Display for SerializeErr {
             CannotSerialize(message) => {
                 f.write_fmt(format_args!("cannot serialize value, invalid representation: {:?}", message))
             }
+            BufferFull { needed, remaining } => {
+                f.write_fmt(format_args!("output buffer full, {} bytes needed but only {} remaining", needed, remaining))
+            }
         }
     }
 }
@@ -32,6 +36,20 @@ pub type SerializeResult = Result<(), SerializeErr>;
 
 pub trait Serialize: Sized {
     fn mc_serialize<S: Serializer>(&self, to: &mut S) -> SerializeResult;
+
+    fn mc_serialize_to_vec(&self) -> Result<Vec<u8>, SerializeErr> {
+        let mut out = crate::types::BytesSerializer::default();
+        self.mc_serialize(&mut out)?;
+        Ok(out.into_bytes())
+    }
+
+    // the number of bytes mc_serialize writes, types with a fixed layout override this with the exact
+    // math. The result is meaningless for values which fail to serialize.
+    fn mc_size(&self) -> usize {
+        let mut counter = crate::types::CountingSerializer::default();
+        let _ = self.mc_serialize(&mut counter);
+        counter.count
+    }
 }
 
 pub trait Serializer: Sized {
@@ -8,7 +8,7 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use alloc::{string::String, fmt, vec::Vec, borrow::ToOwned};
 use alloc::format;
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 use crate::protocol::TestRandom;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -43,18 +43,18 @@ impl McDeserialize for StatusSpec {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for StatusSpec {
     fn test_gen_random() -> Self {
         Self {
             version: Some(StatusVersionSpec {
-                protocol: rand::random(),
+                protocol: crate::protocol::test_random(),
                 name: String::test_gen_random(),
             }),
             players: StatusPlayersSpec {
                 sample: Vec::default(),
-                max: rand::random(),
-                online: rand::random(),
+                max: crate::protocol::test_random(),
+                online: crate::protocol::test_random(),
             },
             favicon: None,
             description: Chat::test_gen_random(),
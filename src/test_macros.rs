@@ -4,7 +4,7 @@ macro_rules! packet_test_cases {
     ($rawnam: ident, $pnam: ident, $varnam: ident, $bodnam: ident, $testnam: ident, $benchnams: ident, $benchnamd: ident) => {
         #[test]
         fn $testnam() {
-            use crate::protocol::{RawPacket, HasPacketBody, HasPacketId};
+            use crate::protocol::{RawPacket, HasPacketBody, HasPacketId, Packet};
             for k in 0..50 {
                 let packet = $pnam::$varnam($bodnam::test_gen_random());
                 if k == 0 {
@@ -13,6 +13,8 @@ macro_rules! packet_test_cases {
                 let mut out = crate::types::BytesSerializer::default();
                 packet.mc_serialize_body(&mut out).expect("serialize succeeds");
                 let bytes = out.into_bytes();
+                assert_eq!(packet.mc_body_size(), bytes.len(), "mc_body_size() == serialized body length");
+                assert_eq!(packet.serialized_size(), crate::types::VarInt(packet.id().id).len() + bytes.len());
 
                 let raw_packet = $rawnam::create(packet.id(), bytes.as_slice()).expect("valid id");
                 let deserialized = match raw_packet.deserialize() {
@@ -23,7 +25,17 @@ macro_rules! packet_test_cases {
                     Ok(out) => out
                 };
                 assert_eq!(packet, deserialized, "deserialize(serialize(packet)) == packet");
-                assert_eq!(packet.clone(), deserialized.clone(), "deserialized.clone() == packet.clone()")
+                assert_eq!(packet.clone(), deserialized.clone(), "deserialized.clone() == packet.clone()");
+
+                #[cfg(feature = "serde")]
+                {
+                    let json = serde_json::to_string(&packet).expect("serialize to json succeeds");
+                    let from_json: $pnam = match serde_json::from_str(json.as_str()) {
+                        Err(err) => panic!("error: {:?} in json {}", err, json),
+                        Ok(out) => out
+                    };
+                    assert_eq!(packet, from_json, "from_json(to_json(packet)) == packet");
+                }
             }
         }
 
@@ -91,3 +103,113 @@ impl BenchSerializer {
         self.data.len()
     }
 }
+
+// tests for the helpers from entity_metadata_helpers!, expanded in the tests module of a version
+#[cfg(all(test, feature = "std"))]
+#[macro_export]
+macro_rules! entity_metadata_test_cases {
+    () => {
+        #[test]
+        fn test_entity_metadata_builder_and_iteration() {
+            use EntityMetadataFieldData::*;
+            let mut metadata = EntityMetadata::default()
+                .with_field(0, Byte(-1))
+                .with_field(7, Float(20.0))
+                .with_field(0, Byte(2));
+
+            assert_eq!(metadata.iter().map(|field| field.index).collect::<Vec<_>>(), vec![0, 7]);
+            assert_eq!(metadata.get(0), Some(&Byte(2)));
+            assert!(metadata.contains(7));
+            assert!(!metadata.contains(8));
+
+            for field in metadata.iter_mut() {
+                if let Float(value) = &mut field.data {
+                    *value /= 2.0;
+                }
+            }
+            assert_eq!(metadata.remove(7), Some(Float(10.0)));
+            assert_eq!(metadata.remove(7), None);
+            assert!(!metadata.contains(7));
+            assert_eq!(metadata.fields.len(), 1);
+        }
+
+        #[test]
+        fn test_entity_metadata_typed_accessors() {
+            const ARMOR_STAND_FLAGS: u8 = 14;
+            let name = Chat::from_text("Spawn");
+
+            let mut metadata = EntityMetadata::default();
+            assert!(matches!(metadata.flags(), Err(EntityMetadataErr::Absent(METADATA_ENTITY_FLAGS))));
+
+            metadata.set_flags(EntityFlags::none().with_glowing(true));
+            metadata.set_air_ticks(VarInt(300));
+            metadata.set_custom_name(Some(name.clone()));
+            metadata.set_custom_name_visible(true);
+            metadata.set_pose(EntityPose::Standing);
+            metadata.set_health(20.0);
+            metadata.set_hand_states(LivingEntityHandStates::none());
+            // small, with arms
+            metadata.set(ARMOR_STAND_FLAGS, EntityMetadataFieldData::Byte(0x05));
+
+            let mut bytes = BytesSerializer::default();
+            metadata.mc_serialize(&mut bytes).expect("serializes");
+            let bytes = bytes.into_bytes();
+            let Deserialized { value: metadata, data: rest } = EntityMetadata::mc_deserialize(bytes.as_slice()).expect("parses");
+            assert!(rest.is_empty());
+
+            let flags = metadata.flags().expect("flags present");
+            assert!(flags.is_glowing());
+            assert!(!flags.is_on_fire());
+            assert!(!flags.is_invisible());
+            assert_eq!(metadata.air_ticks().expect("air present"), VarInt(300));
+            assert_eq!(metadata.custom_name().expect("name present"), Some(&name));
+            assert!(metadata.is_custom_name_visible().expect("name visibility present"));
+            assert_eq!(metadata.pose().expect("pose present"), EntityPose::Standing);
+            assert_eq!(metadata.health().expect("health present"), 20.0);
+            assert!(!metadata.hand_states().expect("hand states present").is_hand_active());
+            assert_eq!(metadata.get(ARMOR_STAND_FLAGS), Some(&EntityMetadataFieldData::Byte(0x05)));
+
+            let mut metadata = metadata;
+            metadata.set(METADATA_HEALTH, EntityMetadataFieldData::VarInt(VarInt(20)));
+            match metadata.health() {
+                Err(EntityMetadataErr::WrongType { index, expected, found }) => {
+                    assert_eq!(index, METADATA_HEALTH);
+                    assert_eq!(expected, "Float");
+                    assert_eq!(found, "VarInt");
+                }
+                other => panic!("expected wrong type, got {:?}", other),
+            }
+
+            metadata.set_custom_name(None);
+            assert_eq!(metadata.custom_name().expect("name present"), None);
+        }
+
+        #[test]
+        fn test_entity_metadata_apply_diff() {
+            let mut old = EntityMetadata::default();
+            old.set_flags(EntityFlags::none());
+            old.set_air_ticks(VarInt(300));
+            old.set_health(20.0);
+
+            let mut newer = old.clone();
+            newer.set_flags(EntityFlags::none().with_on_fire(true));
+            newer.set_health(20.0);
+            newer.set_custom_name(Some(Chat::from_text("Steve")));
+
+            let delta = old.diff(&newer);
+            assert_eq!(delta.fields.len(), 2);
+            assert!(delta.flags().expect("flags changed").is_on_fire());
+            assert!(delta.custom_name().is_ok());
+            assert!(matches!(delta.health(), Err(EntityMetadataErr::Absent(METADATA_HEALTH))));
+            assert!(matches!(delta.air_ticks(), Err(EntityMetadataErr::Absent(METADATA_AIR_TICKS))));
+
+            let mut applied = old.clone();
+            applied.apply(&delta);
+            assert_eq!(applied, newer);
+
+            let empty = newer.diff(&newer);
+            assert!(empty.fields.is_empty());
+            assert_eq!(empty.mc_serialize_to_vec().expect("serializes"), alloc::vec![0xFF]);
+        }
+    };
+}
@@ -7,7 +7,7 @@ use crate::*;
 
 pub use super::chat::*;
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 use crate::protocol::TestRandom;
 use crate::byte_order::{ProtoByteOrder, ByteOrder};
 
@@ -16,6 +16,10 @@ impl Serialize for bool {
     fn mc_serialize<S: Serializer>(&self, to: &mut S) -> SerializeResult {
         to.serialize_byte(if *self { 1 } else { 0 })
     }
+
+    fn mc_size(&self) -> usize {
+        1
+    }
 }
 
 impl Deserialize for bool {
@@ -28,10 +32,10 @@ impl Deserialize for bool {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for bool {
     fn test_gen_random() -> Self {
-        rand::random()
+        crate::protocol::test_random()
     }
 }
 
@@ -42,6 +46,10 @@ macro_rules! def_primitive {
                 let data = ProtoByteOrder::$write(*self);
                 to.serialize_bytes(&data)
             }
+
+            fn mc_size(&self) -> usize {
+                core::mem::size_of::<$nam>()
+            }
         }
 
         impl Deserialize for $nam {
@@ -50,10 +58,10 @@ macro_rules! def_primitive {
             }
         }
 
-        #[cfg(all(test, feature = "std"))]
+        #[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
         impl TestRandom for $nam {
             fn test_gen_random() -> Self {
-                rand::random()
+                crate::protocol::test_random()
             }
         }
     };
@@ -102,6 +110,10 @@ macro_rules! def_varnum {
 
                 to.serialize_bytes(&out[..byte_idx])
             }
+
+            fn mc_size(&self) -> usize {
+                self.len()
+            }
         }
 
         impl Deserialize for $nam {
@@ -118,6 +130,12 @@ macro_rules! def_varnum {
                     }
                     let Deserialized { value: byte, data: rest } = ProtoByteOrder::read_ubyte(data)?;
                     data = rest;
+                    // the last byte only has room for the bits left over, anything above them would
+                    // be silently shifted out of the value
+                    let bits_left = (core::mem::size_of::<$working_type>() * 8) - bit_place;
+                    if bits_left < 7 && (byte & 0x7F) >> bits_left != 0 {
+                        return DeserializeErr::VarNumTooLong(Vec::from(&orig_data[..=i])).into();
+                    }
                     has_more = byte & 0x80 != 0;
                     v |= ((byte as $working_type) & 0x7F) << bit_place;
                     bit_place += 7;
@@ -154,6 +172,49 @@ macro_rules! def_varnum {
             }
         }
 
+        impl $nam {
+            pub const MAX_LEN: usize = $max_bytes;
+
+            // the number of bytes mc_serialize writes for this value
+            #[allow(clippy::len_without_is_empty)]
+            pub fn len(self) -> usize {
+                let bits = (core::mem::size_of::<$working_type>() * 8) - (self.0 as $working_type).leading_zeros() as usize;
+                core::cmp::max(1, (bits + 6) / 7)
+            }
+
+            pub fn checked_add(self, rhs: $nam) -> Option<$nam> {
+                self.0.checked_add(rhs.0).map($nam)
+            }
+
+            pub fn saturating_add(self, rhs: $nam) -> $nam {
+                $nam(self.0.saturating_add(rhs.0))
+            }
+        }
+
+        impl core::ops::Add for $nam {
+            type Output = $nam;
+
+            fn add(self, rhs: $nam) -> Self::Output {
+                $nam(self.0 + rhs.0)
+            }
+        }
+
+        impl core::ops::Sub for $nam {
+            type Output = $nam;
+
+            fn sub(self, rhs: $nam) -> Self::Output {
+                $nam(self.0 - rhs.0)
+            }
+        }
+
+        impl core::ops::Mul<$data_type> for $nam {
+            type Output = $nam;
+
+            fn mul(self, rhs: $data_type) -> Self::Output {
+                $nam(self.0 * rhs)
+            }
+        }
+
         impl fmt::Debug for $nam {
             fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
                 f.write_str(stringify!($nam))?;
@@ -164,10 +225,24 @@ macro_rules! def_varnum {
             }
         }
 
-        #[cfg(all(test, feature = "std"))]
+        #[cfg(feature = "serde")]
+        impl serde::Serialize for $nam {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                self.0.serialize(serializer)
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> serde::Deserialize<'de> for $nam {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                <$data_type>::deserialize(deserializer).map($nam)
+            }
+        }
+
+        #[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
         impl TestRandom for $nam {
             fn test_gen_random() -> Self {
-                let out: $data_type = rand::random();
+                let out: $data_type = crate::protocol::test_random();
                 Self(out)
             }
         }
@@ -178,35 +253,84 @@ def_varnum!(VarInt, i32, u32, 5);
 def_varnum!(VarLong, i64, u64, 10);
 
 // STRING
+pub const STRING_MAX_LENGTH: usize = 32767;
+pub const CHAT_MAX_LENGTH: usize = 262144;
+pub const USERNAME_MAX_LENGTH: usize = 16;
+pub const CHAT_MESSAGE_MAX_LENGTH: usize = 256;
+
+// Vanilla counts string lengths in UTF-16 code units, so `max` is in code units both when writing
+// and reading.
+fn utf16_len_exceeds(value: &str, max: usize) -> Option<usize> {
+    // a string can't have more code units than bytes
+    if value.len() <= max {
+        return None;
+    }
+
+    let units = value.encode_utf16().count();
+    if units > max {
+        Some(units)
+    } else {
+        None
+    }
+}
+
+pub fn serialize_string_limited<S: Serializer>(value: &str, max: usize, to: &mut S) -> SerializeResult {
+    if let Some(units) = utf16_len_exceeds(value, max) {
+        return Err(SerializeErr::CannotSerialize(alloc::format!(
+            "string of {} code units exceeds the maximum of {} code units",
+            units, max
+        )));
+    }
+
+    to.serialize_other(&VarInt(value.len() as i32))?;
+    to.serialize_bytes(value.as_bytes())
+}
+
+// The declared byte length may be up to 4 times `max`. It's checked against that and the remaining
+// input before anything is allocated for the string.
+pub fn deserialize_string_limited(data: &[u8], max: usize) -> DeserializeResult<'_, String> {
+    VarInt::mc_deserialize(data)?.and_then(move |length, rest| {
+        let max_bytes = max.saturating_mul(4);
+        if length.0 < 0 {
+            Err(DeserializeErr::NegativeLength(length))
+        } else if length.0 as usize > max_bytes {
+            Err(DeserializeErr::StringTooLong { length: length.0 as usize, max: max_bytes })
+        } else {
+            take(length.0 as usize, rest)?.try_map(move |taken| {
+                let value = String::from_utf8(taken.to_vec()).map_err(DeserializeErr::BadStringEncoding)?;
+                if let Some(units) = utf16_len_exceeds(value.as_str(), max) {
+                    return Err(DeserializeErr::StringTooLong { length: units, max });
+                }
+                Ok(value)
+            })
+        }
+    })
+}
+
 impl Serialize for String {
     fn mc_serialize<S: Serializer>(&self, to: &mut S) -> SerializeResult {
-        to.serialize_other(&VarInt(self.len() as i32))?;
-        to.serialize_bytes(self.as_bytes())
+        serialize_string_limited(self.as_str(), STRING_MAX_LENGTH, to)
+    }
+
+    fn mc_size(&self) -> usize {
+        VarInt(self.len() as i32).len() + self.len()
     }
 }
 
 impl Deserialize for String {
     fn mc_deserialize(data: &[u8]) -> DeserializeResult<'_, Self> {
-        VarInt::mc_deserialize(data)?.and_then(move |length, rest| {
-            if length.0 < 0 {
-                Err(DeserializeErr::NegativeLength(length))
-            } else {
-                take(length.0 as usize, rest)?.try_map(move |taken| {
-                    String::from_utf8(taken.to_vec()).map_err(DeserializeErr::BadStringEncoding)
-                })
-            }
-        })
+        deserialize_string_limited(data, STRING_MAX_LENGTH)
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for String {
     fn test_gen_random() -> Self {
-        let raw_len: u8 = rand::random();
+        let raw_len: u8 = crate::protocol::test_random();
         let len = raw_len as usize;
         let mut out = String::with_capacity(len);
         for _ in 0..len {
-            let c_idx: u8 = rand::random::<u8>() % 36;
+            let c_idx: u8 = crate::protocol::test_random::<u8>() % 36;
 
             let c = if c_idx <= 10 {
                 (48 + c_idx) as char
@@ -221,16 +345,138 @@ impl TestRandom for String {
     }
 }
 
+// A string field with a tighter limit than STRING_MAX_LENGTH, like the 16 characters of a username.
+// MAX counts UTF-16 code units, like serialize_string_limited and deserialize_string_limited.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Default)]
+pub struct LimitedString<const MAX: usize> {
+    value: String,
+}
+
+impl<const MAX: usize> Serialize for LimitedString<MAX> {
+    fn mc_serialize<S: Serializer>(&self, to: &mut S) -> SerializeResult {
+        serialize_string_limited(self.value.as_str(), MAX, to)
+    }
+}
+
+impl<const MAX: usize> Deserialize for LimitedString<MAX> {
+    fn mc_deserialize(data: &[u8]) -> DeserializeResult<'_, Self> {
+        Ok(deserialize_string_limited(data, MAX)?.map(move |value| Self { value }))
+    }
+}
+
+impl<const MAX: usize> core::ops::Deref for LimitedString<MAX> {
+    type Target = String;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<const MAX: usize> From<LimitedString<MAX>> for String {
+    fn from(other: LimitedString<MAX>) -> Self {
+        other.value
+    }
+}
+
+impl<const MAX: usize> From<String> for LimitedString<MAX> {
+    fn from(value: String) -> Self {
+        Self { value }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<const MAX: usize> serde::Serialize for LimitedString<MAX> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.value.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, const MAX: usize> serde::Deserialize<'de> for LimitedString<MAX> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        if let Some(units) = utf16_len_exceeds(value.as_str(), MAX) {
+            return Err(serde::de::Error::custom(DeserializeErr::StringTooLong { length: units, max: MAX }));
+        }
+
+        Ok(value.into())
+    }
+}
+
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
+impl<const MAX: usize> TestRandom for LimitedString<MAX> {
+    fn test_gen_random() -> Self {
+        let mut value = String::test_gen_random();
+        value.truncate(MAX);
+        value.into()
+    }
+}
+
 // position
-#[derive(Clone, Copy, PartialEq, Hash, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IntPosition {
     pub x: i32,
     pub y: i16,
     pub z: i32,
 }
 
+pub enum IntPositionErr {
+    OutOfRange { x: i32, y: i16, z: i32 },
+}
+
+impl fmt::Display for IntPositionErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use IntPositionErr::*;
+        match self {
+            OutOfRange { x, y, z } => f.write_fmt(format_args!(
+                "position ({}, {}, {}) doesn't fit in a packed position",
+                x, y, z
+            )),
+        }
+    }
+}
+
+impl fmt::Debug for IntPositionErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        <dyn fmt::Display>::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for IntPositionErr {}
+
+impl IntPosition {
+    // x and z are packed into 26 bits and y into 12
+    pub const MIN_XZ: i32 = -(1 << 25);
+    pub const MAX_XZ: i32 = (1 << 25) - 1;
+    pub const MIN_Y: i16 = -(1 << 11);
+    pub const MAX_Y: i16 = (1 << 11) - 1;
+
+    pub fn new(x: i32, y: i16, z: i32) -> Result<Self, IntPositionErr> {
+        let position = Self::new_unchecked(x, y, z);
+        if position.is_in_range() {
+            Ok(position)
+        } else {
+            Err(IntPositionErr::OutOfRange { x, y, z })
+        }
+    }
+
+    // out of range coordinates are truncated when the position is serialized
+    pub const fn new_unchecked(x: i32, y: i16, z: i32) -> Self {
+        Self { x, y, z }
+    }
+
+    pub fn is_in_range(&self) -> bool {
+        (Self::MIN_XZ..=Self::MAX_XZ).contains(&self.x) &&
+            (Self::MIN_Y..=Self::MAX_Y).contains(&self.y) &&
+            (Self::MIN_XZ..=Self::MAX_XZ).contains(&self.z)
+    }
+}
+
 impl Serialize for IntPosition {
     fn mc_serialize<S: Serializer>(&self, to: &mut S) -> SerializeResult {
+        debug_assert!(self.is_in_range(), "position {:?} doesn't fit in a packed position", self);
         let x_raw = if self.x < 0 {
             (self.x + 0x2000000) as u64 | 0x2000000
         } else {
@@ -286,16 +532,68 @@ impl Deserialize for IntPosition {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for IntPosition {
     fn test_gen_random() -> Self {
-        let x: i32 = ((rand::random::<u32>() % (1 << 26)) as i32) - (1 << 25);
-        let z: i32 = ((rand::random::<u32>() % (1 << 26)) as i32) - (1 << 25);
-        let y: i16 = ((rand::random::<u16>() % (1 << 12)) as i16) - (1 << 11);
+        let x: i32 = ((crate::protocol::test_random::<u32>() % (1 << 26)) as i32) - (1 << 25);
+        let z: i32 = ((crate::protocol::test_random::<u32>() % (1 << 26)) as i32) - (1 << 25);
+        let y: i16 = ((crate::protocol::test_random::<u16>() % (1 << 12)) as i16) - (1 << 11);
         Self { x, y, z }
     }
 }
 
+// Before 1.14 positions were packed as x (26 bits), y (12 bits), z (26 bits), modern versions put
+// y in the low bits instead. Otherwise the same as IntPosition, which it converts to and from.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LegacyIntPosition {
+    pub x: i32,
+    pub y: i16,
+    pub z: i32,
+}
+
+impl Serialize for LegacyIntPosition {
+    fn mc_serialize<S: Serializer>(&self, to: &mut S) -> SerializeResult {
+        let x_raw = (self.x as u64) & 0x3FFFFFF;
+        let y_raw = (self.y as u64) & 0xFFF;
+        let z_raw = (self.z as u64) & 0x3FFFFFF;
+
+        let data = ProtoByteOrder::write_ulong((x_raw << 38) | (y_raw << 26) | z_raw);
+        to.serialize_bytes(&data)
+    }
+}
+
+impl Deserialize for LegacyIntPosition {
+    fn mc_deserialize(data: &[u8]) -> DeserializeResult<'_, Self> {
+        let Deserialized { value: raw, data } = ProtoByteOrder::read_ulong(data)?;
+        // shift each field up to the top of an i64 and back down to sign extend it
+        let x = ((raw as i64) >> 38) as i32;
+        let y = (((raw << 26) as i64) >> 52) as i16;
+        let z = (((raw << 38) as i64) >> 38) as i32;
+
+        Deserialized::ok(LegacyIntPosition { x, y, z }, data)
+    }
+}
+
+impl From<IntPosition> for LegacyIntPosition {
+    fn from(other: IntPosition) -> Self {
+        Self { x: other.x, y: other.y, z: other.z }
+    }
+}
+
+impl From<LegacyIntPosition> for IntPosition {
+    fn from(other: LegacyIntPosition) -> Self {
+        Self { x: other.x, y: other.y, z: other.z }
+    }
+}
+
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
+impl TestRandom for LegacyIntPosition {
+    fn test_gen_random() -> Self {
+        IntPosition::test_gen_random().into()
+    }
+}
+
 // angle
 #[derive(Copy, Clone, PartialEq, Hash, Debug)]
 pub struct Angle {
@@ -314,11 +612,65 @@ impl Deserialize for Angle {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+// Angles are 1/256ths of a turn. Converting to one rounds to the nearest step and wraps, so -90 and
+// 270 degrees are the same angle.
+impl Angle {
+    pub fn from_degrees(degrees: f32) -> Self {
+        Self::from_turns(degrees as f64 / 360.0)
+    }
+
+    pub fn to_degrees(self) -> f32 {
+        (self.value as f32) * 360.0 / 256.0
+    }
+
+    pub fn from_radians(radians: f32) -> Self {
+        Self::from_turns(radians as f64 / core::f64::consts::TAU)
+    }
+
+    pub fn to_radians(self) -> f32 {
+        (self.value as f32) * core::f32::consts::TAU / 256.0
+    }
+
+    // The yaw and pitch of something at `from` looking at `to`, like vanilla: a yaw of 0 faces south
+    // (+z) and increases clockwise seen from above, a negative pitch looks up.
+    #[cfg(feature = "std")]
+    pub fn between(from: (f64, f64, f64), to: (f64, f64, f64)) -> (Angle, Angle) {
+        let (dx, dy, dz) = (to.0 - from.0, to.1 - from.1, to.2 - from.2);
+        let yaw = (-dx).atan2(dz);
+        let pitch = (-dy).atan2((dx * dx + dz * dz).sqrt());
+        (
+            Self::from_turns(yaw / core::f64::consts::TAU),
+            Self::from_turns(pitch / core::f64::consts::TAU),
+        )
+    }
+
+    fn from_turns(turns: f64) -> Self {
+        let steps = turns * 256.0;
+        let rounded = if steps < 0.0 { steps - 0.5 } else { steps + 0.5 } as i64;
+        Angle { value: rounded.rem_euclid(256) as u8 }
+    }
+}
+
+// in serde formats an angle is written in degrees, rounded to the nearest 1/256th of a turn when read
+#[cfg(feature = "serde")]
+impl serde::Serialize for Angle {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64((self.value as f64) * 360.0 / 256.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Angle {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Angle::from_turns(f64::deserialize(deserializer)? / 360.0))
+    }
+}
+
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for Angle {
     fn test_gen_random() -> Self {
         Self {
-            value: rand::random(),
+            value: crate::protocol::test_random(),
         }
     }
 }
@@ -338,16 +690,17 @@ impl Deserialize for UUID4 {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for UUID4 {
     fn test_gen_random() -> Self {
-        UUID4::random()
+        UUID4::from(crate::protocol::test_random::<u128>())
     }
 }
 
 // NBT
 
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
 pub struct NamedNbtTag {
     pub root: nbt::NamedTag,
 }
@@ -368,6 +721,120 @@ impl Deserialize for NamedNbtTag {
     }
 }
 
+impl NamedNbtTag {
+    pub fn to_snbt(&self) -> String {
+        self.root.to_snbt()
+    }
+
+    pub fn get_path(&self, path: &str) -> Result<&nbt::Tag, nbt::NbtPathErr> {
+        self.root.get_path(path)
+    }
+
+    pub fn get_path_mut(&mut self, path: &str) -> Result<&mut nbt::Tag, nbt::NbtPathErr> {
+        self.root.get_path_mut(path)
+    }
+
+    // the root of a NamedNbtTag is always an unnamed compound
+    pub fn parse_snbt(input: &str) -> Result<Self, nbt::SnbtErr> {
+        match nbt::parse_snbt(input)? {
+            payload @ nbt::Tag::Compound(_) => Ok(Self { root: payload.with_name("") }),
+            _ => Err(nbt::SnbtErr::NotCompound { offset: input.len() - input.trim_start().len() }),
+        }
+    }
+}
+
+#[cfg(feature = "compression")]
+pub enum CompressedNbtErr {
+    UnknownCompression(Option<u8>),
+    Decompress(std::io::Error),
+    TooLarge { max: usize },
+    Nbt(DeserializeErr),
+}
+
+#[cfg(feature = "compression")]
+impl fmt::Display for CompressedNbtErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use CompressedNbtErr::*;
+        match self {
+            UnknownCompression(Some(first)) => f.write_fmt(format_args!("unknown nbt compression (first byte {:#04x})", first)),
+            UnknownCompression(None) => f.write_str("unknown nbt compression (no data)"),
+            Decompress(err) => f.write_fmt(format_args!("failed to decompress nbt: {}", err)),
+            TooLarge { max } => f.write_fmt(format_args!("decompressed nbt is larger than {} bytes", max)),
+            Nbt(err) => f.write_fmt(format_args!("failed to read decompressed nbt: {:?}", err)),
+        }
+    }
+}
+
+#[cfg(feature = "compression")]
+impl fmt::Debug for CompressedNbtErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        <dyn fmt::Display>::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "compression")]
+impl std::error::Error for CompressedNbtErr {}
+
+// region files, level.dat and some plugin messages carry compressed nbt, packets never do
+#[cfg(feature = "compression")]
+impl NamedNbtTag {
+    pub fn from_gzip(data: &[u8]) -> Result<Self, CompressedNbtErr> {
+        Self::from_decoder(flate2::read::GzDecoder::new(data))
+    }
+
+    pub fn from_zlib(data: &[u8]) -> Result<Self, CompressedNbtErr> {
+        Self::from_decoder(flate2::read::ZlibDecoder::new(data))
+    }
+
+    // gzip starts with 1f 8b, zlib with a CMF byte of 0x78 (deflate, 32k window). Uncompressed data
+    // starts with the compound tag id and is read as is.
+    pub fn from_compressed(data: &[u8]) -> Result<Self, CompressedNbtErr> {
+        match data {
+            [0x1f, 0x8b, ..] => Self::from_gzip(data),
+            [0x78, flags, ..] if (0x7800u16 | *flags as u16) % 31 == 0 => Self::from_zlib(data),
+            [0x0a, ..] => Self::mc_deserialize_complete(data).map_err(CompressedNbtErr::Nbt),
+            _ => Err(CompressedNbtErr::UnknownCompression(data.first().copied())),
+        }
+    }
+
+    pub fn to_gzip(&self) -> Vec<u8> {
+        self.write_encoder(flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default()))
+            .and_then(move |encoder| encoder.finish())
+            .expect("writing to a vec can't fail")
+    }
+
+    pub fn to_zlib(&self) -> Vec<u8> {
+        self.write_encoder(flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default()))
+            .and_then(move |encoder| encoder.finish())
+            .expect("writing to a vec can't fail")
+    }
+
+    // inflates at most one byte more than the codec allows for a packet, which is enough to tell
+    // that the data is too large
+    fn from_decoder<R: std::io::Read>(decoder: R) -> Result<Self, CompressedNbtErr> {
+        use std::io::Read;
+        let max = crate::codec::DEFAULT_MAX_UNCOMPRESSED_SIZE;
+        let mut decompressed = Vec::new();
+        decoder.take(max as u64 + 1).read_to_end(&mut decompressed).map_err(CompressedNbtErr::Decompress)?;
+        if decompressed.len() > max {
+            return Err(CompressedNbtErr::TooLarge { max });
+        }
+
+        Self::mc_deserialize_complete(decompressed.as_slice()).map_err(CompressedNbtErr::Nbt)
+    }
+
+    fn write_encoder<W: std::io::Write>(&self, mut encoder: W) -> std::io::Result<W> {
+        encoder.write_all(self.root.bytes().as_slice())?;
+        Ok(encoder)
+    }
+}
+
+impl fmt::Display for NamedNbtTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.root.payload.fmt_pretty(f)
+    }
+}
+
 impl From<nbt::NamedTag> for NamedNbtTag {
     fn from(root: nbt::NamedTag) -> Self {
         Self { root }
@@ -380,7 +847,7 @@ impl Into<nbt::NamedTag> for NamedNbtTag {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for NamedNbtTag {
     fn test_gen_random() -> Self {
         Self {
@@ -389,6 +856,12 @@ impl TestRandom for NamedNbtTag {
     }
 }
 
+// the protocol's fixed point numbers use 5 fractional bits
+pub const FIXED_INT_FRACTIONAL_BITS: usize = 5;
+
+// the fixed point positions in sound packets only have 3
+pub const SOUND_FIXED_INT_FRACTIONAL_BITS: usize = 3;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct FixedInt {
     raw: i32,
@@ -416,15 +889,87 @@ impl FixedInt {
     pub fn into_float(self, fractional_bytes: usize) -> f64 {
         (self.raw as f64) / ((1 << fractional_bytes) as f64)
     }
+
+    // sound positions are sent as 8 times the coordinate, truncated like vanilla does
+    pub fn from_f64(value: f64) -> Self {
+        Self::new(value, SOUND_FIXED_INT_FRACTIONAL_BITS)
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.into_float(SOUND_FIXED_INT_FRACTIONAL_BITS)
+    }
+
+    pub fn raw(self) -> i32 {
+        self.raw
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for FixedInt {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.into_float(FIXED_INT_FRACTIONAL_BITS))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for FixedInt {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(FixedInt::new(f64::deserialize(deserializer)?, FIXED_INT_FRACTIONAL_BITS))
+    }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for FixedInt {
     fn test_gen_random() -> Self {
         FixedInt::new(f64::test_gen_random(), 16)
     }
 }
 
+// Where a sound is played, in blocks. Sound packets carry it as a Vec3<FixedInt>.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SoundPosition {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl SoundPosition {
+    pub fn to_fixed(self) -> Vec3<FixedInt> {
+        Vec3 {
+            x: FixedInt::from_f64(self.x),
+            y: FixedInt::from_f64(self.y),
+            z: FixedInt::from_f64(self.z),
+        }
+    }
+}
+
+impl From<Vec3<FixedInt>> for SoundPosition {
+    fn from(other: Vec3<FixedInt>) -> Self {
+        Self {
+            x: other.x.to_f64(),
+            y: other.y.to_f64(),
+            z: other.z.to_f64(),
+        }
+    }
+}
+
+impl From<SoundPosition> for Vec3<FixedInt> {
+    fn from(other: SoundPosition) -> Self {
+        other.to_fixed()
+    }
+}
+
+// the center of the block, which is where vanilla plays block sounds
+impl From<IntPosition> for SoundPosition {
+    fn from(other: IntPosition) -> Self {
+        Self {
+            x: other.x as f64 + 0.5,
+            y: other.y as f64 + 0.5,
+            z: other.z as f64 + 0.5,
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct BytesSerializer {
     data: Vec<u8>,
@@ -447,20 +992,113 @@ impl BytesSerializer {
     pub fn into_bytes(self) -> Vec<u8> {
         self.data
     }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.data.as_slice()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.data.capacity()
+    }
+
+    // empties the buffer but keeps its allocation, so the serializer can be reused
+    pub fn reset(&mut self) {
+        self.data.clear();
+    }
 }
 
-impl<T> Serialize for Option<T>
-    where
-        T: Serialize,
-{
-    fn mc_serialize<S: Serializer>(&self, to: &mut S) -> SerializeResult {
-        match self {
-            Some(value) => {
-                to.serialize_other(&true)?;
-                to.serialize_other(value)
-            }
-            None => to.serialize_other(&false),
-        }
+// Discards everything written to it, only counting the bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CountingSerializer {
+    pub count: usize,
+}
+
+impl Serializer for CountingSerializer {
+    fn serialize_bytes(&mut self, data: &[u8]) -> SerializeResult {
+        self.count += data.len();
+        Ok(())
+    }
+
+    fn serialize_byte(&mut self, _: u8) -> SerializeResult {
+        self.count += 1;
+        Ok(())
+    }
+}
+
+// Writes into a caller provided buffer, advancing past whatever has been written. Nothing is written
+// when the remaining space can't hold all of the data.
+impl Serializer for &mut [u8] {
+    fn serialize_bytes(&mut self, data: &[u8]) -> SerializeResult {
+        if data.len() > self.len() {
+            return Err(SerializeErr::BufferFull { needed: data.len(), remaining: self.len() });
+        }
+
+        let (head, tail) = core::mem::take(self).split_at_mut(data.len());
+        head.copy_from_slice(data);
+        *self = tail;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct SliceSerializer<'a> {
+    buf: &'a mut [u8],
+    pos: usize,
+}
+
+impl<'a> SliceSerializer<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.pos]
+    }
+
+    pub fn into_bytes(self) -> &'a mut [u8] {
+        &mut self.buf[..self.pos]
+    }
+}
+
+impl Serializer for SliceSerializer<'_> {
+    fn serialize_bytes(&mut self, data: &[u8]) -> SerializeResult {
+        let mut rest = &mut self.buf[self.pos..];
+        rest.serialize_bytes(data)?;
+        self.pos += data.len();
+        Ok(())
+    }
+}
+
+pub fn mc_serialize_byte_len<T: Serialize>(value: &T) -> Result<usize, SerializeErr> {
+    let mut counter = CountingSerializer::default();
+    value.mc_serialize(&mut counter)?;
+    Ok(counter.count)
+}
+
+impl<T> Serialize for Option<T>
+    where
+        T: Serialize,
+{
+    fn mc_serialize<S: Serializer>(&self, to: &mut S) -> SerializeResult {
+        match self {
+            Some(value) => {
+                to.serialize_other(&true)?;
+                to.serialize_other(value)
+            }
+            None => to.serialize_other(&false),
+        }
+    }
+
+    fn mc_size(&self) -> usize {
+        1 + self.as_ref().map_or(0, Serialize::mc_size)
     }
 }
 
@@ -479,13 +1117,13 @@ impl<T> Deserialize for Option<T>
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl<T> TestRandom for Option<T>
     where
         T: TestRandom,
 {
     fn test_gen_random() -> Self {
-        let is_present: bool = rand::random();
+        let is_present: bool = crate::protocol::test_random();
         if is_present {
             Some(T::test_gen_random())
         } else {
@@ -496,6 +1134,7 @@ impl<T> TestRandom for Option<T>
 
 // SLOT
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ItemStack {
     pub item_id: VarInt,
     pub item_count: i8,
@@ -537,7 +1176,218 @@ impl Deserialize for ItemStack {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+// Accessors for the vanilla item tags. Names and lore are stored as JSON chat strings, values which
+// can't be understood read as absent. Setters only touch their own keys, creating the tag if needed.
+// Clearing a value never creates a tag, and drops compounds (including the root tag) it leaves empty.
+impl ItemStack {
+    pub fn display_name(&self) -> Option<Chat> {
+        serde_json::from_str(self.tag()?.get_compound("display")?.get_str("Name")?).ok()
+    }
+
+    pub fn set_display_name(&mut self, name: Option<&Chat>) {
+        match name {
+            Some(name) => self.display_mut().insert("Name", nbt::Tag::String(chat_json(name))),
+            None => self.remove_tag_entry(Some("display"), "Name"),
+        }
+    }
+
+    pub fn lore(&self) -> Vec<Chat> {
+        let lines = self.tag().and_then(move |tag| tag.get_compound("display")?.get_list("Lore"));
+        lines.unwrap_or(&[]).iter()
+            .filter_map(move |line| match line {
+                nbt::Tag::String(json) => serde_json::from_str(json.as_str()).ok(),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn set_lore(&mut self, lore: &[Chat]) {
+        let lines = lore.iter().map(move |line| nbt::Tag::String(chat_json(line))).collect();
+        self.display_mut().insert("Lore", nbt::Tag::List(lines));
+    }
+
+    pub fn enchantments(&self) -> Vec<(String, i16)> {
+        let enchantments = self.tag().and_then(move |tag| tag.get_list("Enchantments"));
+        enchantments.unwrap_or(&[]).iter()
+            .filter_map(move |enchantment| Some((enchantment.get_str("id")?.into(), enchantment.get_i16("lvl")?)))
+            .collect()
+    }
+
+    pub fn set_enchantments(&mut self, enchantments: &[(String, i16)]) {
+        let enchantments = enchantments.iter().map(move |(id, level)| nbt::Tag::Compound(alloc::vec![
+            nbt::Tag::String(id.clone()).with_name("id"),
+            nbt::Tag::Short(*level).with_name("lvl"),
+        ])).collect();
+        self.tag_mut().insert("Enchantments", nbt::Tag::List(enchantments));
+    }
+
+    pub fn damage(&self) -> Option<i32> {
+        self.tag()?.get_i32("Damage")
+    }
+
+    pub fn set_damage(&mut self, damage: Option<i32>) {
+        match damage {
+            Some(damage) => self.tag_mut().insert("Damage", nbt::Tag::Int(damage)),
+            None => self.remove_tag_entry(None, "Damage"),
+        }
+    }
+
+    pub fn unbreakable(&self) -> bool {
+        self.tag().and_then(move |tag| tag.get_i8("Unbreakable")).map(move |b| b != 0).unwrap_or(false)
+    }
+
+    pub fn set_unbreakable(&mut self, unbreakable: bool) {
+        if unbreakable {
+            self.tag_mut().insert("Unbreakable", nbt::Tag::Byte(1));
+        } else {
+            self.remove_tag_entry(None, "Unbreakable");
+        }
+    }
+
+    fn tag(&self) -> Option<&nbt::Tag> {
+        self.nbt.as_ref().map(move |nbt| &nbt.payload)
+    }
+
+    fn tag_mut(&mut self) -> &mut nbt::Tag {
+        let root = self.nbt.get_or_insert_with(|| nbt::Tag::Compound(Vec::new()).with_name(""));
+        if !matches!(root.payload, nbt::Tag::Compound(_)) {
+            root.payload = nbt::Tag::Compound(Vec::new());
+        }
+
+        &mut root.payload
+    }
+
+    // removes `key` from the root tag, or from its `parent` compound
+    fn remove_tag_entry(&mut self, parent: Option<&str>, key: &str) {
+        let root = match self.nbt.as_mut() {
+            Some(root) => &mut root.payload,
+            None => return,
+        };
+
+        let removed = match parent {
+            Some(parent) => match root.get_mut(parent) {
+                Some(compound) => {
+                    let removed = compound.remove(key).is_some();
+                    if removed && is_empty_compound(compound) {
+                        root.remove(parent);
+                    }
+                    removed
+                }
+                None => false,
+            },
+            None => root.remove(key).is_some(),
+        };
+
+        if removed && is_empty_compound(root) {
+            self.nbt = None;
+        }
+    }
+
+    fn display_mut(&mut self) -> &mut nbt::Tag {
+        let tag = self.tag_mut();
+        if tag.get_compound("display").is_none() {
+            tag.insert("display", nbt::Tag::Compound(Vec::new()));
+        }
+
+        tag.get_mut("display").expect("display was just inserted")
+    }
+}
+
+fn is_empty_compound(tag: &nbt::Tag) -> bool {
+    matches!(tag, nbt::Tag::Compound(entries) if entries.is_empty())
+}
+
+fn chat_json(chat: &Chat) -> String {
+    serde_json::to_string(chat).expect("chat serializes to json")
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ItemStackBuilder {
+    item_id: VarInt,
+    count: i8,
+    nbt: Option<nbt::NamedTag>,
+    name: Option<Chat>,
+    lore: Vec<Chat>,
+    enchantments: Vec<(String, i16)>,
+    damage: Option<i32>,
+}
+
+impl ItemStack {
+    pub fn builder(item_id: i32) -> ItemStackBuilder {
+        ItemStackBuilder {
+            item_id: VarInt(item_id),
+            count: 1,
+            nbt: None,
+            name: None,
+            lore: Vec::new(),
+            enchantments: Vec::new(),
+            damage: None,
+        }
+    }
+}
+
+impl ItemStackBuilder {
+    pub fn count(mut self, count: i8) -> Self {
+        self.count = count;
+        self
+    }
+
+    pub fn name(mut self, name: Chat) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    pub fn lore(mut self, lines: Vec<Chat>) -> Self {
+        self.lore = lines;
+        self
+    }
+
+    pub fn enchant(mut self, id: &str, level: i16) -> Self {
+        self.enchantments.push((id.into(), level));
+        self
+    }
+
+    pub fn damage(mut self, damage: i32) -> Self {
+        self.damage = Some(damage);
+        self
+    }
+
+    // the tag the name, lore and enchantments are written into, keys it already has are kept
+    pub fn nbt(mut self, tag: nbt::NamedTag) -> Self {
+        self.nbt = Some(tag);
+        self
+    }
+
+    // plain items are built without any tag rather than an empty compound, like vanilla sends them
+    pub fn build(self) -> Slot {
+        let mut stack = ItemStack {
+            item_id: self.item_id,
+            item_count: self.count,
+            nbt: self.nbt,
+        };
+
+        if let Some(name) = self.name {
+            stack.set_display_name(Some(&name));
+        }
+        if !self.lore.is_empty() {
+            stack.set_lore(self.lore.as_slice());
+        }
+        if !self.enchantments.is_empty() {
+            stack.set_enchantments(self.enchantments.as_slice());
+        }
+        if self.damage.is_some() {
+            stack.set_damage(self.damage);
+        }
+
+        if matches!(&stack.nbt, Some(nbt::NamedTag { payload: nbt::Tag::Compound(entries), .. }) if entries.is_empty()) {
+            stack.nbt = None;
+        }
+
+        Some(stack)
+    }
+}
+
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for ItemStack {
     fn test_gen_random() -> Self {
         let item_id = VarInt::test_gen_random();
@@ -554,9 +1404,33 @@ impl TestRandom for ItemStack {
 
 pub type Slot = Option<ItemStack>;
 
+// reads the stack in a slot without matching on it first, an empty slot has no item or count
+pub trait SlotExt {
+    fn item_id(&self) -> Option<VarInt>;
+
+    fn item_count(&self) -> Option<i8>;
+
+    fn is_present(&self) -> bool;
+}
+
+impl SlotExt for Slot {
+    fn item_id(&self) -> Option<VarInt> {
+        self.as_ref().map(move |stack| stack.item_id)
+    }
+
+    fn item_count(&self) -> Option<i8> {
+        self.as_ref().map(move |stack| stack.item_count)
+    }
+
+    fn is_present(&self) -> bool {
+        self.is_some()
+    }
+}
+
 macro_rules! def_vector_type {
     ($name: ident, $($fnam: ident),+) => {
         crate::as_item! {
+            #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
             pub struct $name<T> {
                 $(pub $fnam: T),+
             }
@@ -569,6 +1443,10 @@ macro_rules! def_vector_type {
                 )+
                 Ok(())
             }
+
+            fn mc_size(&self) -> usize {
+                0 $(+ self.$fnam.mc_size())+
+            }
         }
 
         impl<T> Deserialize for $name<T> where T: Deserialize {
@@ -578,7 +1456,7 @@ macro_rules! def_vector_type {
             }
         }
 
-        #[cfg(all(test, feature = "std"))]
+        #[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
         impl<T> TestRandom for $name<T> where T: TestRandom {
             fn test_gen_random() -> Self {
                 Self {
@@ -712,8 +1590,12 @@ proto_struct!(EntityLocation<P, R> {
     rotation: EntityRotation<R>
 });
 
+// Arrays longer than this are rejected while deserializing, before anything is allocated for them.
+// Fields which legitimately carry more elements (like chunk data) raise the limit in their type.
+pub const DEFAULT_MAX_ARRAY_COUNT: usize = 65536;
+
 #[derive(Clone, Debug, PartialEq)]
-pub struct CountedArray<E, C> {
+pub struct CountedArray<E, C, const MAX: usize = DEFAULT_MAX_ARRAY_COUNT> {
     data: Vec<E>,
     _counter_type: core::marker::PhantomData<C>,
 }
@@ -723,10 +1605,22 @@ pub trait ArrayCounter: Serialize + Deserialize {
     fn as_count(&self) -> usize;
 
     fn from_count(count: usize) -> Self;
+
+    // signed counters reject negative counts here instead of wrapping them into huge ones
+    fn checked_count(&self) -> Result<usize, DeserializeErr> {
+        Ok(self.as_count())
+    }
 }
 
-impl<E, C> Serialize for CountedArray<E, C> where E: Serialize, C: ArrayCounter {
+impl<E, C, const MAX: usize> Serialize for CountedArray<E, C, MAX> where E: Serialize, C: ArrayCounter {
     fn mc_serialize<S: Serializer>(&self, to: &mut S) -> SerializeResult {
+        if self.data.len() > MAX {
+            return Err(SerializeErr::CannotSerialize(alloc::format!(
+                "array of {} elements exceeds the limit of {} elements",
+                self.data.len(), MAX
+            )));
+        }
+
         let count = C::from_count(self.data.len());
         to.serialize_other(&count)?;
         for elem in &self.data {
@@ -734,13 +1628,22 @@ impl<E, C> Serialize for CountedArray<E, C> where E: Serialize, C: ArrayCounter
         }
         Ok(())
     }
+
+    fn mc_size(&self) -> usize {
+        C::from_count(self.data.len()).mc_size() + self.data.iter().map(Serialize::mc_size).sum::<usize>()
+    }
 }
 
-impl<E, C> Deserialize for CountedArray<E, C> where E: Deserialize, C: ArrayCounter {
+impl<E, C, const MAX: usize> Deserialize for CountedArray<E, C, MAX> where E: Deserialize, C: ArrayCounter {
     fn mc_deserialize(data: &[u8]) -> DeserializeResult<'_, Self> {
         let Deserialized { value: count, mut data } = C::mc_deserialize(data)?;
-        let count = count.as_count();
-        let mut elems = Vec::with_capacity(count);
+        let count = count.checked_count()?;
+        if count > MAX {
+            return Err(DeserializeErr::ArrayTooLarge { count, limit: MAX });
+        }
+
+        // every element takes at least one byte, so don't trust the count any further than that
+        let mut elems = Vec::with_capacity(core::cmp::min(count, data.len()));
         for _ in 0..count {
             let Deserialized { value: elem, data: rest } = E::mc_deserialize(data)?;
             data = rest;
@@ -754,7 +1657,7 @@ impl<E, C> Deserialize for CountedArray<E, C> where E: Deserialize, C: ArrayCoun
     }
 }
 
-impl<E, C> core::ops::Deref for CountedArray<E, C> where C: ArrayCounter {
+impl<E, C, const MAX: usize> core::ops::Deref for CountedArray<E, C, MAX> where C: ArrayCounter {
     type Target = Vec<E>;
 
     fn deref(&self) -> &Self::Target {
@@ -762,19 +1665,19 @@ impl<E, C> core::ops::Deref for CountedArray<E, C> where C: ArrayCounter {
     }
 }
 
-impl<E, C> core::ops::DerefMut for CountedArray<E, C> where C: ArrayCounter {
+impl<E, C, const MAX: usize> core::ops::DerefMut for CountedArray<E, C, MAX> where C: ArrayCounter {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.data
     }
 }
 
-impl<E, C> From<CountedArray<E, C>> for Vec<E> where C: ArrayCounter {
-    fn from(other: CountedArray<E, C>) -> Self {
+impl<E, C, const MAX: usize> From<CountedArray<E, C, MAX>> for Vec<E> where C: ArrayCounter {
+    fn from(other: CountedArray<E, C, MAX>) -> Self {
         other.data
     }
 }
 
-impl<E, C> From<Vec<E>> for CountedArray<E, C> where C: ArrayCounter {
+impl<E, C, const MAX: usize> From<Vec<E>> for CountedArray<E, C, MAX> where C: ArrayCounter {
     fn from(data: Vec<E>) -> Self {
         Self {
             data,
@@ -783,12 +1686,90 @@ impl<E, C> From<Vec<E>> for CountedArray<E, C> where C: ArrayCounter {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
-impl<E, C> TestRandom for CountedArray<E, C>
+impl<E, C, const MAX: usize, I> core::ops::Index<I> for CountedArray<E, C, MAX>
+    where C: ArrayCounter, I: core::slice::SliceIndex<[E]>
+{
+    type Output = I::Output;
+
+    fn index(&self, index: I) -> &Self::Output {
+        &self.data[index]
+    }
+}
+
+impl<E, C, const MAX: usize, I> core::ops::IndexMut<I> for CountedArray<E, C, MAX>
+    where C: ArrayCounter, I: core::slice::SliceIndex<[E]>
+{
+    fn index_mut(&mut self, index: I) -> &mut Self::Output {
+        &mut self.data[index]
+    }
+}
+
+impl<E, C, const MAX: usize> IntoIterator for CountedArray<E, C, MAX> where C: ArrayCounter {
+    type Item = E;
+    type IntoIter = alloc::vec::IntoIter<E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.data.into_iter()
+    }
+}
+
+impl<'a, E, C, const MAX: usize> IntoIterator for &'a CountedArray<E, C, MAX> where C: ArrayCounter {
+    type Item = &'a E;
+    type IntoIter = core::slice::Iter<'a, E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.data.iter()
+    }
+}
+
+impl<'a, E, C, const MAX: usize> IntoIterator for &'a mut CountedArray<E, C, MAX> where C: ArrayCounter {
+    type Item = &'a mut E;
+    type IntoIter = core::slice::IterMut<'a, E>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.data.iter_mut()
+    }
+}
+
+impl<E, C, const MAX: usize> core::iter::FromIterator<E> for CountedArray<E, C, MAX> where C: ArrayCounter {
+    fn from_iter<T: IntoIterator<Item = E>>(iter: T) -> Self {
+        iter.into_iter().collect::<Vec<E>>().into()
+    }
+}
+
+impl<E, C, const MAX: usize> Extend<E> for CountedArray<E, C, MAX> where C: ArrayCounter {
+    fn extend<T: IntoIterator<Item = E>>(&mut self, iter: T) {
+        self.data.extend(iter)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<E, C, const MAX: usize> serde::Serialize for CountedArray<E, C, MAX> where E: serde::Serialize {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.data.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, E, C, const MAX: usize> serde::Deserialize<'de> for CountedArray<E, C, MAX>
+    where E: serde::Deserialize<'de>, C: ArrayCounter
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = Vec::<E>::deserialize(deserializer)?;
+        if data.len() > MAX {
+            return Err(serde::de::Error::custom(DeserializeErr::ArrayTooLarge { count: data.len(), limit: MAX }));
+        }
+
+        Ok(data.into())
+    }
+}
+
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
+impl<E, C, const MAX: usize> TestRandom for CountedArray<E, C, MAX>
     where E: TestRandom, C: ArrayCounter
 {
     fn test_gen_random() -> Self {
-        let elem_count: usize = rand::random::<usize>() % 32;
+        let elem_count: usize = core::cmp::min((crate::protocol::test_random::<u32>() % 32) as usize, MAX);
         let mut out = Vec::with_capacity(elem_count);
         for _ in 0..elem_count {
             out.push(E::test_gen_random());
@@ -806,6 +1787,14 @@ impl ArrayCounter for VarInt {
     fn from_count(count: usize) -> Self {
         Self(count as i32)
     }
+
+    fn checked_count(&self) -> Result<usize, DeserializeErr> {
+        if self.0 < 0 {
+            Err(DeserializeErr::NegativeLength(*self))
+        } else {
+            Ok(self.as_count())
+        }
+    }
 }
 
 impl ArrayCounter for i16 {
@@ -816,6 +1805,14 @@ impl ArrayCounter for i16 {
     fn from_count(count: usize) -> Self {
         count as i16
     }
+
+    fn checked_count(&self) -> Result<usize, DeserializeErr> {
+        if *self < 0 {
+            Err(DeserializeErr::NegativeLength(VarInt(*self as i32)))
+        } else {
+            Ok(self.as_count())
+        }
+    }
 }
 
 impl ArrayCounter for i32 {
@@ -826,6 +1823,14 @@ impl ArrayCounter for i32 {
     fn from_count(count: usize) -> Self {
         count as i32
     }
+
+    fn checked_count(&self) -> Result<usize, DeserializeErr> {
+        if *self < 0 {
+            Err(DeserializeErr::NegativeLength(VarInt(*self)))
+        } else {
+            Ok(self.as_count())
+        }
+    }
 }
 
 impl ArrayCounter for i8 {
@@ -836,9 +1841,18 @@ impl ArrayCounter for i8 {
     fn from_count(count: usize) -> Self {
         count as i8
     }
+
+    fn checked_count(&self) -> Result<usize, DeserializeErr> {
+        if *self < 0 {
+            Err(DeserializeErr::NegativeLength(VarInt(*self as i32)))
+        } else {
+            Ok(self.as_count())
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RemainingBytes {
     pub data: Vec<u8>,
 }
@@ -847,6 +1861,10 @@ impl Serialize for RemainingBytes {
     fn mc_serialize<S: Serializer>(&self, to: &mut S) -> SerializeResult {
         to.serialize_bytes(self.data.as_slice())
     }
+
+    fn mc_size(&self) -> usize {
+        self.data.len()
+    }
 }
 
 impl Deserialize for RemainingBytes {
@@ -872,6 +1890,52 @@ impl From<Vec<u8>> for RemainingBytes {
     }
 }
 
+#[cfg(feature = "bytes")]
+impl From<RemainingBytes> for bytes::Bytes {
+    fn from(other: RemainingBytes) -> Self {
+        other.data.into()
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl From<bytes::Bytes> for RemainingBytes {
+    fn from(other: bytes::Bytes) -> Self {
+        Self { data: other.into() }
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl Serializer for bytes::BytesMut {
+    fn serialize_bytes(&mut self, data: &[u8]) -> SerializeResult {
+        bytes::BufMut::put_slice(self, data);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl Serialize for bytes::Bytes {
+    fn mc_serialize<S: Serializer>(&self, to: &mut S) -> SerializeResult {
+        to.serialize_bytes(self.as_ref())
+    }
+}
+
+// Like RemainingBytes, takes everything that's left. The input is only borrowed for the duration of
+// the call, so the bytes are copied once into the new buffer.
+#[cfg(feature = "bytes")]
+impl Deserialize for bytes::Bytes {
+    fn mc_deserialize(data: &[u8]) -> DeserializeResult<'_, Self> {
+        Deserialized::ok(bytes::Bytes::copy_from_slice(data), &[])
+    }
+}
+
+// The zero-copy version of the above for input that is already a Bytes: `rest` must point into
+// `source` (e.g. the data left over after deserializing the head of a packet from it), and the
+// result shares the buffer of `source`. Panics if `rest` is not part of `source`.
+#[cfg(feature = "bytes")]
+pub fn bytes_remaining(source: &bytes::Bytes, rest: &[u8]) -> bytes::Bytes {
+    source.slice_ref(rest)
+}
+
 impl core::ops::Deref for RemainingBytes {
     type Target = Vec<u8>;
 
@@ -886,13 +1950,93 @@ impl core::ops::DerefMut for RemainingBytes {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+// Like RemainingBytes, but can borrow the tail of the input instead of copying it, which matters
+// for large payloads that are only passed through. The Deserialize impl can't borrow (the trait
+// doesn't tie the output to the input), so use `borrow_from` for the zero-copy path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CowRemainingBytes<'a> {
+    pub data: alloc::borrow::Cow<'a, [u8]>,
+}
+
+impl<'a> CowRemainingBytes<'a> {
+    pub fn borrow_from(data: &'a [u8]) -> Deserialized<'a, Self> {
+        Deserialized::create(Self { data: alloc::borrow::Cow::Borrowed(data) }, &[])
+    }
+
+    pub fn is_borrowed(&self) -> bool {
+        matches!(self.data, alloc::borrow::Cow::Borrowed(_))
+    }
+
+    pub fn into_owned(self) -> CowRemainingBytes<'static> {
+        CowRemainingBytes { data: alloc::borrow::Cow::Owned(self.data.into_owned()) }
+    }
+}
+
+impl<'a> Serialize for CowRemainingBytes<'a> {
+    fn mc_serialize<S: Serializer>(&self, to: &mut S) -> SerializeResult {
+        to.serialize_bytes(self.data.as_ref())
+    }
+}
+
+impl Deserialize for CowRemainingBytes<'static> {
+    fn mc_deserialize(data: &[u8]) -> DeserializeResult<'_, Self> {
+        Deserialized::ok(Self { data: alloc::borrow::Cow::Owned(Vec::from(data)) }, &[])
+    }
+}
+
+impl<'a> core::ops::Deref for CowRemainingBytes<'a> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        self.data.as_ref()
+    }
+}
+
+impl<'a> From<&'a [u8]> for CowRemainingBytes<'a> {
+    fn from(data: &'a [u8]) -> Self {
+        Self { data: alloc::borrow::Cow::Borrowed(data) }
+    }
+}
+
+impl<'a> From<&'a RemainingBytes> for CowRemainingBytes<'a> {
+    fn from(other: &'a RemainingBytes) -> Self {
+        other.data.as_slice().into()
+    }
+}
+
+impl From<RemainingBytes> for CowRemainingBytes<'static> {
+    fn from(other: RemainingBytes) -> Self {
+        Self { data: alloc::borrow::Cow::Owned(other.data) }
+    }
+}
+
+impl<'a> From<CowRemainingBytes<'a>> for RemainingBytes {
+    fn from(other: CowRemainingBytes<'a>) -> Self {
+        Self { data: other.data.into_owned() }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'a> serde::Serialize for CowRemainingBytes<'a> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.data.as_ref().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for CowRemainingBytes<'static> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self { data: alloc::borrow::Cow::Owned(Vec::<u8>::deserialize(deserializer)?) })
+    }
+}
+
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for RemainingBytes {
     fn test_gen_random() -> Self {
-        let size: usize = rand::random::<usize>() % 256;
+        let size: usize = (crate::protocol::test_random::<u32>() % 256) as usize;
         let mut out = Vec::with_capacity(size);
         for _ in 0..size {
-            out.push(rand::random());
+            out.push(crate::protocol::test_random());
         }
 
         Self { data: out }
@@ -994,6 +2138,211 @@ mod tests {
         test_type(VarLong(-1001237));
     }
 
+    #[test]
+    fn test_var_int_arithmetic() {
+        assert_eq!(VarInt(5) + VarInt(-7), VarInt(-2));
+        assert_eq!(VarInt(5) - VarInt(7), VarInt(-2));
+        assert_eq!(VarInt(-3) * 4, VarInt(-12));
+        assert_eq!(VarInt(i32::MAX - 1).checked_add(VarInt(1)), Some(VarInt(i32::MAX)));
+        assert_eq!(VarInt(i32::MAX).checked_add(VarInt(1)), None);
+        assert_eq!(VarInt(i32::MIN).checked_add(VarInt(-1)), None);
+        assert_eq!(VarInt(i32::MAX).saturating_add(VarInt(1)), VarInt(i32::MAX));
+        assert_eq!(VarInt(i32::MIN).saturating_add(VarInt(-1)), VarInt(i32::MIN));
+
+        assert_eq!(serialized(VarInt(i32::MAX).saturating_add(VarInt(1))), alloc::vec![0xFF, 0xFF, 0xFF, 0xFF, 0x07]);
+        assert_eq!(serialized(VarInt(i32::MIN).saturating_add(VarInt(-1))), alloc::vec![0x80, 0x80, 0x80, 0x80, 0x08]);
+    }
+
+    #[test]
+    fn test_var_long_arithmetic() {
+        assert_eq!(VarLong(5) + VarLong(-7), VarLong(-2));
+        assert_eq!(VarLong(5) - VarLong(7), VarLong(-2));
+        assert_eq!(VarLong(-3) * 4, VarLong(-12));
+        assert_eq!(VarLong(i64::MAX).checked_add(VarLong(1)), None);
+        assert_eq!(VarLong(i64::MIN).checked_add(VarLong(-1)), None);
+        assert_eq!(VarLong(i64::MAX).saturating_add(VarLong(1)), VarLong(i64::MAX));
+        assert_eq!(VarLong(i64::MIN).saturating_add(VarLong(-1)), VarLong(i64::MIN));
+
+        assert_eq!(
+            serialized(VarLong(i64::MAX).saturating_add(VarLong(1))),
+            alloc::vec![0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F]
+        );
+        assert_eq!(
+            serialized(VarLong(i64::MIN).saturating_add(VarLong(-1))),
+            alloc::vec![0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01]
+        );
+    }
+
+    #[test]
+    fn test_var_num_length_limits() {
+        for len in 0..20usize {
+            let data = alloc::vec![0xFFu8; len];
+            match VarInt::mc_deserialize(data.as_slice()) {
+                Err(DeserializeErr::VarNumTooLong(read)) => {
+                    assert!(len >= 5);
+                    assert_eq!(read, alloc::vec![0xFF; 5]);
+                }
+                Err(DeserializeErr::Eof) => assert!(len < 5),
+                other => panic!("unexpected result {:?}", other.map(|d| d.value)),
+            }
+
+            match VarLong::mc_deserialize(data.as_slice()) {
+                Err(DeserializeErr::VarNumTooLong(read)) => {
+                    assert!(len >= 10);
+                    assert_eq!(read, alloc::vec![0xFF; 10]);
+                }
+                Err(DeserializeErr::Eof) => assert!(len < 10),
+                other => panic!("unexpected result {:?}", other.map(|d| d.value)),
+            }
+        }
+    }
+
+    #[test]
+    fn test_var_int_boundary_encodings() {
+        let cases: [(i32, &[u8]); 7] = [
+            (0, &[0x00]),
+            (127, &[0x7F]),
+            (128, &[0x80, 0x01]),
+            (255, &[0xFF, 0x01]),
+            (-1, &[0xFF, 0xFF, 0xFF, 0xFF, 0x0F]),
+            (i32::MAX, &[0xFF, 0xFF, 0xFF, 0xFF, 0x07]),
+            (i32::MIN, &[0x80, 0x80, 0x80, 0x80, 0x08]),
+        ];
+
+        for (value, bytes) in cases.iter() {
+            assert_eq!(serialized(VarInt(*value)).as_slice(), *bytes);
+            let Deserialized { value: read, data: rest } = VarInt::mc_deserialize(bytes).expect("valid encoding");
+            assert_eq!(read, VarInt(*value));
+            assert!(rest.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_var_long_boundary_encodings() {
+        let cases: [(i64, &[u8]); 8] = [
+            (0, &[0x00]),
+            (127, &[0x7F]),
+            (128, &[0x80, 0x01]),
+            (i32::MAX as i64, &[0xFF, 0xFF, 0xFF, 0xFF, 0x07]),
+            (1 << 35, &[0x80, 0x80, 0x80, 0x80, 0x80, 0x01]),
+            (-1, &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01]),
+            (i64::MAX, &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F]),
+            (i64::MIN, &[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01]),
+        ];
+
+        for (value, bytes) in cases.iter() {
+            assert_eq!(serialized(VarLong(*value)).as_slice(), *bytes);
+            assert_eq!(VarLong(*value).len(), bytes.len());
+            let Deserialized { value: read, data: rest } = VarLong::mc_deserialize(bytes).expect("valid encoding");
+            assert_eq!(read, VarLong(*value));
+            assert!(rest.is_empty());
+        }
+
+        let mut too_long = alloc::vec![0x80u8; VarLong::MAX_LEN];
+        too_long.push(0x00);
+        match VarLong::mc_deserialize(too_long.as_slice()) {
+            Err(DeserializeErr::VarNumTooLong(read)) => assert_eq!(read.len(), VarLong::MAX_LEN),
+            other => panic!("expected var num too long, got {:?}", other.map(|d| d.value)),
+        }
+    }
+
+    #[test]
+    fn test_var_num_len() {
+        for value in [0, 1, 127, 128, 16383, 16384, 2097151, 2097152, i32::MAX, -1, i32::MIN].iter() {
+            assert_eq!(VarInt(*value).len(), serialized(VarInt(*value)).len());
+        }
+        for shift in 0..64 {
+            let value = 1i64 << shift;
+            assert_eq!(VarLong(value).len(), serialized(VarLong(value)).len());
+            assert_eq!(VarLong(value.wrapping_sub(1)).len(), serialized(VarLong(value.wrapping_sub(1))).len());
+        }
+        assert_eq!(VarInt(-1).len(), VarInt::MAX_LEN);
+        assert_eq!(VarLong(-1).len(), VarLong::MAX_LEN);
+    }
+
+    #[test]
+    fn test_var_num_rejects_malformed() {
+        let malformed: [&[u8]; 5] = [
+            &[0x80, 0x80, 0x80, 0x80, 0x80, 0x00],
+            &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF],
+            &[0xFF, 0xFF, 0xFF, 0xFF, 0x1F],
+            &[0x80, 0x80, 0x80, 0x80, 0x10],
+            &[0x80, 0x80, 0x80, 0x80, 0x70],
+        ];
+        for bytes in malformed.iter() {
+            match VarInt::mc_deserialize(bytes) {
+                Err(DeserializeErr::VarNumTooLong(read)) => assert_eq!(read.as_slice(), &bytes[..5]),
+                other => panic!("expected var num too long for {:?}, got {:?}", bytes, other.map(|d| d.value)),
+            }
+        }
+
+        assert!(matches!(VarInt::mc_deserialize(&[0x80, 0x80]), Err(DeserializeErr::Eof)));
+
+        let mut long_max = alloc::vec![0xFFu8; 9];
+        long_max.push(0x01);
+        assert_eq!(VarLong::mc_deserialize(long_max.as_slice()).expect("valid encoding").value, VarLong(-1));
+        long_max[9] = 0x02;
+        assert!(matches!(VarLong::mc_deserialize(long_max.as_slice()), Err(DeserializeErr::VarNumTooLong(_))));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_var_num_arbitrary_bytes() {
+        use rand::RngCore;
+        let mut rng = crate::protocol::test_rng();
+        for _ in 0..10000 {
+            let mut data = alloc::vec![0u8; (rng.next_u32() % 16) as usize];
+            rng.fill_bytes(data.as_mut_slice());
+            // bias towards long runs of continuation bits
+            for byte in data.iter_mut() {
+                if rng.next_u32() % 4 != 0 {
+                    *byte |= 0x80;
+                }
+            }
+
+            if let Ok(Deserialized { data: rest, .. }) = VarInt::mc_deserialize(data.as_slice()) {
+                assert!(data.len() - rest.len() <= 5);
+            }
+
+            if let Ok(Deserialized { data: rest, .. }) = VarLong::mc_deserialize(data.as_slice()) {
+                assert!(data.len() - rest.len() <= 10);
+            }
+        }
+    }
+
+    #[test]
+    fn test_limited_string() {
+        let name: LimitedString<16> = String::from("Notch").into();
+        test_type(name);
+
+        let long = serialized("a".repeat(17));
+        match LimitedString::<16>::mc_deserialize(long.as_slice()) {
+            Err(DeserializeErr::StringTooLong { length, max }) => {
+                assert_eq!(length, 17);
+                assert_eq!(max, 16);
+            }
+            other => panic!("expected string too long, got {:?}", other.map(|d| d.value)),
+        }
+        let too_long: LimitedString<16> = "a".repeat(17).into();
+        assert!(too_long.mc_serialize_to_vec().is_err());
+
+        // a name of 16 accented characters is 32 bytes but only 16 code units
+        let wide: LimitedString<16> = "é".repeat(16).into();
+        test_type(wide);
+        let too_wide: LimitedString<16> = "é".repeat(17).into();
+        assert!(too_wide.mc_serialize_to_vec().is_err());
+    }
+
+    #[test]
+    fn test_two_gigabyte_string() {
+        // a login start packet claiming a ~2 GB name, nothing after the length
+        let mut packet = serialized(VarInt(0x00));
+        packet.extend(serialized(VarInt(i32::MAX)));
+        let name = VarInt::mc_deserialize(packet.as_slice()).expect("has packet id").data;
+        assert!(matches!(String::mc_deserialize(name), Err(DeserializeErr::StringTooLong { .. })));
+        assert!(matches!(deserialize_string_limited(name, CHAT_MAX_LENGTH), Err(DeserializeErr::StringTooLong { .. })));
+    }
+
     #[test]
     fn test_string() {
         test_type(String::from("hello my name is joey 123"));
@@ -1002,6 +2351,537 @@ mod tests {
         test_type(String::from("hello my name is joey 123").repeat(1000));
     }
 
+    #[test]
+    fn test_string_length_limits() {
+        test_type("a".repeat(STRING_MAX_LENGTH));
+
+        let mut out = BytesSerializer::default();
+        assert!("a".repeat(STRING_MAX_LENGTH + 1).mc_serialize(&mut out).is_err());
+        // multi-byte characters count as one code unit each, the same as when reading
+        assert!("é".repeat(STRING_MAX_LENGTH + 1).mc_serialize(&mut out).is_err());
+        test_type("é".repeat(STRING_MAX_LENGTH));
+        // characters outside the BMP take two code units
+        assert!("😀".repeat(STRING_MAX_LENGTH / 2 + 1).mc_serialize(&mut out).is_err());
+        test_type("😀".repeat(STRING_MAX_LENGTH / 2));
+
+        let declared = serialized(VarInt(STRING_MAX_LENGTH as i32 * 4 + 1));
+        match String::mc_deserialize(declared.as_slice()) {
+            Err(DeserializeErr::StringTooLong { length, max }) => {
+                assert_eq!(length, STRING_MAX_LENGTH * 4 + 1);
+                assert_eq!(max, STRING_MAX_LENGTH * 4);
+            }
+            other => panic!("expected string too long, got {:?}", other.map(|d| d.value)),
+        }
+
+        let mut oversized = serialized(VarInt(STRING_MAX_LENGTH as i32 + 1));
+        assert!(matches!(String::mc_deserialize(oversized.as_slice()), Err(DeserializeErr::Eof)));
+        oversized.extend("a".repeat(STRING_MAX_LENGTH + 1).as_bytes());
+        match String::mc_deserialize(oversized.as_slice()) {
+            Err(DeserializeErr::StringTooLong { length, max }) => {
+                assert_eq!(length, STRING_MAX_LENGTH + 1);
+                assert_eq!(max, STRING_MAX_LENGTH);
+            }
+            other => panic!("expected string too long, got {:?}", other.map(|d| d.value)),
+        }
+
+        // multi-byte characters count as one code unit each when reading, like vanilla
+        let mut wide = serialized(VarInt((STRING_MAX_LENGTH * 2) as i32));
+        wide.extend("é".repeat(STRING_MAX_LENGTH).as_bytes());
+        assert_eq!(String::mc_deserialize(wide.as_slice()).expect("fits in the limit").value.chars().count(), STRING_MAX_LENGTH);
+
+        let huge = serialized(VarInt(i32::MAX));
+        assert!(matches!(String::mc_deserialize(huge.as_slice()), Err(DeserializeErr::StringTooLong { .. })));
+
+        let chat = "a".repeat(STRING_MAX_LENGTH + 1);
+        let mut out = BytesSerializer::default();
+        serialize_string_limited(chat.as_str(), CHAT_MAX_LENGTH, &mut out).expect("fits in the chat limit");
+        let bytes = out.into_bytes();
+        assert_eq!(deserialize_string_limited(bytes.as_slice(), CHAT_MAX_LENGTH).expect("fits in the chat limit").value, chat);
+    }
+
+    #[test]
+    fn test_counted_array_limits() {
+        test_type(<CountedArray<u8, VarInt>>::from(alloc::vec![7u8; DEFAULT_MAX_ARRAY_COUNT]));
+        test_type(<CountedArray<u8, VarInt, 4>>::from(alloc::vec![1u8, 2, 3, 4]));
+
+        let huge = serialized(VarInt(i32::MAX));
+        match <CountedArray<u8, VarInt>>::mc_deserialize(huge.as_slice()) {
+            Err(DeserializeErr::ArrayTooLarge { count, limit }) => {
+                assert_eq!(count, i32::MAX as usize);
+                assert_eq!(limit, DEFAULT_MAX_ARRAY_COUNT);
+            }
+            other => panic!("expected array too large, got {:?}", other.map(|d| d.value)),
+        }
+
+        let negative = serialized(VarInt(-1));
+        assert!(matches!(<CountedArray<u8, VarInt>>::mc_deserialize(negative.as_slice()), Err(DeserializeErr::NegativeLength(VarInt(-1)))));
+
+        let mut small = serialized(VarInt(5));
+        small.extend_from_slice(&[1, 2, 3, 4, 5]);
+        assert!(matches!(<CountedArray<u8, VarInt, 4>>::mc_deserialize(small.as_slice()), Err(DeserializeErr::ArrayTooLarge { count: 5, limit: 4 })));
+        assert_eq!(<CountedArray<u8, VarInt, 5>>::mc_deserialize(small.as_slice()).expect("within the limit").value.len(), 5);
+
+        // a count within the limit but beyond the available data doesn't preallocate for it
+        let truncated = serialized(VarInt(DEFAULT_MAX_ARRAY_COUNT as i32));
+        assert!(matches!(<CountedArray<u64, VarInt>>::mc_deserialize(truncated.as_slice()), Err(DeserializeErr::Eof)));
+
+        let mut out = BytesSerializer::default();
+        assert!(<CountedArray<u8, VarInt, 4>>::from(alloc::vec![0u8; 5]).mc_serialize(&mut out).is_err());
+    }
+
+    #[test]
+    fn test_counted_array_collections() {
+        let mut array: CountedArray<u32, VarInt> = (1..=3u32).collect();
+        assert_eq!(array[0], 1);
+        assert_eq!(&array[1..], &[2, 3]);
+
+        array.extend(alloc::vec![4, 5]);
+        array[4] = 50;
+        for elem in &mut array {
+            *elem *= 2;
+        }
+
+        let mut sum = 0;
+        for elem in &array {
+            sum += *elem;
+        }
+        assert_eq!(sum, 2 + 4 + 6 + 8 + 100);
+
+        let copied: CountedArray<u32, i16> = array.iter().copied().collect();
+        assert_eq!(copied.len(), 5);
+        assert_eq!(array.into_iter().collect::<Vec<u32>>(), alloc::vec![2, 4, 6, 8, 100]);
+        test_type(copied);
+    }
+
+    #[test]
+    fn test_counted_array_hostile_counts() {
+        fn check<C: ArrayCounter>(counts: &[i64]) {
+            for count in counts {
+                let mut data = serialized(C::from_count(*count as usize));
+                data.extend_from_slice(&[1, 2, 3]);
+                let result = <CountedArray<u32, C>>::mc_deserialize(data.as_slice()).map(|d| d.value);
+                match (*count < 0, *count as usize > DEFAULT_MAX_ARRAY_COUNT) {
+                    (true, _) => assert!(matches!(result, Err(DeserializeErr::NegativeLength(_))), "count {}", count),
+                    (false, true) => assert!(matches!(result, Err(DeserializeErr::ArrayTooLarge { .. })), "count {}", count),
+                    (false, false) => assert!(matches!(result, Err(DeserializeErr::Eof)), "count {}", count),
+                }
+            }
+        }
+
+        check::<VarInt>(&[2, 65536, i32::MAX as i64, -1, i32::MIN as i64]);
+        check::<i32>(&[2, 65536, i32::MAX as i64, -1, i32::MIN as i64]);
+        check::<i16>(&[2, i16::MAX as i64, -1, i16::MIN as i64]);
+        check::<i8>(&[2, i8::MAX as i64, -1, i8::MIN as i64]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_deserialize_err_is_error() {
+        fn read_string(data: &[u8]) -> Result<String, Box<dyn std::error::Error>> {
+            Ok(String::mc_deserialize(data)?.value)
+        }
+
+        let err = read_string(&[]).expect_err("empty input");
+        assert_eq!(err.to_string(), "unexpected end of input");
+        assert!(err.source().is_none());
+
+        let err = read_string(&[0x02, 0xC3, 0x28]).expect_err("invalid utf-8");
+        assert!(err.source().is_some());
+
+        let err = DeserializeErr::NegativeLength(VarInt(-4));
+        assert_eq!(alloc::format!("{}", err), "negative length prefix: -4");
+        let err = DeserializeErr::CannotUnderstandValue("bad".to_owned());
+        assert_eq!(alloc::format!("{}", err), "cannot understand value: bad");
+    }
+
+    #[test]
+    fn test_serialize_to_vec_and_deserialize_complete() {
+        let bytes = VarInt(300).mc_serialize_to_vec().expect("serializes");
+        assert_eq!(bytes, alloc::vec![0xAC, 0x02]);
+        assert_eq!(VarInt::mc_deserialize_complete(bytes.as_slice()).expect("parses"), VarInt(300));
+
+        let mut extra = bytes.clone();
+        extra.push(0x01);
+        match VarInt::mc_deserialize_complete(extra.as_slice()) {
+            Err(DeserializeErr::ExtraData(rest)) => assert_eq!(rest, alloc::vec![0x01]),
+            other => panic!("expected extra data, got {:?}", other),
+        }
+
+        assert!(matches!(VarInt::mc_deserialize_complete(&bytes[..1]), Err(DeserializeErr::Eof)));
+        assert!("a".repeat(STRING_MAX_LENGTH + 1).mc_serialize_to_vec().is_err());
+    }
+
+    #[test]
+    fn test_counting_serializer() {
+        assert_eq!(mc_serialize_byte_len(&VarInt(0)).expect("counts"), 1);
+        assert_eq!(mc_serialize_byte_len(&VarInt(-1)).expect("counts"), 5);
+        assert_eq!(mc_serialize_byte_len(&VarLong(-1)).expect("counts"), 10);
+        assert_eq!(mc_serialize_byte_len(&true).expect("counts"), 1);
+
+        let value = String::from("hello my name is joey 123").repeat(100);
+        assert_eq!(mc_serialize_byte_len(&value).expect("counts"), serialized(value.clone()).len());
+
+        let array: CountedArray<IntPosition, VarInt> = alloc::vec![IntPosition { x: 1, y: 2, z: 3 }; 200].into();
+        let mut counter = CountingSerializer::default();
+        array.mc_serialize(&mut counter).expect("counts");
+        assert_eq!(counter.count, 2 + 200 * 8);
+        assert_eq!(array.mc_size(), counter.count);
+
+        assert!(mc_serialize_byte_len(&"a".repeat(STRING_MAX_LENGTH + 1)).is_err());
+
+        assert_eq!(VarInt(-1).mc_size(), 5);
+        assert_eq!(0u16.mc_size(), 2);
+        assert_eq!(value.mc_size(), serialized(value.clone()).len());
+        assert_eq!(Some(VarLong(300)).mc_size(), 3);
+        assert_eq!(<Option<u64>>::None.mc_size(), 1);
+        assert_eq!(Vec3 { x: 1.0f64, y: 2.0, z: 3.0 }.mc_size(), 24);
+    }
+
+    #[test]
+    fn test_bytes_serializer_reuse() {
+        let first = IntPosition { x: 1, y: 2, z: 3 };
+        let len = mc_serialize_byte_len(&first).expect("counts");
+        let mut out = BytesSerializer::with_capacity(len);
+        let capacity = out.capacity();
+        assert!(capacity >= len);
+
+        first.mc_serialize(&mut out).expect("serializes");
+        assert_eq!(out.as_bytes(), serialized(first).as_slice());
+
+        out.reset();
+        assert!(out.as_bytes().is_empty());
+        assert_eq!(out.capacity(), capacity);
+
+        let second = IntPosition { x: -1, y: -2, z: -3 };
+        second.mc_serialize(&mut out).expect("serializes");
+        assert_eq!(out.capacity(), capacity);
+        assert_eq!(out.into_bytes(), serialized(second));
+    }
+
+    #[test]
+    fn test_slice_serializer() {
+        let value = IntPosition { x: 1, y: 2, z: 3 };
+        let expected = serialized(value);
+
+        let mut stack = [0u8; 16];
+        let mut out = SliceSerializer::new(&mut stack);
+        value.mc_serialize(&mut out).expect("fits");
+        assert_eq!(out.position(), expected.len());
+        assert_eq!(out.remaining(), 16 - expected.len());
+        assert_eq!(out.as_bytes(), expected.as_slice());
+
+        VarInt(-1).mc_serialize(&mut out).expect("fits");
+        match value.mc_serialize(&mut out) {
+            Err(SerializeErr::BufferFull { needed, remaining }) => {
+                assert_eq!(needed, 8);
+                assert_eq!(remaining, 3);
+            }
+            other => panic!("expected buffer full, got {:?}", other),
+        }
+        assert_eq!(out.position(), expected.len() + 5);
+        assert_eq!(&out.into_bytes()[expected.len()..], &[0xFF, 0xFF, 0xFF, 0xFF, 0x0F]);
+
+        let mut raw = [0u8; 9];
+        let mut cursor = &mut raw[..];
+        value.mc_serialize(&mut cursor).expect("fits");
+        assert_eq!(cursor.len(), 1);
+        assert!(VarInt(300).mc_serialize(&mut cursor).is_err());
+        true.mc_serialize(&mut cursor).expect("fits");
+        assert!(cursor.is_empty());
+        assert_eq!(&raw[..8], expected.as_slice());
+        assert_eq!(raw[8], 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_representations() {
+        use serde_json::{json, to_value, from_value};
+
+        assert_eq!(to_value(VarInt(-300)).expect("serializes"), json!(-300));
+        assert_eq!(to_value(VarLong(1 << 40)).expect("serializes"), json!(1i64 << 40));
+        assert_eq!(to_value(Angle { value: 64 }).expect("serializes"), json!(90.0));
+        assert_eq!(from_value::<Angle>(json!(-90.0)).expect("deserializes"), Angle { value: 192 });
+        assert_eq!(from_value::<Angle>(json!(361.0)).expect("deserializes"), Angle { value: 1 });
+        assert_eq!(to_value(FixedInt::new(2.5, FIXED_INT_FRACTIONAL_BITS)).expect("serializes"), json!(2.5));
+
+        let uuid = UUID4::from(0x0123456789abcdef0123456789abcdefu128);
+        assert_eq!(to_value(uuid).expect("serializes"), json!("01234567-89ab-cdef-0123-456789abcdef"));
+
+        let tag = NamedNbtTag { root: nbt::Tag::Compound(alloc::vec![nbt::Tag::Int(7).with_name("x")]).with_name("") };
+        let encoded = to_value(&tag).expect("serializes");
+        assert_eq!(encoded, json!(base64::encode(serialized(tag.clone()))));
+        assert_eq!(from_value::<NamedNbtTag>(encoded).expect("deserializes"), tag);
+
+        let array: CountedArray<u8, VarInt, 2> = alloc::vec![1, 2].into();
+        assert_eq!(to_value(&array).expect("serializes"), json!([1, 2]));
+        assert!(from_value::<CountedArray<u8, VarInt, 2>>(json!([1, 2, 3])).is_err());
+
+        let name: LimitedString<16> = from_value(json!("é".repeat(16))).expect("deserializes");
+        assert_eq!(to_value(&name).expect("serializes"), json!("é".repeat(16)));
+        assert!(from_value::<LimitedString<16>>(json!("a".repeat(17))).is_err());
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn test_bytes_integration() {
+        let value = IntPosition { x: 1, y: 2, z: 3 };
+        let mut out = bytes::BytesMut::new();
+        value.mc_serialize(&mut out).expect("serializes");
+        VarInt(300).mc_serialize(&mut out).expect("serializes");
+        let expected = [serialized(value), serialized(VarInt(300))].concat();
+        assert_eq!(&out[..], expected.as_slice());
+
+        let frozen = out.freeze();
+        let Deserialized { value: rest, data } = bytes::Bytes::mc_deserialize(&frozen[8..]).expect("deserializes");
+        assert!(data.is_empty());
+        assert_eq!(&rest[..], &[0xAC, 0x02]);
+        assert_eq!(serialized(rest.clone()), alloc::vec![0xAC, 0x02]);
+
+        let remaining: RemainingBytes = frozen.clone().into();
+        assert_eq!(remaining.data, expected);
+        let back: bytes::Bytes = remaining.into();
+        assert_eq!(back, frozen);
+
+        let Deserialized { data: rest, .. } = IntPosition::mc_deserialize(&frozen[..]).expect("deserializes");
+        let shared = bytes_remaining(&frozen, rest);
+        assert_eq!(&shared[..], &[0xAC, 0x02]);
+        assert_eq!(shared.as_ptr(), frozen[8..].as_ptr());
+    }
+
+    #[test]
+    fn test_item_stack_nbt_accessors() {
+        // {Damage:12,Enchantments:[{id:"minecraft:sharpness",lvl:5s},{id:"minecraft:unbreaking",lvl:3s}],
+        //  RepairCost:3,display:{Name:'{"text":"Excalibur"}'}} on a diamond sword
+        let captured: &[u8] = &[
+            0xDB, 0x04, 0x01, 0x0A, 0x00, 0x00, 0x03, 0x00, 0x06, 0x44, 0x61, 0x6D, 0x61, 0x67, 0x65, 0x00,
+            0x00, 0x00, 0x0C, 0x09, 0x00, 0x0C, 0x45, 0x6E, 0x63, 0x68, 0x61, 0x6E, 0x74, 0x6D, 0x65, 0x6E,
+            0x74, 0x73, 0x0A, 0x00, 0x00, 0x00, 0x02, 0x08, 0x00, 0x02, 0x69, 0x64, 0x00, 0x13, 0x6D, 0x69,
+            0x6E, 0x65, 0x63, 0x72, 0x61, 0x66, 0x74, 0x3A, 0x73, 0x68, 0x61, 0x72, 0x70, 0x6E, 0x65, 0x73,
+            0x73, 0x02, 0x00, 0x03, 0x6C, 0x76, 0x6C, 0x00, 0x05, 0x00, 0x08, 0x00, 0x02, 0x69, 0x64, 0x00,
+            0x14, 0x6D, 0x69, 0x6E, 0x65, 0x63, 0x72, 0x61, 0x66, 0x74, 0x3A, 0x75, 0x6E, 0x62, 0x72, 0x65,
+            0x61, 0x6B, 0x69, 0x6E, 0x67, 0x02, 0x00, 0x03, 0x6C, 0x76, 0x6C, 0x00, 0x03, 0x00, 0x03, 0x00,
+            0x0A, 0x52, 0x65, 0x70, 0x61, 0x69, 0x72, 0x43, 0x6F, 0x73, 0x74, 0x00, 0x00, 0x00, 0x03, 0x0A,
+            0x00, 0x07, 0x64, 0x69, 0x73, 0x70, 0x6C, 0x61, 0x79, 0x08, 0x00, 0x04, 0x4E, 0x61, 0x6D, 0x65,
+            0x00, 0x14, 0x7B, 0x22, 0x74, 0x65, 0x78, 0x74, 0x22, 0x3A, 0x22, 0x45, 0x78, 0x63, 0x61, 0x6C,
+            0x69, 0x62, 0x75, 0x72, 0x22, 0x7D, 0x00, 0x00,
+        ];
+
+        let sword = ItemStack::mc_deserialize_complete(captured).expect("captured sword deserializes");
+        assert_eq!(sword.item_id, VarInt(603));
+        assert_eq!(sword.display_name(), Some(Chat::from_text("Excalibur")));
+        assert!(sword.lore().is_empty());
+        assert_eq!(sword.enchantments(), alloc::vec![
+            ("minecraft:sharpness".to_owned(), 5),
+            ("minecraft:unbreaking".to_owned(), 3),
+        ]);
+        assert_eq!(sword.damage(), Some(12));
+        assert!(!sword.unbreakable());
+        assert_eq!(serialized(sword.clone()).as_slice(), captured);
+
+        let mut edited = sword;
+        let mut enchantments = edited.enchantments();
+        enchantments.push(("minecraft:mending".to_owned(), 1));
+        edited.set_enchantments(enchantments.as_slice());
+        edited.set_damage(Some(40));
+        edited.set_unbreakable(true);
+        edited.set_lore(&[Chat::from_text("Pulled from the stone")]);
+
+        let edited = ItemStack::mc_deserialize_complete(serialized(edited).as_slice()).expect("edited sword deserializes");
+        assert_eq!(edited.display_name(), Some(Chat::from_text("Excalibur")));
+        assert_eq!(edited.lore(), alloc::vec![Chat::from_text("Pulled from the stone")]);
+        assert_eq!(edited.enchantments(), enchantments);
+        assert_eq!(edited.damage(), Some(40));
+        assert!(edited.unbreakable());
+        let tag = &edited.nbt.as_ref().expect("has nbt").payload;
+        assert_eq!(tag.get("RepairCost"), Some(&nbt::Tag::Int(3)));
+
+        let mut plain = ItemStack { item_id: VarInt(1), item_count: 1, nbt: None };
+        assert_eq!(plain.display_name(), None);
+        assert_eq!(plain.damage(), None);
+        plain.set_display_name(Some(&Chat::from_text("Rock")));
+        plain.set_damage(Some(1));
+        plain.set_damage(None);
+        assert_eq!(plain.display_name(), Some(Chat::from_text("Rock")));
+        assert_eq!(plain.damage(), None);
+        plain.set_display_name(None);
+        assert_eq!(plain.display_name(), None);
+        // the display compound and then the root tag were left empty
+        assert_eq!(plain.nbt, None);
+
+        let mut untagged = ItemStack { item_id: VarInt(1), item_count: 1, nbt: None };
+        untagged.set_display_name(None);
+        untagged.set_damage(None);
+        untagged.set_unbreakable(false);
+        assert_eq!(untagged.nbt, None);
+
+        // clearing leaves other entries, and tags that were already empty, alone
+        let mut named = ItemStack { item_id: VarInt(1), item_count: 1, nbt: None };
+        named.set_display_name(Some(&Chat::from_text("Rock")));
+        named.set_lore(&[Chat::from_text("Grey")]);
+        named.set_unbreakable(true);
+        named.set_display_name(None);
+        named.set_unbreakable(false);
+        let tag = &named.nbt.as_ref().expect("lore is left").payload;
+        assert_eq!(tag.get("Unbreakable"), None);
+        assert_eq!(tag.get_compound("display").and_then(move |display| display.get("Name")), None);
+        assert_eq!(named.lore(), alloc::vec![Chat::from_text("Grey")]);
+
+        let mut empty = ItemStack { item_id: VarInt(1), item_count: 1, nbt: Some(nbt::Tag::Compound(Vec::new()).with_name("")) };
+        empty.set_damage(None);
+        assert_eq!(empty.nbt, Some(nbt::Tag::Compound(Vec::new()).with_name("")));
+    }
+
+    #[test]
+    fn test_item_stack_builder() {
+        let plain = ItemStack::builder(1).count(64).build();
+        assert_eq!(plain, Some(ItemStack { item_id: VarInt(1), item_count: 64, nbt: None }));
+        assert_eq!(serialized(plain), alloc::vec![0x01, 0x01, 0x40, 0x00]);
+
+        let empty_tag = ItemStack::builder(1).nbt(nbt::Tag::Compound(Vec::new()).with_name("")).build();
+        assert_eq!(serialized(empty_tag), alloc::vec![0x01, 0x01, 0x01, 0x00]);
+
+        let sword = ItemStack::builder(603)
+            .name(Chat::from_text("Excalibur"))
+            .lore(alloc::vec![Chat::from_text("Pulled from the stone")])
+            .enchant("minecraft:sharpness", 5)
+            .enchant("minecraft:unbreaking", 3)
+            .nbt(nbt::Tag::Compound(alloc::vec![nbt::Tag::Int(3).with_name("RepairCost")]).with_name(""))
+            .build()
+            .expect("builds a stack");
+        assert_eq!(sword.item_count, 1);
+
+        let tag = &sword.nbt.as_ref().expect("has nbt").payload;
+        assert_eq!(tag.get("RepairCost"), Some(&nbt::Tag::Int(3)));
+        assert_eq!(tag.get("Enchantments"), Some(&nbt::Tag::List(alloc::vec![
+            nbt::Tag::Compound(alloc::vec![
+                nbt::Tag::String("minecraft:sharpness".to_owned()).with_name("id"),
+                nbt::Tag::Short(5).with_name("lvl"),
+            ]),
+            nbt::Tag::Compound(alloc::vec![
+                nbt::Tag::String("minecraft:unbreaking".to_owned()).with_name("id"),
+                nbt::Tag::Short(3).with_name("lvl"),
+            ]),
+        ])));
+        let display = tag.get("display").expect("has display");
+        assert!(matches!(display.get("Name"), Some(nbt::Tag::String(_))));
+        assert!(matches!(display.get("Lore"), Some(nbt::Tag::List(lines)) if lines.len() == 1));
+
+        let sword = Slot::mc_deserialize_complete(serialized(Some(sword)).as_slice()).expect("deserializes").expect("present");
+        assert_eq!(sword.display_name(), Some(Chat::from_text("Excalibur")));
+        assert_eq!(sword.lore(), alloc::vec![Chat::from_text("Pulled from the stone")]);
+        assert_eq!(sword.enchantments().len(), 2);
+    }
+
+    #[test]
+    fn test_slot_ext() {
+        let pick = ItemStack::builder(600).count(1).damage(12).build();
+        assert!(pick.is_present());
+        assert_eq!(pick.item_id(), Some(VarInt(600)));
+        assert_eq!(pick.item_count(), Some(1));
+        assert_eq!(pick.as_ref().and_then(ItemStack::damage), Some(12));
+
+        let empty: Slot = None;
+        assert!(!empty.is_present());
+        assert_eq!(empty.item_id(), None);
+        assert_eq!(empty.item_count(), None);
+    }
+
+    #[cfg(feature = "compression")]
+    const COMPRESSED_NBT_RAW: [u8; 24] = [
+        0x0a, 0x00, 0x00, 0x03, 0x00, 0x01, 0x78, 0x00, 0x00, 0x00, 0x07, 0x08, 0x00, 0x04, 0x6e, 0x61,
+        0x6d, 0x65, 0x00, 0x03, 0x62, 0x6f, 0x62, 0x00,
+    ];
+
+    #[cfg(feature = "compression")]
+    const COMPRESSED_NBT_GZIP: [u8; 44] = [
+        0x1f, 0x8b, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0xe3, 0x62, 0x60, 0x60, 0x66, 0x60,
+        0xac, 0x60, 0x60, 0x60, 0x60, 0xe7, 0x60, 0x60, 0xc9, 0x4b, 0xcc, 0x4d, 0x65, 0x60, 0x4e, 0xca,
+        0x4f, 0x62, 0x00, 0x00, 0x2d, 0xbf, 0xbc, 0x81, 0x18, 0x00, 0x00, 0x00,
+    ];
+
+    #[cfg(feature = "compression")]
+    const COMPRESSED_NBT_ZLIB: [u8; 32] = [
+        0x78, 0x9c, 0xe3, 0x62, 0x60, 0x60, 0x66, 0x60, 0xac, 0x60, 0x60, 0x60, 0x60, 0xe7, 0x60, 0x60,
+        0xc9, 0x4b, 0xcc, 0x4d, 0x65, 0x60, 0x4e, 0xca, 0x4f, 0x62, 0x00, 0x00, 0x1c, 0x48, 0x03, 0x71,
+    ];
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_compressed_nbt() {
+        let expected = NamedNbtTag::mc_deserialize_complete(&COMPRESSED_NBT_RAW).expect("valid nbt");
+        assert_eq!(expected.get_path("x"), Ok(&nbt::Tag::Int(7)));
+
+        assert_eq!(NamedNbtTag::from_gzip(&COMPRESSED_NBT_GZIP).expect("valid gzip"), expected);
+        assert_eq!(NamedNbtTag::from_zlib(&COMPRESSED_NBT_ZLIB).expect("valid zlib"), expected);
+        for data in [&COMPRESSED_NBT_GZIP[..], &COMPRESSED_NBT_ZLIB[..], &COMPRESSED_NBT_RAW[..]] {
+            assert_eq!(NamedNbtTag::from_compressed(data).expect("detects compression"), expected);
+        }
+
+        assert_eq!(NamedNbtTag::from_compressed(expected.to_gzip().as_slice()).expect("round trips"), expected);
+        assert_eq!(NamedNbtTag::from_compressed(expected.to_zlib().as_slice()).expect("round trips"), expected);
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_compressed_nbt_errors() {
+        let mut corrupt = COMPRESSED_NBT_GZIP;
+        corrupt[20] ^= 0xff;
+        assert!(matches!(NamedNbtTag::from_gzip(&corrupt), Err(CompressedNbtErr::Decompress(_))));
+        assert!(matches!(NamedNbtTag::from_zlib(&COMPRESSED_NBT_GZIP), Err(CompressedNbtErr::Decompress(_))));
+
+        let zlib = NamedNbtTag::mc_deserialize_complete(&COMPRESSED_NBT_RAW).expect("valid nbt").to_zlib();
+        let not_nbt = {
+            let mut out = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+            std::io::Write::write_all(&mut out, &[0x0a, 0x00]).expect("write succeeds");
+            out.finish().expect("finish succeeds")
+        };
+        assert!(matches!(NamedNbtTag::from_zlib(not_nbt.as_slice()), Err(CompressedNbtErr::Nbt(_))));
+        assert!(matches!(NamedNbtTag::from_zlib(&zlib[..zlib.len() / 2]), Err(CompressedNbtErr::Decompress(_))));
+        assert!(matches!(NamedNbtTag::from_compressed(&[0x42, 0x00]), Err(CompressedNbtErr::UnknownCompression(Some(0x42)))));
+        assert!(matches!(NamedNbtTag::from_compressed(&[]), Err(CompressedNbtErr::UnknownCompression(None))));
+
+        // a few kilobytes which inflate past the limit
+        let max = crate::codec::DEFAULT_MAX_UNCOMPRESSED_SIZE;
+        let bomb = {
+            let mut out = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+            std::io::Write::write_all(&mut out, &alloc::vec![0u8; max + 1]).expect("write succeeds");
+            out.finish().expect("finish succeeds")
+        };
+        assert!(bomb.len() < max / 100);
+        assert!(matches!(NamedNbtTag::from_gzip(bomb.as_slice()), Err(CompressedNbtErr::TooLarge { max: limit }) if limit == max));
+        assert!(matches!(NamedNbtTag::from_compressed(bomb.as_slice()), Err(CompressedNbtErr::TooLarge { .. })));
+
+        let large = NamedNbtTag { root: nbt::Tag::Compound(alloc::vec![nbt::Tag::ByteArray(alloc::vec![7; max - 64]).with_name("data")]).with_name("") };
+        assert_eq!(NamedNbtTag::from_gzip(large.to_gzip().as_slice()).expect("fits in the limit"), large);
+    }
+
+    #[test]
+    fn test_nbt_display() {
+        let tag = NamedNbtTag::parse_snbt(r#"{name:"Steve",pos:[1.5d,2.0d],inventory:[{id:"minecraft:stone",Count:3b}],
+            tags:[],empty:{},heights:[L;1L,2L,3L,4L,5L,6L,7L,8L,9L,10L,11L,12L,13L,14L,15L,16L,17L,18L],flags:[B;1b,2b]}"#)
+            .expect("valid snbt");
+
+        assert_eq!(
+            format!("{}", tag),
+            r#"{name: "Steve", pos: [1.5d, 2d], inventory: [{id: "minecraft:stone", Count: 3b}], tags: [], empty: {}, heights: [L; 1L, 2L, 3L, 4L, 5L, 6L, 7L, 8L, 9L, 10L, 11L, 12L, 13L, 14L, 15L, 16L, ... (2 more)], flags: [B; 1b, 2b]}"#
+        );
+        assert_eq!(format!("{:#}", tag), r#"{
+  name: "Steve",
+  pos: [1.5d, 2d],
+  inventory: [
+    {
+      id: "minecraft:stone",
+      Count: 3b
+    }
+  ],
+  tags: [],
+  empty: {},
+  heights: [L; 1L, 2L, 3L, 4L, 5L, 6L, 7L, 8L, 9L, 10L, 11L, 12L, 13L, 14L, 15L, 16L, ... (2 more)],
+  flags: [B; 1b, 2b]
+}"#);
+        assert_eq!(format!("{:#4}", NamedNbtTag::parse_snbt("{a:{b:1}}").expect("valid snbt")), "{\n    a: {\n        b: 1\n    }\n}");
+    }
+
     #[test]
     fn test_nbt() {
         test_type(NamedNbtTag {
@@ -1065,6 +2945,53 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_int_position_range() {
+        let max = IntPosition::new(33554431, 2047, 33554431).expect("in range");
+        test_type(max);
+        let min = IntPosition::new(-33554432, -2048, -33554432).expect("in range");
+        test_type(min);
+
+        assert!(IntPosition::new(33554432, 0, 0).is_err());
+        assert!(IntPosition::new(0, 0, -33554433).is_err());
+        assert!(IntPosition::new(0, 2048, 0).is_err());
+        assert!(IntPosition::new(0, -2049, 0).is_err());
+        assert!(IntPosition::new(100_000_000, 64, 0).is_err());
+        assert!(!IntPosition::new_unchecked(100_000_000, 64, 0).is_in_range());
+
+        for _ in 0..1000 {
+            assert!(IntPosition::test_gen_random().is_in_range());
+        }
+    }
+
+    #[test]
+    fn test_legacy_int_position() {
+        let cases: [((i32, i16, i32), u64); 3] = [
+            ((-33554432, 64, -33554431), 0x8000000102000001),
+            ((-1, -1, -1), 0xFFFFFFFFFFFFFFFF),
+            ((100, 70, -200), 0x0000_1901_1BFF_FF38),
+        ];
+
+        for ((x, y, z), raw) in cases.iter() {
+            let legacy = LegacyIntPosition { x: *x, y: *y, z: *z };
+            assert_eq!(serialized(legacy), raw.to_be_bytes().to_vec());
+            test_type(legacy);
+
+            // the same position comes out differently in the modern layout
+            let modern: IntPosition = legacy.into();
+            let modern_bytes = serialized(modern);
+            assert_eq!(IntPosition::mc_deserialize(modern_bytes.as_slice()).expect("valid position").value, modern);
+            assert_eq!(LegacyIntPosition::from(modern), legacy);
+        }
+
+        test_type(LegacyIntPosition { x: 33554431, y: 2047, z: 33554431 });
+        test_type(LegacyIntPosition { x: -33554432, y: -2048, z: -33554432 });
+        test_type(LegacyIntPosition { x: -33554431, y: 0, z: 33554430 });
+
+        let modern = IntPosition { x: 1, y: 2, z: 3 };
+        assert_ne!(serialized(modern), serialized(LegacyIntPosition::from(modern)));
+    }
+
     #[cfg(feature = "std")]
     #[test]
     fn test_uuid() {
@@ -1081,6 +3008,75 @@ mod tests {
         test_type(Angle { value: 8 });
     }
 
+    #[test]
+    fn test_sound_fixed_int() {
+        let fixed = FixedInt::from_f64(12.5);
+        assert_eq!(fixed.raw(), 100);
+        assert_eq!(serialized(fixed), alloc::vec![0, 0, 0, 100]);
+        test_type(fixed);
+        assert_eq!(fixed.to_f64(), 12.5);
+        assert_eq!(FixedInt::from_f64(-0.3).raw(), -2);
+
+        let position = SoundPosition { x: 12.5, y: 64.0, z: -3.125 };
+        let fixed = position.to_fixed();
+        assert_eq!((fixed.x.raw(), fixed.y.raw(), fixed.z.raw()), (100, 512, -25));
+        assert_eq!(SoundPosition::from(fixed), position);
+
+        let block = SoundPosition::from(IntPosition { x: 1, y: 2, z: -3 });
+        assert_eq!(block, SoundPosition { x: 1.5, y: 2.5, z: -2.5 });
+    }
+
+    #[test]
+    fn test_angle_degrees() {
+        assert_eq!(Angle::from_degrees(0.0), Angle { value: 0 });
+        assert_eq!(Angle::from_degrees(90.0), Angle { value: 64 });
+        assert_eq!(Angle::from_degrees(1.0), Angle { value: 1 });
+        assert_eq!(Angle::from_degrees(359.0), Angle { value: 255 });
+        assert_eq!(Angle::from_degrees(360.0), Angle { value: 0 });
+        assert_eq!(Angle::from_degrees(450.0), Angle { value: 64 });
+        assert_eq!(Angle::from_degrees(-90.0), Angle { value: 192 });
+        assert_eq!(Angle::from_degrees(-450.0), Angle { value: 192 });
+        assert_eq!(Angle::from_degrees(-720.0), Angle { value: 0 });
+
+        assert_eq!(Angle { value: 64 }.to_degrees(), 90.0);
+        assert_eq!(Angle { value: 192 }.to_degrees(), 270.0);
+        for value in 0..=255u8 {
+            let angle = Angle { value };
+            assert_eq!(Angle::from_degrees(angle.to_degrees()), angle);
+            assert_eq!(Angle::from_radians(angle.to_radians()), angle);
+        }
+
+        assert_eq!(Angle::from_radians(core::f32::consts::PI), Angle { value: 128 });
+        assert_eq!(Angle::from_radians(-core::f32::consts::FRAC_PI_2), Angle { value: 192 });
+        assert_eq!(Angle { value: 128 }.to_radians(), core::f32::consts::PI);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_angle_between() {
+        let origin = (0.0, 64.0, 0.0);
+        let yaw = |to: (f64, f64, f64)| Angle::between(origin, to).0.to_degrees();
+        let pitch = |to: (f64, f64, f64)| Angle::between(origin, to).1.to_degrees();
+
+        // south, west, north and east
+        assert_eq!(yaw((0.0, 64.0, 10.0)), 0.0);
+        assert_eq!(yaw((-10.0, 64.0, 0.0)), 90.0);
+        assert_eq!(yaw((0.0, 64.0, -10.0)), 180.0);
+        assert_eq!(yaw((10.0, 64.0, 0.0)), 270.0);
+        assert_eq!(yaw((-5.0, 64.0, 5.0)), 45.0);
+
+        assert_eq!(pitch((0.0, 64.0, 10.0)), 0.0);
+        assert_eq!(pitch((0.0, 74.0, 0.0)), 270.0);
+        assert_eq!(pitch((0.0, 54.0, 0.0)), 90.0);
+        assert_eq!(pitch((0.0, 69.0, 5.0)), 315.0);
+    }
+
+    fn serialized<S: Serialize>(value: S) -> Vec<u8> {
+        let mut out = BytesSerializer::default();
+        value.mc_serialize(&mut out).expect("serialization should succeed");
+        out.into_bytes()
+    }
+
     fn test_type<S: Serialize + Deserialize + PartialEq + Debug>(value: S) {
         let bytes = {
             let mut test = BytesSerializer::default();
@@ -1,5 +1,9 @@
 use crate::{DeserializeErr, DeserializeResult};
 use alloc::string::String;
+#[cfg(feature = "std")]
+use alloc::{collections::VecDeque, fmt};
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
 
 pub fn take(amount: usize, data: &[u8]) -> DeserializeResult<&[u8]> {
     if data.len() < amount {
@@ -62,3 +66,112 @@ pub fn parse_hex_char(data: u8) -> Option<u8> {
         None
     }
 }
+
+// the most keep alives expected to be in flight, older ones are dropped once this many are pending
+#[cfg(feature = "std")]
+pub const KEEP_ALIVE_CAPACITY: usize = 20;
+
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum KeepAliveErr {
+    UnknownId(i64),
+}
+
+#[cfg(feature = "std")]
+impl fmt::Display for KeepAliveErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeepAliveErr::UnknownId(id) => f.write_fmt(format_args!("keep alive response {} matches no pending keep alive", id)),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl fmt::Debug for KeepAliveErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        <dyn fmt::Display>::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for KeepAliveErr {}
+
+// Matches keep alive responses to the keep alives they answer. The vanilla server drops clients
+// which leave a keep alive unanswered for 30 seconds.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Default)]
+pub struct KeepAliveManager {
+    pending: VecDeque<(i64, Instant)>,
+}
+
+#[cfg(feature = "std")]
+impl KeepAliveManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_sent(&mut self, id: i64, sent_at: Instant) {
+        if self.pending.len() == KEEP_ALIVE_CAPACITY {
+            self.pending.pop_front();
+        }
+        self.pending.push_back((id, sent_at));
+    }
+
+    // returns the round trip time of the answered keep alive
+    pub fn record_received(&mut self, id: i64, now: Instant) -> Result<Duration, KeepAliveErr> {
+        let idx = self.pending.iter()
+            .position(move |(pending_id, _)| *pending_id == id)
+            .ok_or(KeepAliveErr::UnknownId(id))?;
+        let (_, sent_at) = self.pending.remove(idx).expect("index is in bounds");
+        Ok(now.saturating_duration_since(sent_at))
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    // true once the oldest unanswered keep alive has been waiting for longer than timeout
+    pub fn is_timed_out(&self, now: Instant, timeout: Duration) -> bool {
+        self.pending.iter()
+            .map(move |(_, sent_at)| *sent_at)
+            .min()
+            .map(move |oldest| now.saturating_duration_since(oldest) > timeout)
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keep_alive_round_trip() {
+        let start = Instant::now();
+        let mut manager = KeepAliveManager::new();
+        manager.record_sent(1, start);
+        manager.record_sent(2, start + Duration::from_secs(15));
+
+        assert_eq!(manager.record_received(2, start + Duration::from_secs(16)), Ok(Duration::from_secs(1)));
+        assert_eq!(manager.record_received(2, start + Duration::from_secs(16)), Err(KeepAliveErr::UnknownId(2)));
+        assert_eq!(manager.pending(), 1);
+
+        let timeout = Duration::from_secs(30);
+        assert!(!manager.is_timed_out(start + Duration::from_secs(30), timeout));
+        assert!(manager.is_timed_out(start + Duration::from_secs(31), timeout));
+        assert_eq!(manager.record_received(1, start + Duration::from_millis(30_500)), Ok(Duration::from_millis(30_500)));
+        assert!(!manager.is_timed_out(start + Duration::from_secs(60), timeout));
+    }
+
+    #[test]
+    fn test_keep_alive_capacity() {
+        let start = Instant::now();
+        let mut manager = KeepAliveManager::new();
+        for id in 0..(KEEP_ALIVE_CAPACITY as i64 + 5) {
+            manager.record_sent(id, start + Duration::from_secs(id as u64));
+        }
+
+        assert_eq!(manager.pending(), KEEP_ALIVE_CAPACITY);
+        assert_eq!(manager.record_received(4, start), Err(KeepAliveErr::UnknownId(4)));
+        assert_eq!(manager.record_received(5, start + Duration::from_secs(6)), Ok(Duration::from_secs(1)));
+    }
+}
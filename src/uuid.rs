@@ -66,11 +66,26 @@ impl<'de> serde::Deserialize<'de> for UUID4 {
     }
 }
 
+// the namespaces from rfc 4122 appendix C
+pub const NAMESPACE_DNS: UUID4 = UUID4 { raw: 0x6ba7b810_9dad_11d1_80b4_00c04fd430c8 };
+pub const NAMESPACE_URL: UUID4 = UUID4 { raw: 0x6ba7b811_9dad_11d1_80b4_00c04fd430c8 };
+pub const NAMESPACE_OID: UUID4 = UUID4 { raw: 0x6ba7b812_9dad_11d1_80b4_00c04fd430c8 };
+pub const NAMESPACE_X500: UUID4 = UUID4 { raw: 0x6ba7b814_9dad_11d1_80b4_00c04fd430c8 };
+
 impl UUID4 {
     pub fn parse(from: &str) -> Option<UUID4> {
         RawUUID::from_str(from).and_then(move |raw| raw.parse4())
     }
 
+    // only accepts the 8-4-4-4-12 form, parse also takes the 32 character form without dashes
+    pub fn parse_hyphenated(from: &str) -> Option<UUID4> {
+        if from.len() != 36 {
+            return None;
+        }
+
+        Self::parse(from)
+    }
+
     #[cfg(feature = "std")]
     pub fn random() -> Self {
         UUID4 {
@@ -78,10 +93,65 @@ impl UUID4 {
         }
     }
 
+    // The uuid an offline mode server gives a player, which is the name based (version 3) uuid of
+    // "OfflinePlayer:<name>", like Java's UUID.nameUUIDFromBytes. Java doesn't hash a namespace
+    // first, so this isn't the same as name_based_md5 with any namespace.
+    pub fn offline_player(name: &str) -> Self {
+        use md5::{Digest, Md5};
+
+        let mut hasher = Md5::new();
+        hasher.update(b"OfflinePlayer:");
+        hasher.update(name.as_bytes());
+        Self::from_name_hash(hasher.finalize().as_slice(), 3)
+    }
+
+    // version 3 (md5) uuid of a name in a namespace, see rfc 4122 section 4.3
+    pub fn name_based_md5(namespace: UUID4, name: &[u8]) -> Self {
+        use md5::{Digest, Md5};
+
+        let mut hasher = Md5::new();
+        hasher.update(namespace.raw.to_be_bytes());
+        hasher.update(name);
+        Self::from_name_hash(hasher.finalize().as_slice(), 3)
+    }
+
+    // version 5 (sha-1) uuid of a name in a namespace
+    #[cfg(feature = "std")]
+    pub fn name_based_sha1(namespace: UUID4, name: &[u8]) -> Self {
+        use sha1::{Digest, Sha1};
+
+        let mut hasher = Sha1::new();
+        hasher.update(namespace.raw.to_be_bytes());
+        hasher.update(name);
+        Self::from_name_hash(hasher.finalize().as_slice(), 5)
+    }
+
+    // the first 16 bytes of the hash with the version and rfc 4122 variant bits set
+    fn from_name_hash(hash: &[u8], version: u8) -> Self {
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(&hash[..16]);
+        bytes[6] = (bytes[6] & 0x0f) | (version << 4);
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+        UUID4 {
+            raw: u128::from_be_bytes(bytes),
+        }
+    }
+
     pub fn to_u128(self) -> u128 {
         self.raw
     }
 
+    // the 8-4-4-4-12 form, same as hex()
+    pub fn to_hyphenated_string(self) -> String {
+        self.hex()
+    }
+
+    // 32 hex characters without dashes, like the uuids in mojang's session api responses
+    pub fn to_compact_string(self) -> String {
+        hex(&self.raw.to_be_bytes())
+    }
+
     pub fn hex(self) -> String {
         let bytes = self.raw.to_be_bytes();
         let parts = [
@@ -193,7 +263,7 @@ fn str_check_hex(mut source: &str) -> Option<()> {
 }
 
 fn str_split(source: &str, n: usize) -> Option<(&str, &str)> {
-    if source.len() < n {
+    if source.len() < n || !source.is_char_boundary(n) {
         None
     } else {
         Some(source.split_at(n))
@@ -226,6 +296,23 @@ mod tests {
         UUID4::parse(VALID_UUID_NO_DASHES).expect("should parse valid uuid with no dashes correctly");
     }
 
+    #[test]
+    fn test_uuid4_parse_hyphenated() {
+        let expected = UUID4::parse(VALID_UUID).expect("should parse valid uuid correctly");
+        assert_eq!(UUID4::parse_hyphenated(VALID_UUID), Some(expected));
+        assert_eq!(UUID4::parse_hyphenated(VALID_UUID_NO_DASHES), None);
+        assert_eq!(UUID4::parse(VALID_UUID_NO_DASHES), Some(expected));
+        assert_eq!(UUID4::parse("E1CDE35A075847F6ADF89DCB44884E5D"), Some(expected));
+        assert_eq!(UUID4::parse_hyphenated("E1CDE35A-0758-47F6-ADF8-9DCB44884E5D"), Some(expected));
+    }
+
+    #[test]
+    fn test_uuid4_to_compact_string() {
+        let uuid = UUID4::parse(VALID_UUID).expect("should parse valid uuid correctly");
+        assert_eq!(uuid.to_compact_string().as_str(), VALID_UUID_NO_DASHES);
+        assert_eq!(UUID4::parse(uuid.to_compact_string().as_str()), Some(uuid));
+    }
+
     #[test]
     fn test_parsed_uuid4_to_hex() {
         let uuid_hex = UUID4::parse(VALID_UUID)
@@ -235,6 +322,68 @@ mod tests {
         assert_eq!(uuid_hex.as_str(), VALID_UUID)
     }
 
+    #[test]
+    fn test_offline_player_uuid() {
+        for (name, expected) in &[
+            ("Notch", "b50ad385-829d-3141-a216-7e7d7539ba7f"),
+            ("jeb_", "a762f560-4fce-3236-812a-b80efff0b62b"),
+            ("Steve", "5627dd98-e6be-3c21-b8a8-e92344183641"),
+        ] {
+            let uuid = UUID4::offline_player(name);
+            assert_eq!(uuid.hex().as_str(), *expected);
+            assert_eq!((uuid.to_u128() >> 76) & 0xF, 3);
+            assert_eq!((uuid.to_u128() >> 62) & 0x3, 0b10);
+        }
+        assert_ne!(UUID4::offline_player("notch"), UUID4::offline_player("Notch"));
+    }
+
+    #[test]
+    fn test_name_based_md5_uuid() {
+        use super::{NAMESPACE_DNS, NAMESPACE_URL};
+
+        let uuid = UUID4::name_based_md5(NAMESPACE_DNS, b"www.example.com");
+        assert_eq!(uuid.hex().as_str(), "5df41881-3aed-3515-88a7-2f4a814cf09e");
+        let uuid = UUID4::name_based_md5(NAMESPACE_URL, b"https://example.com/");
+        assert_eq!(uuid.hex().as_str(), "b9dcdff8-af4a-365d-8043-0f8361942709");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_name_based_sha1_uuid() {
+        use super::{NAMESPACE_DNS, NAMESPACE_URL};
+
+        let uuid = UUID4::name_based_sha1(NAMESPACE_DNS, b"www.example.com");
+        assert_eq!(uuid.hex().as_str(), "2ed6657d-e927-568b-95e1-2665a8aea6a2");
+        let uuid = UUID4::name_based_sha1(NAMESPACE_URL, b"https://example.com/");
+        assert_eq!(uuid.hex().as_str(), "dd2c1780-811a-5296-81c5-178a0ef488bc");
+    }
+
+    #[test]
+    fn test_uuid4_serde() {
+        let uuid = UUID4::parse(VALID_UUID).expect("should parse valid uuid correctly");
+        let json = serde_json::to_string(&uuid).expect("serializes");
+        assert_eq!(json, alloc::format!("\"{}\"", VALID_UUID));
+        assert_eq!(serde_json::from_str::<UUID4>(json.as_str()).expect("deserializes"), uuid);
+
+        let compact = alloc::format!("\"{}\"", VALID_UUID_NO_DASHES);
+        assert_eq!(serde_json::from_str::<UUID4>(compact.as_str()).expect("deserializes"), uuid);
+        assert_eq!(serde_json::from_str::<UUID4>("\"E1CDE35A-0758-47F6-ADF8-9DCB44884E5D\"").expect("deserializes"), uuid);
+
+        for bad in &[
+            "\"\"",
+            "\"not a uuid\"",
+            "\"e1cde35a-0758-47f6-adf8-9dcb44884e5\"",
+            "\"e1cde35a-0758-47f6-adf8-9dcb44884e5d0\"",
+            "\"e1cde35a-075847f6-adf8-9dcb44884e5d\"",
+            "\"e1cde35a-0758-47f6-adf8-9dcb44884é5d\"",
+            "\"é1cde35a075847f6adf89dcb44884e5d\"",
+            "\"e1cde35g-0758-47f6-adf8-9dcb44884e5d\"",
+            "42",
+        ] {
+            assert!(serde_json::from_str::<UUID4>(bad).is_err(), "accepted {}", bad);
+        }
+    }
+
     #[test]
     fn test_uuid4_equal() {
         let uuid_a = UUID4::parse(VALID_UUID).expect("should parse valid uuid correctly");
@@ -2,8 +2,9 @@ use crate::{types::*, uuid::*, *};
 use alloc::{string::{String, ToString}, vec::Vec, borrow::ToOwned, boxed::Box};
 use alloc::fmt;
 use fmt::Debug;
+use crate::protocol::{HasPacketId, HasPacketKind, PacketDirection, State};
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 use crate::protocol::TestRandom;
 
 define_protocol!(578, Packet578, RawPacket578, RawPacket578Body, Packet578Kind => {
@@ -37,8 +38,9 @@ define_protocol!(578, Packet578, RawPacket578, RawPacket578Body, Packet578Kind =
         verify_token: CountedArray<u8, VarInt>
     },
     LoginSuccess, 0x02, Login, ClientBound => LoginSuccessSpec {
+        #[doc = "Prefer calling .uuid() instead of parsing this string manually"]
         uuid_string: String,
-        username: String
+        username: LimitedString<USERNAME_MAX_LENGTH>
     },
     LoginSetCompression, 0x03, Login, ClientBound => LoginSetCompressionSpec {
         threshold: VarInt
@@ -49,7 +51,7 @@ define_protocol!(578, Packet578, RawPacket578, RawPacket578Body, Packet578Kind =
         data: RemainingBytes
     },
     LoginStart, 0x00, Login, ServerBound => LoginStartSpec {
-        name: String
+        name: LimitedString<USERNAME_MAX_LENGTH>
     },
     LoginEncryptionResponse, 0x01, Login, ServerBound => LoginEncryptionResponseSpec {
         shared_secret: CountedArray<u8, VarInt>,
@@ -514,7 +516,7 @@ define_protocol!(578, Packet578, RawPacket578, RawPacket578Body, Packet578Kind =
         new_difficulty: Difficulty
     },
     PlayClientChatMessage, 0x03, Play, ServerBound => PlayClientChatMessageSpec {
-        message: String
+        message: LimitedString<CHAT_MESSAGE_MAX_LENGTH>
     },
     PlayClientStatus, 0x04, Play, ServerBound => PlayClientStatusSpec {
         action: ClientStatusAction
@@ -710,6 +712,79 @@ proto_byte_enum!(HandshakeNextState,
     0x02 :: Login
 );
 
+impl LoginSuccessSpec {
+    pub fn uuid(&self) -> Option<UUID4> {
+        UUID4::parse(self.uuid_string.as_str())
+    }
+
+    pub fn with_uuid(mut self, uuid: UUID4) -> Self {
+        self.uuid_string = uuid.to_hyphenated_string();
+        self
+    }
+}
+
+pub enum StateErr {
+    InvalidPacket { name: &'static str, state: State },
+}
+
+impl fmt::Display for StateErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateErr::InvalidPacket { name, state } => f.write_fmt(format_args!(
+                "packet {} is not valid in state {}", name, state.name()
+            )),
+        }
+    }
+}
+
+impl fmt::Debug for StateErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        <dyn fmt::Display>::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for StateErr {}
+
+// Tracks which state a connection is in while packets are sent or received on it, catching
+// packets which don't belong to the current state or were sent in the wrong direction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConnectionStateMachine {
+    pub current_state: State,
+}
+
+impl Default for ConnectionStateMachine {
+    fn default() -> Self {
+        Self { current_state: State::Handshaking }
+    }
+}
+
+impl ConnectionStateMachine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn transition(&mut self, direction: PacketDirection, pkt: &Packet578) -> Result<(), StateErr> {
+        let id = pkt.id();
+        if id.state != self.current_state || id.direction != direction {
+            return Err(StateErr::InvalidPacket { name: pkt.kind().name(), state: self.current_state });
+        }
+
+        match pkt {
+            Packet578::Handshake(body) => {
+                self.current_state = match body.next_state {
+                    HandshakeNextState::Status => State::Status,
+                    HandshakeNextState::Login => State::Login,
+                };
+            }
+            Packet578::LoginSuccess(_) => self.current_state = State::Play,
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
 proto_byte_enum!(CardinalDirection,
     0x00 :: South,
     0x01 :: West,
@@ -796,6 +871,168 @@ proto_struct!(Statistic {
     value: VarInt
 });
 
+const STATISTIC_KIND_IDENTIFIERS: [(StatisticKind, &str); StatisticKind::variant_count()] = [
+    (StatisticKind::LeaveGame, "minecraft:leave_game"),
+    (StatisticKind::PlayOneMinute, "minecraft:play_one_minute"),
+    (StatisticKind::TimeSinceDeath, "minecraft:time_since_death"),
+    (StatisticKind::SneakTime, "minecraft:sneak_time"),
+    (StatisticKind::WealkOneCm, "minecraft:walk_one_cm"),
+    (StatisticKind::CrouchOneCm, "minecraft:crouch_one_cm"),
+    (StatisticKind::SprintOneCm, "minecraft:sprint_one_cm"),
+    (StatisticKind::SwimOneCm, "minecraft:swim_one_cm"),
+    (StatisticKind::FallOneCm, "minecraft:fall_one_cm"),
+    (StatisticKind::ClimbOneCm, "minecraft:climb_one_cm"),
+    (StatisticKind::FlyOneCm, "minecraft:fly_one_cm"),
+    (StatisticKind::DiveOneCm, "minecraft:walk_under_water_one_cm"),
+    (StatisticKind::MinecartOneCm, "minecraft:minecart_one_cm"),
+    (StatisticKind::BoatOneCm, "minecraft:boat_one_cm"),
+    (StatisticKind::PigOneCm, "minecraft:pig_one_cm"),
+    (StatisticKind::HorseOneCm, "minecraft:horse_one_cm"),
+    (StatisticKind::AviateOneCm, "minecraft:aviate_one_cm"),
+    (StatisticKind::Jumps, "minecraft:jump"),
+    (StatisticKind::Drops, "minecraft:drop"),
+    (StatisticKind::DamageDealt, "minecraft:damage_dealt"),
+    (StatisticKind::DamageTaken, "minecraft:damage_taken"),
+    (StatisticKind::Deaths, "minecraft:deaths"),
+    (StatisticKind::MobKills, "minecraft:mob_kills"),
+    (StatisticKind::AnimalsBread, "minecraft:animals_bred"),
+    (StatisticKind::PlayerKills, "minecraft:player_kills"),
+    (StatisticKind::FishCaught, "minecraft:fish_caught"),
+    (StatisticKind::TalkedToVillager, "minecraft:talked_to_villager"),
+    (StatisticKind::TradedWithVillager, "minecraft:traded_with_villager"),
+    (StatisticKind::EatCakeSlice, "minecraft:eat_cake_slice"),
+    (StatisticKind::FillCauldron, "minecraft:fill_cauldron"),
+    (StatisticKind::UseCauldron, "minecraft:use_cauldron"),
+    (StatisticKind::CleanArmor, "minecraft:clean_armor"),
+    (StatisticKind::CleanBanner, "minecraft:clean_banner"),
+    (StatisticKind::InteractWithBrewingStand, "minecraft:interact_with_brewingstand"),
+    (StatisticKind::InteractWithBeaccon, "minecraft:interact_with_beacon"),
+    (StatisticKind::InspectDropper, "minecraft:inspect_dropper"),
+    (StatisticKind::InspectHopper, "minecraft:inspect_hopper"),
+    (StatisticKind::InspectDispenser, "minecraft:inspect_dispenser"),
+    (StatisticKind::PlayNoteBlock, "minecraft:play_noteblock"),
+    (StatisticKind::TuneNoteBlock, "minecraft:tune_noteblock"),
+    (StatisticKind::PotFlower, "minecraft:pot_flower"),
+    (StatisticKind::TriggerTrappedChest, "minecraft:trigger_trapped_chest"),
+    (StatisticKind::OpenEnderChest, "minecraft:open_enderchest"),
+    (StatisticKind::EnchantItem, "minecraft:enchant_item"),
+    (StatisticKind::PlayRecord, "minecraft:play_record"),
+    (StatisticKind::InteractWithFurnace, "minecraft:interact_with_furnace"),
+    (StatisticKind::InteractWithCraftingTable, "minecraft:interact_with_crafting_table"),
+    (StatisticKind::OpenChest, "minecraft:open_chest"),
+    (StatisticKind::SleepInBed, "minecraft:sleep_in_bed"),
+    (StatisticKind::OpenShulkerBox, "minecraft:open_shulker_box"),
+];
+
+impl StatisticKind {
+    pub fn identifier(&self) -> &'static str {
+        STATISTIC_KIND_IDENTIFIERS.iter()
+            .find(move |(kind, _)| kind == self)
+            .map(|(_, identifier)| *identifier)
+            .expect("every statistic kind has an identifier")
+    }
+
+    pub fn from_identifier(identifier: &str) -> Option<Self> {
+        STATISTIC_KIND_IDENTIFIERS.iter()
+            .find(move |(_, other)| *other == identifier)
+            .map(|(kind, _)| kind.clone())
+    }
+
+    pub fn unit(&self) -> StatisticUnit {
+        use StatisticKind::*;
+        match self {
+            PlayOneMinute | TimeSinceDeath | SneakTime => StatisticUnit::Ticks,
+            WealkOneCm | CrouchOneCm | SprintOneCm | SwimOneCm | FallOneCm | ClimbOneCm | FlyOneCm | DiveOneCm |
+            MinecartOneCm | BoatOneCm | PigOneCm | HorseOneCm | AviateOneCm => StatisticUnit::Centimetres,
+            DamageDealt | DamageTaken => StatisticUnit::HalfHearts,
+            _ => StatisticUnit::Count,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatisticUnit {
+    Centimetres,
+    Ticks,
+    Count,
+    // the raw value is the damage (in half hearts) times 10
+    HalfHearts,
+}
+
+impl StatisticUnit {
+    // distances are shown in metres, times in seconds and damage in hearts
+    pub fn format(&self, value: VarInt) -> String {
+        use StatisticUnit::*;
+        match self {
+            Centimetres => alloc::format!("{:.1} m", value.0 as f64 / 100.0),
+            Ticks => alloc::format!("{:.1} s", value.0 as f64 / 20.0),
+            Count => value.0.to_string(),
+            HalfHearts => alloc::format!("{:.1} \u{2764}", value.0 as f64 / 20.0),
+        }
+    }
+}
+
+impl StatisticCategory {
+    pub fn identifier(&self) -> &'static str {
+        use StatisticCategory::*;
+        match self {
+            Mined(_) => "minecraft:mined",
+            Crafted(_) => "minecraft:crafted",
+            Used(_) => "minecraft:used",
+            Broken(_) => "minecraft:broken",
+            PickedUp(_) => "minecraft:picked_up",
+            Dropped(_) => "minecraft:dropped",
+            Killed(_) => "minecraft:killed",
+            KilledBy(_) => "minecraft:killed_by",
+            Custom(_) => "minecraft:custom",
+        }
+    }
+}
+
+impl Statistic {
+    // only custom statistics have units, the others count blocks, items or entities
+    pub fn unit(&self) -> StatisticUnit {
+        match &self.kind {
+            StatisticCategory::Custom(kind) => kind.unit(),
+            _ => StatisticUnit::Count,
+        }
+    }
+
+    pub fn formatted_value(&self) -> String {
+        self.unit().format(self.value)
+    }
+
+    // Names the statistic like "minecraft:custom/jump" or "minecraft:mined/minecraft.stone". All
+    // categories other than Custom carry a registry id (blocks for Mined, entities for Killed and
+    // KilledBy, items otherwise), which `lookup` resolves to a name. Ids it can't resolve are
+    // written as numbers.
+    pub fn name_with<F>(&self, lookup: F) -> String
+        where F: FnOnce(&StatisticCategory, i32) -> Option<String>
+    {
+        use StatisticCategory::*;
+        let category = self.kind.identifier();
+        let registry_id = match &self.kind {
+            Custom(kind) => {
+                let identifier = kind.identifier();
+                let path = identifier.strip_prefix("minecraft:").unwrap_or(identifier);
+                return alloc::format!("{}/{}", category, path);
+            }
+            Mined(id) | Crafted(id) | Used(id) | Broken(id) | PickedUp(id) | Dropped(id) | Killed(id) | KilledBy(id) => id.0,
+        };
+
+        match lookup(&self.kind, registry_id) {
+            Some(name) => alloc::format!("{}/{}", category, name.replace(':', ".")),
+            None => alloc::format!("{}/{}", category, registry_id),
+        }
+    }
+}
+
+impl PlayStatisticsSpec {
+    pub fn formatted(&self) -> impl Iterator<Item = (&Statistic, String)> + '_ {
+        self.entries.iter().map(move |entry| (entry, entry.formatted_value()))
+    }
+}
+
 proto_byte_enum!(DiggingStatus,
     0x00 :: Started,
     0x01 :: Cancelled,
@@ -832,6 +1069,7 @@ proto_byte_enum!(ChatPosition,
 );
 
 #[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BlockChangeHorizontalPosition {
     pub rel_x: u8,
     pub rel_z: u8,
@@ -854,12 +1092,12 @@ impl Deserialize for BlockChangeHorizontalPosition {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for BlockChangeHorizontalPosition {
     fn test_gen_random() -> Self {
         BlockChangeHorizontalPosition {
-            rel_x: rand::random::<u8>() % 16,
-            rel_z: rand::random::<u8>() % 16,
+            rel_x: crate::protocol::test_random::<u8>() % 16,
+            rel_z: crate::protocol::test_random::<u8>() % 16,
         }
     }
 }
@@ -870,6 +1108,106 @@ proto_struct!(MultiBlockChangeRecord {
     block_id: VarInt
 });
 
+pub enum MultiBlockChangeErr {
+    PositionOutsideChunk { position: IntPosition, chunk: ChunkPosition<i32> },
+}
+
+impl fmt::Display for MultiBlockChangeErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use MultiBlockChangeErr::*;
+        match self {
+            PositionOutsideChunk { position, chunk } => f.write_fmt(format_args!(
+                "block at ({}, {}, {}) is not inside chunk ({}, {})",
+                position.x, position.y, position.z, chunk.x, chunk.z
+            )),
+        }
+    }
+}
+
+impl fmt::Debug for MultiBlockChangeErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        <dyn fmt::Display>::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MultiBlockChangeErr {}
+
+impl PlayMultiBlockChangeSpec {
+    pub fn chunk_position(&self) -> (i32, i32) {
+        (self.chunk.x, self.chunk.z)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (IntPosition, VarInt)> + '_ {
+        self.changes_absolute()
+    }
+
+    // later changes to the same position win, like they would when applied in order
+    pub fn apply_to(&self, map: &mut alloc::collections::BTreeMap<IntPosition, VarInt>) {
+        map.extend(self.iter());
+    }
+
+    pub fn changes_absolute(&self) -> impl Iterator<Item = (IntPosition, VarInt)> + '_ {
+        let (chunk_x, chunk_z) = (self.chunk.x, self.chunk.z);
+        self.changes.iter().map(move |change| (IntPosition {
+            x: (chunk_x << 4) | (change.horizontal_position.rel_x & 0xF) as i32,
+            y: change.y_coordinate as i16,
+            z: (chunk_z << 4) | (change.horizontal_position.rel_z & 0xF) as i32,
+        }, change.block_id))
+    }
+
+    pub fn from_absolute<I>(chunk_x: i32, chunk_z: i32, changes: I) -> Result<Self, MultiBlockChangeErr>
+        where I: IntoIterator<Item = (IntPosition, VarInt)>
+    {
+        let chunk = ChunkPosition { x: chunk_x, z: chunk_z };
+        let changes = changes.into_iter().map(move |(position, block_id)| {
+            if position.x >> 4 != chunk_x || position.z >> 4 != chunk_z || position.y < 0 || position.y > 255 {
+                return Err(MultiBlockChangeErr::PositionOutsideChunk { position, chunk });
+            }
+
+            Ok(MultiBlockChangeRecord {
+                horizontal_position: BlockChangeHorizontalPosition {
+                    rel_x: (position.x & 0xF) as u8,
+                    rel_z: (position.z & 0xF) as u8,
+                },
+                y_coordinate: position.y as u8,
+                block_id,
+            })
+        }).collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            chunk,
+            changes: changes.into(),
+        })
+    }
+}
+
+impl PlayExplosionSpec {
+    pub fn explosion_center(&self) -> (f32, f32, f32) {
+        (self.position.x, self.position.y, self.position.z)
+    }
+
+    // records are offsets from the block the explosion is centered in
+    pub fn affected_block_positions(&self) -> impl Iterator<Item = (i32, i32, i32)> + '_ {
+        let (x, y, z) = (floor_f32(self.position.x), floor_f32(self.position.y), floor_f32(self.position.z));
+        self.records.iter().map(move |record| (x + record.x as i32, y + record.y as i32, z + record.z as i32))
+    }
+
+    pub fn player_push_velocity(&self) -> (f32, f32, f32) {
+        (self.player_motion.x, self.player_motion.y, self.player_motion.z)
+    }
+}
+
+// f32::floor needs std
+fn floor_f32(value: f32) -> i32 {
+    let truncated = value as i32;
+    if (truncated as f32) > value {
+        truncated - 1
+    } else {
+        truncated
+    }
+}
+
 proto_varint_enum!(BossBarAction,
     0x00 :: Add(BossBarAddSpec),
     0x01 :: Remove,
@@ -898,9 +1236,9 @@ proto_varint_enum!(BossBarDivision,
 );
 
 proto_byte_flag!(BossBarFlags,
-    0x01 :: is_darken_sky set_darken_sky,
-    0x02 :: is_dragon_bar set_dragon_bar,
-    0x04 :: is_create_fog set_create_fog
+    0x01 :: is_darken_sky set_darken_sky with_darken_sky,
+    0x02 :: is_dragon_bar set_dragon_bar with_dragon_bar,
+    0x04 :: is_create_fog set_create_fog with_create_fog
 );
 
 proto_struct!(BossBarAddSpec {
@@ -930,6 +1268,7 @@ proto_struct!(TabCompleteMatch {
 });
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CommandNodeSpec {
     pub children_indices: CountedArray<VarInt, VarInt>,
     pub redirect_node: Option<VarInt>,
@@ -938,6 +1277,7 @@ pub struct CommandNodeSpec {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CommandNode {
     Root,
     Argument(CommandArgumentNodeSpec),
@@ -1013,13 +1353,13 @@ impl Deserialize for CommandNodeSpec {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for CommandNodeSpec {
     fn test_gen_random() -> Self {
         let children_indices = <CountedArray<VarInt, VarInt>>::test_gen_random();
         let redirect_node = <Option<VarInt>>::test_gen_random();
-        let is_executable = rand::random::<bool>();
-        let idx = rand::random::<usize>() % 3;
+        let is_executable = crate::protocol::test_random::<bool>();
+        let idx = (crate::protocol::test_random::<u32>() % 3) as usize;
         let node = match idx {
             0 => CommandNode::Root,
             1 => CommandNode::Argument(CommandArgumentNodeSpec::test_gen_random()),
@@ -1036,7 +1376,43 @@ impl TestRandom for CommandNodeSpec {
     }
 }
 
+impl CommandNodeSpec {
+    // children which point outside of the tree are skipped
+    pub fn children<'a>(&'a self, tree: &'a PlayDeclareCommandsSpec) -> impl Iterator<Item = &'a CommandNodeSpec> + 'a {
+        self.children_indices.iter().filter_map(move |idx| tree.node_at(*idx))
+    }
+
+    pub fn literal_name(&self) -> Option<&str> {
+        match &self.node {
+            CommandNode::Literal(body) => Some(body.name.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn argument_name(&self) -> Option<&str> {
+        match &self.node {
+            CommandNode::Argument(body) => Some(body.name.as_str()),
+            _ => None,
+        }
+    }
+}
+
+impl PlayDeclareCommandsSpec {
+    pub fn root(&self) -> Option<&CommandNodeSpec> {
+        self.node_at(self.root_index)
+    }
+
+    pub fn node_at(&self, index: VarInt) -> Option<&CommandNodeSpec> {
+        if index.0 < 0 {
+            return None;
+        }
+
+        self.nodes.get(index.0 as usize)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CommandArgumentNodeSpec {
     pub name: String,
     pub parser: CommandParserSpec,
@@ -1072,7 +1448,7 @@ impl CommandArgumentNodeSpec {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for CommandArgumentNodeSpec {
     fn test_gen_random() -> Self {
         let name = String::test_gen_random();
@@ -1094,6 +1470,12 @@ proto_str_enum!(SuggestionsTypeSpec,
     "minecraft:summonable_entities" :: SummonableEntities
 );
 
+impl SuggestionsTypeSpec {
+    pub fn resource_location(&self) -> &'static str {
+        self.name()
+    }
+}
+
 proto_struct!(CommandLiteralNodeSpec {
     name: String
 });
@@ -1145,6 +1527,19 @@ proto_str_enum!(CommandParserSpec,
     "minecraft:time" :: Time
 );
 
+impl CommandParserSpec {
+    // the parser's identifier, like "brigadier:bool" or "minecraft:entity"
+    pub fn display_name(&self) -> &'static str {
+        self.name()
+    }
+
+    // the parsers which come with brigadier itself rather than minecraft
+    pub fn is_brigadier(&self) -> bool {
+        self.name().starts_with("brigadier:")
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NumParserProps<T> {
     pub min: Option<T>,
     pub max: Option<T>,
@@ -1179,6 +1574,36 @@ impl<T> PartialEq for NumParserProps<T> where T: PartialEq {
     }
 }
 
+// no bounds, which is what brigadier sends when flags is 0
+impl<T> Default for NumParserProps<T> {
+    fn default() -> Self {
+        Self {
+            min: None,
+            max: None,
+        }
+    }
+}
+
+impl<T> NumParserProps<T> where T: PartialOrd + Copy {
+    pub fn clamp(&self, value: T) -> T {
+        match (self.min, self.max) {
+            (Some(min), _) if value < min => min,
+            (_, Some(max)) if value > max => max,
+            _ => value,
+        }
+    }
+
+    pub fn validate(&self, value: T) -> bool {
+        self.min.map_or(true, |min| value >= min) && self.max.map_or(true, |max| value <= max)
+    }
+}
+
+impl<T> NumParserProps<T> {
+    pub fn is_unbounded(&self) -> bool {
+        self.min.is_none() && self.max.is_none()
+    }
+}
+
 impl<T> Serialize for NumParserProps<T> where T: Serialize {
     fn mc_serialize<S: Serializer>(&self, to: &mut S) -> SerializeResult {
         let mut flags: u8 = 0;
@@ -1228,17 +1653,17 @@ impl<T> Deserialize for NumParserProps<T> where T: Deserialize {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl<T> TestRandom for NumParserProps<T> where
     T: TestRandom + std::cmp::PartialOrd,
     rand::distributions::Standard: rand::distributions::Distribution<T>,
 {
     fn test_gen_random() -> Self {
-        let has_min = rand::random::<bool>();
-        let has_max = rand::random::<bool>();
+        let has_min = crate::protocol::test_random::<bool>();
+        let has_max = crate::protocol::test_random::<bool>();
         let (min, max) = if has_min && has_max {
-            let a = rand::random::<T>();
-            let b = rand::random::<T>();
+            let a = crate::protocol::test_random::<T>();
+            let b = crate::protocol::test_random::<T>();
             if a < b {
                 (Some(a), Some(b))
             } else {
@@ -1247,7 +1672,7 @@ impl<T> TestRandom for NumParserProps<T> where
         } else if !has_min && !has_max {
             (None, None)
         } else {
-            let v = rand::random::<T>();
+            let v = crate::protocol::test_random::<T>();
             if has_min {
                 (Some(v), None)
             } else {
@@ -1268,19 +1693,287 @@ proto_varint_enum!(StringParserMode,
     0x02 :: GreedyPhrase
 );
 
+impl StringParserMode {
+    pub fn allows_spaces(&self) -> bool {
+        use StringParserMode::*;
+        matches!(self, QuotablePharse | GreedyPhrase)
+    }
+}
+
 proto_byte_flag!(EntityParserFlags,
-    0x01 :: is_single_target set_single_target,
-    0x02 :: is_players_only set_players_only
+    0x01 :: is_single_target set_single_target with_single_target,
+    0x02 :: is_players_only set_players_only with_players_only
 );
 
 proto_byte_flag!(ScoreHolderFlags,
-    0x01 :: is_multiple set_multiple
+    0x01 :: is_multiple set_multiple with_multiple
 );
 
 proto_struct!(RangeParserProps {
     decimal: bool
 });
 
+// command graphs nested deeper than this are refused, vanilla's are a few dozen nodes deep at most
+pub const MAX_COMMAND_TREE_DEPTH: usize = 256;
+
+pub enum CommandTreeErr {
+    RootOutOfRange(VarInt),
+    ChildOutOfRange { node: usize, child: VarInt },
+    RedirectOutOfRange { node: usize, redirect: VarInt },
+    Cycle(usize),
+    TooDeep { node: usize, max: usize },
+}
+
+impl fmt::Display for CommandTreeErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use CommandTreeErr::*;
+        match self {
+            RootOutOfRange(idx) => f.write_fmt(format_args!("root index {} out of range", idx)),
+            ChildOutOfRange { node, child } => {
+                f.write_fmt(format_args!("node {} has child index {} out of range", node, child))
+            }
+            RedirectOutOfRange { node, redirect } => {
+                f.write_fmt(format_args!("node {} has redirect index {} out of range", node, redirect))
+            }
+            Cycle(node) => f.write_fmt(format_args!("command graph has a cycle through node {}", node)),
+            TooDeep { node, max } => f.write_fmt(format_args!(
+                "command graph is nested deeper than {} nodes at node {}",
+                max, node
+            )),
+        }
+    }
+}
+
+impl fmt::Debug for CommandTreeErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        <dyn fmt::Display>::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CommandTreeErr {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandTreeNode {
+    pub node: CommandNode,
+    pub is_executable: bool,
+    pub redirect: Option<usize>,
+    pub children: Vec<usize>,
+}
+
+impl CommandTreeNode {
+    pub fn name(&self) -> Option<&str> {
+        use CommandNode::*;
+        match &self.node {
+            Root => None,
+            Argument(body) => Some(body.name.as_str()),
+            Literal(body) => Some(body.name.as_str()),
+        }
+    }
+}
+
+// A command graph with every node stored once. Children and redirects refer to other nodes by their
+// index, so nodes can be shared between several parents (like the spec allows) and converting to and
+// from PlayDeclareCommandsSpec keeps the node order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandTree {
+    nodes: Vec<CommandTreeNode>,
+    root: usize,
+}
+
+impl Default for CommandTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CommandTree {
+    pub fn new() -> Self {
+        Self {
+            nodes: alloc::vec![CommandTreeNode {
+                node: CommandNode::Root,
+                is_executable: false,
+                redirect: None,
+                children: Vec::new(),
+            }],
+            root: 0,
+        }
+    }
+
+    pub fn root(&self) -> usize {
+        self.root
+    }
+
+    pub fn nodes(&self) -> &[CommandTreeNode] {
+        self.nodes.as_slice()
+    }
+
+    pub fn node(&self, idx: usize) -> Option<&CommandTreeNode> {
+        self.nodes.get(idx)
+    }
+
+    pub fn child(&self, parent: usize, name: &str) -> Option<usize> {
+        self.node(parent)?.children.iter().copied().find(|child| self.nodes[*child].name() == Some(name))
+    }
+
+    // the builder methods below panic when given an index which isn't a node of this tree
+
+    pub fn literal(&mut self, parent: usize, name: &str) -> usize {
+        self.push_child(parent, CommandNode::Literal(CommandLiteralNodeSpec { name: name.to_owned() }))
+    }
+
+    pub fn argument(&mut self, parent: usize, name: &str, parser: CommandParserSpec) -> usize {
+        self.push_child(parent, CommandNode::Argument(CommandArgumentNodeSpec {
+            name: name.to_owned(),
+            parser,
+            suggestions_types: None,
+        }))
+    }
+
+    fn push_child(&mut self, parent: usize, node: CommandNode) -> usize {
+        let idx = self.nodes.len();
+        self.nodes[parent].children.push(idx);
+        self.nodes.push(CommandTreeNode {
+            node,
+            is_executable: false,
+            redirect: None,
+            children: Vec::new(),
+        });
+        idx
+    }
+
+    // makes an existing node a child of another one as well, to_spec refuses the tree if this
+    // introduces a cycle
+    pub fn add_child(&mut self, parent: usize, child: usize) {
+        assert!(child < self.nodes.len(), "child {} is not a node of this tree", child);
+        self.nodes[parent].children.push(child);
+    }
+
+    pub fn suggestions(&mut self, idx: usize, suggestions: SuggestionsTypeSpec) {
+        if let CommandNode::Argument(body) = &mut self.nodes[idx].node {
+            body.suggestions_types = Some(suggestions);
+        }
+    }
+
+    pub fn executable(&mut self, idx: usize) {
+        self.nodes[idx].is_executable = true;
+    }
+
+    pub fn redirect(&mut self, idx: usize, target: usize) {
+        assert!(target < self.nodes.len(), "redirect target {} is not a node of this tree", target);
+        self.nodes[idx].redirect = Some(target);
+    }
+
+    pub fn to_spec(&self) -> Result<PlayDeclareCommandsSpec, CommandTreeErr> {
+        Self::validate(self.nodes.as_slice())?;
+        let nodes: Vec<CommandNodeSpec> = self.nodes.iter().map(move |node| CommandNodeSpec {
+            children_indices: node.children.iter().map(move |child| VarInt(*child as i32)).collect(),
+            redirect_node: node.redirect.map(move |target| VarInt(target as i32)),
+            is_executable: node.is_executable,
+            node: node.node.clone(),
+        }).collect();
+
+        Ok(PlayDeclareCommandsSpec { nodes: nodes.into(), root_index: VarInt(self.root as i32) })
+    }
+
+    pub fn from_spec(spec: &PlayDeclareCommandsSpec) -> Result<Self, CommandTreeErr> {
+        let count = spec.nodes.len();
+        let in_range = move |idx: VarInt| idx.0 >= 0 && (idx.0 as usize) < count;
+        if !in_range(spec.root_index) {
+            return Err(CommandTreeErr::RootOutOfRange(spec.root_index));
+        }
+
+        let mut nodes = Vec::with_capacity(count);
+        for (idx, node) in spec.nodes.iter().enumerate() {
+            let mut children = Vec::with_capacity(node.children_indices.len());
+            for child in node.children_indices.iter() {
+                if !in_range(*child) {
+                    return Err(CommandTreeErr::ChildOutOfRange { node: idx, child: *child });
+                }
+                children.push(child.0 as usize);
+            }
+
+            let redirect = match node.redirect_node {
+                Some(redirect) if !in_range(redirect) => {
+                    return Err(CommandTreeErr::RedirectOutOfRange { node: idx, redirect });
+                }
+                redirect => redirect.map(move |target| target.0 as usize),
+            };
+
+            nodes.push(CommandTreeNode {
+                node: node.node.clone(),
+                is_executable: node.is_executable,
+                redirect,
+                children,
+            });
+        }
+
+        Self::validate(nodes.as_slice())?;
+        Ok(Self { nodes, root: spec.root_index.0 as usize })
+    }
+
+    // Checks the child edges form no cycle and no path is longer than MAX_COMMAND_TREE_DEPTH, with
+    // an explicit stack so hostile graphs can't overflow the real one. Every node is visited once,
+    // however many parents share it. Redirects may point anywhere (usually back up the tree).
+    fn validate(nodes: &[CommandTreeNode]) -> Result<(), CommandTreeErr> {
+        const UNVISITED: u8 = 0;
+        const ON_PATH: u8 = 1;
+        const DONE: u8 = 2;
+
+        let mut state = alloc::vec![UNVISITED; nodes.len()];
+        let mut height = alloc::vec![0usize; nodes.len()];
+        for (idx, node) in nodes.iter().enumerate() {
+            for child in &node.children {
+                if *child >= nodes.len() {
+                    return Err(CommandTreeErr::ChildOutOfRange { node: idx, child: VarInt(*child as i32) });
+                }
+            }
+            if let Some(target) = node.redirect {
+                if target >= nodes.len() {
+                    return Err(CommandTreeErr::RedirectOutOfRange { node: idx, redirect: VarInt(target as i32) });
+                }
+            }
+        }
+
+        let mut stack: Vec<(usize, usize)> = Vec::new();
+        for start in 0..nodes.len() {
+            if state[start] != UNVISITED {
+                continue;
+            }
+
+            state[start] = ON_PATH;
+            stack.push((start, 0));
+            while let Some((idx, next_child)) = stack.last_mut() {
+                let idx = *idx;
+                if let Some(child) = nodes[idx].children.get(*next_child).copied() {
+                    *next_child += 1;
+                    match state[child] {
+                        ON_PATH => return Err(CommandTreeErr::Cycle(child)),
+                        DONE => continue,
+                        _ => {}
+                    }
+
+                    if stack.len() >= MAX_COMMAND_TREE_DEPTH {
+                        return Err(CommandTreeErr::TooDeep { node: child, max: MAX_COMMAND_TREE_DEPTH });
+                    }
+                    state[child] = ON_PATH;
+                    stack.push((child, 0));
+                } else {
+                    stack.pop();
+                    state[idx] = DONE;
+                    let node_height = 1 + nodes[idx].children.iter().map(|child| height[*child]).max().unwrap_or(0);
+                    if node_height > MAX_COMMAND_TREE_DEPTH {
+                        return Err(CommandTreeErr::TooDeep { node: idx, max: MAX_COMMAND_TREE_DEPTH });
+                    }
+                    height[idx] = node_height;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
 proto_byte_enum!(TeamAction,
     0x00 :: Create(TeamActionCreateSpec),
     0x01 :: Remove,
@@ -1290,6 +1983,7 @@ proto_byte_enum!(TeamAction,
 );
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TeamMember {
     Player(String),
     Entity(UUID4),
@@ -1310,7 +2004,8 @@ impl Deserialize for TeamMember {
         use TeamMember::*;
 
         Ok(String::mc_deserialize(data)?.map(move |raw| {
-            if let Some(entity_id) = UUID4::parse(raw.as_str()) {
+            // a player name can be 32 hex characters, entity uuids are always sent with dashes
+            if let Some(entity_id) = UUID4::parse_hyphenated(raw.as_str()) {
                 Entity(entity_id)
             } else {
                 Player(raw)
@@ -1319,16 +2014,16 @@ impl Deserialize for TeamMember {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for TeamMember {
     fn test_gen_random() -> Self {
         use TeamMember::*;
 
-        let rand_bool: bool = rand::random();
+        let rand_bool: bool = crate::protocol::test_random();
         if rand_bool {
             Player(String::test_gen_random())
         } else {
-            Entity(UUID4::random())
+            Entity(UUID4::test_gen_random())
         }
     }
 }
@@ -1373,8 +2068,8 @@ proto_struct!(TeamActionUpdateInfoSpec {
 });
 
 proto_byte_flag!(TeamFriendlyFlags,
-    0x01 :: allow_friendly_fire set_friendly_fire,
-    0x02 :: show_invisible_teammates set_show_invisible_teammates
+    0x01 :: allow_friendly_fire set_friendly_fire with_friendly_fire,
+    0x02 :: show_invisible_teammates set_show_invisible_teammates with_show_invisible_teammates
 );
 
 proto_byte_enum!(UpdateScoreAction,
@@ -1383,6 +2078,7 @@ proto_byte_enum!(UpdateScoreAction,
 );
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UpdateScoreSpec {
     pub objective_name: String,
     pub action: UpdateScoreAction,
@@ -1412,7 +2108,7 @@ impl Deserialize for UpdateScoreSpec {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for UpdateScoreSpec {
     fn test_gen_random() -> Self {
         Self {
@@ -1431,12 +2127,46 @@ proto_varint_enum!(TitleActionSpec,
     0x05 :: Reset
 );
 
-proto_struct!(TitleTimesSpec {
-    fade_in: i32,
-    stay: i32,
+impl TitleActionSpec {
+    pub fn text(&self) -> Option<&Chat> {
+        use TitleActionSpec::*;
+        match self {
+            SetTitle(text) | SetSubtitle(text) | SetActionBar(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn times(&self) -> Option<&TitleTimesSpec> {
+        match self {
+            TitleActionSpec::SetTimesAndDisplay(times) => Some(times),
+            _ => None,
+        }
+    }
+
+    pub fn is_control(&self) -> bool {
+        use TitleActionSpec::*;
+        matches!(self, SetTimesAndDisplay(_) | Hide | Reset)
+    }
+}
+
+proto_struct!(TitleTimesSpec {
+    fade_in: i32,
+    stay: i32,
     fade_out: i32
 });
 
+impl PlayNamedSoundEffectSpec {
+    pub fn sound_position(&self) -> SoundPosition {
+        self.position.into()
+    }
+}
+
+impl PlaySoundEffectSpec {
+    pub fn sound_position(&self) -> SoundPosition {
+        self.position.into()
+    }
+}
+
 proto_varint_enum!(SoundCategory,
     0x00 :: Master,
     0x01 :: Music,
@@ -1451,11 +2181,19 @@ proto_varint_enum!(SoundCategory,
 );
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StopSoundSpec {
     pub source: Option<SoundCategory>,
     pub sound: Option<String>,
 }
 
+impl StopSoundSpec {
+    // whether a sound should be stopped, a missing source or sound in the packet matches anything
+    pub fn matches(&self, category: Option<SoundCategory>, name: Option<&str>) -> bool {
+        (self.source.is_none() || self.source == category) && (self.sound.is_none() || self.sound.as_deref() == name)
+    }
+}
+
 impl Serialize for StopSoundSpec {
     fn mc_serialize<S: Serializer>(&self, to: &mut S) -> SerializeResult {
         let has_sound = self.sound.is_some();
@@ -1509,16 +2247,16 @@ impl Deserialize for StopSoundSpec {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for StopSoundSpec {
     fn test_gen_random() -> Self {
-        let source = if rand::random::<bool>() {
+        let source = if crate::protocol::test_random::<bool>() {
             Some(SoundCategory::test_gen_random())
         } else {
             None
         };
 
-        let sound = if source.is_none() || rand::random::<bool>() {
+        let sound = if source.is_none() || crate::protocol::test_random::<bool>() {
             Some(String::test_gen_random())
         } else {
             None
@@ -1562,7 +2300,43 @@ proto_int_enum!(Dimension,
      0x01 :: End
 );
 
+impl Dimension {
+    #[inline]
+    pub fn resource_location(&self) -> &'static str {
+        use Dimension::*;
+        match self {
+            Nether => "minecraft:the_nether",
+            Overworld => "minecraft:overworld",
+            End => "minecraft:the_end",
+        }
+    }
+
+    #[inline]
+    pub fn from_resource_location(location: &str) -> Option<Self> {
+        use Dimension::*;
+        match location {
+            "minecraft:the_nether" | "nether" => Some(Nether),
+            "minecraft:overworld" | "overworld" => Some(Overworld),
+            "minecraft:the_end" | "end" => Some(End),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        use Dimension::*;
+        match s {
+            "-1" => Some(Nether),
+            "0" => Some(Overworld),
+            "1" => Some(End),
+            other => Self::from_resource_location(other),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GameChangeReason {
     NoRespawnAvailable,
     EndRaining,
@@ -1638,11 +2412,24 @@ impl Deserialize for GameChangeReason {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for GameChangeReason {
     fn test_gen_random() -> Self {
-        // todo
-        GameChangeReason::PufferfishSting
+        use GameChangeReason::*;
+        match crate::protocol::test_random::<u8>() % 12 {
+            0x00 => NoRespawnAvailable,
+            0x01 => EndRaining,
+            0x02 => BeginRaining,
+            0x03 => ChangeGameMode(GameMode::test_gen_random()),
+            0x04 => WinGame(WinGameAction::test_gen_random()),
+            0x05 => Demo(DemoEvent::test_gen_random()),
+            0x06 => ArrowHitPlayer,
+            0x07 => RainLevelChange(crate::protocol::test_random::<f32>()),
+            0x08 => ThunderLevelChange(crate::protocol::test_random::<f32>()),
+            0x09 => PufferfishSting,
+            0x0A => ElderGuardianMobAppearance,
+            _ => Respawn(RespawnRequestType::test_gen_random()),
+        }
     }
 }
 
@@ -1675,6 +2462,40 @@ proto_varint_enum!(MapIconType,
     0x19 :: TreasureMarker
 );
 
+impl MapIconType {
+    pub fn is_banner(&self) -> bool {
+        self.banner_color_name().is_some()
+    }
+
+    // the dye color of the banner, named like the dye items (light_gray, not silver)
+    pub fn banner_color_name(&self) -> Option<&'static str> {
+        use MapIconType::*;
+        Some(match self {
+            WhiteBanner => "white",
+            OrangeBanner => "orange",
+            MagentaBanner => "magenta",
+            YellowBanner => "yellow",
+            LimeBanner => "lime",
+            PinkBanner => "pink",
+            GrayBanner => "gray",
+            LightGrayBanner => "light_gray",
+            CyanBanner => "cyan",
+            PurpleBanner => "purple",
+            BlueBanner => "blue",
+            BrownBanner => "brown",
+            GreenBanner => "green",
+            RedBanner => "red",
+            BlackBanner => "black",
+            _ => return None,
+        })
+    }
+
+    pub fn is_structure_marker(&self) -> bool {
+        use MapIconType::*;
+        matches!(self, Mansion | Temple)
+    }
+}
+
 proto_struct!(MapIconSpec {
     kind: MapIconType,
     position: TopDownPosition<i8>,
@@ -1682,7 +2503,15 @@ proto_struct!(MapIconSpec {
     display_name: Option<Chat>
 });
 
+impl MapIconSpec {
+    // direction is sent in 1/16ths of a full turn
+    pub fn direction_degrees(&self) -> f32 {
+        self.direction as f32 * 22.5
+    }
+}
+
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MapColumns {
     NoUpdates,
     Updated(MapColumnsSpec),
@@ -1742,7 +2571,7 @@ impl From<Option<MapColumnsSpec>> for MapColumns {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for MapColumns {
     fn test_gen_random() -> Self {
         <Option<MapColumnsSpec>>::test_gen_random().into()
@@ -1762,6 +2591,37 @@ proto_struct!(TradeSpec {
     demand: i32
 });
 
+impl TradeSpec {
+    pub fn is_available(&self) -> bool {
+        !self.trade_disabled && self.trade_uses < self.max_trade_uses
+    }
+
+    pub fn uses_remaining(&self) -> i32 {
+        self.max_trade_uses - self.trade_uses
+    }
+
+    // The number of the first input item the trade really costs, like vanilla works it out. Demand
+    // only ever raises the price, and the special price (from reputation or hero of the village)
+    // can't bring it below one. Vanilla also caps it at the item's stack size, which isn't known
+    // here. An empty first input costs nothing.
+    pub fn effective_input_count(&self) -> i32 {
+        let base = match &self.input_item_1 {
+            Some(item) => item.item_count as i32,
+            None => return 0,
+        };
+
+        // truncating is the same as flooring here, since negative values are clamped to zero
+        let demand_bonus = core::cmp::max(0, (base as f32 * self.demand as f32 * self.price_multiplier) as i32);
+        core::cmp::max(1, base + demand_bonus + self.special_price)
+    }
+}
+
+impl PlayTradeListSpec {
+    pub fn available_trades(&self) -> impl Iterator<Item = &TradeSpec> + '_ {
+        self.trades.iter().filter(move |trade| trade.is_available())
+    }
+}
+
 proto_varint_enum!(Hand,
     0x00 :: MainHand,
     0x01 :: OffHand
@@ -1793,11 +2653,152 @@ proto_varint_enum!(WindowType,
     0x16 :: StoneCutter
 );
 
+pub const WINDOW_GENERIC_9X1: &str = "minecraft:generic_9x1";
+pub const WINDOW_GENERIC_9X2: &str = "minecraft:generic_9x2";
+pub const WINDOW_GENERIC_9X3: &str = "minecraft:generic_9x3";
+pub const WINDOW_GENERIC_9X4: &str = "minecraft:generic_9x4";
+pub const WINDOW_GENERIC_9X5: &str = "minecraft:generic_9x5";
+pub const WINDOW_GENERIC_9X6: &str = "minecraft:generic_9x6";
+pub const WINDOW_GENERIC_3X3: &str = "minecraft:generic_3x3";
+pub const WINDOW_ANVIL: &str = "minecraft:anvil";
+pub const WINDOW_BEACON: &str = "minecraft:beacon";
+pub const WINDOW_BLAST_FURNACE: &str = "minecraft:blast_furnace";
+pub const WINDOW_BREWING_STAND: &str = "minecraft:brewing_stand";
+pub const WINDOW_CRAFTING: &str = "minecraft:crafting";
+pub const WINDOW_ENCHANTMENT: &str = "minecraft:enchantment";
+pub const WINDOW_FURNACE: &str = "minecraft:furnace";
+pub const WINDOW_GRINDSTONE: &str = "minecraft:grindstone";
+pub const WINDOW_HOPPER: &str = "minecraft:hopper";
+pub const WINDOW_LECTERN: &str = "minecraft:lectern";
+pub const WINDOW_LOOM: &str = "minecraft:loom";
+pub const WINDOW_MERCHANT: &str = "minecraft:merchant";
+pub const WINDOW_SHULKER_BOX: &str = "minecraft:shulker_box";
+pub const WINDOW_SMOKER: &str = "minecraft:smoker";
+pub const WINDOW_CARTOGRAPHY_TABLE: &str = "minecraft:cartography_table";
+pub const WINDOW_STONECUTTER: &str = "minecraft:stonecutter";
+
+impl WindowType {
+    // number of slots belonging to the window itself, not counting the player's inventory
+    pub fn slot_count(&self) -> Option<u32> {
+        use WindowType::*;
+        match self {
+            GenericOneRow => Some(9),
+            GenericTwoRow => Some(18),
+            GenericThreeRow => Some(27),
+            GenericFourRow => Some(36),
+            GenericFiveRow => Some(45),
+            GenericSixRow => Some(54),
+            GenericSquare => Some(9),
+            Anvil => Some(3),
+            Beacon => Some(1),
+            BlastFurnace => Some(3),
+            BrewingStand => Some(5),
+            CraftingTable => Some(10),
+            EnchantmentTable => Some(2),
+            Furnace => Some(3),
+            Grindstone => Some(3),
+            Hopper => Some(5),
+            Lectern => Some(1),
+            Loom => Some(4),
+            Merchant => Some(3),
+            ShulkerBox => Some(27),
+            Smoker => Some(3),
+            Cartography => Some(3),
+            StoneCutter => Some(2),
+        }
+    }
+
+    #[inline]
+    pub fn identifier(&self) -> &'static str {
+        use WindowType::*;
+        match self {
+            GenericOneRow => WINDOW_GENERIC_9X1,
+            GenericTwoRow => WINDOW_GENERIC_9X2,
+            GenericThreeRow => WINDOW_GENERIC_9X3,
+            GenericFourRow => WINDOW_GENERIC_9X4,
+            GenericFiveRow => WINDOW_GENERIC_9X5,
+            GenericSixRow => WINDOW_GENERIC_9X6,
+            GenericSquare => WINDOW_GENERIC_3X3,
+            Anvil => WINDOW_ANVIL,
+            Beacon => WINDOW_BEACON,
+            BlastFurnace => WINDOW_BLAST_FURNACE,
+            BrewingStand => WINDOW_BREWING_STAND,
+            CraftingTable => WINDOW_CRAFTING,
+            EnchantmentTable => WINDOW_ENCHANTMENT,
+            Furnace => WINDOW_FURNACE,
+            Grindstone => WINDOW_GRINDSTONE,
+            Hopper => WINDOW_HOPPER,
+            Lectern => WINDOW_LECTERN,
+            Loom => WINDOW_LOOM,
+            Merchant => WINDOW_MERCHANT,
+            ShulkerBox => WINDOW_SHULKER_BOX,
+            Smoker => WINDOW_SMOKER,
+            Cartography => WINDOW_CARTOGRAPHY_TABLE,
+            StoneCutter => WINDOW_STONECUTTER,
+        }
+    }
+
+    #[inline]
+    pub fn from_identifier(identifier: &str) -> Option<Self> {
+        use WindowType::*;
+        match identifier {
+            WINDOW_GENERIC_9X1 => Some(GenericOneRow),
+            WINDOW_GENERIC_9X2 => Some(GenericTwoRow),
+            WINDOW_GENERIC_9X3 => Some(GenericThreeRow),
+            WINDOW_GENERIC_9X4 => Some(GenericFourRow),
+            WINDOW_GENERIC_9X5 => Some(GenericFiveRow),
+            WINDOW_GENERIC_9X6 => Some(GenericSixRow),
+            WINDOW_GENERIC_3X3 => Some(GenericSquare),
+            WINDOW_ANVIL => Some(Anvil),
+            WINDOW_BEACON => Some(Beacon),
+            WINDOW_BLAST_FURNACE => Some(BlastFurnace),
+            WINDOW_BREWING_STAND => Some(BrewingStand),
+            WINDOW_CRAFTING => Some(CraftingTable),
+            WINDOW_ENCHANTMENT => Some(EnchantmentTable),
+            WINDOW_FURNACE => Some(Furnace),
+            WINDOW_GRINDSTONE => Some(Grindstone),
+            WINDOW_HOPPER => Some(Hopper),
+            WINDOW_LECTERN => Some(Lectern),
+            WINDOW_LOOM => Some(Loom),
+            WINDOW_MERCHANT => Some(Merchant),
+            WINDOW_SHULKER_BOX => Some(ShulkerBox),
+            WINDOW_SMOKER => Some(Smoker),
+            WINDOW_CARTOGRAPHY_TABLE => Some(Cartography),
+            WINDOW_STONECUTTER => Some(StoneCutter),
+            _ => None,
+        }
+    }
+}
+
+impl PlayWindowItemsSpec {
+    pub fn window_id(&self) -> u8 {
+        self.window_id
+    }
+
+    pub fn slot_at(&self, index: u16) -> Option<&Slot> {
+        self.slots.get(index as usize)
+    }
+
+    pub fn occupied_slots(&self) -> impl Iterator<Item = (u16, &ItemStack)> + '_ {
+        self.slots.iter()
+            .enumerate()
+            .filter_map(|(index, slot)| slot.as_ref().map(move |stack| (index as u16, stack)))
+    }
+
+    // indices past the end of the window are empty too
+    pub fn is_empty_slot(&self, index: u16) -> bool {
+        match self.slot_at(index) {
+            Some(Some(stack)) => stack.item_count <= 0,
+            _ => true,
+        }
+    }
+}
+
 proto_byte_flag!(PlayerAbilityFlags,
-    0x01 :: is_invulnerable set_invulnerable,
-    0x02 :: is_flying set_flying,
-    0x04 :: is_flight_allowed set_flight_allowed,
-    0x08 :: is_instant_break set_instant_break
+    0x01 :: is_invulnerable set_invulnerable with_invulnerable,
+    0x02 :: is_flying set_flying with_flying,
+    0x04 :: is_flight_allowed set_flight_allowed with_flight_allowed,
+    0x08 :: is_instant_break set_instant_break with_instant_break
 );
 
 proto_varint_enum!(CombatEvent,
@@ -1844,6 +2845,156 @@ proto_struct!(PlayerAddProperty {
     signature: Option<String>
 });
 
+impl PlayerInfoActionList {
+    // the players this update is about, in the order they're sent
+    pub fn uuids(&self) -> Box<dyn Iterator<Item = UUID4> + '_> {
+        use PlayerInfoActionList::*;
+        match self {
+            Add(players) => Box::new(players.iter().map(move |player| player.uuid)),
+            UpdateGameMode(players) => Box::new(players.iter().map(move |player| player.uuid)),
+            UpdateLatency(players) => Box::new(players.iter().map(move |player| player.uuid)),
+            UpdateDisplayName(players) => Box::new(players.iter().map(move |player| player.uuid)),
+            Remove(uuids) => Box::new(uuids.iter().copied()),
+        }
+    }
+}
+
+impl PlayPlayerInfoSpec {
+    pub fn uuids(&self) -> impl Iterator<Item = UUID4> + '_ {
+        self.actions.uuids()
+    }
+}
+
+impl PlayerAddActionSpec {
+    // the base64 encoded textures property and its signature, which offline mode servers leave out
+    pub fn skin_texture(&self) -> Option<(&str, Option<&str>)> {
+        self.properties.iter()
+            .find(move |property| property.name == "textures")
+            .map(move |property| (property.value.as_str(), property.signature.as_deref()))
+    }
+}
+
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlayerListEntry {
+    pub uuid: UUID4,
+    pub name: String,
+    pub properties: Vec<PlayerAddProperty>,
+    pub game_mode: GameMode,
+    pub ping_ms: VarInt,
+    pub display_name: Option<Chat>,
+}
+
+// The tab list as seen by a client, built by applying every PlayPlayerInfo packet in order. Updates
+// and removals for players which were never added are ignored, unless the list is recording them,
+// in which case their uuids are kept in `unknown_updates`.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PlayerList {
+    entries: std::collections::HashMap<UUID4, PlayerListEntry>,
+    record_unknown_updates: bool,
+    unknown_updates: Vec<UUID4>,
+}
+
+#[cfg(feature = "std")]
+impl PlayerList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn recording_unknown_updates() -> Self {
+        Self {
+            record_unknown_updates: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn apply(&mut self, actions: &PlayerInfoActionList) {
+        use PlayerInfoActionList::*;
+        match actions {
+            Add(players) => {
+                for player in players.iter() {
+                    let PlayerAddActionSpec { name, properties, game_mode, ping_ms, display_name } = &player.action;
+                    self.entries.insert(player.uuid, PlayerListEntry {
+                        uuid: player.uuid,
+                        name: name.clone(),
+                        properties: properties.to_vec(),
+                        game_mode: game_mode.clone(),
+                        ping_ms: *ping_ms,
+                        display_name: display_name.clone(),
+                    });
+                }
+            }
+            UpdateGameMode(players) => {
+                for player in players.iter() {
+                    if let Some(entry) = self.entry_mut(player.uuid) {
+                        entry.game_mode = player.action.clone();
+                    }
+                }
+            }
+            UpdateLatency(players) => {
+                for player in players.iter() {
+                    if let Some(entry) = self.entry_mut(player.uuid) {
+                        entry.ping_ms = player.action;
+                    }
+                }
+            }
+            UpdateDisplayName(players) => {
+                for player in players.iter() {
+                    if let Some(entry) = self.entry_mut(player.uuid) {
+                        entry.display_name = player.action.clone();
+                    }
+                }
+            }
+            Remove(uuids) => {
+                for uuid in uuids.iter() {
+                    if self.entries.remove(uuid).is_none() {
+                        self.unknown(*uuid);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn get(&self, uuid: &UUID4) -> Option<&PlayerListEntry> {
+        self.entries.get(uuid)
+    }
+
+    pub fn get_by_name(&self, name: &str) -> Option<&PlayerListEntry> {
+        self.entries.values().find(move |entry| entry.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &PlayerListEntry> + '_ {
+        self.entries.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn unknown_updates(&self) -> &[UUID4] {
+        self.unknown_updates.as_slice()
+    }
+
+    fn entry_mut(&mut self, uuid: UUID4) -> Option<&mut PlayerListEntry> {
+        if !self.entries.contains_key(&uuid) {
+            self.unknown(uuid);
+        }
+
+        self.entries.get_mut(&uuid)
+    }
+
+    fn unknown(&mut self, uuid: UUID4) {
+        if self.record_unknown_updates {
+            self.unknown_updates.push(uuid);
+        }
+    }
+}
+
 proto_varint_enum!(FacePlayerKind,
     0x00 :: Feet,
     0x01 :: Eyes
@@ -1855,13 +3006,94 @@ proto_struct!(FacePlayerEntityTarget {
 });
 
 proto_byte_flag!(PositionAndLookFlags,
-    0x01 :: is_x_rel set_x_rel,
-    0x02 :: is_y_rel set_y_rel,
-    0x04 :: is_z_rel set_z_rel,
-    0x08 :: is_y_rotation_rel set_y_rotation_rel,
-    0x10 :: is_x_rotation_rel set_x_rotation_rel
+    0x01 :: is_x_rel set_x_rel with_x_rel,
+    0x02 :: is_y_rel set_y_rel with_y_rel,
+    0x04 :: is_z_rel set_z_rel with_z_rel,
+    0x08 :: is_y_rotation_rel set_y_rotation_rel with_y_rotation_rel,
+    0x10 :: is_x_rotation_rel set_x_rotation_rel with_x_rotation_rel
 );
 
+// entity movement deltas are sent in 1/4096ths of a block
+pub const POSITION_DELTA_SCALE: f64 = 4096.0;
+
+// truncates like the server does, movements too large for a delta saturate (the server sends a
+// teleport instead)
+pub fn from_delta_f64(dx: f64, dy: f64, dz: f64) -> (i16, i16, i16) {
+    (
+        (dx * POSITION_DELTA_SCALE) as i16,
+        (dy * POSITION_DELTA_SCALE) as i16,
+        (dz * POSITION_DELTA_SCALE) as i16,
+    )
+}
+
+fn delta_to_f64(delta: &Vec3<i16>) -> (f64, f64, f64) {
+    (
+        delta.x as f64 / POSITION_DELTA_SCALE,
+        delta.y as f64 / POSITION_DELTA_SCALE,
+        delta.z as f64 / POSITION_DELTA_SCALE,
+    )
+}
+
+impl PlayEntityPositionSpec {
+    pub fn delta_as_f64(&self) -> (f64, f64, f64) {
+        delta_to_f64(&self.delta)
+    }
+}
+
+impl PlayEntityPositionAndRotationSpec {
+    pub fn delta_as_f64(&self) -> (f64, f64, f64) {
+        delta_to_f64(&self.delta.position)
+    }
+}
+
+// unit vector a player with the given yaw and pitch (in degrees) is looking along, a yaw of 0 faces
+// south (+z), 90 faces west (-x) and a positive pitch looks down
+#[cfg(feature = "std")]
+pub fn angles_to_look_vec(yaw_deg: f32, pitch_deg: f32) -> (f64, f64, f64) {
+    let yaw = (yaw_deg as f64).to_radians();
+    let pitch = (pitch_deg as f64).to_radians();
+    (-yaw.sin() * pitch.cos(), -pitch.sin(), yaw.cos() * pitch.cos())
+}
+
+#[cfg(feature = "std")]
+impl PlayClientPlayerPositionAndRotationSpec {
+    pub fn look_direction_vec(&self) -> (f64, f64, f64) {
+        let rotation = &self.feet_location.rotation;
+        angles_to_look_vec(rotation.yaw, rotation.pitch)
+    }
+}
+
+#[cfg(feature = "std")]
+impl PlayPlayerRotationSpec {
+    pub fn look_direction_vec(&self) -> (f64, f64, f64) {
+        angles_to_look_vec(self.rotation.yaw, self.rotation.pitch)
+    }
+}
+
+impl PlayServerPlayerPositionAndLookSpec {
+    pub fn is_fully_absolute(&self) -> bool {
+        self.flags == PositionAndLookFlags::none()
+    }
+
+    pub fn is_fully_relative(&self) -> bool {
+        self.flags == PositionAndLookFlags::all()
+    }
+
+    // takes and returns (x, y, z, yaw, pitch), relative components are offsets from the current value
+    pub fn apply_to_position(&self, current: (f64, f64, f64, f32, f32)) -> (f64, f64, f64, f32, f32) {
+        let (x, y, z, yaw, pitch) = current;
+        let EntityLocation { position, rotation } = &self.location;
+        let flags = self.flags;
+        (
+            if flags.is_x_rel() { x + position.x } else { position.x },
+            if flags.is_y_rel() { y + position.y } else { position.y },
+            if flags.is_z_rel() { z + position.z } else { position.z },
+            if flags.is_y_rotation_rel() { yaw + rotation.yaw } else { rotation.yaw },
+            if flags.is_x_rotation_rel() { pitch + rotation.pitch } else { rotation.pitch },
+        )
+    }
+}
+
 proto_byte_enum!(EntityEffectKind,
     0x01 :: Speed,
     0x02 :: Slowness,
@@ -1897,6 +3129,14 @@ proto_byte_enum!(EntityEffectKind,
     0x20 :: HeroOfTheVillage
 );
 
+impl EntityEffectKind {
+    // Amplifiers count from zero, so this is level I of any effect. Level II (like Strength II or
+    // Regeneration II from a potion) is amplifier 1.
+    pub fn default_amplifier(&self) -> i8 {
+        0
+    }
+}
+
 proto_varint_enum!(WorldBorderAction,
     0x00 :: SetSize(WorldBorderSetSizeSpec),
     0x01 :: LerpSize(WorldBorderLerpSizeSpec),
@@ -1906,6 +3146,93 @@ proto_varint_enum!(WorldBorderAction,
     0x05 :: SetWarningBlocks(WorldBorderWarningBlocksSpec)
 );
 
+impl WorldBorderAction {
+    pub fn center(&self) -> Option<(f64, f64)> {
+        use WorldBorderAction::*;
+        match self {
+            SetCenter(position) => Some((position.x, position.z)),
+            Initialize(spec) => Some((spec.position.x, spec.position.z)),
+            _ => None,
+        }
+    }
+
+    // for a lerp this is the diameter the border is moving towards
+    pub fn diameter(&self) -> Option<f64> {
+        use WorldBorderAction::*;
+        match self {
+            SetSize(spec) => Some(spec.diameter),
+            LerpSize(spec) => Some(spec.new_diameter),
+            Initialize(spec) => Some(spec.new_diameter),
+            _ => None,
+        }
+    }
+
+    pub fn warning_time(&self) -> Option<VarInt> {
+        use WorldBorderAction::*;
+        match self {
+            SetWarningTime(spec) => Some(spec.warning_time),
+            Initialize(spec) => Some(spec.warning_time),
+            _ => None,
+        }
+    }
+
+    pub fn warning_blocks(&self) -> Option<VarInt> {
+        use WorldBorderAction::*;
+        match self {
+            SetWarningBlocks(spec) => Some(spec.warning_blocks),
+            Initialize(spec) => Some(spec.warning_blocks),
+            _ => None,
+        }
+    }
+}
+
+// Tracks the world border as it's updated by a series of world border packets, every value is
+// None until an action which sets it has been applied.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct WorldBorderState {
+    center: Option<(f64, f64)>,
+    diameter: Option<f64>,
+    warning_time: Option<VarInt>,
+    warning_blocks: Option<VarInt>,
+}
+
+impl WorldBorderState {
+    pub fn apply(&mut self, action: &WorldBorderAction) {
+        if let Some(center) = action.center() {
+            self.center = Some(center);
+        }
+        if let Some(diameter) = action.diameter() {
+            self.diameter = Some(diameter);
+        }
+        if let Some(warning_time) = action.warning_time() {
+            self.warning_time = Some(warning_time);
+        }
+        if let Some(warning_blocks) = action.warning_blocks() {
+            self.warning_blocks = Some(warning_blocks);
+        }
+    }
+
+    pub fn center(&self) -> Option<(f64, f64)> {
+        self.center
+    }
+
+    pub fn diameter(&self) -> Option<f64> {
+        self.diameter
+    }
+
+    pub fn warning_time(&self) -> Option<VarInt> {
+        self.warning_time
+    }
+
+    pub fn warning_blocks(&self) -> Option<VarInt> {
+        self.warning_blocks
+    }
+
+    pub fn is_initialized(&self) -> bool {
+        self.center.is_some() && self.diameter.is_some() && self.warning_time.is_some() && self.warning_blocks.is_some()
+    }
+}
+
 proto_struct!(WorldBorderSetSizeSpec {
     diameter: f64
 });
@@ -1988,6 +3315,7 @@ proto_struct!(AdvancementDisplaySpec {
 });
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AdvancementDisplayFlags {
     pub background_texture: Option<String>,
     pub show_toast: bool,
@@ -2037,16 +3365,16 @@ impl Deserialize for AdvancementDisplayFlags {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for AdvancementDisplayFlags {
     fn test_gen_random() -> Self {
-        let background_texture = if rand::random::<bool>() {
+        let background_texture = if crate::protocol::test_random::<bool>() {
             Some(String::test_gen_random())
         } else {
             None
         };
-        let show_toast = rand::random::<bool>();
-        let hidden = rand::random::<bool>();
+        let show_toast = crate::protocol::test_random::<bool>();
+        let hidden = crate::protocol::test_random::<bool>();
 
         Self {
             background_texture,
@@ -2080,6 +3408,110 @@ proto_struct!(AdvancementCriterionProgressSpec {
     achieved_at: Option<i64>
 });
 
+impl AdvancementCriterionProgressSpec {
+    pub fn achieved(&self) -> bool {
+        self.achieved_at.is_some()
+    }
+}
+
+impl AdvancementProgressSpec {
+    pub fn is_complete(&self) -> bool {
+        self.criteria.iter().all(move |criterion| criterion.progress.achieved())
+    }
+}
+
+impl AdvancementSpec {
+    pub fn is_root(&self) -> bool {
+        self.parent.is_none()
+    }
+}
+
+impl PlayAdvancementsSpec {
+    pub fn completed_advancements(&self) -> impl Iterator<Item = (&str, &AdvancementProgressSpec)> {
+        self.progress.iter()
+            .filter(move |entry| entry.value.is_complete())
+            .map(move |entry| (entry.key.as_str(), &entry.value))
+    }
+
+    // Updates the advancements a client knows about the way the vanilla client does: a reset clears
+    // everything, then removed advancements (and their progress) are dropped before new ones are
+    // added, and progress entries replace whatever was stored for that advancement.
+    pub fn apply_to(
+        &self,
+        advancements: &mut alloc::collections::BTreeMap<String, AdvancementSpec>,
+        progress: &mut alloc::collections::BTreeMap<String, AdvancementProgressSpec>,
+    ) {
+        self.apply_to_maps(advancements, progress)
+    }
+
+    #[cfg(feature = "std")]
+    pub fn apply_to_hash(
+        &self,
+        advancements: &mut std::collections::HashMap<String, AdvancementSpec>,
+        progress: &mut std::collections::HashMap<String, AdvancementProgressSpec>,
+    ) {
+        self.apply_to_maps(advancements, progress)
+    }
+
+    fn apply_to_maps<A, P>(&self, advancements: &mut A, progress: &mut P)
+        where A: AdvancementMap<AdvancementSpec>, P: AdvancementMap<AdvancementProgressSpec>
+    {
+        if self.reset {
+            advancements.clear();
+            progress.clear();
+        }
+
+        for identifier in self.identifiers.iter() {
+            advancements.remove(identifier);
+            progress.remove(identifier);
+        }
+        for entry in self.mappings.iter() {
+            advancements.insert(entry.key.clone(), entry.value.clone());
+        }
+        for entry in self.progress.iter() {
+            progress.insert(entry.key.clone(), entry.value.clone());
+        }
+    }
+}
+
+// the map operations PlayAdvancementsSpec::apply_to_maps needs, so it works on both map types
+trait AdvancementMap<V> {
+    fn clear(&mut self);
+
+    fn remove(&mut self, key: &str);
+
+    fn insert(&mut self, key: String, value: V);
+}
+
+impl<V> AdvancementMap<V> for alloc::collections::BTreeMap<String, V> {
+    fn clear(&mut self) {
+        alloc::collections::BTreeMap::clear(self)
+    }
+
+    fn remove(&mut self, key: &str) {
+        alloc::collections::BTreeMap::remove(self, key);
+    }
+
+    fn insert(&mut self, key: String, value: V) {
+        alloc::collections::BTreeMap::insert(self, key, value);
+    }
+}
+
+#[cfg(feature = "std")]
+impl<V> AdvancementMap<V> for std::collections::HashMap<String, V> {
+    fn clear(&mut self) {
+        std::collections::HashMap::clear(self)
+    }
+
+    fn remove(&mut self, key: &str) {
+        std::collections::HashMap::remove(self, key);
+    }
+
+    fn insert(&mut self, key: String, value: V) {
+        std::collections::HashMap::insert(self, key, value);
+    }
+}
+
 proto_struct!(EntityPropertySpec {
     key: String,
     value: f64,
@@ -2098,17 +3530,164 @@ proto_byte_enum!(EntityPropertyModifierOperation,
     0x02 :: MultiplyByAmountPercent
 );
 
-proto_byte_flag!(EntityEffectFlags,
-    0x01 :: is_ambient set_ambient,
-    0x02 :: is_show_particles set_show_particles,
-    0x04 :: is_show_icon set_show_icon
-);
+impl EntityPropertyModifierOperation {
+    // the value after applying one modifier, base is the value before any percentage modifiers
+    pub fn apply(&self, base: f64, current: f64, amount: f64) -> f64 {
+        use EntityPropertyModifierOperation::*;
+        match self {
+            AddSubtractAmount => current + amount,
+            AddSubtractAmountPercentOfCurrent => current + base * amount,
+            MultiplyByAmountPercent => current * (1.0 + amount),
+        }
+    }
+}
 
-proto_struct!(TagSpec {
-    name: String,
-    entries: CountedArray<VarInt, VarInt>
+impl EntityPropertySpec {
+    // vanilla applies modifiers grouped by operation: additions make up the base, then percentages
+    // of that base are added, then every multiplier scales the result
+    pub fn compute_value(&self) -> f64 {
+        use EntityPropertyModifierOperation::*;
+        let apply_all = |operation: EntityPropertyModifierOperation, base: f64, start: f64| {
+            self.modifiers.iter()
+                .filter(|modifier| modifier.operation == operation)
+                .fold(start, |current, modifier| operation.apply(base, current, modifier.amount))
+        };
+
+        let base = apply_all(AddSubtractAmount, self.value, self.value);
+        let current = apply_all(AddSubtractAmountPercentOfCurrent, base, base);
+        apply_all(MultiplyByAmountPercent, base, current)
+    }
+}
+
+impl PlayEntityPropertiesSpec {
+    pub fn attribute_value(&self, key: &str) -> Option<f64> {
+        self.properties.iter()
+            .find(move |property| property.key == key)
+            .map(EntityPropertySpec::compute_value)
+    }
+}
+
+proto_byte_flag!(EntityEffectFlags,
+    0x01 :: is_ambient set_ambient with_ambient,
+    0x02 :: is_show_particles set_show_particles with_show_particles,
+    0x04 :: is_show_icon set_show_icon with_show_icon
+);
+
+// the tick rate of an unlagged server
+pub const DEFAULT_TPS: f32 = 20.0;
+
+impl PlayEntityEffectSpec {
+    pub fn duration_seconds(&self, tps: f32) -> f32 {
+        self.duration_ticks.0 as f32 / tps
+    }
+
+    pub fn is_ambient(&self) -> bool {
+        self.flags.is_ambient()
+    }
+
+    pub fn shows_particles(&self) -> bool {
+        self.flags.is_show_particles()
+    }
+
+    pub fn shows_icon(&self) -> bool {
+        self.flags.is_show_icon()
+    }
+}
+
+proto_struct!(TagSpec {
+    name: String,
+    entries: CountedArray<VarInt, VarInt>
 });
 
+impl TagSpec {
+    pub fn contains_id(&self, id: VarInt) -> bool {
+        self.entries.contains(&id)
+    }
+}
+
+impl PlayTagsSpec {
+    pub fn lookup_block_tag(&self, name: &str) -> Option<&TagSpec> {
+        find_tag(&self.block_tags, name)
+    }
+
+    pub fn lookup_item_tag(&self, name: &str) -> Option<&TagSpec> {
+        find_tag(&self.item_tags, name)
+    }
+
+    pub fn lookup_fluid_tag(&self, name: &str) -> Option<&TagSpec> {
+        find_tag(&self.fluid_tags, name)
+    }
+
+    pub fn lookup_entity_tag(&self, name: &str) -> Option<&TagSpec> {
+        find_tag(&self.entity_tags, name)
+    }
+}
+
+fn find_tag<'a>(tags: &'a [TagSpec], name: &str) -> Option<&'a TagSpec> {
+    tags.iter().find(move |tag| tag.name == name)
+}
+
+// Indexes the tags of a PlayTags packet by name for repeated lookups, each index is only built the
+// first time a tag of that kind is looked up. When a name appears twice the first tag wins, like
+// with the lookup methods on PlayTagsSpec.
+#[cfg(feature = "std")]
+pub struct TagLookup<'a> {
+    tags: &'a PlayTagsSpec,
+    block_tags: std::cell::OnceCell<std::collections::HashMap<&'a str, &'a TagSpec>>,
+    item_tags: std::cell::OnceCell<std::collections::HashMap<&'a str, &'a TagSpec>>,
+    fluid_tags: std::cell::OnceCell<std::collections::HashMap<&'a str, &'a TagSpec>>,
+    entity_tags: std::cell::OnceCell<std::collections::HashMap<&'a str, &'a TagSpec>>,
+}
+
+#[cfg(feature = "std")]
+impl<'a> TagLookup<'a> {
+    pub fn new(tags: &'a PlayTagsSpec) -> Self {
+        Self {
+            tags,
+            block_tags: std::cell::OnceCell::new(),
+            item_tags: std::cell::OnceCell::new(),
+            fluid_tags: std::cell::OnceCell::new(),
+            entity_tags: std::cell::OnceCell::new(),
+        }
+    }
+
+    pub fn block_tag(&self, name: &str) -> Option<&'a TagSpec> {
+        Self::index(&self.block_tags, &self.tags.block_tags).get(name).copied()
+    }
+
+    pub fn item_tag(&self, name: &str) -> Option<&'a TagSpec> {
+        Self::index(&self.item_tags, &self.tags.item_tags).get(name).copied()
+    }
+
+    pub fn fluid_tag(&self, name: &str) -> Option<&'a TagSpec> {
+        Self::index(&self.fluid_tags, &self.tags.fluid_tags).get(name).copied()
+    }
+
+    pub fn entity_tag(&self, name: &str) -> Option<&'a TagSpec> {
+        Self::index(&self.entity_tags, &self.tags.entity_tags).get(name).copied()
+    }
+
+    fn index<'b>(
+        cell: &'b std::cell::OnceCell<std::collections::HashMap<&'a str, &'a TagSpec>>,
+        tags: &'a [TagSpec],
+    ) -> &'b std::collections::HashMap<&'a str, &'a TagSpec> {
+        cell.get_or_init(move || {
+            let mut index = std::collections::HashMap::with_capacity(tags.len());
+            for tag in tags {
+                index.entry(tag.name.as_str()).or_insert(tag);
+            }
+            index
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a> From<&'a PlayTagsSpec> for TagLookup<'a> {
+    fn from(tags: &'a PlayTagsSpec) -> Self {
+        Self::new(tags)
+    }
+}
+
 proto_varint_enum!(ClientStatusAction,
     0x00 :: PerformRespawn,
     0x01 :: RequestStats
@@ -2126,13 +3705,13 @@ proto_varint_enum!(ClientMainHand,
 );
 
 proto_byte_flag!(ClientDisplayedSkinParts,
-    0x01 :: is_cape_enabled set_cape_enabled,
-    0x02 :: is_jacket_enabled set_jacket_enabled,
-    0x04 :: is_left_sleeve_enabled set_left_sleeve_enabled,
-    0x08 :: is_right_sleeve_enabled set_right_sleeve_enabled,
-    0x10 :: is_left_pants_leg_enabled set_left_pants_leg_enabled,
-    0x20 :: is_right_pant_legs_enabled set_right_pant_legs_enabled,
-    0x40 :: is_hat_enabled set_hat_enabled
+    0x01 :: is_cape_enabled set_cape_enabled with_cape_enabled,
+    0x02 :: is_jacket_enabled set_jacket_enabled with_jacket_enabled,
+    0x04 :: is_left_sleeve_enabled set_left_sleeve_enabled with_left_sleeve_enabled,
+    0x08 :: is_right_sleeve_enabled set_right_sleeve_enabled with_right_sleeve_enabled,
+    0x10 :: is_left_pants_leg_enabled set_left_pants_leg_enabled with_left_pants_leg_enabled,
+    0x20 :: is_right_pant_legs_enabled set_right_pant_legs_enabled with_right_pant_legs_enabled,
+    0x40 :: is_hat_enabled set_hat_enabled with_hat_enabled
 );
 
 proto_varint_enum!(InventoryOperationMode,
@@ -2157,10 +3736,10 @@ proto_varint_enum!(InteractKind,
 );
 
 proto_byte_flag!(ClientPlayerAbilities,
-    0x01 :: is_creative set_creative,
-    0x02 :: is_flying set_flying,
-    0x04 :: is_fly_enabled set_fly_enabled,
-    0x08 :: is_damaged_disabled set_damaged_disabled
+    0x01 :: is_creative set_creative with_creative,
+    0x02 :: is_flying set_flying with_flying,
+    0x04 :: is_fly_enabled set_fly_enabled with_fly_enabled,
+    0x08 :: is_damaged_disabled set_damaged_disabled with_damaged_disabled
 );
 
 proto_varint_enum!(PlayerDiggingStatus,
@@ -2195,8 +3774,8 @@ proto_varint_enum!(EntityActionKind,
 );
 
 proto_byte_flag!(SteerVehicleFlags,
-    0x01 :: is_jump set_jump,
-    0x02 :: is_unmount set_unmount
+    0x01 :: is_jump set_jump with_jump,
+    0x02 :: is_unmount set_unmount with_unmount
 );
 
 proto_varint_enum!(RecipeBookStatus,
@@ -2234,9 +3813,9 @@ proto_varint_enum!(CommandBlockMode,
 );
 
 proto_byte_flag!(CommandBlockFlags,
-    0x01 :: is_track_output set_track_output,
-    0x02 :: is_conditional set_conditional,
-    0x04 :: is_automatic set_automatic
+    0x01 :: is_track_output set_track_output with_track_output,
+    0x02 :: is_conditional set_conditional with_conditional,
+    0x04 :: is_automatic set_automatic with_automatic
 );
 
 proto_varint_enum!(UpdateStructureBlockAction,
@@ -2267,12 +3846,13 @@ proto_varint_enum!(UpdateStructureBlockRotation,
 );
 
 proto_byte_flag!(UpdateStructureBlockFlags,
-    0x01 :: is_ignore_entities set_ignore_entities,
-    0x02 :: is_show_air set_show_air,
-    0x04 :: is_show_bounding_box set_show_bounding_box
+    0x01 :: is_ignore_entities set_ignore_entities with_ignore_entities,
+    0x02 :: is_show_air set_show_air with_show_air,
+    0x04 :: is_show_bounding_box set_show_bounding_box with_show_bounding_box
 );
 
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RecipeSpec {
     pub recipe: Recipe,
     pub id: String,
@@ -2328,7 +3908,7 @@ impl Deserialize for RecipeSpec {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for RecipeSpec {
     fn test_gen_random() -> Self {
         RecipeSpec {
@@ -2349,6 +3929,7 @@ proto_struct!(RecipeCraftingShapelessSpec {
 });
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RecipeCraftingShapedSpec {
     pub width: VarInt,
     pub height: VarInt,
@@ -2357,8 +3938,44 @@ pub struct RecipeCraftingShapedSpec {
     pub result: Slot,
 }
 
+impl RecipeCraftingShapedSpec {
+    // the number of ingredients implied by the dimensions, or None if they're negative
+    pub fn ingredient_count(&self) -> Option<usize> {
+        if self.width.0 < 0 || self.height.0 < 0 {
+            None
+        } else {
+            Some(self.width.0 as usize * self.height.0 as usize)
+        }
+    }
+
+    // an (x, y) outside of the grid has no ingredient, rather than wrapping onto the next row
+    pub fn ingredient_at(&self, x: usize, y: usize) -> Option<&RecipeIngredient> {
+        if self.width.0 < 0 || x >= self.width.0 as usize || self.height.0 < 0 || y >= self.height.0 as usize {
+            return None;
+        }
+
+        self.ingredients.get(y * self.width.0 as usize + x)
+    }
+
+    // ((column, row), ingredient) for every position in the grid, row by row
+    pub fn grid(&self) -> impl Iterator<Item = ((usize, usize), &RecipeIngredient)> + '_ {
+        let width = core::cmp::max(self.width.0, 1) as usize;
+        self.ingredients.iter()
+            .take(self.ingredient_count().unwrap_or(0))
+            .enumerate()
+            .map(move |(i, ingredient)| ((i % width, i / width), ingredient))
+    }
+}
+
 impl Serialize for RecipeCraftingShapedSpec {
     fn mc_serialize<S: Serializer>(&self, to: &mut S) -> SerializeResult {
+        if self.ingredient_count() != Some(self.ingredients.len()) {
+            return Err(SerializeErr::CannotSerialize(alloc::format!(
+                "shaped recipe is {}x{} but has {} ingredients",
+                self.width, self.height, self.ingredients.len()
+            )));
+        }
+
         to.serialize_other(&self.width)?;
         to.serialize_other(&self.height)?;
         to.serialize_other(&self.group)?;
@@ -2376,7 +3993,17 @@ impl Deserialize for RecipeCraftingShapedSpec {
         let Deserialized { value: height, data } = <VarInt>::mc_deserialize(data)?;
         let Deserialized { value: group, mut data } = <String>::mc_deserialize(data)?;
 
-        let ingredients_count = width.0 as usize * height.0 as usize;
+        if width.0 < 0 || height.0 < 0 {
+            return Err(DeserializeErr::CannotUnderstandValue(alloc::format!(
+                "shaped recipe has invalid dimensions {}x{}", width, height
+            )));
+        }
+        // every ingredient takes at least one byte, so a grid bigger than the rest of the packet is corrupt
+        let ingredients_count = (width.0 as usize).checked_mul(height.0 as usize)
+            .filter(|count| *count <= data.len())
+            .ok_or_else(|| DeserializeErr::CannotUnderstandValue(alloc::format!(
+                "shaped recipe is {}x{} but only {} bytes are left for its ingredients", width, height, data.len()
+            )))?;
         let mut ingredients: Vec<RecipeIngredient> = Vec::with_capacity(ingredients_count);
         for _ in 0..ingredients_count {
             let Deserialized { value: elem, data: rest } = RecipeIngredient::mc_deserialize(data)?;
@@ -2399,12 +4026,12 @@ impl Deserialize for RecipeCraftingShapedSpec {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for RecipeCraftingShapedSpec {
     fn test_gen_random() -> Self {
         use rand::distributions::Distribution;
         let size_distr = rand::distributions::Uniform::new(1, 9);
-        let mut rng = rand::thread_rng();
+        let mut rng = crate::protocol::test_rng();
 
         let width: VarInt = size_distr.sample(&mut rng).into();
         let height: VarInt = size_distr.sample(&mut rng).into();
@@ -2438,22 +4065,249 @@ proto_struct!(RecipeStonecuttingSpec {
     result: Slot
 });
 
+pub enum RecipeErr {
+    BadDimensions { id: String, width: VarInt, height: VarInt },
+    IngredientCountMismatch { id: String, expected: usize, found: usize },
+}
+
+impl fmt::Display for RecipeErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use RecipeErr::*;
+        match self {
+            BadDimensions { id, width, height } => f.write_fmt(format_args!(
+                "shaped recipe {} has invalid dimensions {}x{}", id, width, height
+            )),
+            IngredientCountMismatch { id, expected, found } => f.write_fmt(format_args!(
+                "shaped recipe {} needs {} ingredients but has {}", id, expected, found
+            )),
+        }
+    }
+}
+
+impl fmt::Debug for RecipeErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        <dyn fmt::Display>::fmt(self, f)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RecipeErr {}
+
+impl RecipeSpec {
+    pub fn group(&self) -> Option<&str> {
+        self.recipe.group()
+    }
+
+    pub fn shaped(id: &str, width: i32, height: i32, ingredients: Vec<RecipeIngredient>, result: Slot) -> Result<Self, RecipeErr> {
+        let spec = RecipeSpec {
+            recipe: Recipe::CraftingShaped(RecipeCraftingShapedSpec {
+                width: VarInt(width),
+                height: VarInt(height),
+                group: String::new(),
+                ingredients,
+                result,
+            }),
+            id: id.to_owned(),
+        };
+        spec.validate()?;
+        Ok(spec)
+    }
+
+    pub fn shapeless(id: &str, ingredients: Vec<RecipeIngredient>, result: Slot) -> Self {
+        RecipeSpec {
+            recipe: Recipe::CraftingShapeless(RecipeCraftingShapelessSpec {
+                group: String::new(),
+                ingredients: ingredients.into(),
+                result,
+            }),
+            id: id.to_owned(),
+        }
+    }
+
+    pub fn smelting(id: &str, ingredient: RecipeIngredient, result: Slot, experience: f32, cooking_time: i32) -> Self {
+        RecipeSpec {
+            recipe: Recipe::Smelting(RecipeSmeltingSpec {
+                group: String::new(),
+                ingredient,
+                result,
+                experience,
+                cooking_time: VarInt(cooking_time),
+            }),
+            id: id.to_owned(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), RecipeErr> {
+        match &self.recipe {
+            Recipe::CraftingShaped(shaped) => match shaped.ingredient_count() {
+                None => Err(RecipeErr::BadDimensions {
+                    id: self.id.clone(),
+                    width: shaped.width,
+                    height: shaped.height,
+                }),
+                Some(expected) if expected != shaped.ingredients.len() => Err(RecipeErr::IngredientCountMismatch {
+                    id: self.id.clone(),
+                    expected,
+                    found: shaped.ingredients.len(),
+                }),
+                Some(_) => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+impl PlayDeclareRecipesSpec {
+    pub fn validate(&self) -> Result<(), RecipeErr> {
+        self.recipes.iter().try_for_each(RecipeSpec::validate)
+    }
+
+    pub fn recipe_by_id(&self, id: &str) -> Option<&RecipeSpec> {
+        self.recipes.iter().find(move |recipe| recipe.id == id)
+    }
+
+    pub fn recipes_for_item(&self, item_id: VarInt) -> impl Iterator<Item = &RecipeSpec> + '_ {
+        self.recipes.iter().filter(move |recipe| matches!(
+            recipe.recipe.result_item(),
+            Some(Some(result)) if result.item_id == item_id
+        ))
+    }
+}
+
+impl Recipe {
+    // the special crafting recipes have their result computed by the client, so they have none here
+    pub fn result_item(&self) -> Option<&Slot> {
+        use Recipe::*;
+        match self {
+            CraftingShapeless(body) => Some(&body.result),
+            CraftingShaped(body) => Some(&body.result),
+            Smelting(body) | Blasting(body) | Smoking(body) | CampfireCooking(body) => Some(&body.result),
+            StoneCutting(body) => Some(&body.result),
+            _ => None,
+        }
+    }
+
+    // an empty group is how vanilla sends a recipe which isn't in a group
+    pub fn group(&self) -> Option<&str> {
+        use Recipe::*;
+        let group = match self {
+            CraftingShapeless(body) => &body.group,
+            CraftingShaped(body) => &body.group,
+            Smelting(body) | Blasting(body) | Smoking(body) | CampfireCooking(body) => &body.group,
+            StoneCutting(body) => &body.group,
+            _ => return None,
+        };
+
+        if group.is_empty() {
+            None
+        } else {
+            Some(group.as_str())
+        }
+    }
+}
+
 proto_varint_enum!(RecipeUnlockAction,
     0x00 :: Init,
     0x01 :: Add,
     0x02 :: Remove
 );
 
+// The recipes a client knows about and which of them the player has unlocked. Declaring recipes
+// replaces every known recipe, unlocks are kept by id so they can arrive before the declaration.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RecipeBook {
+    recipes: std::collections::HashMap<String, RecipeSpec>,
+    unlocked: std::collections::HashSet<String>,
+}
+
+#[cfg(feature = "std")]
+impl RecipeBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn apply_declare(&mut self, packet: &PlayDeclareRecipesSpec) {
+        self.recipes.clear();
+        self.recipes.extend(packet.recipes.iter().map(|recipe| (recipe.id.clone(), recipe.clone())));
+    }
+
+    // Init lists every unlocked recipe, the second list it carries only marks recipes as new in the
+    // book so it doesn't change what's unlocked
+    pub fn apply_unlock(&mut self, packet: &PlayUnlockRecipesSpec) {
+        use RecipeUnlockAction::*;
+        match packet.action {
+            Init => {
+                self.unlocked.clear();
+                self.unlocked.extend(packet.recipe_ids.iter().cloned());
+            }
+            Add => self.unlocked.extend(packet.recipe_ids.iter().cloned()),
+            Remove => {
+                for id in packet.recipe_ids.iter() {
+                    self.unlocked.remove(id);
+                }
+            }
+        }
+    }
+
+    pub fn recipe_by_id(&self, id: &str) -> Option<&RecipeSpec> {
+        self.recipes.get(id)
+    }
+
+    pub fn recipes_for_item(&self, item_id: VarInt) -> impl Iterator<Item = &RecipeSpec> + '_ {
+        self.recipes.values().filter(move |recipe| matches!(
+            recipe.recipe.result_item(),
+            Some(Some(result)) if result.item_id == item_id
+        ))
+    }
+
+    pub fn is_unlocked(&self, id: &str) -> bool {
+        self.unlocked.contains(id)
+    }
+
+    pub fn unlocked_recipes(&self) -> impl Iterator<Item = &RecipeSpec> + '_ {
+        self.recipes.values().filter(move |recipe| self.unlocked.contains(&recipe.id))
+    }
+}
+
+// the vanilla client refuses chunk packets with more section data than this
+pub const MAX_CHUNK_DATA_LENGTH: usize = 2097152;
+
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ChunkData {
     pub position: ChunkPosition<i32>,
     pub primary_bit_mask: VarInt,
     pub heightmaps: NamedNbtTag,
-    pub biomes: Option<Box<[i32; 1024]>>,
-    pub data: CountedArray<u8, VarInt>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_biomes"))]
+    pub biomes: Option<Box<[i32; BIOME_CELLS]>>,
+    pub data: CountedArray<u8, VarInt, MAX_CHUNK_DATA_LENGTH>,
     pub block_entities: Vec<NamedNbtTag>,
 }
 
+#[cfg(feature = "serde")]
+mod serde_biomes {
+    use super::*;
+    use core::convert::TryInto;
+    use serde::{Deserialize, Serialize, de::Error};
+
+    pub fn serialize<S: serde::Serializer>(biomes: &Option<Box<[i32; BIOME_CELLS]>>, serializer: S) -> Result<S::Ok, S::Error> {
+        biomes.as_ref().map(move |biomes| &biomes[..]).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<Box<[i32; BIOME_CELLS]>>, D::Error> {
+        match Option::<Vec<i32>>::deserialize(deserializer)? {
+            Some(biomes) => {
+                let len = biomes.len();
+                biomes.into_boxed_slice().try_into()
+                    .map(Some)
+                    .map_err(move |_| D::Error::invalid_length(len, &"1024 biome ids"))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
 impl Serialize for ChunkData {
     fn mc_serialize<S: Serializer>(&self, to: &mut S) -> SerializeResult {
         to.serialize_other(&self.position)?;
@@ -2497,10 +4351,14 @@ impl Deserialize for ChunkData {
         } else {
             None
         };
-        let Deserialized { value: chunk_data, data } = <CountedArray<u8, VarInt>>::mc_deserialize(data)?;
+        let Deserialized { value: chunk_data, data } = <CountedArray<u8, VarInt, MAX_CHUNK_DATA_LENGTH>>::mc_deserialize(data)?;
         let Deserialized { value: n_block_entities_raw, mut data } = VarInt::mc_deserialize(data)?;
+        if n_block_entities_raw.0 < 0 {
+            return Err(DeserializeErr::NegativeLength(n_block_entities_raw));
+        }
         let n_block_entities = n_block_entities_raw.0 as usize;
-        let mut block_entities = Vec::with_capacity(n_block_entities);
+        // every block entity takes at least one byte
+        let mut block_entities = Vec::with_capacity(core::cmp::min(n_block_entities, data.len()));
         for _ in 0..n_block_entities {
             let Deserialized { value: entity, data: rest } = NamedNbtTag::mc_deserialize(data)?;
             data = rest;
@@ -2518,7 +4376,7 @@ impl Deserialize for ChunkData {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for ChunkData {
     fn test_gen_random() -> Self {
         ChunkData {
@@ -2526,83 +4384,934 @@ impl TestRandom for ChunkData {
             primary_bit_mask: VarInt::test_gen_random(),
             heightmaps: NamedNbtTag::test_gen_random(),
             biomes: None,
-            data: <CountedArray<u8, VarInt>>::test_gen_random(),
+            data: <CountedArray<u8, VarInt, MAX_CHUNK_DATA_LENGTH>>::test_gen_random(),
             block_entities: vec![],
         }
     }
 }
 
-pub const LIGHT_DATA_LENGTH: usize = 2048;
-pub const LIGHT_DATA_SECTIONS: usize = 18;
+pub const CHUNK_SECTIONS: usize = 16;
+pub const SECTION_BLOCKS: usize = 4096;
+pub const MIN_BITS_PER_BLOCK: u8 = 4;
+pub const MAX_PALETTE_BITS_PER_BLOCK: u8 = 8;
+pub const DIRECT_BITS_PER_BLOCK: u8 = 14;
+pub const AIR_BLOCK_STATE: i32 = 0;
+pub const HEIGHTMAP_ENTRIES: usize = 256;
+pub const HEIGHTMAP_BITS_PER_ENTRY: usize = 9;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeightmapKind {
+    MotionBlocking,
+    MotionBlockingNoLeaves,
+    OceanFloor,
+    OceanFloorWg,
+    WorldSurface,
+    WorldSurfaceWg,
+}
 
-#[derive(Clone, PartialEq)]
-pub struct LightingData {
-    pub data: Box<[Option<[u8; LIGHT_DATA_LENGTH]>; LIGHT_DATA_SECTIONS]>,
+impl HeightmapKind {
+    #[inline]
+    pub fn nbt_name(&self) -> &'static str {
+        use HeightmapKind::*;
+        match self {
+            MotionBlocking => "MOTION_BLOCKING",
+            MotionBlockingNoLeaves => "MOTION_BLOCKING_NO_LEAVES",
+            OceanFloor => "OCEAN_FLOOR",
+            OceanFloorWg => "OCEAN_FLOOR_WG",
+            WorldSurface => "WORLD_SURFACE",
+            WorldSurfaceWg => "WORLD_SURFACE_WG",
+        }
+    }
 }
 
-impl LightingData {
-    fn deserialize(update_mask: VarInt, mut data: &[u8]) -> DeserializeResult<Self> {
-        let mut out = Box::new([None; LIGHT_DATA_SECTIONS]);
-        for i in 0..LIGHT_DATA_SECTIONS {
-            // gotta read the var int
-            if update_mask.0 & (1 << i) != 0 {
-                let Deserialized { value: length, data: rest } = VarInt::mc_deserialize(data)?;
-                if (length.0 as usize) != LIGHT_DATA_LENGTH {
-                    return Err(DeserializeErr::CannotUnderstandValue(alloc::format!("bad data length in light update {}", length)));
-                }
+pub const BIOME_CELLS: usize = 1024;
+
+// vanilla 1.15.2 biome registry ids
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Biome {
+    Ocean,
+    Plains,
+    Desert,
+    Mountains,
+    Forest,
+    Taiga,
+    Swamp,
+    River,
+    Nether,
+    TheEnd,
+    FrozenOcean,
+    FrozenRiver,
+    SnowyTundra,
+    SnowyMountains,
+    MushroomFields,
+    MushroomFieldShore,
+    Beach,
+    DesertHills,
+    WoodedHills,
+    TaigaHills,
+    MountainEdge,
+    Jungle,
+    JungleHills,
+    JungleEdge,
+    DeepOcean,
+    StoneShore,
+    SnowyBeach,
+    BirchForest,
+    BirchForestHills,
+    DarkForest,
+    SnowyTaiga,
+    SnowyTaigaHills,
+    GiantTreeTaiga,
+    GiantTreeTaigaHills,
+    WoodedMountains,
+    Savanna,
+    SavannaPlateau,
+    Badlands,
+    WoodedBadlandsPlateau,
+    BadlandsPlateau,
+    SmallEndIslands,
+    EndMidlands,
+    EndHighlands,
+    EndBarrens,
+    WarmOcean,
+    LukewarmOcean,
+    ColdOcean,
+    DeepWarmOcean,
+    DeepLukewarmOcean,
+    DeepColdOcean,
+    DeepFrozenOcean,
+    TheVoid,
+    SunflowerPlains,
+    DesertLakes,
+    GravellyMountains,
+    FlowerForest,
+    TaigaMountains,
+    SwampHills,
+    IceSpikes,
+    ModifiedJungle,
+    ModifiedJungleEdge,
+    TallBirchForest,
+    TallBirchHills,
+    DarkForestHills,
+    SnowyTaigaMountains,
+    GiantSpruceTaiga,
+    GiantSpruceTaigaHills,
+    ModifiedGravellyMountains,
+    ShatteredSavanna,
+    ShatteredSavannaPlateau,
+    ErodedBadlands,
+    ModifiedWoodedBadlandsPlateau,
+    ModifiedBadlandsPlateau,
+    BambooJungle,
+    BambooJungleHills,
+    Other(i32),
+}
 
-                data = rest;
-                if data.len() < LIGHT_DATA_LENGTH {
-                    return Err(DeserializeErr::Eof);
-                }
+impl Biome {
+    pub fn id(&self) -> i32 {
+        use Biome::*;
+        match self {
+            Ocean => 0,
+            Plains => 1,
+            Desert => 2,
+            Mountains => 3,
+            Forest => 4,
+            Taiga => 5,
+            Swamp => 6,
+            River => 7,
+            Nether => 8,
+            TheEnd => 9,
+            FrozenOcean => 10,
+            FrozenRiver => 11,
+            SnowyTundra => 12,
+            SnowyMountains => 13,
+            MushroomFields => 14,
+            MushroomFieldShore => 15,
+            Beach => 16,
+            DesertHills => 17,
+            WoodedHills => 18,
+            TaigaHills => 19,
+            MountainEdge => 20,
+            Jungle => 21,
+            JungleHills => 22,
+            JungleEdge => 23,
+            DeepOcean => 24,
+            StoneShore => 25,
+            SnowyBeach => 26,
+            BirchForest => 27,
+            BirchForestHills => 28,
+            DarkForest => 29,
+            SnowyTaiga => 30,
+            SnowyTaigaHills => 31,
+            GiantTreeTaiga => 32,
+            GiantTreeTaigaHills => 33,
+            WoodedMountains => 34,
+            Savanna => 35,
+            SavannaPlateau => 36,
+            Badlands => 37,
+            WoodedBadlandsPlateau => 38,
+            BadlandsPlateau => 39,
+            SmallEndIslands => 40,
+            EndMidlands => 41,
+            EndHighlands => 42,
+            EndBarrens => 43,
+            WarmOcean => 44,
+            LukewarmOcean => 45,
+            ColdOcean => 46,
+            DeepWarmOcean => 47,
+            DeepLukewarmOcean => 48,
+            DeepColdOcean => 49,
+            DeepFrozenOcean => 50,
+            TheVoid => 127,
+            SunflowerPlains => 129,
+            DesertLakes => 130,
+            GravellyMountains => 131,
+            FlowerForest => 132,
+            TaigaMountains => 133,
+            SwampHills => 134,
+            IceSpikes => 140,
+            ModifiedJungle => 149,
+            ModifiedJungleEdge => 151,
+            TallBirchForest => 155,
+            TallBirchHills => 156,
+            DarkForestHills => 157,
+            SnowyTaigaMountains => 158,
+            GiantSpruceTaiga => 160,
+            GiantSpruceTaigaHills => 161,
+            ModifiedGravellyMountains => 162,
+            ShatteredSavanna => 163,
+            ShatteredSavannaPlateau => 164,
+            ErodedBadlands => 165,
+            ModifiedWoodedBadlandsPlateau => 166,
+            ModifiedBadlandsPlateau => 167,
+            BambooJungle => 168,
+            BambooJungleHills => 169,
+            Other(id) => *id,
+        }
+    }
 
-                let (section, rest) = data.split_at(LIGHT_DATA_LENGTH);
-                let mut to_vec = [0u8; LIGHT_DATA_LENGTH];
-                to_vec.copy_from_slice(section);
-                out[i] = Some(to_vec);
+    pub fn from_id(id: i32) -> Self {
+        use Biome::*;
+        match id {
+            0 => Ocean,
+            1 => Plains,
+            2 => Desert,
+            3 => Mountains,
+            4 => Forest,
+            5 => Taiga,
+            6 => Swamp,
+            7 => River,
+            8 => Nether,
+            9 => TheEnd,
+            10 => FrozenOcean,
+            11 => FrozenRiver,
+            12 => SnowyTundra,
+            13 => SnowyMountains,
+            14 => MushroomFields,
+            15 => MushroomFieldShore,
+            16 => Beach,
+            17 => DesertHills,
+            18 => WoodedHills,
+            19 => TaigaHills,
+            20 => MountainEdge,
+            21 => Jungle,
+            22 => JungleHills,
+            23 => JungleEdge,
+            24 => DeepOcean,
+            25 => StoneShore,
+            26 => SnowyBeach,
+            27 => BirchForest,
+            28 => BirchForestHills,
+            29 => DarkForest,
+            30 => SnowyTaiga,
+            31 => SnowyTaigaHills,
+            32 => GiantTreeTaiga,
+            33 => GiantTreeTaigaHills,
+            34 => WoodedMountains,
+            35 => Savanna,
+            36 => SavannaPlateau,
+            37 => Badlands,
+            38 => WoodedBadlandsPlateau,
+            39 => BadlandsPlateau,
+            40 => SmallEndIslands,
+            41 => EndMidlands,
+            42 => EndHighlands,
+            43 => EndBarrens,
+            44 => WarmOcean,
+            45 => LukewarmOcean,
+            46 => ColdOcean,
+            47 => DeepWarmOcean,
+            48 => DeepLukewarmOcean,
+            49 => DeepColdOcean,
+            50 => DeepFrozenOcean,
+            127 => TheVoid,
+            129 => SunflowerPlains,
+            130 => DesertLakes,
+            131 => GravellyMountains,
+            132 => FlowerForest,
+            133 => TaigaMountains,
+            134 => SwampHills,
+            140 => IceSpikes,
+            149 => ModifiedJungle,
+            151 => ModifiedJungleEdge,
+            155 => TallBirchForest,
+            156 => TallBirchHills,
+            157 => DarkForestHills,
+            158 => SnowyTaigaMountains,
+            160 => GiantSpruceTaiga,
+            161 => GiantSpruceTaigaHills,
+            162 => ModifiedGravellyMountains,
+            163 => ShatteredSavanna,
+            164 => ShatteredSavannaPlateau,
+            165 => ErodedBadlands,
+            166 => ModifiedWoodedBadlandsPlateau,
+            167 => ModifiedBadlandsPlateau,
+            168 => BambooJungle,
+            169 => BambooJungleHills,
+            other => Other(other),
+        }
+    }
+}
+
+// Biomes of a full chunk, stored per 4x4x4 block cell. Cell coordinates are x and z in 0..4 and y in
+// 0..64, and cells are ordered by y, then z, then x.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BiomeGrid<B> {
+    pub biomes: B,
+}
+
+impl<B> BiomeGrid<B> where B: AsRef<[i32]> {
+    pub fn cell_index(x: usize, y: usize, z: usize) -> Option<usize> {
+        if x >= 4 || y >= 64 || z >= 4 {
+            None
+        } else {
+            Some((y << 4) | (z << 2) | x)
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> Option<Biome> {
+        self.biomes.as_ref().get(Self::cell_index(x, y, z)?).map(move |id| Biome::from_id(*id))
+    }
+}
+
+impl<B> BiomeGrid<B> where B: AsRef<[i32]> + AsMut<[i32]> {
+    // returns false if the cell is out of range
+    pub fn set(&mut self, x: usize, y: usize, z: usize, biome: Biome) -> bool {
+        match Self::cell_index(x, y, z).and_then(|index| self.biomes.as_mut().get_mut(index)) {
+            Some(cell) => {
+                *cell = biome.id();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl ChunkData {
+    // a chunk with no sections, biomes or heightmaps
+    pub fn empty(position: ChunkPosition<i32>) -> Self {
+        Self {
+            position,
+            primary_bit_mask: VarInt(0),
+            heightmaps: NamedNbtTag { root: nbt::Tag::Compound(alloc::vec![]).with_name("") },
+            biomes: None,
+            data: alloc::vec![].into(),
+            block_entities: alloc::vec![],
+        }
+    }
+
+    pub fn sections(&self) -> Result<Vec<Option<ChunkSection>>, DeserializeErr> {
+        let mut data = self.data.as_slice();
+        let mut out = Vec::with_capacity(CHUNK_SECTIONS);
+        for i in 0..CHUNK_SECTIONS {
+            if self.primary_bit_mask.0 & (1 << i) != 0 {
+                let Deserialized { value: section, data: rest } = ChunkSection::mc_deserialize(data)?;
                 data = rest;
+                out.push(Some(section));
+            } else {
+                out.push(None);
             }
         }
 
-        let result = Self {
-            data: out,
-        };
+        if !data.is_empty() {
+            return Err(DeserializeErr::CannotUnderstandValue(alloc::format!(
+                "{} unexpected bytes after chunk sections",
+                data.len()
+            )));
+        }
 
-        Deserialized::ok(result, data)
+        Ok(out)
     }
 
-    fn update_mask(&self) -> VarInt {
-        self.compute_has_mask(true)
+    pub fn set_sections(&mut self, sections: &[Option<ChunkSection>]) -> SerializeResult {
+        let (data, primary_bit_mask) = Self::encode_sections(sections)?;
+        self.data = data;
+        self.primary_bit_mask = VarInt(primary_bit_mask);
+        Ok(())
     }
 
-    fn reset_mask(&self) -> VarInt {
-        self.compute_has_mask(false)
-    }
+    fn encode_sections(sections: &[Option<ChunkSection>]) -> Result<(CountedArray<u8, VarInt, MAX_CHUNK_DATA_LENGTH>, i32), SerializeErr> {
+        if sections.len() > CHUNK_SECTIONS {
+            return Err(SerializeErr::CannotSerialize(alloc::format!(
+                "chunk cannot have {} sections",
+                sections.len()
+            )));
+        }
 
-    fn compute_has_mask(&self, has: bool) -> VarInt {
-        let mut out: u32 = 0;
-        for i in 0..LIGHT_DATA_SECTIONS {
-            if self.data[i].is_some() == has {
-                out |= 1 << i;
+        let mut out = BytesSerializer::default();
+        let mut primary_bit_mask = 0;
+        for (i, section) in sections.iter().enumerate() {
+            if let Some(section) = section {
+                out.serialize_other(section)?;
+                primary_bit_mask |= 1 << i;
             }
         }
 
-        VarInt(out as i32)
+        Ok((out.into_bytes().into(), primary_bit_mask))
     }
 
-    fn serialize_data<S: Serializer>(&self, to: &mut S) -> SerializeResult {
-        for item in self.data.iter() {
-            if let Some(contents) = item {
-                to.serialize_other(&VarInt(2048))?;
-                to.serialize_bytes(&contents[..])?;
+    // decodes sections only up to the one requested
+    pub fn section(&self, index: usize) -> Result<Option<ChunkSection>, DeserializeErr> {
+        if index >= CHUNK_SECTIONS {
+            return Ok(None);
+        }
+
+        self.locate_section(index).map(move |(_, section)| section)
+    }
+
+    // where section `index` starts and ends in `data` (an empty range at the spot it would go if
+    // it isn't sent), along with the section
+    fn locate_section(&self, index: usize) -> Result<(core::ops::Range<usize>, Option<ChunkSection>), DeserializeErr> {
+        let mut data = self.data.as_slice();
+        for i in 0..index {
+            if self.primary_bit_mask.0 & (1 << i) != 0 {
+                data = ChunkSection::mc_deserialize(data)?.data;
             }
         }
 
-        Ok(())
+        let start = self.data.len() - data.len();
+        if self.primary_bit_mask.0 & (1 << index) == 0 {
+            return Ok((start..start, None));
+        }
+
+        let Deserialized { value: section, data: rest } = ChunkSection::mc_deserialize(data)?;
+        Ok((start..self.data.len() - rest.len(), Some(section)))
     }
-}
 
-impl fmt::Debug for LightingData {
+    // x and z are in 0..16, y is in 0..256, positions outside of that are Ok(None). Blocks in
+    // sections which aren't sent are air.
+    pub fn get_block_state(&self, x: usize, y: usize, z: usize) -> Result<Option<VarInt>, DeserializeErr> {
+        if x >= 16 || y >= 16 * CHUNK_SECTIONS || z >= 16 {
+            return Ok(None);
+        }
+
+        let read = move |section: Option<&ChunkSection>| match section {
+            Some(section) => section.get_block_state(x, y % 16, z)
+                .map(Some)
+                .ok_or_else(move || DeserializeErr::CannotUnderstandValue(alloc::format!(
+                    "block ({}, {}, {}) is outside of its section's palette",
+                    x, y, z
+                ))),
+            None => Ok(Some(VarInt(AIR_BLOCK_STATE))),
+        };
+
+        read(self.section(y / 16)?.as_ref())
+    }
+
+    // decodes the section holding the block and writes it back into `data`, the other sections are
+    // left as they are
+    pub fn set_block_state(&mut self, x: usize, y: usize, z: usize, state: VarInt) -> SerializeResult {
+        if x >= 16 || y >= 16 * CHUNK_SECTIONS || z >= 16 {
+            return Err(SerializeErr::CannotSerialize(alloc::format!(
+                "block position ({}, {}, {}) is outside of the chunk",
+                x, y, z
+            )));
+        }
+
+        let index = y / 16;
+        let (range, section) = self.locate_section(index)
+            .map_err(move |err| SerializeErr::CannotSerialize(alloc::format!("cannot decode chunk section {}: {:?}", index, err)))?;
+        let mut section = match section {
+            Some(section) => section,
+            None if state.0 == AIR_BLOCK_STATE => return Ok(()),
+            None => ChunkSection::empty(),
+        };
+        section.set_block_state(x, y % 16, z, state)?;
+
+        let mut encoded = BytesSerializer::default();
+        encoded.serialize_other(&section)?;
+        self.data.splice(range, encoded.into_bytes());
+        self.primary_bit_mask.0 |= 1 << index;
+        Ok(())
+    }
+
+    pub fn heightmap(&self, kind: HeightmapKind) -> Option<[u16; HEIGHTMAP_ENTRIES]> {
+        let longs = self.heightmaps.root.payload.get_long_array(kind.nbt_name())?;
+
+        let bits = HEIGHTMAP_BITS_PER_ENTRY;
+        let mask = (1u64 << bits) - 1;
+        let mut out = [0u16; HEIGHTMAP_ENTRIES];
+        if longs.len() == HEIGHTMAP_ENTRIES * bits / 64 {
+            // vanilla 1.15.2 packs entries tightly, so they may straddle two longs
+            for (i, height) in out.iter_mut().enumerate() {
+                let bit = i * bits;
+                let (long, offset) = (bit / 64, bit % 64);
+                let mut value = (longs[long] as u64) >> offset;
+                if offset + bits > 64 {
+                    value |= (longs[long + 1] as u64) << (64 - offset);
+                }
+                *height = (value & mask) as u16;
+            }
+        } else if longs.len() == (HEIGHTMAP_ENTRIES - 1) / (64 / bits) + 1 {
+            // padded so that no entry straddles longs, which later versions send
+            let per_long = 64 / bits;
+            for (i, height) in out.iter_mut().enumerate() {
+                *height = (((longs[i / per_long] as u64) >> ((i % per_long) * bits)) & mask) as u16;
+            }
+        } else {
+            return None;
+        }
+
+        Some(out)
+    }
+
+    pub fn set_heightmap(&mut self, kind: HeightmapKind, heights: &[u16; HEIGHTMAP_ENTRIES]) -> SerializeResult {
+        let bits = HEIGHTMAP_BITS_PER_ENTRY;
+        let mut longs = alloc::vec![0u64; HEIGHTMAP_ENTRIES * bits / 64];
+        for (i, height) in heights.iter().enumerate() {
+            if (*height as u64) >> bits != 0 {
+                return Err(SerializeErr::CannotSerialize(alloc::format!(
+                    "height {} at {} does not fit in {} bits",
+                    height, i, bits
+                )));
+            }
+
+            let bit = i * bits;
+            let (long, offset) = (bit / 64, bit % 64);
+            longs[long] |= (*height as u64) << offset;
+            if offset + bits > 64 {
+                longs[long + 1] |= (*height as u64) >> (64 - offset);
+            }
+        }
+
+        let tags = &mut self.heightmaps.root.payload;
+        if !matches!(tags, nbt::Tag::Compound(_)) {
+            return Err(SerializeErr::CannotSerialize(alloc::format!(
+                "heightmaps must be a compound tag, got {}",
+                tags
+            )));
+        }
+
+        tags.insert(kind.nbt_name(), nbt::Tag::LongArray(longs.into_iter().map(move |long| long as i64).collect()));
+        Ok(())
+    }
+
+    pub fn section_count(&self) -> usize {
+        self.primary_bit_mask.0.count_ones() as usize
+    }
+
+    pub fn is_section_present(&self, section_y: u8) -> bool {
+        (section_y as usize) < CHUNK_SECTIONS && self.primary_bit_mask.0 & (1 << section_y) != 0
+    }
+
+    pub fn has_biomes(&self) -> bool {
+        self.biomes.is_some()
+    }
+
+    // The raw biome id of the column at block x and z. Biomes aren't stored per column in 1.15 but
+    // per 4x4x4 cell (see BiomeGrid), so this reads the bottom cell covering the column, at index
+    // (z >> 2) * 4 + (x >> 2). Use biome_grid to look at other heights.
+    pub fn biome_at(&self, x: u8, z: u8) -> Option<i32> {
+        if x >= 16 || z >= 16 {
+            return None;
+        }
+
+        let index = BiomeGrid::<&[i32]>::cell_index((x >> 2) as usize, 0, (z >> 2) as usize)?;
+        self.biomes.as_ref().map(move |biomes| biomes[index])
+    }
+
+    pub fn biome_grid(&self) -> Option<BiomeGrid<&[i32; BIOME_CELLS]>> {
+        self.biomes.as_deref().map(move |biomes| BiomeGrid { biomes })
+    }
+
+    pub fn biome_grid_mut(&mut self) -> Option<BiomeGrid<&mut [i32; BIOME_CELLS]>> {
+        self.biomes.as_deref_mut().map(move |biomes| BiomeGrid { biomes })
+    }
+}
+
+// One 16x16x16 section of a chunk. Block states are packed into `data` at `bits_per_block` bits each
+// (entries may straddle two longs), either as indices into `palette` or, without a palette, as
+// global block state ids.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChunkSection {
+    pub block_count: i16,
+    pub bits_per_block: u8,
+    pub palette: Option<CountedArray<VarInt, VarInt>>,
+    pub data: CountedArray<i64, VarInt>,
+}
+
+impl ChunkSection {
+    pub fn data_longs(bits_per_block: u8) -> usize {
+        SECTION_BLOCKS * (bits_per_block as usize) / 64
+    }
+
+    // Sections without a palette always use DIRECT_BITS_PER_BLOCK. Sizes between that and
+    // MAX_PALETTE_BITS_PER_BLOCK are accepted when reading but re-packed to DIRECT_BITS_PER_BLOCK.
+    pub fn is_valid_bits_per_block(bits_per_block: u8) -> bool {
+        (MIN_BITS_PER_BLOCK..=MAX_PALETTE_BITS_PER_BLOCK).contains(&bits_per_block) ||
+            bits_per_block == DIRECT_BITS_PER_BLOCK
+    }
+
+    pub fn empty() -> Self {
+        Self {
+            block_count: 0,
+            bits_per_block: MIN_BITS_PER_BLOCK,
+            palette: Some(alloc::vec![VarInt(AIR_BLOCK_STATE)].into()),
+            data: alloc::vec![0; Self::data_longs(MIN_BITS_PER_BLOCK)].into(),
+        }
+    }
+
+    // x, y and z are all in 0..16
+    pub fn get_block_state(&self, x: usize, y: usize, z: usize) -> Option<VarInt> {
+        let value = self.get_entry(Self::block_index(x, y, z)?)?;
+        match &self.palette {
+            Some(palette) => palette.get(value as usize).copied(),
+            None => Some(VarInt(value as i32)),
+        }
+    }
+
+    pub fn set_block_state(&mut self, x: usize, y: usize, z: usize, state: VarInt) -> SerializeResult {
+        let index = Self::block_index(x, y, z).ok_or_else(move || SerializeErr::CannotSerialize(alloc::format!(
+            "block position ({}, {}, {}) is outside of the section",
+            x, y, z
+        )))?;
+
+        if state.0 < 0 || state.0 >= (1 << DIRECT_BITS_PER_BLOCK) {
+            return Err(SerializeErr::CannotSerialize(alloc::format!("invalid block state {}", state)));
+        }
+
+        let previous = self.get_block_state(x, y, z).ok_or_else(move || SerializeErr::CannotSerialize(
+            "chunk section contains an entry outside of its palette".to_owned()))?;
+        if previous == state {
+            return Ok(());
+        }
+
+        let value = match self.palette.as_ref().map(move |palette| palette.iter().position(move |entry| *entry == state)) {
+            Some(Some(palette_index)) => palette_index as u64,
+            Some(None) => {
+                let palette_index = self.palette.as_ref().map(move |palette| palette.len()).unwrap_or(0);
+                if palette_index >= (1 << self.bits_per_block) {
+                    let bits_per_block = self.bits_per_block + 1;
+                    self.resize(if bits_per_block > MAX_PALETTE_BITS_PER_BLOCK {
+                        DIRECT_BITS_PER_BLOCK
+                    } else {
+                        bits_per_block
+                    });
+                }
+
+                match &mut self.palette {
+                    Some(palette) => {
+                        palette.push(state);
+                        palette_index as u64
+                    }
+                    None => state.0 as u64,
+                }
+            }
+            None => state.0 as u64,
+        };
+
+        // a value that doesn't fit, like a state id in a direct section with fewer than
+        // DIRECT_BITS_PER_BLOCK bits, would be truncated
+        let value = if value >> self.bits_per_block != 0 {
+            self.resize(DIRECT_BITS_PER_BLOCK);
+            state.0 as u64
+        } else {
+            value
+        };
+
+        self.set_entry(index, value);
+        if previous.0 == AIR_BLOCK_STATE {
+            self.block_count += 1;
+        } else if state.0 == AIR_BLOCK_STATE {
+            self.block_count -= 1;
+        }
+
+        Ok(())
+    }
+
+    fn block_index(x: usize, y: usize, z: usize) -> Option<usize> {
+        if x >= 16 || y >= 16 || z >= 16 {
+            None
+        } else {
+            Some((y * 16 + z) * 16 + x)
+        }
+    }
+
+    fn get_entry(&self, index: usize) -> Option<u64> {
+        let bits_per_block = self.bits_per_block as usize;
+        let bit = index * bits_per_block;
+        let (long, offset) = (bit / 64, bit % 64);
+        let mut value = (*self.data.get(long)? as u64) >> offset;
+        if offset + bits_per_block > 64 {
+            value |= (*self.data.get(long + 1)? as u64) << (64 - offset);
+        }
+
+        Some(value & ((1 << bits_per_block) - 1))
+    }
+
+    // callers make sure value fits in bits_per_block, see set_block_state
+    fn set_entry(&mut self, index: usize, value: u64) {
+        let bits_per_block = self.bits_per_block as usize;
+        let mask = (1u64 << bits_per_block) - 1;
+        let bit = index * bits_per_block;
+        let (long, offset) = (bit / 64, bit % 64);
+        let value = value & mask;
+        self.data[long] = (((self.data[long] as u64) & !(mask << offset)) | (value << offset)) as i64;
+        if offset + bits_per_block > 64 {
+            let high_bits = offset + bits_per_block - 64;
+            let high_mask = (1u64 << high_bits) - 1;
+            self.data[long + 1] = (((self.data[long + 1] as u64) & !high_mask) | (value >> (64 - offset))) as i64;
+        }
+    }
+
+    // re-packs the data at a new size, dropping the palette when switching to direct encoding
+    fn resize(&mut self, bits_per_block: u8) {
+        let entries: Vec<u64> = (0..SECTION_BLOCKS)
+            .map(|index| self.get_entry(index).unwrap_or(0))
+            .map(|value| match (&self.palette, bits_per_block > MAX_PALETTE_BITS_PER_BLOCK) {
+                (Some(palette), true) => palette.get(value as usize).map(|state| state.0 as u64).unwrap_or(0),
+                _ => value,
+            })
+            .collect();
+
+        if bits_per_block > MAX_PALETTE_BITS_PER_BLOCK {
+            self.palette = None;
+        }
+        self.bits_per_block = bits_per_block;
+        self.data = alloc::vec![0; Self::data_longs(bits_per_block)].into();
+        for (index, value) in entries.into_iter().enumerate() {
+            self.set_entry(index, value);
+        }
+    }
+}
+
+impl Serialize for ChunkSection {
+    fn mc_serialize<S: Serializer>(&self, to: &mut S) -> SerializeResult {
+        if !Self::is_valid_bits_per_block(self.bits_per_block) {
+            return Err(SerializeErr::CannotSerialize(alloc::format!(
+                "invalid bits per block {}",
+                self.bits_per_block
+            )));
+        }
+
+        if self.palette.is_some() != (self.bits_per_block <= MAX_PALETTE_BITS_PER_BLOCK) {
+            return Err(SerializeErr::CannotSerialize(alloc::format!(
+                "palette must be present iff bits per block <= {}",
+                MAX_PALETTE_BITS_PER_BLOCK
+            )));
+        }
+
+        to.serialize_other(&self.block_count)?;
+        to.serialize_byte(self.bits_per_block)?;
+        if let Some(palette) = &self.palette {
+            to.serialize_other(palette)?;
+        }
+        to.serialize_other(&self.data)
+    }
+}
+
+impl Deserialize for ChunkSection {
+    fn mc_deserialize(data: &[u8]) -> DeserializeResult<'_, Self> {
+        let Deserialized { value: block_count, data } = i16::mc_deserialize(data)?;
+        let Deserialized { value: bits_per_block, data } = u8::mc_deserialize(data)?;
+        if !(MIN_BITS_PER_BLOCK..=DIRECT_BITS_PER_BLOCK).contains(&bits_per_block) {
+            return Err(DeserializeErr::CannotUnderstandValue(alloc::format!(
+                "invalid bits per block {}, expected {}..={}",
+                bits_per_block, MIN_BITS_PER_BLOCK, DIRECT_BITS_PER_BLOCK
+            )));
+        }
+
+        let (palette, data) = if bits_per_block <= MAX_PALETTE_BITS_PER_BLOCK {
+            let Deserialized { value: palette, data } = <CountedArray<VarInt, VarInt>>::mc_deserialize(data)?;
+            (Some(palette), data)
+        } else {
+            (None, data)
+        };
+
+        let Deserialized { value: longs, data } = <CountedArray<i64, VarInt>>::mc_deserialize(data)?;
+        let expected_longs = Self::data_longs(bits_per_block);
+        if longs.len() != expected_longs {
+            return Err(DeserializeErr::CannotUnderstandValue(alloc::format!(
+                "chunk section with {} bits per block should have {} longs, got {}",
+                bits_per_block, expected_longs, longs.len()
+            )));
+        }
+
+        let mut section = Self {
+            block_count,
+            bits_per_block,
+            palette,
+            data: longs,
+        };
+        if !Self::is_valid_bits_per_block(bits_per_block) {
+            section.resize(DIRECT_BITS_PER_BLOCK);
+        }
+
+        Deserialized::ok(section, data)
+    }
+}
+
+pub const LIGHT_DATA_LENGTH: usize = 2048;
+pub const LIGHT_DATA_SECTIONS: usize = 18;
+
+#[derive(Clone, PartialEq)]
+pub struct LightingData {
+    pub data: Box<[Option<[u8; LIGHT_DATA_LENGTH]>; LIGHT_DATA_SECTIONS]>,
+}
+
+impl LightingData {
+    // no light data for any section
+    pub fn empty() -> Self {
+        Self {
+            data: Box::new([None; LIGHT_DATA_SECTIONS]),
+        }
+    }
+
+    // every block in every section at light level 15
+    pub fn full_bright() -> Self {
+        Self {
+            data: Box::new([Some([0xFF; LIGHT_DATA_LENGTH]); LIGHT_DATA_SECTIONS]),
+        }
+    }
+
+    pub fn get(&self, section: usize, x: u8, y: u8, z: u8) -> Option<u8> {
+        let index = Self::light_index(x, y, z)?;
+        let data = self.data.get(section)?.as_ref()?;
+        Some((data[index / 2] >> ((index % 2) * 4)) & 0x0F)
+    }
+
+    // returns false if the position is out of range or the level is above 15
+    pub fn set(&mut self, section: usize, x: u8, y: u8, z: u8, level: u8) -> bool {
+        let index = match Self::light_index(x, y, z) {
+            Some(index) if level <= 0x0F => index,
+            _ => return false,
+        };
+
+        let data = match self.data.get_mut(section) {
+            Some(data) => data.get_or_insert([0; LIGHT_DATA_LENGTH]),
+            None => return false,
+        };
+
+        let shift = (index % 2) * 4;
+        data[index / 2] = (data[index / 2] & !(0x0F << shift)) | (level << shift);
+        true
+    }
+
+    pub fn get_section(&self, idx: usize) -> Option<&[u8; LIGHT_DATA_LENGTH]> {
+        self.data.get(idx)?.as_ref()
+    }
+
+    // out of range sections are a bug in the caller, release builds ignore them
+    pub fn set_section(&mut self, idx: usize, data: [u8; LIGHT_DATA_LENGTH]) {
+        debug_assert!(idx < LIGHT_DATA_SECTIONS, "light section {} out of range", idx);
+        if let Some(section) = self.data.get_mut(idx) {
+            *section = Some(data);
+        }
+    }
+
+    pub fn clear_section(&mut self, idx: usize) {
+        if let Some(section) = self.data.get_mut(idx) {
+            *section = None;
+        }
+    }
+
+    pub fn count_present(&self) -> usize {
+        self.data.iter().filter(move |section| section.is_some()).count()
+    }
+
+    // sections present in newer replace the ones here, the rest are kept
+    pub fn merge(&self, newer: &LightingData) -> LightingData {
+        let mut out = self.clone();
+        for (target, section) in out.data.iter_mut().zip(newer.data.iter()) {
+            if section.is_some() {
+                *target = *section;
+            }
+        }
+
+        out
+    }
+
+    fn light_index(x: u8, y: u8, z: u8) -> Option<usize> {
+        if x >= 16 || y >= 16 || z >= 16 {
+            None
+        } else {
+            Some(((y as usize) << 8) | ((z as usize) << 4) | (x as usize))
+        }
+    }
+
+    fn deserialize(update_mask: VarInt, mut data: &[u8]) -> DeserializeResult<Self> {
+        let mut out = Box::new([None; LIGHT_DATA_SECTIONS]);
+        for i in 0..LIGHT_DATA_SECTIONS {
+            // gotta read the var int
+            if update_mask.0 & (1 << i) != 0 {
+                let Deserialized { value: length, data: rest } = VarInt::mc_deserialize(data)?;
+                if (length.0 as usize) != LIGHT_DATA_LENGTH {
+                    return Err(DeserializeErr::CannotUnderstandValue(alloc::format!("bad data length in light update {}", length)));
+                }
+
+                data = rest;
+                if data.len() < LIGHT_DATA_LENGTH {
+                    return Err(DeserializeErr::Eof);
+                }
+
+                let (section, rest) = data.split_at(LIGHT_DATA_LENGTH);
+                let mut to_vec = [0u8; LIGHT_DATA_LENGTH];
+                to_vec.copy_from_slice(section);
+                out[i] = Some(to_vec);
+                data = rest;
+            }
+        }
+
+        let result = Self {
+            data: out,
+        };
+
+        Deserialized::ok(result, data)
+    }
+
+    fn update_mask(&self) -> VarInt {
+        self.compute_has_mask(true)
+    }
+
+    fn reset_mask(&self) -> VarInt {
+        self.compute_has_mask(false)
+    }
+
+    fn compute_has_mask(&self, has: bool) -> VarInt {
+        let mut out: u32 = 0;
+        for i in 0..LIGHT_DATA_SECTIONS {
+            if self.data[i].is_some() == has {
+                out |= 1 << i;
+            }
+        }
+
+        VarInt(out as i32)
+    }
+
+    fn serialize_data<S: Serializer>(&self, to: &mut S) -> SerializeResult {
+        for item in self.data.iter() {
+            if let Some(contents) = item {
+                to.serialize_other(&VarInt(2048))?;
+                to.serialize_bytes(&contents[..])?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for LightingData {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
@@ -2617,21 +5326,53 @@ impl fmt::Debug for LightingData {
     }
 }
 
+// in serde formats the sections are a list, with null for sections which aren't present
+#[cfg(feature = "serde")]
+impl serde::Serialize for LightingData {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.data.iter().map(move |section| section.as_ref().map(move |arr| &arr[..])))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for LightingData {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use core::convert::TryInto;
+        use serde::de::Error;
+        let sections = Vec::<Option<Vec<u8>>>::deserialize(deserializer)?;
+        if sections.len() != LIGHT_DATA_SECTIONS {
+            return Err(D::Error::invalid_length(sections.len(), &"18 light sections"));
+        }
+
+        let mut data = Box::new([None; LIGHT_DATA_SECTIONS]);
+        for (target, section) in data.iter_mut().zip(sections) {
+            if let Some(bytes) = section {
+                let len = bytes.len();
+                let bytes: [u8; LIGHT_DATA_LENGTH] = bytes.try_into()
+                    .map_err(move |_| D::Error::invalid_length(len, &"2048 bytes of light data"))?;
+                *target = Some(bytes);
+            }
+        }
+
+        Ok(LightingData { data })
+    }
+}
+
 impl fmt::Display for LightingData {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         <dyn fmt::Debug>::fmt(self, f)
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl LightingData {
     fn gen_random_mask() -> i32 {
-        let rand: u32 = rand::random();
+        let rand: u32 = crate::protocol::test_random();
         (rand & ((1 << 19) - 1)) as i32
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for LightingData {
     fn test_gen_random() -> Self {
         let set_mask = Self::gen_random_mask();
@@ -2640,7 +5381,7 @@ impl TestRandom for LightingData {
             if (set_mask & (1 << i)) != 0 {
                 let mut data_arr = [0u8; LIGHT_DATA_LENGTH];
                 for k in 0..LIGHT_DATA_LENGTH {
-                    data_arr[k] = rand::random();
+                    data_arr[k] = crate::protocol::test_random();
                 }
                 data[i] = Some(data_arr);
             }
@@ -2653,17 +5394,49 @@ impl TestRandom for LightingData {
 }
 
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LightingUpdateSpec {
     pub skylight_data: LightingData,
     pub blocklight_data: LightingData,
+    pub skylight_reset_mask: VarInt,
+    pub blocklight_reset_mask: VarInt,
+}
+
+impl LightingUpdateSpec {
+    // resets every section which has no data sent for it
+    pub fn new(skylight_data: LightingData, blocklight_data: LightingData) -> Self {
+        Self {
+            skylight_reset_mask: skylight_data.reset_mask(),
+            blocklight_reset_mask: blocklight_data.reset_mask(),
+            skylight_data,
+            blocklight_data,
+        }
+    }
+}
+
+// applies a light update to the light data kept for a chunk: sections in the reset masks are
+// cleared, then the sections sent in the update replace what was there
+pub fn apply_update(update: &LightingUpdateSpec, sky: &mut LightingData, block: &mut LightingData) {
+    let targets = [
+        (sky, &update.skylight_data, update.skylight_reset_mask),
+        (block, &update.blocklight_data, update.blocklight_reset_mask),
+    ];
+    for (target, data, reset_mask) in targets {
+        for i in 0..LIGHT_DATA_SECTIONS {
+            if reset_mask.0 & (1 << i) != 0 {
+                target.clear_section(i);
+            }
+        }
+        *target = target.merge(data);
+    }
 }
 
 impl Serialize for LightingUpdateSpec {
     fn mc_serialize<S: Serializer>(&self, to: &mut S) -> SerializeResult {
         self.skylight_data.update_mask().mc_serialize(to)?;
         self.blocklight_data.update_mask().mc_serialize(to)?;
-        self.skylight_data.reset_mask().mc_serialize(to)?;
-        self.blocklight_data.reset_mask().mc_serialize(to)?;
+        self.skylight_reset_mask.mc_serialize(to)?;
+        self.blocklight_reset_mask.mc_serialize(to)?;
         self.skylight_data.serialize_data(to)?;
         self.blocklight_data.serialize_data(to)
     }
@@ -2673,8 +5446,8 @@ impl Deserialize for LightingUpdateSpec {
     fn mc_deserialize(data: &[u8]) -> DeserializeResult<'_, Self> {
         let Deserialized { value: skylight_update_mask, data } = VarInt::mc_deserialize(data)?;
         let Deserialized { value: blocklight_update_mask, data } = VarInt::mc_deserialize(data)?;
-        let Deserialized { value: _, data } = VarInt::mc_deserialize(data)?;
-        let Deserialized { value: _, data } = VarInt::mc_deserialize(data)?;
+        let Deserialized { value: skylight_reset_mask, data } = VarInt::mc_deserialize(data)?;
+        let Deserialized { value: blocklight_reset_mask, data } = VarInt::mc_deserialize(data)?;
 
         let Deserialized { value: skylight_data, data } = LightingData::deserialize(skylight_update_mask, data)?;
         let Deserialized { value: blocklight_data, data } = LightingData::deserialize(blocklight_update_mask, data)?;
@@ -2682,21 +5455,26 @@ impl Deserialize for LightingUpdateSpec {
         Deserialized::ok(Self {
             skylight_data,
             blocklight_data,
+            skylight_reset_mask,
+            blocklight_reset_mask,
         }, data)
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for LightingUpdateSpec {
     fn test_gen_random() -> Self {
         Self {
             skylight_data: LightingData::test_gen_random(),
             blocklight_data: LightingData::test_gen_random(),
+            skylight_reset_mask: VarInt(LightingData::gen_random_mask()),
+            blocklight_reset_mask: VarInt(LightingData::gen_random_mask()),
         }
     }
 }
 
 #[derive(Clone, Debug, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EntityMetadata {
     pub fields: Vec<EntityMetadataField>
 }
@@ -2735,10 +5513,10 @@ impl Deserialize for EntityMetadata {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for EntityMetadata {
     fn test_gen_random() -> Self {
-        let n_fields = rand::random::<usize>() % 10;
+        let n_fields = (crate::protocol::test_random::<u32>() % 10) as usize;
         let mut fields = Vec::with_capacity(n_fields);
         for i in 0..n_fields {
             fields.push(EntityMetadataField{
@@ -2753,55 +5531,7 @@ impl TestRandom for EntityMetadata {
     }
 }
 
-impl EntityMetadata {
-    pub fn set(&mut self, index: u8, data: EntityMetadataFieldData) {
-        for field in &mut self.fields {
-            if field.index == index {
-                field.data = data;
-                return;
-            }
-        }
-
-        self.fields.push(EntityMetadataField{
-            index,
-            data,
-        })
-    }
-
-    pub fn remove(&mut self, index: u8) -> bool {
-        for i in 0..self.fields.len() {
-            let field = self.fields
-                .get(i)
-                .expect("iterating through this vec, definitely have this index");
-            if field.index == index {
-                self.fields.remove(i);
-                return true;
-            }
-        }
-
-        false
-    }
-
-    pub fn get(&self, index: u8) -> Option<&EntityMetadataFieldData> {
-        for field in &self.fields {
-            if field.index == index {
-                return Some(&field.data);
-            }
-        }
-
-        None
-    }
-
-    pub fn get_mut(&mut self, index: u8) -> Option<&mut EntityMetadataFieldData> {
-        for field in &mut self.fields {
-            if field.index == index {
-                return Some(&mut field.data);
-            }
-        }
-
-        None
-    }
-}
+entity_metadata_helpers!();
 
 impl<'a> core::iter::IntoIterator for &'a EntityMetadata {
     type Item = (u8, &'a EntityMetadataFieldData);
@@ -2834,6 +5564,7 @@ impl<'a> core::iter::Iterator for FieldIter<'a> {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EntityMetadataField {
     pub index: u8,
     pub data: EntityMetadataFieldData
@@ -2938,7 +5669,7 @@ proto_varint_enum!(ParticleSpec,
     0x14 :: EntityEffect,
     0x15 :: ExposionEmitter,
     0x16 :: Explosion,
-    0x17 :: FallingDust(DustParticleData),
+    0x17 :: FallingDust(BlockParticleData),
     0x18 :: Firework,
     0x19 :: Fishing,
     0x1A :: Flame,
@@ -2990,10 +5721,94 @@ proto_struct!(DustParticleData {
     scale: f32
 });
 
+impl ParticleSpec {
+    pub fn has_extra_data(&self) -> bool {
+        use ParticleSpec::*;
+        matches!(self, Block(_) | Dust(_) | FallingDust(_) | Item(_))
+    }
+
+    // FallingDust is the dust falling from a block like sand, so it carries a block state too
+    pub fn as_block_state(&self) -> Option<VarInt> {
+        use ParticleSpec::*;
+        match self {
+            Block(data) | FallingDust(data) => Some(data.block_state),
+            _ => None,
+        }
+    }
+
+    pub fn as_dust(&self) -> Option<&DustParticleData> {
+        match self {
+            ParticleSpec::Dust(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    pub fn as_item(&self) -> Option<&Slot> {
+        match self {
+            ParticleSpec::Item(item) => Some(item),
+            _ => None,
+        }
+    }
+}
+
+impl DustParticleData {
+    pub fn as_rgba(&self, alpha: f32) -> (f32, f32, f32, f32) {
+        (self.red, self.green, self.blue, alpha)
+    }
+}
+
+// plugin payloads can be large, proxies which only forward them can split the raw body without
+// copying the data
+fn borrow_field<T: Deserialize>(data: &mut &[u8]) -> Result<T, crate::protocol::PacketErr> {
+    let Deserialized { value, data: rest } = T::mc_deserialize(data)
+        .map_err(crate::protocol::PacketErr::DeserializeFailed)?;
+    *data = rest;
+    Ok(value)
+}
+
+fn borrow_plugin_message(mut data: &[u8]) -> Result<(String, CowRemainingBytes<'_>), crate::protocol::PacketErr> {
+    let channel = borrow_field(&mut data)?;
+    Ok((channel, CowRemainingBytes::borrow_from(data).value))
+}
+
+impl<'a> RawPacket578Body<'a, PlayServerPluginMessageSpec> {
+    pub fn borrow_parts(&self) -> Result<(String, CowRemainingBytes<'a>), crate::protocol::PacketErr> {
+        borrow_plugin_message(self.data)
+    }
+}
+
+impl<'a> RawPacket578Body<'a, PlayClientPluginMessageSpec> {
+    pub fn borrow_parts(&self) -> Result<(String, CowRemainingBytes<'a>), crate::protocol::PacketErr> {
+        borrow_plugin_message(self.data)
+    }
+}
+
+impl<'a> RawPacket578Body<'a, LoginPluginRequestSpec> {
+    pub fn borrow_parts(&self) -> Result<(VarInt, String, CowRemainingBytes<'a>), crate::protocol::PacketErr> {
+        let mut data = self.data;
+        let message_id = borrow_field(&mut data)?;
+        let channel = borrow_field(&mut data)?;
+        Ok((message_id, channel, CowRemainingBytes::borrow_from(data).value))
+    }
+}
+
+impl<'a> RawPacket578Body<'a, LoginPluginResponseSpec> {
+    pub fn borrow_parts(&self) -> Result<(VarInt, bool, CowRemainingBytes<'a>), crate::protocol::PacketErr> {
+        let mut data = self.data;
+        let message_id = borrow_field(&mut data)?;
+        let successful = borrow_field(&mut data)?;
+        Ok((message_id, successful, CowRemainingBytes::borrow_from(data).value))
+    }
+}
+
 #[cfg(all(test, feature = "std"))]
 pub mod tests {
     use super::*;
     use crate::packet_test_cases;
+    use crate::entity_metadata_test_cases;
+    use crate::protocol::MinecraftPacket;
+
+    entity_metadata_test_cases!();
 
     packet_test_cases!(RawPacket578, Packet578, Handshake, HandshakeSpec,
         test_handshake, bench_write_handshake, bench_read_handshake);
@@ -3451,6 +6266,1940 @@ pub mod tests {
     packet_test_cases!(RawPacket578, Packet578, PlayUseItem, PlayUseItemSpec,
         test_play_use_item, bench_write_play_use_item, bench_read_play_use_item);
 
+    fn chunk_section_fixture(bits_per_block: u8, palette: Option<&[u8]>, fill: u64) -> Vec<u8> {
+        // hand-encoded the way the vanilla server writes a section
+        let mut out = Vec::new();
+        out.extend_from_slice(&4096i16.to_be_bytes());
+        out.push(bits_per_block);
+        if let Some(palette) = palette {
+            out.push(palette.len() as u8);
+            out.extend_from_slice(palette);
+        }
+        let n_longs = 64 * bits_per_block as usize;
+        // all lengths used here fit in a two byte VarInt
+        out.push(((n_longs & 0x7F) as u8) | 0x80);
+        out.push((n_longs >> 7) as u8);
+        for i in 0..n_longs {
+            out.extend_from_slice(&(fill ^ (i as u64)).to_be_bytes());
+        }
+        out
+    }
+
+    fn chunk_with_data(primary_bit_mask: i32, data: Vec<u8>) -> ChunkData {
+        let mut chunk = ChunkData::empty((0, 0).into());
+        chunk.primary_bit_mask = VarInt(primary_bit_mask);
+        chunk.data = data.into();
+        chunk
+    }
+
+    #[test]
+    fn test_chunk_sections_round_trip() {
+        let mut raw = chunk_section_fixture(4, Some(&[0x00, 0x01, 0x09]), 0x1111_2222_0000_1010);
+        raw.extend(chunk_section_fixture(14, None, 0x0123_4567_89AB_CDEF));
+        let mut chunk = chunk_with_data(0b101, raw.clone());
+
+        let sections = chunk.sections().expect("parses");
+        assert_eq!(sections.len(), CHUNK_SECTIONS);
+        assert!(sections[1].is_none());
+        let paletted = sections[0].as_ref().expect("section 0 present");
+        assert_eq!(paletted.bits_per_block, 4);
+        assert_eq!(paletted.palette.as_ref().map(|p| p.len()), Some(3));
+        assert_eq!(paletted.data.len(), 256);
+        let direct = sections[2].as_ref().expect("section 2 present");
+        assert_eq!(direct.bits_per_block, 14);
+        assert!(direct.palette.is_none());
+        assert_eq!(direct.data.len(), 896);
+
+        chunk.primary_bit_mask = VarInt(0);
+        chunk.set_sections(sections.as_slice()).expect("packs");
+        assert_eq!(chunk.primary_bit_mask, VarInt(0b101));
+        assert_eq!(chunk.data.as_slice(), raw.as_slice());
+    }
+
+    #[test]
+    fn test_chunk_heightmaps() {
+        // MOTION_BLOCKING and WORLD_SURFACE packed the way 1.15.2 sends them, 36 longs of tightly
+        // packed 9 bit entries. The file was written by a separate packer from the heights below (x is
+        // i % 16, z is i / 16), it isn't a capture from a vanilla server.
+        let motion_blocking = move |i: usize| if i == 255 { 256 } else { 63 + ((i % 16) * 7 + (i / 16) * 3) % 20 } as u16;
+        let world_surface = move |i: usize| motion_blocking(i) + if (i % 16 + i / 16) & 3 == 0 { 1 } else { 0 };
+        let raw: &[u8] = include_bytes!("../fixtures/v1_15_2_heightmaps.nbt");
+        let Deserialized { value: heightmaps, data: rest } = NamedNbtTag::mc_deserialize(raw).expect("parses");
+        assert!(rest.is_empty());
+        assert_eq!(heightmaps.root.payload.get_long_array("MOTION_BLOCKING").map(move |longs| longs.len()), Some(36));
+        let mut chunk = chunk_with_data(0, alloc::vec![]);
+        chunk.heightmaps = heightmaps;
+
+        let heights = chunk.heightmap(HeightmapKind::MotionBlocking).expect("tightly packed heightmap");
+        for (i, height) in heights.iter().enumerate() {
+            assert_eq!(*height, motion_blocking(i), "motion blocking height {}", i);
+        }
+        // entries 7, 14 and 21 straddle two longs
+        assert_eq!((heights[7], heights[14], heights[21]), (72, 81, 81));
+        let heights = chunk.heightmap(HeightmapKind::WorldSurface).expect("tightly packed heightmap");
+        for (i, height) in heights.iter().enumerate() {
+            assert_eq!(*height, world_surface(i), "world surface height {}", i);
+        }
+        assert_eq!(chunk.heightmap(HeightmapKind::OceanFloor), None);
+
+        // and writing the same heights gives back the same longs
+        let mut rewritten = chunk_with_data(0, alloc::vec![]);
+        rewritten.heightmaps = NamedNbtTag { root: nbt::Tag::Compound(alloc::vec![]).with_name("") };
+        let mut heights = [0u16; HEIGHTMAP_ENTRIES];
+        for (i, height) in heights.iter_mut().enumerate() {
+            *height = motion_blocking(i);
+        }
+        rewritten.set_heightmap(HeightmapKind::MotionBlocking, &heights).expect("set heightmap");
+        for (i, height) in heights.iter_mut().enumerate() {
+            *height = world_surface(i);
+        }
+        rewritten.set_heightmap(HeightmapKind::WorldSurface, &heights).expect("set heightmap");
+        assert_eq!(rewritten.heightmaps.mc_serialize_to_vec().expect("serializes").as_slice(), raw);
+
+        heights[3] = 512;
+        assert!(chunk.set_heightmap(HeightmapKind::WorldSurface, &heights).is_err());
+    }
+
+    #[test]
+    fn test_chunk_padded_heightmap() {
+        // later versions pad each long to 7 entries instead, 0x0040201008040201 is seven entries of 1
+        let mut chunk = chunk_with_data(0, alloc::vec![]);
+        chunk.heightmaps = NamedNbtTag {
+            root: nbt::Tag::Compound(alloc::vec![
+                nbt::Tag::LongArray(alloc::vec![0x0040_2010_0804_0201; 37]).with_name("WORLD_SURFACE"),
+            ]).with_name(""),
+        };
+        assert_eq!(chunk.heightmap(HeightmapKind::WorldSurface), Some([1; HEIGHTMAP_ENTRIES]));
+    }
+
+    fn player_info_add(uuid: UUID4, name: &str, ping_ms: i32) -> PlayerInfoAction<PlayerAddActionSpec> {
+        PlayerInfoAction {
+            uuid,
+            action: PlayerAddActionSpec {
+                name: name.to_owned(),
+                properties: alloc::vec![PlayerAddProperty {
+                    name: "textures".to_owned(),
+                    value: "e30=".to_owned(),
+                    signature: None,
+                }].into(),
+                game_mode: GameMode::Survival,
+                ping_ms: VarInt(ping_ms),
+                display_name: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_player_list_tracks_player_info() {
+        let alice = UUID4::from(1u128);
+        let bob = UUID4::from(2u128);
+        let carol = UUID4::from(3u128);
+        let stranger = UUID4::from(4u128);
+
+        let sequence = alloc::vec![
+            PlayerInfoActionList::Add(alloc::vec![player_info_add(alice, "alice", 20), player_info_add(bob, "bob", 35)].into()),
+            PlayerInfoActionList::UpdateLatency(alloc::vec![
+                PlayerInfoAction { uuid: alice, action: VarInt(25) },
+                PlayerInfoAction { uuid: stranger, action: VarInt(100) },
+            ].into()),
+            PlayerInfoActionList::Add(alloc::vec![player_info_add(carol, "carol", 80)].into()),
+            PlayerInfoActionList::UpdateGameMode(alloc::vec![PlayerInfoAction { uuid: bob, action: GameMode::Creative }].into()),
+            PlayerInfoActionList::UpdateDisplayName(alloc::vec![
+                PlayerInfoAction { uuid: carol, action: Some(Chat::from_text("[Admin] carol")) },
+            ].into()),
+            // bob reconnects, which replaces the old entry entirely
+            PlayerInfoActionList::Add(alloc::vec![player_info_add(bob, "bob", 40)].into()),
+            PlayerInfoActionList::Remove(alloc::vec![alice, stranger].into()),
+        ];
+
+        let mut list = PlayerList::new();
+        let mut recording = PlayerList::recording_unknown_updates();
+        for actions in &sequence {
+            list.apply(actions);
+            recording.apply(actions);
+        }
+
+        assert_eq!(list.len(), 2);
+        assert!(list.get(&alice).is_none());
+        assert!(list.get_by_name("alice").is_none());
+        assert!(list.get(&stranger).is_none());
+
+        let bob_entry = list.get(&bob).expect("bob is listed");
+        assert_eq!(bob_entry.name, "bob");
+        assert_eq!(bob_entry.game_mode, GameMode::Survival);
+        assert_eq!(bob_entry.ping_ms, VarInt(40));
+
+        let carol_entry = list.get_by_name("carol").expect("carol is listed");
+        assert_eq!(carol_entry.uuid, carol);
+        assert_eq!(carol_entry.ping_ms, VarInt(80));
+        assert_eq!(carol_entry.display_name, Some(Chat::from_text("[Admin] carol")));
+        assert_eq!(carol_entry.properties.len(), 1);
+        assert_eq!(carol_entry.properties[0].name, "textures");
+
+        let mut names: Vec<&str> = list.iter().map(|entry| entry.name.as_str()).collect();
+        names.sort_unstable();
+        assert_eq!(names, alloc::vec!["bob", "carol"]);
+
+        assert!(list.unknown_updates().is_empty());
+        assert_eq!(recording.unknown_updates(), &[stranger, stranger]);
+        assert_eq!(recording.iter().count(), 2);
+    }
+
+    #[test]
+    fn test_statistic_identifiers() {
+        for id in 0..StatisticKind::variant_count() {
+            let kind = StatisticKind::deserialize_with_id(VarInt(id as i32), &[]).expect("valid id").value;
+            assert_eq!(StatisticKind::from_identifier(kind.identifier()), Some(kind));
+        }
+
+        assert_eq!(StatisticKind::Jumps.identifier(), "minecraft:jump");
+        assert_eq!(StatisticKind::from_identifier("minecraft:animals_bred"), Some(StatisticKind::AnimalsBread));
+        assert_eq!(StatisticKind::from_identifier("minecraft:jumps"), None);
+
+        let blocks = |category: &StatisticCategory, id: i32| match (category, id) {
+            (StatisticCategory::Mined(_), 1) => Some("minecraft:stone".to_owned()),
+            _ => None,
+        };
+        let stat = |kind| Statistic { kind, value: VarInt(10) };
+        assert_eq!(stat(StatisticCategory::Custom(StatisticKind::Jumps)).name_with(blocks), "minecraft:custom/jump");
+        assert_eq!(stat(StatisticCategory::Mined(VarInt(1))).name_with(blocks), "minecraft:mined/minecraft.stone");
+        assert_eq!(stat(StatisticCategory::KilledBy(VarInt(1))).name_with(blocks), "minecraft:killed_by/1");
+        assert_eq!(stat(StatisticCategory::PickedUp(VarInt(42))).name_with(|_, _| None), "minecraft:picked_up/42");
+    }
+
+    #[test]
+    fn test_recipe_builders_validate() {
+        let ingredient = |item_id: i32| RecipeIngredient {
+            items: alloc::vec![Some(ItemStack { item_id: VarInt(item_id), item_count: 1, nbt: None })].into(),
+        };
+        let result = Some(ItemStack { item_id: VarInt(100), item_count: 4, nbt: None });
+
+        let planks = RecipeSpec::shaped("minecraft:oak_planks_2x2", 2, 2, alloc::vec![ingredient(5); 4], result.clone())
+            .expect("2x2 recipe with 4 ingredients is valid");
+        let shapeless = RecipeSpec::shapeless("minecraft:oak_planks", alloc::vec![ingredient(17)], result.clone());
+        let smelting = RecipeSpec::smelting("minecraft:glass", ingredient(12), result.clone(), 0.1, 200);
+        let mut packet = PlayDeclareRecipesSpec {
+            recipes: alloc::vec![planks, shapeless, smelting].into(),
+        };
+        assert!(packet.validate().is_ok());
+        let bytes = packet.mc_serialize_to_vec().expect("valid recipes serialize");
+        assert_eq!(PlayDeclareRecipesSpec::mc_deserialize_complete(bytes.as_slice()).expect("deserializes"), packet);
+
+        match RecipeSpec::shaped("minecraft:bad", 3, 3, alloc::vec![ingredient(5); 8], result.clone()) {
+            Err(RecipeErr::IngredientCountMismatch { id, expected, found }) => {
+                assert_eq!(id, "minecraft:bad");
+                assert_eq!(expected, 9);
+                assert_eq!(found, 8);
+            }
+            other => panic!("expected ingredient count mismatch, got {:?}", other.map(|spec| spec.id)),
+        }
+        assert!(matches!(
+            RecipeSpec::shaped("minecraft:negative", -1, 3, Vec::new(), result.clone()),
+            Err(RecipeErr::BadDimensions { .. })
+        ));
+
+        // a spec built by hand with the wrong number of ingredients
+        packet.recipes.push(RecipeSpec {
+            recipe: Recipe::CraftingShaped(RecipeCraftingShapedSpec {
+                width: VarInt(3),
+                height: VarInt(1),
+                group: String::new(),
+                ingredients: alloc::vec![ingredient(1); 2],
+                result,
+            }),
+            id: "minecraft:hand_built".to_owned(),
+        });
+        match packet.validate() {
+            Err(RecipeErr::IngredientCountMismatch { id, expected: 3, found: 2 }) => assert_eq!(id, "minecraft:hand_built"),
+            other => panic!("expected ingredient count mismatch, got {:?}", other),
+        }
+        assert!(packet.mc_serialize_to_vec().is_err());
+    }
+
+    #[test]
+    fn test_recipe_lookups() {
+        let ingredient = RecipeIngredient {
+            items: alloc::vec![Some(ItemStack { item_id: VarInt(17), item_count: 1, nbt: None })].into(),
+        };
+        let planks = Some(ItemStack { item_id: VarInt(13), item_count: 4, nbt: None });
+        let mut shapeless = RecipeSpec::shapeless("minecraft:oak_planks", alloc::vec![ingredient.clone()], planks.clone());
+        if let Recipe::CraftingShapeless(body) = &mut shapeless.recipe {
+            body.group = "planks".to_owned();
+        }
+        let packet = PlayDeclareRecipesSpec {
+            recipes: alloc::vec![
+                shapeless,
+                RecipeSpec::smelting("minecraft:charcoal", ingredient, Some(ItemStack { item_id: VarInt(600), item_count: 1, nbt: None }), 0.15, 200),
+                RecipeSpec { recipe: Recipe::CraftingArmorDye, id: "minecraft:armor_dye".to_owned() },
+                RecipeSpec {
+                    recipe: Recipe::StoneCutting(RecipeStonecuttingSpec {
+                        group: String::new(),
+                        ingredient: RecipeIngredient { items: Vec::new().into() },
+                        result: planks.clone(),
+                    }),
+                    id: "minecraft:stone_slab".to_owned(),
+                },
+            ].into(),
+        };
+
+        let found = packet.recipe_by_id("minecraft:oak_planks").expect("has planks recipe");
+        assert_eq!(found.group(), Some("planks"));
+        assert_eq!(found.recipe.result_item(), Some(&planks));
+        assert_eq!(packet.recipe_by_id("minecraft:charcoal").and_then(RecipeSpec::group), None);
+        let dye = packet.recipe_by_id("minecraft:armor_dye").expect("has dye recipe");
+        assert_eq!(dye.recipe.result_item(), None);
+        assert_eq!(dye.group(), None);
+        assert_eq!(packet.recipe_by_id("minecraft:missing"), None);
+
+        let ids: Vec<&str> = packet.recipes_for_item(VarInt(13)).map(|recipe| recipe.id.as_str()).collect();
+        assert_eq!(ids, alloc::vec!["minecraft:oak_planks", "minecraft:stone_slab"]);
+        assert_eq!(packet.recipes_for_item(VarInt(1)).count(), 0);
+    }
+
+    #[test]
+    fn test_recipe_book() {
+        let ingredient = RecipeIngredient {
+            items: alloc::vec![Some(ItemStack { item_id: VarInt(17), item_count: 1, nbt: None })].into(),
+        };
+        let planks = Some(ItemStack { item_id: VarInt(13), item_count: 4, nbt: None });
+        let declare = PlayDeclareRecipesSpec {
+            recipes: alloc::vec![
+                RecipeSpec::shapeless("minecraft:oak_planks", alloc::vec![ingredient.clone()], planks.clone()),
+                RecipeSpec::shapeless("minecraft:birch_planks", alloc::vec![ingredient.clone()], planks),
+                RecipeSpec::smelting("minecraft:charcoal", ingredient, Some(ItemStack { item_id: VarInt(600), item_count: 1, nbt: None }), 0.15, 200),
+            ].into(),
+        };
+        let unlock = |action: RecipeUnlockAction, ids: &[&str]| PlayUnlockRecipesSpec {
+            action,
+            crafting_book_open: false,
+            crafting_book_active: false,
+            smelting_book_open: false,
+            smelting_book_active: false,
+            recipe_ids: ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().into(),
+            other_recipe_ids: RemainingBytes { data: Vec::new() },
+        };
+
+        let mut book = RecipeBook::new();
+        book.apply_unlock(&unlock(RecipeUnlockAction::Init, &["minecraft:oak_planks", "minecraft:charcoal"]));
+        book.apply_declare(&declare);
+        assert!(book.is_unlocked("minecraft:oak_planks"));
+        assert!(!book.is_unlocked("minecraft:birch_planks"));
+        assert_eq!(book.recipe_by_id("minecraft:charcoal").map(|recipe| recipe.id.as_str()), Some("minecraft:charcoal"));
+        assert_eq!(book.recipe_by_id("minecraft:missing"), None);
+
+        let mut ids: Vec<&str> = book.recipes_for_item(VarInt(13)).map(|recipe| recipe.id.as_str()).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, alloc::vec!["minecraft:birch_planks", "minecraft:oak_planks"]);
+
+        book.apply_unlock(&unlock(RecipeUnlockAction::Add, &["minecraft:birch_planks"]));
+        book.apply_unlock(&unlock(RecipeUnlockAction::Remove, &["minecraft:charcoal"]));
+        assert!(book.is_unlocked("minecraft:birch_planks"));
+        assert!(!book.is_unlocked("minecraft:charcoal"));
+        assert_eq!(book.unlocked_recipes().count(), 2);
+    }
+
+    #[test]
+    fn test_shaped_recipe_grid() {
+        let ingredient = |item_id: i32| RecipeIngredient {
+            items: alloc::vec![Some(ItemStack { item_id: VarInt(item_id), item_count: 1, nbt: None })].into(),
+        };
+        let recipe = RecipeCraftingShapedSpec {
+            width: VarInt(3),
+            height: VarInt(2),
+            group: String::new(),
+            ingredients: (0..6).map(ingredient).collect(),
+            result: None,
+        };
+
+        assert_eq!(recipe.ingredient_at(0, 0), Some(&ingredient(0)));
+        assert_eq!(recipe.ingredient_at(2, 0), Some(&ingredient(2)));
+        assert_eq!(recipe.ingredient_at(1, 1), Some(&ingredient(4)));
+        assert_eq!(recipe.ingredient_at(3, 0), None);
+        assert_eq!(recipe.ingredient_at(0, 2), None);
+        let positions: Vec<(usize, usize)> = recipe.grid().map(|(position, _)| position).collect();
+        assert_eq!(positions, alloc::vec![(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)]);
+        assert_eq!(recipe.grid().nth(5).map(|(_, ingredient)| ingredient), Some(&ingredient(5)));
+
+        let bytes = recipe.mc_serialize_to_vec().expect("serializes");
+        assert_eq!(RecipeCraftingShapedSpec::mc_deserialize_complete(bytes.as_slice()).expect("deserializes"), recipe);
+
+        let mut negative = BytesSerializer::default();
+        VarInt(-1).mc_serialize(&mut negative).expect("serializes");
+        VarInt(3).mc_serialize(&mut negative).expect("serializes");
+        String::new().mc_serialize(&mut negative).expect("serializes");
+        assert!(matches!(
+            RecipeCraftingShapedSpec::mc_deserialize(negative.into_bytes().as_slice()),
+            Err(DeserializeErr::CannotUnderstandValue(_))
+        ));
+
+        let mut huge = BytesSerializer::default();
+        VarInt(100_000).mc_serialize(&mut huge).expect("serializes");
+        VarInt(100_000).mc_serialize(&mut huge).expect("serializes");
+        String::new().mc_serialize(&mut huge).expect("serializes");
+        huge.serialize_bytes(&[0; 16]).expect("serializes");
+        match RecipeCraftingShapedSpec::mc_deserialize(huge.into_bytes().as_slice()) {
+            Err(DeserializeErr::CannotUnderstandValue(msg)) => assert!(msg.contains("100000x100000"), "{}", msg),
+            other => panic!("expected a bad grid, got {:?}", other.map(|d| d.value)),
+        }
+    }
+
+    #[test]
+    fn test_login_success_uuid() {
+        let uuid = UUID4::parse("35a8ad3d-9d3e-4c5b-a4b3-347b9ad96a0c").expect("valid uuid");
+        let spec = LoginSuccessSpec {
+            uuid_string: String::new(),
+            username: String::from("Notch").into(),
+        }.with_uuid(uuid);
+
+        assert_eq!(spec.uuid_string, "35a8ad3d-9d3e-4c5b-a4b3-347b9ad96a0c");
+        assert_eq!(spec.uuid(), Some(uuid));
+        assert_eq!(LoginSuccessSpec { uuid_string: "not a uuid".to_owned(), username: spec.username }.uuid(), None);
+    }
+
+    #[test]
+    fn test_connection_state_machine() {
+        use PacketDirection::*;
+        let mut machine = ConnectionStateMachine::new();
+        let chat = Packet578::PlayClientChatMessage(PlayClientChatMessageSpec { message: String::from("hi").into() });
+        match machine.transition(ServerBound, &chat) {
+            Err(StateErr::InvalidPacket { name, state }) => {
+                assert_eq!(name, "PlayClientChatMessage");
+                assert_eq!(state, State::Handshaking);
+            }
+            other => panic!("expected invalid packet, got {:?}", other),
+        }
+
+        let handshake = Packet578::Handshake(HandshakeSpec {
+            version: VarInt(578),
+            server_address: "localhost".to_owned(),
+            server_port: 25565,
+            next_state: HandshakeNextState::Login,
+        });
+        machine.transition(ServerBound, &handshake).expect("handshake is valid");
+        assert_eq!(machine.current_state, State::Login);
+        assert!(machine.transition(ServerBound, &handshake).is_err());
+
+        let success = Packet578::LoginSuccess(LoginSuccessSpec {
+            uuid_string: "35a8ad3d-9d3e-4c5b-a4b3-347b9ad96a0c".to_owned(),
+            username: String::from("Notch").into(),
+        });
+        assert!(machine.transition(ServerBound, &success).is_err());
+        machine.transition(ClientBound, &success).expect("login success is valid");
+        assert_eq!(machine.current_state, State::Play);
+        machine.transition(ServerBound, &chat).expect("chat is valid in play");
+
+        let mut status = ConnectionStateMachine::new();
+        status.transition(ServerBound, &Packet578::Handshake(HandshakeSpec {
+            version: VarInt(578),
+            server_address: "localhost".to_owned(),
+            server_port: 25565,
+            next_state: HandshakeNextState::Status,
+        })).expect("handshake is valid");
+        assert_eq!(status.current_state, State::Status);
+        assert_eq!(
+            format!("{}", status.transition(ServerBound, &chat).unwrap_err()),
+            "packet PlayClientChatMessage is not valid in state Status"
+        );
+    }
+
+    #[test]
+    fn test_declare_commands_navigation() {
+        let mut tree = CommandTree::new();
+        let tp = tree.literal(tree.root(), "tp");
+        let target = tree.argument(tp, "target", CommandParserSpec::Entity(EntityParserFlags(0x03)));
+        tree.executable(target);
+        let help = tree.literal(tree.root(), "help");
+        tree.executable(help);
+        let spec = tree.to_spec().expect("valid tree");
+
+        let root = spec.root().expect("has root");
+        assert_eq!(root.node, CommandNode::Root);
+        assert_eq!(root.literal_name(), None);
+        let mut literals: Vec<&str> = root.children(&spec).filter_map(CommandNodeSpec::literal_name).collect();
+        literals.sort_unstable();
+        assert_eq!(literals, vec!["help", "tp"]);
+
+        let tp = root.children(&spec).find(|node| node.literal_name() == Some("tp")).expect("has tp");
+        let arguments: Vec<&str> = tp.children(&spec).filter_map(CommandNodeSpec::argument_name).collect();
+        assert_eq!(arguments, vec!["target"]);
+
+        assert!(spec.node_at(VarInt(-1)).is_none());
+        assert!(spec.node_at(VarInt(spec.nodes.len() as i32)).is_none());
+        let broken = PlayDeclareCommandsSpec { nodes: spec.nodes.clone(), root_index: VarInt(99) };
+        assert!(broken.root().is_none());
+    }
+
+    #[test]
+    fn test_entity_property_compute_value() {
+        use EntityPropertyModifierOperation::*;
+        let modifier = |amount: f64, operation| EntityPropertyModifierSpec { uuid: UUID4::from(0), amount, operation };
+        let speed = EntityPropertySpec {
+            key: "generic.movementSpeed".to_owned(),
+            value: 0.1,
+            modifiers: vec![
+                modifier(0.3, MultiplyByAmountPercent),
+                modifier(0.5, AddSubtractAmountPercentOfCurrent),
+                modifier(0.1, AddSubtractAmount),
+                modifier(0.5, AddSubtractAmountPercentOfCurrent),
+                modifier(-0.5, MultiplyByAmountPercent),
+            ].into(),
+        };
+        // (0.1 + 0.1) = 0.2 base, + 0.2 * 0.5 twice = 0.4, * 1.3 * 0.5
+        assert!((speed.compute_value() - 0.26).abs() < 1e-12);
+
+        let health = EntityPropertySpec { key: "generic.maxHealth".to_owned(), value: 20.0, modifiers: vec![].into() };
+        let spec = PlayEntityPropertiesSpec { entity_id: VarInt(1), properties: vec![speed, health].into() };
+        assert_eq!(spec.attribute_value("generic.maxHealth"), Some(20.0));
+        assert!((spec.attribute_value("generic.movementSpeed").expect("present") - 0.26).abs() < 1e-12);
+        assert_eq!(spec.attribute_value("generic.armor"), None);
+
+        assert_eq!(AddSubtractAmount.apply(1.0, 2.0, 3.0), 5.0);
+        assert_eq!(AddSubtractAmountPercentOfCurrent.apply(2.0, 3.0, 0.5), 4.0);
+        assert_eq!(MultiplyByAmountPercent.apply(2.0, 3.0, 1.0), 6.0);
+    }
+
+    #[test]
+    fn test_advancement_progress() {
+        let criterion = |identifier: &str, achieved_at: Option<i64>| AdvancementCriteriaSpec {
+            identifier: identifier.to_owned(),
+            progress: AdvancementCriterionProgressSpec { achieved_at },
+        };
+        let progress = |key: &str, criteria: Vec<AdvancementCriteriaSpec>| AdvancementProgressEntrySpec {
+            key: key.to_owned(),
+            value: AdvancementProgressSpec { criteria: criteria.into() },
+        };
+        let spec = PlayAdvancementsSpec {
+            reset: false,
+            mappings: vec![AdvancementMappingEntrySpec {
+                key: "minecraft:story/root".to_owned(),
+                value: AdvancementSpec { parent: None, display: None, criteria: vec![].into(), requirements: vec![].into() },
+            }].into(),
+            identifiers: vec![].into(),
+            progress: vec![
+                progress("minecraft:story/root", vec![criterion("crafting_table", Some(1000))]),
+                progress("minecraft:story/mine_stone", vec![criterion("get_stone", Some(2000)), criterion("other", None)]),
+                progress("minecraft:story/smelt_iron", vec![criterion("iron", Some(3000))]),
+            ].into(),
+        };
+
+        assert!(spec.mappings[0].value.is_root());
+        assert!(!spec.progress[1].value.criteria[1].progress.achieved());
+        assert!(!spec.progress[1].value.is_complete());
+        let completed: Vec<&str> = spec.completed_advancements().map(|(key, _)| key).collect();
+        assert_eq!(completed, vec!["minecraft:story/root", "minecraft:story/smelt_iron"]);
+    }
+
+    #[test]
+    fn test_advancements_apply_to() {
+        let advancement = |parent: Option<&str>| AdvancementSpec {
+            parent: parent.map(String::from),
+            display: None,
+            criteria: vec![].into(),
+            requirements: vec![].into(),
+        };
+        let progress = |key: &str| AdvancementProgressEntrySpec {
+            key: key.to_owned(),
+            value: AdvancementProgressSpec { criteria: vec![].into() },
+        };
+        let initial = PlayAdvancementsSpec {
+            reset: true,
+            mappings: vec![
+                AdvancementMappingEntrySpec { key: "minecraft:story/root".to_owned(), value: advancement(None) },
+                AdvancementMappingEntrySpec { key: "minecraft:story/mine_stone".to_owned(), value: advancement(Some("minecraft:story/root")) },
+            ].into(),
+            identifiers: vec![].into(),
+            progress: vec![progress("minecraft:story/root"), progress("minecraft:story/mine_stone")].into(),
+        };
+        let update = PlayAdvancementsSpec {
+            reset: false,
+            mappings: vec![
+                AdvancementMappingEntrySpec { key: "minecraft:story/smelt_iron".to_owned(), value: advancement(Some("minecraft:story/root")) },
+            ].into(),
+            identifiers: vec!["minecraft:story/mine_stone".to_owned()].into(),
+            progress: vec![progress("minecraft:story/smelt_iron")].into(),
+        };
+
+        let mut advancements = alloc::collections::BTreeMap::new();
+        let mut progress_map = alloc::collections::BTreeMap::new();
+        advancements.insert("minecraft:nether/root".to_owned(), advancement(None));
+        initial.apply_to(&mut advancements, &mut progress_map);
+        update.apply_to(&mut advancements, &mut progress_map);
+        let keys: Vec<&str> = advancements.keys().map(String::as_str).collect();
+        assert_eq!(keys, vec!["minecraft:story/root", "minecraft:story/smelt_iron"]);
+        let keys: Vec<&str> = progress_map.keys().map(String::as_str).collect();
+        assert_eq!(keys, vec!["minecraft:story/root", "minecraft:story/smelt_iron"]);
+
+        let mut advancements = std::collections::HashMap::new();
+        let mut progress_map = std::collections::HashMap::new();
+        initial.apply_to_hash(&mut advancements, &mut progress_map);
+        update.apply_to_hash(&mut advancements, &mut progress_map);
+        assert_eq!(advancements.len(), 2);
+        assert!(advancements.contains_key("minecraft:story/smelt_iron"));
+        assert!(!progress_map.contains_key("minecraft:story/mine_stone"));
+    }
+
+    #[test]
+    fn test_lighting_data_sections() {
+        let mut light = LightingData::empty();
+        assert_eq!(light.count_present(), 0);
+        light.set_section(0, [0x11; LIGHT_DATA_LENGTH]);
+        light.set_section(17, [0x22; LIGHT_DATA_LENGTH]);
+        assert_eq!(light.count_present(), 2);
+        assert_eq!(light.get_section(0).map(|section| section[0]), Some(0x11));
+        assert_eq!(light.get_section(1), None);
+        assert_eq!(light.get_section(LIGHT_DATA_SECTIONS), None);
+
+        let mut newer = LightingData::empty();
+        newer.set_section(0, [0x33; LIGHT_DATA_LENGTH]);
+        newer.set_section(5, [0x44; LIGHT_DATA_LENGTH]);
+        let merged = light.merge(&newer);
+        assert_eq!(merged.count_present(), 3);
+        assert_eq!(merged.get_section(0).map(|section| section[0]), Some(0x33));
+        assert_eq!(merged.get_section(17).map(|section| section[0]), Some(0x22));
+
+        light.clear_section(17);
+        light.clear_section(LIGHT_DATA_SECTIONS);
+        assert_eq!(light.count_present(), 1);
+    }
+
+    #[test]
+    fn test_apply_lighting_update() {
+        let mut sky = LightingData::full_bright();
+        let mut block = LightingData::empty();
+        block.set_section(3, [0x55; LIGHT_DATA_LENGTH]);
+
+        let mut sky_update = LightingData::empty();
+        sky_update.set_section(1, [0x12; LIGHT_DATA_LENGTH]);
+        let mut block_update = LightingData::empty();
+        block_update.set_section(4, [0x34; LIGHT_DATA_LENGTH]);
+        let update = LightingUpdateSpec {
+            skylight_data: sky_update,
+            blocklight_data: block_update,
+            skylight_reset_mask: VarInt(0b101),
+            blocklight_reset_mask: VarInt(0b1000),
+        };
+
+        apply_update(&update, &mut sky, &mut block);
+        assert_eq!(sky.get_section(0), None);
+        assert_eq!(sky.get_section(1).map(|section| section[0]), Some(0x12));
+        assert_eq!(sky.get_section(2), None);
+        assert_eq!(sky.count_present(), LIGHT_DATA_SECTIONS - 2);
+        assert_eq!(block.get_section(3), None);
+        assert_eq!(block.get_section(4).map(|section| section[0]), Some(0x34));
+        assert_eq!(block.count_present(), 1);
+    }
+
+    #[test]
+    fn test_chunk_data_helpers() {
+        let mut biomes = Box::new([0i32; BIOME_CELLS]);
+        biomes[(3 << 2) | 1] = 7;
+        biomes[(1 << 4) | (3 << 2) | 1] = 8;
+        let mut chunk = ChunkData::empty(ChunkPosition { x: 0, z: 0 });
+        chunk.primary_bit_mask = VarInt(0b1000_0101);
+        chunk.biomes = Some(biomes);
+
+        assert_eq!(chunk.section_count(), 3);
+        assert!(chunk.is_section_present(0));
+        assert!(!chunk.is_section_present(1));
+        assert!(chunk.is_section_present(7));
+        assert!(!chunk.is_section_present(16));
+        assert!(chunk.has_biomes());
+        assert_eq!(chunk.biome_at(4, 12), Some(7));
+        assert_eq!(chunk.biome_at(7, 15), Some(7));
+        assert_eq!(chunk.biome_at(3, 12), Some(0));
+        assert_eq!(chunk.biome_at(16, 0), None);
+        assert_eq!(chunk.biome_at(0, 16), None);
+
+        chunk.biomes = None;
+        assert!(!chunk.has_biomes());
+        assert_eq!(chunk.biome_at(4, 12), None);
+    }
+
+    #[test]
+    fn test_multi_block_change_apply_to() {
+        let spec = PlayMultiBlockChangeSpec::from_absolute(-2, 3, alloc::vec![
+            (IntPosition { x: -17, y: 64, z: 48 }, VarInt(1)),
+            (IntPosition { x: -32, y: 0, z: 63 }, VarInt(2)),
+            (IntPosition { x: -17, y: 64, z: 48 }, VarInt(3)),
+        ]).expect("positions are inside the chunk");
+        assert_eq!(spec.chunk_position(), (-2, 3));
+
+        let mut map = alloc::collections::BTreeMap::new();
+        map.insert(IntPosition { x: -32, y: 0, z: 63 }, VarInt(9));
+        map.insert(IntPosition { x: 100, y: 5, z: 100 }, VarInt(4));
+        spec.apply_to(&mut map);
+
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get(&IntPosition { x: -17, y: 64, z: 48 }), Some(&VarInt(3)));
+        assert_eq!(map.get(&IntPosition { x: -32, y: 0, z: 63 }), Some(&VarInt(2)));
+        assert_eq!(map.get(&IntPosition { x: 100, y: 5, z: 100 }), Some(&VarInt(4)));
+        assert_eq!(spec.iter().count(), 3);
+    }
+
+    fn tags_fixture() -> PlayTagsSpec {
+        let tag = |name: &str, ids: &[i32]| TagSpec {
+            name: name.to_owned(),
+            entries: ids.iter().map(|id| VarInt(*id)).collect::<Vec<_>>().into(),
+        };
+        PlayTagsSpec {
+            block_tags: alloc::vec![tag("minecraft:logs", &[33, 34, 35]), tag("minecraft:wool", &[95, 96])].into(),
+            item_tags: alloc::vec![tag("minecraft:wool", &[82, 83])].into(),
+            fluid_tags: alloc::vec![tag("minecraft:water", &[1, 2])].into(),
+            entity_tags: alloc::vec![tag("minecraft:skeletons", &[5]), tag("minecraft:skeletons", &[6])].into(),
+        }
+    }
+
+    #[test]
+    fn test_play_tags_lookup() {
+        let tags = tags_fixture();
+        let logs = tags.lookup_block_tag("minecraft:logs").expect("has logs");
+        assert!(logs.contains_id(VarInt(34)));
+        assert!(!logs.contains_id(VarInt(95)));
+        assert!(tags.lookup_block_tag("minecraft:wool").expect("has wool").contains_id(VarInt(95)));
+        assert!(tags.lookup_item_tag("minecraft:wool").expect("has wool").contains_id(VarInt(82)));
+        assert_eq!(tags.lookup_item_tag("minecraft:logs"), None);
+        assert!(tags.lookup_fluid_tag("minecraft:water").is_some());
+        assert_eq!(tags.lookup_entity_tag("minecraft:skeletons").map(|t| t.entries.to_vec()), Some(alloc::vec![VarInt(5)]));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_tag_lookup_index() {
+        let tags = tags_fixture();
+        let lookup = TagLookup::from(&tags);
+        for _ in 0..2 {
+            assert_eq!(lookup.block_tag("minecraft:logs"), tags.lookup_block_tag("minecraft:logs"));
+            assert_eq!(lookup.item_tag("minecraft:wool"), tags.lookup_item_tag("minecraft:wool"));
+            assert_eq!(lookup.fluid_tag("minecraft:lava"), None);
+            assert_eq!(lookup.entity_tag("minecraft:skeletons"), tags.lookup_entity_tag("minecraft:skeletons"));
+        }
+    }
+
+    #[test]
+    fn test_trade_helpers() {
+        let emeralds = |count: i8| Some(ItemStack { item_id: VarInt(600), item_count: count, nbt: None });
+        let trade = |uses: i32, disabled: bool, count: i8, special_price: i32, demand: i32| TradeSpec {
+            input_item_1: emeralds(count),
+            output_item: Some(ItemStack { item_id: VarInt(1), item_count: 1, nbt: None }),
+            input_item_2: None,
+            trade_disabled: disabled,
+            trade_uses: uses,
+            max_trade_uses: 12,
+            xp: 2,
+            special_price,
+            price_multiplier: 0.05,
+            demand,
+        };
+
+        let fresh = trade(3, false, 10, 0, 5);
+        assert!(fresh.is_available());
+        assert_eq!(fresh.uses_remaining(), 9);
+        assert_eq!(fresh.effective_input_count(), 12);
+        assert_eq!(trade(0, false, 10, -3, 5).effective_input_count(), 9);
+        assert_eq!(trade(0, false, 10, 0, -20).effective_input_count(), 10);
+        assert_eq!(trade(0, false, 4, -10, 0).effective_input_count(), 1);
+        let mut no_input = trade(0, false, 1, 0, 0);
+        no_input.input_item_1 = None;
+        assert_eq!(no_input.effective_input_count(), 0);
+
+        let used_up = trade(12, false, 10, 0, 0);
+        assert!(!used_up.is_available());
+        assert_eq!(used_up.uses_remaining(), 0);
+        let disabled = trade(0, true, 10, 0, 0);
+        assert!(!disabled.is_available());
+
+        let list = PlayTradeListSpec {
+            window_id: VarInt(1),
+            trades: alloc::vec![used_up, fresh.clone(), disabled].into(),
+            villager_level: VarInt(1),
+            experience: VarInt(0),
+            regular_villager: true,
+            can_restock: true,
+        };
+        let available: Vec<&TradeSpec> = list.available_trades().collect();
+        assert_eq!(available, alloc::vec![&fresh]);
+    }
+
+    #[test]
+    fn test_player_info_uuids_and_skin() {
+        let alice = UUID4::from(1u128);
+        let bob = UUID4::from(2u128);
+        let add = PlayerAddActionSpec {
+            name: "alice".to_owned(),
+            properties: alloc::vec![
+                PlayerAddProperty { name: "cape".to_owned(), value: "eA==".to_owned(), signature: None },
+                PlayerAddProperty { name: "textures".to_owned(), value: "ewo=".to_owned(), signature: Some("c2ln".to_owned()) },
+            ].into(),
+            game_mode: GameMode::Survival,
+            ping_ms: VarInt(10),
+            display_name: None,
+        };
+        assert_eq!(add.skin_texture(), Some(("ewo=", Some("c2ln"))));
+
+        let spec = PlayPlayerInfoSpec {
+            actions: PlayerInfoActionList::Add(alloc::vec![
+                PlayerInfoAction { uuid: alice, action: add.clone() },
+                PlayerInfoAction { uuid: bob, action: PlayerAddActionSpec { properties: Vec::new().into(), ..add } },
+            ].into()),
+        };
+        assert_eq!(spec.uuids().collect::<Vec<_>>(), alloc::vec![alice, bob]);
+        if let PlayerInfoActionList::Add(players) = &spec.actions {
+            assert_eq!(players[1].action.skin_texture(), None);
+        }
+
+        let latency = PlayerInfoActionList::UpdateLatency(alloc::vec![PlayerInfoAction { uuid: bob, action: VarInt(40) }].into());
+        assert_eq!(latency.uuids().collect::<Vec<_>>(), alloc::vec![bob]);
+        let remove = PlayerInfoActionList::Remove(alloc::vec![bob, alice].into());
+        assert_eq!(remove.uuids().collect::<Vec<_>>(), alloc::vec![bob, alice]);
+    }
+
+    #[test]
+    fn test_stop_sound_matches() {
+        let all = StopSoundSpec { source: None, sound: None };
+        assert!(all.matches(Some(SoundCategory::Music), Some("minecraft:music.game")));
+        assert!(all.matches(None, None));
+
+        let music = StopSoundSpec { source: Some(SoundCategory::Music), sound: None };
+        assert!(music.matches(Some(SoundCategory::Music), Some("minecraft:music.game")));
+        assert!(!music.matches(Some(SoundCategory::Weather), Some("minecraft:weather.rain")));
+        assert!(!music.matches(None, None));
+
+        let rain = StopSoundSpec { source: Some(SoundCategory::Weather), sound: Some("minecraft:weather.rain".to_owned()) };
+        assert!(rain.matches(Some(SoundCategory::Weather), Some("minecraft:weather.rain")));
+        assert!(!rain.matches(Some(SoundCategory::Weather), Some("minecraft:weather.rain.above")));
+        assert!(!rain.matches(Some(SoundCategory::Master), Some("minecraft:weather.rain")));
+
+        let any_source = StopSoundSpec { source: None, sound: Some("minecraft:entity.cow.ambient".to_owned()) };
+        assert!(any_source.matches(Some(SoundCategory::Neutral), Some("minecraft:entity.cow.ambient")));
+        assert!(!any_source.matches(Some(SoundCategory::Neutral), None));
+    }
+
+    #[test]
+    fn test_title_action_accessors() {
+        let times = TitleTimesSpec { fade_in: 10, stay: 70, fade_out: 20 };
+        for (action, text) in alloc::vec![
+            (TitleActionSpec::SetTitle(Chat::from_text("title")), Some("title")),
+            (TitleActionSpec::SetSubtitle(Chat::from_text("subtitle")), Some("subtitle")),
+            (TitleActionSpec::SetActionBar(Chat::from_text("action bar")), Some("action bar")),
+            (TitleActionSpec::SetTimesAndDisplay(times.clone()), None),
+            (TitleActionSpec::Hide, None),
+            (TitleActionSpec::Reset, None),
+        ] {
+            assert_eq!(action.text().map(Chat::to_plain_text).as_deref(), text);
+            assert_eq!(action.is_control(), text.is_none());
+        }
+
+        assert_eq!(TitleActionSpec::SetTimesAndDisplay(times.clone()).times(), Some(&times));
+        assert_eq!(TitleActionSpec::Hide.times(), None);
+    }
+
+    #[test]
+    fn test_entity_effect_helpers() {
+        let spec = PlayEntityEffectSpec {
+            entity_id: VarInt(7),
+            effect_id: EntityEffectKind::Strength,
+            amplifier: 1,
+            duration_ticks: VarInt(3600),
+            flags: EntityEffectFlags::default().with_ambient(true).with_show_icon(true),
+        };
+        assert_eq!(spec.duration_seconds(DEFAULT_TPS), 180.0);
+        assert_eq!(spec.duration_seconds(10.0), 360.0);
+        assert!(spec.is_ambient());
+        assert!(!spec.shows_particles());
+        assert!(spec.shows_icon());
+        assert_eq!(spec.effect_id.default_amplifier(), 0);
+    }
+
+    #[test]
+    fn test_world_border_state() {
+        let mut state = WorldBorderState::default();
+        assert!(!state.is_initialized());
+        assert_eq!(state.diameter(), None);
+
+        let init = WorldBorderAction::Initialize(WorldBorderInitiaializeSpec {
+            position: TopDownPosition { x: 10.5, z: -20.0 },
+            old_diameter: 100.0,
+            new_diameter: 200.0,
+            speed: VarLong(0),
+            portal_teleport_boundary: VarLong(29999984),
+            warning_time: VarInt(15),
+            warning_blocks: VarInt(5),
+        });
+        assert_eq!(init.center(), Some((10.5, -20.0)));
+        assert_eq!(init.diameter(), Some(200.0));
+        state.apply(&init);
+        assert!(state.is_initialized());
+
+        let lerp = WorldBorderAction::LerpSize(WorldBorderLerpSizeSpec {
+            old_diameter: 200.0,
+            new_diameter: 50.0,
+            speed: VarLong(1000),
+        });
+        assert_eq!(lerp.center(), None);
+        assert_eq!(lerp.warning_time(), None);
+        state.apply(&lerp);
+        state.apply(&WorldBorderAction::SetWarningBlocks(WorldBorderWarningBlocksSpec {
+            warning_blocks: VarInt(8),
+        }));
+
+        assert_eq!(state.center(), Some((10.5, -20.0)));
+        assert_eq!(state.diameter(), Some(50.0));
+        assert_eq!(state.warning_time(), Some(VarInt(15)));
+        assert_eq!(state.warning_blocks(), Some(VarInt(8)));
+    }
+
+    #[test]
+    fn test_team_member_compact_uuid_is_player() {
+        let compact = String::from("e1cde35a075847f6adf89dcb44884e5d");
+        let mut out = BytesSerializer::default();
+        compact.mc_serialize(&mut out).expect("serialize succeeds");
+        let bytes = out.into_bytes();
+        let member = TeamMember::mc_deserialize(bytes.as_slice()).expect("deserialize succeeds").value;
+        assert_eq!(member, TeamMember::Player(compact));
+
+        let uuid = UUID4::parse("e1cde35a-0758-47f6-adf8-9dcb44884e5d").expect("valid uuid");
+        let mut out = BytesSerializer::default();
+        TeamMember::Entity(uuid).mc_serialize(&mut out).expect("serialize succeeds");
+        let bytes = out.into_bytes();
+        let member = TeamMember::mc_deserialize(bytes.as_slice()).expect("deserialize succeeds").value;
+        assert_eq!(member, TeamMember::Entity(uuid));
+    }
+
+    #[test]
+    fn test_map_icon_helpers() {
+        assert!(MapIconType::WhiteBanner.is_banner());
+        assert!(MapIconType::BlackBanner.is_banner());
+        assert!(!MapIconType::TreasureMarker.is_banner());
+        assert_eq!(MapIconType::OrangeBanner.banner_color_name(), Some("orange"));
+        assert_eq!(MapIconType::LightGrayBanner.banner_color_name(), Some("light_gray"));
+        assert_eq!(MapIconType::RedPointer.banner_color_name(), None);
+        assert!(MapIconType::Mansion.is_structure_marker());
+        assert!(MapIconType::Temple.is_structure_marker());
+        assert!(!MapIconType::RedBanner.is_structure_marker());
+
+        let icon = MapIconSpec {
+            kind: MapIconType::WhiteArrow,
+            position: TopDownPosition { x: 0, z: 0 },
+            direction: 4,
+            display_name: None,
+        };
+        assert_eq!(icon.direction_degrees(), 90.0);
+    }
+
+    #[test]
+    fn test_particle_data_accessors() {
+        let block = ParticleSpec::Block(BlockParticleData { block_state: VarInt(1) });
+        assert!(block.has_extra_data());
+        assert_eq!(block.as_block_state(), Some(VarInt(1)));
+        assert_eq!(block.as_dust(), None);
+
+        let dust_data = DustParticleData { red: 1.0, green: 0.5, blue: 0.0, scale: 2.0 };
+        let dust = ParticleSpec::Dust(dust_data.clone());
+        assert!(dust.has_extra_data());
+        assert_eq!(dust.as_dust(), Some(&dust_data));
+        assert_eq!(dust.as_block_state(), None);
+        assert_eq!(dust_data.as_rgba(0.25), (1.0, 0.5, 0.0, 0.25));
+
+        // falling dust of a sand block, as sent by vanilla: id 0x17 then the block state
+        let falling = ParticleSpec::FallingDust(BlockParticleData { block_state: VarInt(66) });
+        assert!(falling.has_extra_data());
+        assert_eq!(falling.as_block_state(), Some(VarInt(66)));
+        assert_eq!(falling.as_dust(), None);
+        let mut out = BytesSerializer::default();
+        falling.mc_serialize(&mut out).expect("serializes");
+        assert_eq!(out.into_bytes(), alloc::vec![0x17, 0x42]);
+
+        let item = ParticleSpec::Item(None);
+        assert!(item.has_extra_data());
+        assert_eq!(item.as_item(), Some(&None));
+
+        let flame = ParticleSpec::Flame;
+        assert!(!flame.has_extra_data());
+        assert_eq!(flame.as_item(), None);
+    }
+
+    #[test]
+    fn test_explosion_helpers() {
+        let explosion = PlayExplosionSpec {
+            position: Vec3 { x: 10.5, y: 64.2, z: -3.5 },
+            strength: 4.0,
+            records: alloc::vec![Vec3 { x: 0, y: 0, z: 0 }, Vec3 { x: -1, y: 2, z: 1 }].into(),
+            player_motion: Vec3 { x: 0.25, y: 0.5, z: -0.25 },
+        };
+
+        assert_eq!(explosion.explosion_center(), (10.5, 64.2, -3.5));
+        assert_eq!(explosion.player_push_velocity(), (0.25, 0.5, -0.25));
+        let positions: Vec<_> = explosion.affected_block_positions().collect();
+        assert_eq!(positions, alloc::vec![(10, 64, -4), (9, 66, -3)]);
+    }
+
+    #[test]
+    fn test_statistic_units() {
+        assert_eq!(StatisticKind::SprintOneCm.unit(), StatisticUnit::Centimetres);
+        assert_eq!(StatisticKind::PlayOneMinute.unit(), StatisticUnit::Ticks);
+        assert_eq!(StatisticKind::DamageTaken.unit(), StatisticUnit::HalfHearts);
+        assert_eq!(StatisticKind::Jumps.unit(), StatisticUnit::Count);
+
+        assert_eq!(StatisticUnit::Centimetres.format(VarInt(1530)), "15.3 m");
+        assert_eq!(StatisticUnit::Ticks.format(VarInt(50)), "2.5 s");
+        assert_eq!(StatisticUnit::Count.format(VarInt(42)), "42");
+        assert_eq!(StatisticUnit::HalfHearts.format(VarInt(6)), "0.3 \u{2764}");
+        assert_eq!(StatisticUnit::HalfHearts.format(VarInt(60)), "3.0 \u{2764}");
+
+        let spec = PlayStatisticsSpec {
+            entries: alloc::vec![
+                Statistic { kind: StatisticCategory::Custom(StatisticKind::WealkOneCm), value: VarInt(250) },
+                Statistic { kind: StatisticCategory::Mined(VarInt(1)), value: VarInt(64) },
+            ].into(),
+        };
+        let formatted: Vec<_> = spec.formatted().map(|(_, text)| text).collect();
+        assert_eq!(formatted, alloc::vec![String::from("2.5 m"), String::from("64")]);
+    }
+
+    #[test]
+    fn test_command_parser_names() {
+        assert_eq!(CommandParserSpec::Bool.display_name(), "brigadier:bool");
+        assert_eq!(CommandParserSpec::Entity(EntityParserFlags(0)).display_name(), "minecraft:entity");
+        assert!(CommandParserSpec::StringParser(StringParserMode::SingleWord).is_brigadier());
+        assert!(!CommandParserSpec::BlockPosition.is_brigadier());
+        assert_eq!(SuggestionsTypeSpec::AskServer.resource_location(), "minecraft:ask_server");
+
+        assert!(!StringParserMode::SingleWord.allows_spaces());
+        assert!(StringParserMode::QuotablePharse.allows_spaces());
+        assert!(StringParserMode::GreedyPhrase.allows_spaces());
+    }
+
+    #[test]
+    fn test_num_parser_props_bounds() {
+        let props = IntegerParserProps { min: Some(1), max: Some(10) };
+        assert_eq!(props.clamp(-5), 1);
+        assert_eq!(props.clamp(5), 5);
+        assert_eq!(props.clamp(50), 10);
+        assert!(props.validate(1));
+        assert!(props.validate(10));
+        assert!(!props.validate(11));
+        assert!(!props.is_unbounded());
+
+        let at_least = DoubleParserProps { min: Some(0.5), max: None };
+        assert_eq!(at_least.clamp(1000.0), 1000.0);
+        assert_eq!(at_least.clamp(0.0), 0.5);
+        assert!(!at_least.validate(0.25));
+
+        let unbounded = FloatParserProps::default();
+        assert!(unbounded.is_unbounded());
+        assert!(unbounded.validate(f32::MIN));
+        assert_eq!(unbounded.clamp(-3.0), -3.0);
+    }
+
+    #[test]
+    fn test_window_items_slots() {
+        let stone = ItemStack { item_id: VarInt(1), item_count: 64, nbt: None };
+        let spec = PlayWindowItemsSpec {
+            window_id: 2,
+            slots: alloc::vec![
+                None,
+                Some(stone.clone()),
+                Some(ItemStack { item_id: VarInt(2), item_count: 0, nbt: None }),
+            ].into(),
+        };
+
+        assert_eq!(spec.window_id(), 2);
+        assert_eq!(spec.slot_at(0), Some(&None));
+        assert_eq!(spec.slot_at(1), Some(&Some(stone.clone())));
+        assert_eq!(spec.slot_at(3), None);
+        assert!(spec.is_empty_slot(0));
+        assert!(!spec.is_empty_slot(1));
+        assert!(spec.is_empty_slot(2));
+        assert!(spec.is_empty_slot(100));
+
+        let occupied: Vec<_> = spec.occupied_slots().map(|(index, stack)| (index, stack.item_id)).collect();
+        assert_eq!(occupied, alloc::vec![(1, VarInt(1)), (2, VarInt(2))]);
+    }
+
+    #[test]
+    fn test_sound_effect_position() {
+        let position = SoundPosition { x: 12.5, y: 70.0, z: -8.25 };
+        let spec = PlaySoundEffectSpec {
+            sound_id: VarInt(0),
+            sound_category: SoundCategory::Block,
+            position: position.into(),
+            volume: 1.0,
+            pitch: 1.0,
+        };
+        assert_eq!(spec.position.x.raw(), 100);
+        assert_eq!(spec.sound_position(), position);
+    }
+
+    fn describe_packet<P: MinecraftPacket>(packet: &P) -> (i32, State, PacketDirection, i32, &'static str, Vec<u8>) {
+        (P::protocol_version(), packet.state(), packet.direction(), packet.packet_id(), packet.packet_name(),
+         packet.serialize_body().expect("serialize succeeds"))
+    }
+
+    #[test]
+    fn test_minecraft_packet() {
+        let packet = Packet578::PlayServerKeepAlive(PlayServerKeepAliveSpec { id: 0x0102 });
+        let expected_body = 0x0102i64.to_be_bytes().to_vec();
+        assert_eq!(describe_packet(&packet),
+                   (578, State::Play, PacketDirection::ClientBound, 0x21, "PlayServerKeepAlive", expected_body.clone()));
+
+        #[cfg(feature = "v1_16_3")]
+        {
+            let packet = crate::v1_16_3::Packet753::PlayServerKeepAlive(
+                crate::v1_16_3::PlayServerKeepAliveSpec { id: 0x0102 });
+            assert_eq!(describe_packet(&packet),
+                       (753, State::Play, PacketDirection::ClientBound, 0x1F, "PlayServerKeepAlive", expected_body));
+        }
+
+        let packets = [
+            Packet578::Handshake(HandshakeSpec {
+                version: 578.into(),
+                server_address: "localhost".to_owned(),
+                server_port: 25565,
+                next_state: HandshakeNextState::Status,
+            }),
+            Packet578::StatusRequest(StatusRequestSpec {}),
+        ];
+        let intercepted: Vec<&'static str> = packets.iter()
+            .filter(|packet| packet.state() == State::Handshaking)
+            .map(MinecraftPacket::packet_name)
+            .collect();
+        assert_eq!(intercepted, alloc::vec!["Handshake"]);
+    }
+
+    #[test]
+    fn test_describe_by_direction() {
+        let client_bound: Vec<&'static crate::protocol::PacketDescription> = Packet578::client_bound_packets().collect();
+        let server_bound: Vec<_> = Packet578::server_bound_packets().collect();
+        assert_eq!(client_bound.len() + server_bound.len(), Packet578::packet_count());
+        assert!(client_bound.iter().all(|packet| packet.id.direction == PacketDirection::ClientBound));
+        assert!(server_bound.iter().all(|packet| packet.id.direction == PacketDirection::ServerBound));
+
+        let handshake = server_bound.iter().find(|packet| packet.name == "Handshake").expect("handshake is server bound");
+        assert_eq!(handshake.body_struct_name(), "HandshakeSpec");
+        assert_eq!(handshake.id, Packet578Kind::Handshake.id());
+        assert!(client_bound.iter().any(|packet| packet.body_struct_name() == "PlayServerKeepAliveSpec"));
+
+        // the table lists the same packets as describe()
+        let described = Packet578::describe().packets;
+        assert_eq!(described.len(), Packet578::packet_count());
+        for (packet, spec) in Packet578::packets().iter().zip(described.iter()) {
+            assert_eq!((packet.name, packet.body_struct, packet.id.id), (spec.name.as_str(), spec.body_struct.as_str(), spec.id));
+            assert_eq!(alloc::format!("{:?}", packet.id.direction), spec.direction);
+        }
+    }
+
+    #[test]
+    fn test_plugin_message_borrow_parts() {
+        use crate::protocol::{RawPacket, HasPacketBody};
+        let packet = Packet578::PlayServerPluginMessage(PlayServerPluginMessageSpec {
+            channel: "minecraft:brand".to_owned(),
+            data: alloc::vec![7u8; 4096].into(),
+        });
+        let mut out = BytesSerializer::default();
+        packet.mc_serialize_body(&mut out).expect("serialize succeeds");
+        let bytes = out.into_bytes();
+
+        let raw = RawPacket578::create(packet.id(), bytes.as_slice()).expect("valid id");
+        let (channel, data) = match &raw {
+            RawPacket578::PlayServerPluginMessage(body) => body.borrow_parts().expect("borrows"),
+            other => panic!("unexpected packet {:?}", other),
+        };
+        assert_eq!(channel, "minecraft:brand");
+        assert!(data.is_borrowed());
+        assert_eq!(data.as_ptr(), bytes[bytes.len() - 4096..].as_ptr());
+
+        let mut out = BytesSerializer::default();
+        channel.mc_serialize(&mut out).expect("serialize succeeds");
+        data.mc_serialize(&mut out).expect("serialize succeeds");
+        assert_eq!(out.into_bytes(), bytes);
+
+        let owned: CowRemainingBytes<'static> = data.into_owned();
+        assert!(!owned.is_borrowed());
+        assert_eq!(RemainingBytes::from(owned), RemainingBytes::from(alloc::vec![7u8; 4096]));
+    }
+
+    #[test]
+    fn test_login_plugin_borrow_parts() {
+        use crate::protocol::{RawPacket, HasPacketBody};
+        let request = Packet578::LoginPluginRequest(LoginPluginRequestSpec {
+            message_id: VarInt(3),
+            channel: "velocity:player_info".to_owned(),
+            data: alloc::vec![1u8, 2, 3].into(),
+        });
+        let mut out = BytesSerializer::default();
+        request.mc_serialize_body(&mut out).expect("serialize succeeds");
+        let bytes = out.into_bytes();
+        let (message_id, channel, data) = match RawPacket578::create(request.id(), bytes.as_slice()).expect("valid id") {
+            RawPacket578::LoginPluginRequest(body) => body.borrow_parts().expect("borrows"),
+            other => panic!("unexpected packet {:?}", other),
+        };
+        assert_eq!(message_id, VarInt(3));
+        assert_eq!(channel, "velocity:player_info");
+        assert!(data.is_borrowed());
+        assert_eq!(data.as_ptr(), bytes[bytes.len() - 3..].as_ptr());
+
+        let response = Packet578::LoginPluginResponse(LoginPluginResponseSpec {
+            message_id: VarInt(3),
+            successful: true,
+            data: alloc::vec![4u8, 5].into(),
+        });
+        let mut out = BytesSerializer::default();
+        response.mc_serialize_body(&mut out).expect("serialize succeeds");
+        let bytes = out.into_bytes();
+        let (message_id, successful, data) = match RawPacket578::create(response.id(), bytes.as_slice()).expect("valid id") {
+            RawPacket578::LoginPluginResponse(body) => body.borrow_parts().expect("borrows"),
+            other => panic!("unexpected packet {:?}", other),
+        };
+        assert_eq!(message_id, VarInt(3));
+        assert!(successful);
+        assert!(data.is_borrowed());
+        assert_eq!(&*data, &[4u8, 5][..]);
+
+        let truncated = RawPacket578Body::<LoginPluginResponseSpec> { data: &[], _typ: core::marker::PhantomData };
+        assert!(truncated.borrow_parts().is_err());
+    }
+
+    #[cfg(feature = "bench")]
+    fn large_plugin_message() -> Vec<u8> {
+        use crate::protocol::HasPacketBody;
+        let packet = Packet578::PlayServerPluginMessage(PlayServerPluginMessageSpec {
+            channel: "minecraft:register".to_owned(),
+            data: alloc::vec![1u8; 1 << 20].into(),
+        });
+        let mut out = BytesSerializer::default();
+        packet.mc_serialize_body(&mut out).expect("serialize succeeds");
+        out.into_bytes()
+    }
+
+    #[cfg(feature = "bench")]
+    #[bench]
+    fn bench_read_large_plugin_message_owned(b: &mut test::Bencher) {
+        let bytes = large_plugin_message();
+        b.bytes = bytes.len() as u64;
+        b.iter(|| PlayServerPluginMessageSpec::mc_deserialize(bytes.as_slice()).expect("deserialize succeeds"))
+    }
+
+    #[cfg(feature = "bench")]
+    #[bench]
+    fn bench_read_large_plugin_message_borrowed(b: &mut test::Bencher) {
+        let bytes = large_plugin_message();
+        b.bytes = bytes.len() as u64;
+        let body = RawPacket578Body::<PlayServerPluginMessageSpec> { data: bytes.as_slice(), _typ: core::marker::PhantomData };
+        b.iter(|| body.borrow_parts().expect("borrows"))
+    }
+
+    #[test]
+    fn test_raw_packet_accessors() {
+        use crate::protocol::RawPacket;
+        let keep_alive = 5i64.to_be_bytes();
+        let raw = RawPacket578::create((0x21, State::Play, PacketDirection::ClientBound).into(), &keep_alive)
+            .expect("valid id");
+        assert_eq!(raw.packet_name(), "PlayServerKeepAlive");
+        assert_eq!(raw.direction_and_state(), (PacketDirection::ClientBound, State::Play));
+        assert!(raw.is_play_packet());
+        assert!(!raw.is_login_packet());
+
+        let raw = RawPacket578::create((0x00, State::Login, PacketDirection::ServerBound).into(), &[])
+            .expect("valid id");
+        assert_eq!(raw.packet_name(), "LoginStart");
+        assert_eq!(raw.direction_and_state(), (PacketDirection::ServerBound, State::Login));
+        assert!(raw.is_login_packet());
+        assert!(!raw.is_play_packet());
+    }
+
+    #[test]
+    fn test_player_position_and_look_apply() {
+        let mut packet = PlayServerPlayerPositionAndLookSpec {
+            location: EntityLocation {
+                position: Vec3 { x: 1.5, y: -2.0, z: 10.0 },
+                rotation: EntityRotation { yaw: 90.0, pitch: -10.0 },
+            },
+            flags: PositionAndLookFlags::none(),
+            teleport_id: VarInt(1),
+        };
+        let current = (100.0, 64.0, -50.0, 180.0, 20.0);
+        assert!(packet.is_fully_absolute());
+        assert!(!packet.is_fully_relative());
+        assert_eq!(packet.apply_to_position(current), (1.5, -2.0, 10.0, 90.0, -10.0));
+
+        packet.flags = PositionAndLookFlags::all();
+        assert!(packet.is_fully_relative());
+        assert!(!packet.is_fully_absolute());
+        assert_eq!(packet.apply_to_position(current), (101.5, 62.0, -40.0, 270.0, 10.0));
+
+        packet.flags = PositionAndLookFlags::none().with_y_rel(true).with_x_rotation_rel(true);
+        assert!(!packet.is_fully_absolute());
+        assert!(!packet.is_fully_relative());
+        assert_eq!(packet.apply_to_position(current), (1.5, 62.0, 10.0, 90.0, 10.0));
+    }
+
+    #[test]
+    fn test_entity_position_deltas() {
+        let packet = PlayEntityPositionSpec {
+            entity_id: VarInt(1),
+            delta: Vec3 { x: 4096, y: -2048, z: 1 },
+            on_ground: true,
+        };
+        assert_eq!(packet.delta_as_f64(), (1.0, -0.5, 1.0 / 4096.0));
+
+        let packet = PlayEntityPositionAndRotationSpec {
+            entity_id: VarInt(1),
+            delta: EntityLocation {
+                position: Vec3 { x: -8192, y: 0, z: 6144 },
+                rotation: EntityRotation { yaw: Angle { value: 0 }, pitch: Angle { value: 0 } },
+            },
+            on_ground: false,
+        };
+        assert_eq!(packet.delta_as_f64(), (-2.0, 0.0, 1.5));
+
+        assert_eq!(from_delta_f64(1.0, -0.5, 1.5), (4096, -2048, 6144));
+        assert_eq!(from_delta_f64(0.25 + 1.0 / 8192.0, -0.00001, 0.0), (1024, 0, 0));
+        assert_eq!(from_delta_f64(100.0, -100.0, f64::NAN), (i16::MAX, i16::MIN, 0));
+    }
+
+    #[test]
+    fn test_look_direction_vec() {
+        fn assert_close(actual: (f64, f64, f64), expected: (f64, f64, f64)) {
+            let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+            assert!(close(actual.0, expected.0) && close(actual.1, expected.1) && close(actual.2, expected.2),
+                    "{:?} != {:?}", actual, expected);
+        }
+
+        assert_close(angles_to_look_vec(0.0, 0.0), (0.0, 0.0, 1.0));
+        assert_close(angles_to_look_vec(90.0, 0.0), (-1.0, 0.0, 0.0));
+        assert_close(angles_to_look_vec(-90.0, 0.0), (1.0, 0.0, 0.0));
+        assert_close(angles_to_look_vec(180.0, 0.0), (0.0, 0.0, -1.0));
+        assert_close(angles_to_look_vec(0.0, 90.0), (0.0, -1.0, 0.0));
+        assert_close(angles_to_look_vec(45.0, -30.0), (-0.5f64.sqrt() * 0.75f64.sqrt(), 0.5, 0.5f64.sqrt() * 0.75f64.sqrt()));
+
+        let rotation = EntityRotation { yaw: 123.0, pitch: 17.5 };
+        let packet = PlayPlayerRotationSpec { rotation, on_ground: true };
+        let (x, y, z) = packet.look_direction_vec();
+        assert!((x * x + y * y + z * z - 1.0).abs() < 1e-9);
+
+        let packet = PlayClientPlayerPositionAndRotationSpec {
+            feet_location: EntityLocation { position: Vec3 { x: 1.0, y: 2.0, z: 3.0 }, rotation },
+            on_ground: true,
+        };
+        assert_eq!(packet.look_direction_vec(), (x, y, z));
+    }
+
+    #[test]
+    fn test_multi_block_change_absolute_positions() {
+        let changes = alloc::vec![
+            (IntPosition { x: -16, y: 0, z: -32 }, VarInt(1)),
+            (IntPosition { x: -1, y: 255, z: -17 }, VarInt(2)),
+            (IntPosition { x: -9, y: 64, z: -25 }, VarInt(3)),
+        ];
+        let spec = PlayMultiBlockChangeSpec::from_absolute(-1, -2, changes.clone()).expect("all inside chunk");
+        assert_eq!(spec.chunk, ChunkPosition { x: -1, z: -2 });
+        assert_eq!(spec.changes[1].horizontal_position, BlockChangeHorizontalPosition { rel_x: 15, rel_z: 15 });
+        assert_eq!(spec.changes[2].horizontal_position, BlockChangeHorizontalPosition { rel_x: 7, rel_z: 7 });
+        assert_eq!(spec.changes_absolute().collect::<Vec<_>>(), changes);
+
+        let positive = PlayMultiBlockChangeSpec::from_absolute(2, 0, alloc::vec![
+            (IntPosition { x: 47, y: 3, z: 0 }, VarInt(4)),
+        ]).expect("inside chunk");
+        assert_eq!(positive.changes_absolute().next(), Some((IntPosition { x: 47, y: 3, z: 0 }, VarInt(4))));
+
+        for outside in &[
+            IntPosition { x: 0, y: 0, z: -32 },
+            IntPosition { x: -17, y: 0, z: -32 },
+            IntPosition { x: -16, y: 0, z: -16 },
+            IntPosition { x: -16, y: -1, z: -32 },
+            IntPosition { x: -16, y: 256, z: -32 },
+        ] {
+            match PlayMultiBlockChangeSpec::from_absolute(-1, -2, alloc::vec![(*outside, VarInt(1))]) {
+                Err(MultiBlockChangeErr::PositionOutsideChunk { position, chunk }) => {
+                    assert_eq!(position, *outside);
+                    assert_eq!(chunk, ChunkPosition { x: -1, z: -2 });
+                }
+                Ok(_) => panic!("{:?} should be outside of the chunk", outside),
+            }
+        }
+    }
+
+    #[test]
+    fn test_lighting_data_levels() {
+        let mut light = LightingData::empty();
+        assert_eq!(light.get(3, 0, 0, 0), None);
+
+        assert!(light.set(3, 0, 0, 0, 7));
+        assert!(light.set(3, 1, 0, 0, 12));
+        assert!(light.set(3, 2, 1, 3, 15));
+        assert_eq!(light.get(3, 0, 0, 0), Some(7));
+        assert_eq!(light.get(3, 1, 0, 0), Some(12));
+        assert_eq!(light.get(3, 2, 1, 3), Some(15));
+        assert_eq!(light.get(3, 3, 0, 0), Some(0));
+
+        let section = light.data[3].as_ref().expect("section created on write");
+        assert_eq!(section[0], 0xC7);
+        assert_eq!(section[(256 + 3 * 16 + 2) / 2], 0x0F);
+        assert!(light.data.iter().enumerate().all(|(i, section)| section.is_some() == (i == 3)));
+
+        assert!(light.set(3, 1, 0, 0, 0));
+        assert_eq!(light.data[3].as_ref().expect("section present")[0], 0x07);
+
+        assert!(!light.set(3, 16, 0, 0, 1));
+        assert!(!light.set(3, 0, 0, 0, 16));
+        assert!(!light.set(LIGHT_DATA_SECTIONS, 0, 0, 0, 1));
+        assert_eq!(light.get(LIGHT_DATA_SECTIONS, 0, 0, 0), None);
+        assert_eq!(light.get(3, 0, 16, 0), None);
+
+        let bright = LightingData::full_bright();
+        for section in 0..LIGHT_DATA_SECTIONS {
+            assert_eq!(bright.get(section, 15, 15, 15), Some(15));
+            assert_eq!(bright.get(section, 0, 8, 0), Some(15));
+        }
+        assert_eq!(bright.update_mask(), VarInt((1 << LIGHT_DATA_SECTIONS) - 1));
+        assert_eq!(LightingData::empty().update_mask(), VarInt(0));
+    }
+
+    #[test]
+    fn test_lighting_update_keeps_reset_masks() {
+        // sky light sent for section 1, only sections 2 (sky) and 0 (block) are reset
+        let mut raw = alloc::vec![0x02, 0x00, 0x04, 0x01];
+        raw.extend_from_slice(&[0x80, 0x10]);
+        raw.extend((0..LIGHT_DATA_LENGTH).map(move |i| i as u8));
+
+        let Deserialized { value: update, data: rest } = LightingUpdateSpec::mc_deserialize(raw.as_slice()).expect("parses");
+        assert!(rest.is_empty());
+        assert_eq!(update.skylight_reset_mask, VarInt(0b100));
+        assert_eq!(update.blocklight_reset_mask, VarInt(0b1));
+
+        let mut out = BytesSerializer::default();
+        update.mc_serialize(&mut out).expect("serializes");
+        assert_eq!(out.into_bytes(), raw);
+
+        let fresh = LightingUpdateSpec::new(update.skylight_data.clone(), update.blocklight_data.clone());
+        assert_eq!(fresh.skylight_reset_mask, VarInt(((1 << LIGHT_DATA_SECTIONS) - 1) ^ 0b10));
+        assert_eq!(fresh.blocklight_reset_mask, VarInt((1 << LIGHT_DATA_SECTIONS) - 1));
+    }
+
+    #[test]
+    fn test_chunk_biome_grid() {
+        let mut chunk = chunk_with_data(0, alloc::vec![]);
+        assert!(chunk.biome_grid().is_none());
+
+        let mut biomes = Box::new([0i32; BIOME_CELLS]);
+        for (i, biome) in biomes.iter_mut().enumerate() {
+            *biome = i as i32;
+        }
+        chunk.biomes = Some(biomes);
+
+        let grid = chunk.biome_grid().expect("full chunk has biomes");
+        assert_eq!(grid.get(0, 0, 0), Some(Biome::Ocean));
+        assert_eq!(grid.get(1, 0, 0), Some(Biome::Plains));
+        assert_eq!(grid.get(0, 0, 1), Some(Biome::Forest));
+        assert_eq!(grid.get(0, 1, 0), Some(Biome::Beach));
+        assert_eq!(grid.get(3, 63, 3), Some(Biome::Other(1023)));
+        assert_eq!(grid.get(4, 0, 0), None);
+        assert_eq!(grid.get(0, 64, 0), None);
+        assert_eq!(grid.get(0, 0, 4), None);
+
+        let mut grid = chunk.biome_grid_mut().expect("full chunk has biomes");
+        assert!(grid.set(2, 5, 1, Biome::BambooJungle));
+        assert!(!grid.set(2, 5, 4, Biome::BambooJungle));
+        assert_eq!(grid.get(2, 5, 1), Some(Biome::BambooJungle));
+        assert_eq!(chunk.biomes.as_ref().map(move |biomes| biomes[(5 * 16) + 4 + 2]), Some(168));
+
+        for id in -1..200 {
+            assert_eq!(Biome::from_id(id).id(), id);
+        }
+    }
+
+    #[test]
+    fn test_chunk_block_states() {
+        let mut raw = chunk_section_fixture(4, Some(&[0x00, 0x01, 0x09]), 0);
+        raw.extend(chunk_section_fixture(14, None, 0));
+        let mut chunk = chunk_with_data(0b101, raw.clone());
+        let original = chunk.clone();
+
+        assert_eq!(chunk.get_block_state(0, 0, 0).expect("parses"), Some(VarInt(0)));
+        assert_eq!(chunk.get_block_state(15, 20, 15).expect("parses"), Some(VarInt(0)));
+        assert_eq!(chunk.get_block_state(16, 0, 0).expect("parses"), None);
+        assert_eq!(chunk.get_block_state(0, 256, 0).expect("parses"), None);
+        assert_eq!(chunk.get_block_state(0, 0, 16).expect("parses"), None);
+        assert!(chunk.set_block_state(0, 256, 0, VarInt(1)).is_err());
+
+        chunk.set_block_state(3, 2, 1, VarInt(9)).expect("set in paletted section");
+        chunk.set_block_state(4, 40, 5, VarInt(1234)).expect("set in direct section");
+        assert_eq!(chunk.get_block_state(3, 2, 1).expect("parses"), Some(VarInt(9)));
+        assert_eq!(chunk.get_block_state(4, 40, 5).expect("parses"), Some(VarInt(1234)));
+        assert_eq!(chunk.primary_bit_mask, VarInt(0b101));
+        // both sections already had room for the new states, so only their bits changed
+        assert_eq!(chunk.data.len(), raw.len());
+        assert_ne!(chunk.data.as_slice(), raw.as_slice());
+        assert_ne!(chunk, original);
+        let sections = chunk.sections().expect("parses");
+        assert_eq!(sections[0].as_ref().and_then(move |section| section.get_block_state(3, 2, 1)), Some(VarInt(9)));
+        assert_eq!(sections[2].as_ref().and_then(move |section| section.get_block_state(4, 8, 5)), Some(VarInt(1234)));
+
+        // air in a missing section doesn't create it
+        chunk.set_block_state(0, 16, 0, VarInt(0)).expect("set air");
+        assert_eq!(chunk.primary_bit_mask, VarInt(0b101));
+        assert!(chunk.set_block_state(0, 16, 0, VarInt(-1)).is_err());
+        assert_eq!(chunk.primary_bit_mask, VarInt(0b101));
+
+        chunk.set_block_state(7, 17, 8, VarInt(42)).expect("set in empty section");
+        assert_eq!(chunk.primary_bit_mask, VarInt(0b111));
+        assert_eq!(chunk.get_block_state(7, 17, 8).expect("parses"), Some(VarInt(42)));
+        assert_eq!(chunk.get_block_state(3, 2, 1).expect("parses"), Some(VarInt(9)));
+        assert_eq!(chunk.get_block_state(4, 40, 5).expect("parses"), Some(VarInt(1234)));
+        let section = chunk.section(1).expect("parses").expect("section 1 present");
+        assert_eq!(section.block_count, 1);
+        assert_eq!(section.bits_per_block, 4);
+
+        let serialized = chunk.mc_serialize_to_vec().expect("serializes");
+        let deserialized = ChunkData::mc_deserialize_complete(serialized.as_slice()).expect("deserializes");
+        assert_eq!(deserialized, chunk);
+        assert_eq!(deserialized.get_block_state(7, 17, 8).expect("parses"), Some(VarInt(42)));
+        assert_eq!(deserialized.get_block_state(4, 40, 5).expect("parses"), Some(VarInt(1234)));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_chunk_serde_after_set_block_state() {
+        let mut chunk = chunk_with_data(0, alloc::vec![]);
+        chunk.set_block_state(1, 2, 3, VarInt(7)).expect("set succeeds");
+        let encoded = serde_json::to_value(&chunk).expect("serializes");
+        let decoded: ChunkData = serde_json::from_value(encoded).expect("deserializes");
+        assert_eq!(decoded.get_block_state(1, 2, 3).expect("parses"), Some(VarInt(7)));
+        assert_eq!(decoded, chunk);
+    }
+
+    #[test]
+    fn test_synthetic_superflat_chunk() {
+        // A chunk packet body for chunk (3, -7) of a default superflat world (bedrock, two dirt,
+        // grass block, plains), written by hand from the documented 1.15.2 layout. It is not a
+        // capture from a vanilla server, so it only checks that the parser and serializer agree
+        // with each other on that layout, not that the layout matches what vanilla sends.
+        let raw: &[u8] = include_bytes!("../fixtures/v1_15_2_synthetic_superflat_chunk.bin");
+        let mut chunk = PlayChunkDataWrapper::mc_deserialize_complete(raw).expect("fixture parses").data;
+        assert_eq!(chunk.position, ChunkPosition { x: 3, z: -7 });
+        assert_eq!(chunk.primary_bit_mask, VarInt(0b1));
+        assert!(chunk.block_entities.is_empty());
+        assert_eq!(chunk.biome_at(9, 2), Some(Biome::Plains.id()));
+
+        let section = chunk.section(0).expect("parses").expect("section 0 present");
+        assert_eq!(section.block_count, 1024);
+        assert_eq!(section.bits_per_block, MIN_BITS_PER_BLOCK);
+        assert_eq!(section.palette.as_ref().map(move |palette| palette.to_vec()), Some(alloc::vec![VarInt(0), VarInt(33), VarInt(10), VarInt(9)]));
+        for (y, state) in [(0, 33), (1, 10), (2, 10), (3, 9), (4, 0), (200, 0)].iter() {
+            assert_eq!(chunk.get_block_state(5, *y, 11).expect("parses"), Some(VarInt(*state)), "at y {}", y);
+        }
+        for kind in [HeightmapKind::MotionBlocking, HeightmapKind::WorldSurface].iter() {
+            assert_eq!(chunk.heightmap(*kind).map(move |heights| heights.to_vec()), Some(alloc::vec![4; HEIGHTMAP_ENTRIES]));
+        }
+        assert_eq!(chunk.heightmap(HeightmapKind::OceanFloor), None);
+
+        let wrapped = PlayChunkDataWrapper { data: chunk.clone() };
+        assert_eq!(wrapped.mc_serialize_to_vec().expect("serializes").as_slice(), raw);
+
+        // a state the section hasn't seen yet grows the palette without touching the other blocks
+        chunk.set_block_state(5, 4, 11, VarInt(1433)).expect("set succeeds");
+        let section = chunk.section(0).expect("parses").expect("section 0 present");
+        assert_eq!(section.block_count, 1025);
+        assert_eq!(section.palette.as_ref().map(move |palette| palette.len()), Some(5));
+        assert_eq!(chunk.get_block_state(5, 4, 11).expect("parses"), Some(VarInt(1433)));
+        assert_eq!(chunk.get_block_state(5, 3, 11).expect("parses"), Some(VarInt(9)));
+        assert_eq!(chunk.get_block_state(6, 4, 11).expect("parses"), Some(VarInt(0)));
+    }
+
+    #[test]
+    fn test_chunk_block_state_errors() {
+        // a palette index past the end of the palette
+        let chunk = chunk_with_data(0b1, chunk_section_fixture(4, Some(&[0x00]), 0x1111_1111_1111_1111));
+        assert!(matches!(chunk.get_block_state(0, 0, 0), Err(DeserializeErr::CannotUnderstandValue(_))));
+        assert!(matches!(chunk.get_block_state(0, 16, 0), Ok(Some(VarInt(AIR_BLOCK_STATE)))));
+
+        let truncated = chunk_with_data(0b1, alloc::vec![0x10, 0x00, 0x04]);
+        assert!(truncated.get_block_state(0, 0, 0).is_err());
+        assert!(truncated.clone().set_block_state(0, 0, 0, VarInt(1)).is_err());
+    }
+
+    #[test]
+    fn test_chunk_section_direct_resize() {
+        // direct sections narrower than DIRECT_BITS_PER_BLOCK can't hold every state id
+        let mut section = ChunkSection {
+            block_count: 0,
+            bits_per_block: 9,
+            palette: None,
+            data: alloc::vec![0; ChunkSection::data_longs(9)].into(),
+        };
+        section.set_block_state(1, 0, 0, VarInt(300)).expect("fits in 9 bits");
+        assert_eq!(section.bits_per_block, 9);
+        section.set_block_state(2, 0, 0, VarInt(9000)).expect("set succeeds");
+        assert_eq!(section.bits_per_block, DIRECT_BITS_PER_BLOCK);
+        assert_eq!(section.data.len(), ChunkSection::data_longs(DIRECT_BITS_PER_BLOCK));
+        assert_eq!(section.get_block_state(1, 0, 0), Some(VarInt(300)));
+        assert_eq!(section.get_block_state(2, 0, 0), Some(VarInt(9000)));
+        assert_eq!(section.get_block_state(3, 0, 0), Some(VarInt(AIR_BLOCK_STATE)));
+    }
+
+    #[test]
+    fn test_chunk_section_palette_growth() {
+        let mut section = ChunkSection::empty();
+        let mut expected = alloc::vec![0i32; SECTION_BLOCKS];
+        for state in 1..300i32 {
+            let index = (state as usize * 13) % SECTION_BLOCKS;
+            let (x, y, z) = (index % 16, index / 256, (index / 16) % 16);
+            section.set_block_state(x, y, z, VarInt(state)).expect("set succeeds");
+            expected[index] = state;
+
+            let palette_len = state as usize + 1;
+            let expected_bits = if palette_len > 256 {
+                DIRECT_BITS_PER_BLOCK
+            } else {
+                core::cmp::max(MIN_BITS_PER_BLOCK, (usize::BITS - (palette_len - 1).leading_zeros()) as u8)
+            };
+            assert_eq!(section.bits_per_block, expected_bits, "after {} states", palette_len);
+        }
+
+        assert!(section.palette.is_none());
+        assert_eq!(section.block_count, 299);
+        assert_eq!(section.data.len(), ChunkSection::data_longs(DIRECT_BITS_PER_BLOCK));
+        for (index, state) in expected.iter().enumerate() {
+            let (x, y, z) = (index % 16, index / 256, (index / 16) % 16);
+            assert_eq!(section.get_block_state(x, y, z), Some(VarInt(*state)));
+        }
+
+        let mut chunk = chunk_with_data(0, alloc::vec![]);
+        chunk.set_sections(&[None, None, Some(section.clone())]).expect("serializes");
+        assert_eq!(chunk.section(2).expect("parses"), Some(section));
+    }
+
+    #[test]
+    fn test_chunk_section_normalizes_direct_bits_per_block() {
+        for bits_per_block in 9..DIRECT_BITS_PER_BLOCK {
+            let raw = chunk_section_fixture(bits_per_block, None, 0x0123_4567_89AB_CDEF);
+            let narrow = ChunkSection {
+                block_count: 4096,
+                bits_per_block,
+                palette: None,
+                data: <CountedArray<i64, VarInt>>::mc_deserialize(&raw[3..]).expect("longs parse").value,
+            };
+            let section = ChunkSection::mc_deserialize_complete(raw.as_slice()).expect("parses");
+            assert_eq!(section.bits_per_block, DIRECT_BITS_PER_BLOCK);
+            assert!(section.palette.is_none());
+            assert_eq!(section.data.len(), ChunkSection::data_longs(DIRECT_BITS_PER_BLOCK));
+            for index in 0..SECTION_BLOCKS {
+                let (x, y, z) = (index % 16, index / 256, (index / 16) % 16);
+                assert_eq!(section.get_block_state(x, y, z), narrow.get_block_state(x, y, z));
+            }
+
+            assert!(!ChunkSection::is_valid_bits_per_block(bits_per_block));
+            assert!(narrow.mc_serialize_to_vec().is_err());
+            let written = section.mc_serialize_to_vec().expect("serializes");
+            assert_eq!(written[2], DIRECT_BITS_PER_BLOCK);
+        }
+    }
+
+    #[test]
+    fn test_chunk_sections_reject_bad_bits_per_block() {
+        for bits_per_block in &[0u8, 3, 15, 64] {
+            let mut raw = Vec::new();
+            raw.extend_from_slice(&4096i16.to_be_bytes());
+            raw.push(*bits_per_block);
+            raw.push(0);
+            raw.push(0);
+            let chunk = chunk_with_data(0b1, raw);
+            assert!(matches!(chunk.sections(), Err(DeserializeErr::CannotUnderstandValue(_))));
+        }
+    }
+
+    #[test]
+    fn test_window_type_identifiers() {
+        for id in 0..WindowType::variant_count() {
+            let kind = WindowType::deserialize_with_id(VarInt(id as i32), &[]).expect("valid id").value;
+            assert_eq!(WindowType::from_identifier(kind.identifier()), Some(kind.clone()));
+            assert!(kind.slot_count().is_some());
+        }
+
+        assert_eq!(WindowType::from_identifier("minecraft:generic_9x1"), Some(WindowType::GenericOneRow));
+        assert_eq!(WindowType::from_identifier(WINDOW_ANVIL), Some(WindowType::Anvil));
+        assert_eq!(WindowType::from_identifier("generic_9x1"), None);
+        assert_eq!(WindowType::GenericOneRow.slot_count(), Some(9));
+        assert_eq!(WindowType::CraftingTable.slot_count(), Some(10));
+        assert_eq!(WindowType::Hopper.slot_count(), Some(5));
+    }
+
+    #[test]
+    fn test_dimension_resource_locations() {
+        for dimension in &[Dimension::Nether, Dimension::Overworld, Dimension::End] {
+            let location = dimension.resource_location();
+            assert_eq!(Dimension::from_resource_location(location).as_ref(), Some(dimension));
+            assert_eq!(Dimension::from_str(location).as_ref(), Some(dimension));
+            assert_eq!(Dimension::from_str(dimension.id().to_string().as_str()).as_ref(), Some(dimension));
+        }
+
+        assert_eq!(Dimension::from_resource_location("nether"), Some(Dimension::Nether));
+        assert_eq!(Dimension::from_resource_location("overworld"), Some(Dimension::Overworld));
+        assert_eq!(Dimension::from_resource_location("end"), Some(Dimension::End));
+        assert_eq!(Dimension::from_resource_location("-1"), None);
+        assert_eq!(Dimension::from_str("2"), None);
+        assert_eq!(Dimension::from_str("minecraft:the_moon"), None);
+    }
+
+    #[test]
+    fn test_command_tree_round_trip() {
+        let mut tree = CommandTree::new();
+        let root = tree.root();
+        let gamemode = tree.literal(root, "gamemode");
+        let creative = tree.literal(gamemode, "creative");
+        tree.executable(creative);
+        let target = tree.argument(creative, "target", CommandParserSpec::Entity(EntityParserFlags(0x03)));
+        tree.suggestions(target, SuggestionsTypeSpec::AskServer);
+        tree.executable(target);
+        let survival = tree.literal(gamemode, "survival");
+        tree.executable(survival);
+        let execute = tree.literal(root, "execute");
+        let run = tree.literal(execute, "run");
+        tree.redirect(run, root);
+        let as_ = tree.literal(execute, "as");
+        let targets = tree.argument(as_, "targets", CommandParserSpec::Entity(EntityParserFlags(0)));
+        tree.redirect(targets, execute);
+        // the same node below two parents is stored once
+        tree.add_child(survival, target);
+
+        let spec = tree.to_spec().expect("flattens");
+        assert_eq!(spec.root_index, VarInt(0));
+        assert_eq!(spec.nodes.len(), 9);
+        assert_eq!(spec.nodes[run].redirect_node, Some(VarInt(0)));
+        assert_eq!(tree.child(gamemode, "creative"), Some(creative));
+        assert_eq!(tree.child(gamemode, "adventure"), None);
+
+        let mut out = BytesSerializer::default();
+        spec.mc_serialize(&mut out).expect("serialize succeeds");
+        let bytes = out.into_bytes();
+        let deserialized = PlayDeclareCommandsSpec::mc_deserialize(bytes.as_slice()).expect("deserialize succeeds");
+        assert!(deserialized.data.is_empty());
+        assert_eq!(deserialized.value, spec);
+
+        let rebuilt = CommandTree::from_spec(&deserialized.value).expect("valid tree");
+        assert_eq!(rebuilt, tree);
+        assert_eq!(rebuilt.to_spec().expect("flattens"), spec);
+    }
+
+    #[test]
+    fn test_command_tree_rejects_bad_specs() {
+        let node = |children: Vec<VarInt>, redirect_node: Option<VarInt>| CommandNodeSpec {
+            children_indices: children.into(),
+            redirect_node,
+            is_executable: false,
+            node: CommandNode::Literal(CommandLiteralNodeSpec { name: "x".to_owned() }),
+        };
+
+        let out_of_range = PlayDeclareCommandsSpec {
+            nodes: alloc::vec![node(alloc::vec![VarInt(3)], None)].into(),
+            root_index: VarInt(0),
+        };
+        assert!(matches!(CommandTree::from_spec(&out_of_range), Err(CommandTreeErr::ChildOutOfRange { node: 0, .. })));
+
+        let bad_redirect = PlayDeclareCommandsSpec {
+            nodes: alloc::vec![node(alloc::vec![], Some(VarInt(-1)))].into(),
+            root_index: VarInt(0),
+        };
+        assert!(matches!(CommandTree::from_spec(&bad_redirect), Err(CommandTreeErr::RedirectOutOfRange { node: 0, .. })));
+
+        let bad_root = PlayDeclareCommandsSpec {
+            nodes: alloc::vec![node(alloc::vec![], None)].into(),
+            root_index: VarInt(1),
+        };
+        assert!(matches!(CommandTree::from_spec(&bad_root), Err(CommandTreeErr::RootOutOfRange(_))));
+
+        let cycle = PlayDeclareCommandsSpec {
+            nodes: alloc::vec![
+                node(alloc::vec![VarInt(1)], None),
+                node(alloc::vec![VarInt(2)], None),
+                node(alloc::vec![VarInt(1)], None),
+            ].into(),
+            root_index: VarInt(0),
+        };
+        assert!(matches!(CommandTree::from_spec(&cycle), Err(CommandTreeErr::Cycle(1))));
+
+        // redirects back up the tree are how vanilla loops commands, they aren't cycles
+        let redirect_up = PlayDeclareCommandsSpec {
+            nodes: alloc::vec![node(alloc::vec![VarInt(1)], None), node(alloc::vec![], Some(VarInt(0)))].into(),
+            root_index: VarInt(0),
+        };
+        assert!(CommandTree::from_spec(&redirect_up).is_ok());
+
+        let mut tree = CommandTree::new();
+        let a = tree.literal(tree.root(), "a");
+        let b = tree.literal(a, "b");
+        tree.add_child(b, a);
+        assert!(matches!(tree.to_spec(), Err(CommandTreeErr::Cycle(_))));
+    }
+
+    #[test]
+    fn test_command_tree_hostile_specs() {
+        let node = |children: Vec<VarInt>| CommandNodeSpec {
+            children_indices: children.into(),
+            redirect_node: None,
+            is_executable: false,
+            node: CommandNode::Literal(CommandLiteralNodeSpec { name: "x".to_owned() }),
+        };
+
+        // each layer has two children which both point at the next layer, expanding this into a
+        // tree would take 2^64 nodes
+        let mut nodes = Vec::new();
+        for layer in 0..64 {
+            let next = VarInt(2 * (layer + 1) - 1);
+            let children = if layer == 63 { alloc::vec![] } else { alloc::vec![next, VarInt(next.0 + 1)] };
+            nodes.push(node(children.clone()));
+            if layer > 0 {
+                nodes.push(node(children));
+            }
+        }
+        let shared = PlayDeclareCommandsSpec { nodes: nodes.into(), root_index: VarInt(0) };
+        let tree = CommandTree::from_spec(&shared).expect("valid tree");
+        assert_eq!(tree.nodes().len(), shared.nodes.len());
+        assert_eq!(tree.to_spec().expect("flattens"), shared);
+
+        let chain: Vec<CommandNodeSpec> = (0..10_000)
+            .map(|idx| node(if idx == 9_999 { alloc::vec![] } else { alloc::vec![VarInt(idx + 1)] }))
+            .collect();
+        let chain = PlayDeclareCommandsSpec { nodes: chain.into(), root_index: VarInt(0) };
+        assert!(matches!(CommandTree::from_spec(&chain), Err(CommandTreeErr::TooDeep { max: MAX_COMMAND_TREE_DEPTH, .. })));
+
+        let shallow: Vec<CommandNodeSpec> = (0..MAX_COMMAND_TREE_DEPTH as i32)
+            .map(|idx| node(if idx + 1 == MAX_COMMAND_TREE_DEPTH as i32 { alloc::vec![] } else { alloc::vec![VarInt(idx + 1)] }))
+            .collect();
+        let shallow = PlayDeclareCommandsSpec { nodes: shallow.into(), root_index: VarInt(0) };
+        assert!(CommandTree::from_spec(&shallow).is_ok());
+    }
+
+    #[test]
+    fn test_byte_flag_builders() {
+        let flags = BossBarFlags::none().with_darken_sky(true).with_create_fog(true);
+        assert!(flags.is_darken_sky());
+        assert!(!flags.is_dragon_bar());
+        assert!(flags.is_create_fog());
+        assert_eq!(flags, BossBarFlags(0x05));
+        assert_eq!(flags.with_create_fog(false), BossBarFlags(0x01));
+
+        assert_eq!(BossBarFlags::none(), BossBarFlags::default());
+        assert_eq!(BossBarFlags::all(), BossBarFlags(0x07));
+        assert_eq!(PlayerAbilityFlags::all(), PlayerAbilityFlags(0x0F));
+        assert_eq!(ScoreHolderFlags::all().with_multiple(false), ScoreHolderFlags::none());
+    }
+
+    #[cfg(feature = "bench")]
+    fn bench_teleport_packet() -> Packet578 {
+        Packet578::PlayEntityTeleport(PlayEntityTeleportSpec {
+            entity_id: VarInt(1234),
+            location: EntityLocation {
+                position: Vec3 { x: 12.5, y: 64.0, z: -301.25 },
+                rotation: EntityRotation { yaw: Angle { value: 12 }, pitch: Angle { value: 200 } },
+            },
+            on_ground: true,
+        })
+    }
+
+    #[cfg(feature = "bench")]
+    #[bench]
+    fn bench_count_play_entity_teleport(b: &mut test::Bencher) {
+        let packet = bench_teleport_packet();
+        b.iter(|| {
+            let mut counter = CountingSerializer::default();
+            crate::protocol::HasPacketBody::mc_serialize_body(&packet, &mut counter).expect("serialize succeeds");
+            counter.count
+        })
+    }
+
+    #[cfg(feature = "bench")]
+    #[bench]
+    fn bench_vec_len_play_entity_teleport(b: &mut test::Bencher) {
+        let packet = bench_teleport_packet();
+        b.iter(|| {
+            let mut out = BytesSerializer::default();
+            crate::protocol::HasPacketBody::mc_serialize_body(&packet, &mut out).expect("serialize succeeds");
+            out.into_bytes().len()
+        })
+    }
+
+    #[test]
+    fn test_game_change_reason_encoding() {
+        use GameChangeReason::*;
+        let cases = alloc::vec![
+            (NoRespawnAvailable, 0x00, 0.0f32),
+            (EndRaining, 0x01, 0.0),
+            (BeginRaining, 0x02, 0.0),
+            (ChangeGameMode(GameMode::Survival), 0x03, 0.0),
+            (ChangeGameMode(GameMode::Creative), 0x03, 1.0),
+            (ChangeGameMode(GameMode::Adventure), 0x03, 2.0),
+            (ChangeGameMode(GameMode::Spectator), 0x03, 3.0),
+            (WinGame(WinGameAction::Respawn), 0x04, 0.0),
+            (WinGame(WinGameAction::RollCreditsAndRespawn), 0x04, 1.0),
+            (Demo(DemoEvent::ShowWelcomeScreen), 0x05, 0.0),
+            (Demo(DemoEvent::TellMovementControls), 0x05, 101.0),
+            (Demo(DemoEvent::TellJumpControl), 0x05, 102.0),
+            (Demo(DemoEvent::TellInventoryControl), 0x05, 103.0),
+            (Demo(DemoEvent::EndDemo), 0x05, 104.0),
+            (ArrowHitPlayer, 0x06, 0.0),
+            (RainLevelChange(0.0), 0x07, 0.0),
+            (RainLevelChange(0.35), 0x07, 0.35),
+            (RainLevelChange(1.0), 0x07, 1.0),
+            (ThunderLevelChange(0.125), 0x08, 0.125),
+            (PufferfishSting, 0x09, 0.0),
+            (ElderGuardianMobAppearance, 0x0A, 0.0),
+            (Respawn(RespawnRequestType::Screen), 0x0B, 0.0),
+            (Respawn(RespawnRequestType::Immediate), 0x0B, 1.0),
+        ];
+
+        for (reason, id, value) in cases {
+            let mut expected = alloc::vec![id];
+            expected.extend_from_slice(&value.to_be_bytes());
+            assert_eq!(reason.mc_serialize_to_vec().expect("serializes"), expected, "{:?}", reason);
+            assert_eq!(GameChangeReason::mc_deserialize_complete(expected.as_slice()).expect("parses"), reason);
+        }
+
+        // vanilla truncates the float when reading ids
+        let mut raw = alloc::vec![0x03];
+        raw.extend_from_slice(&1.75f32.to_be_bytes());
+        assert_eq!(GameChangeReason::mc_deserialize_complete(raw.as_slice()).expect("parses"), ChangeGameMode(GameMode::Creative));
+
+        let mut raw = alloc::vec![0x03];
+        raw.extend_from_slice(&7.0f32.to_be_bytes());
+        assert!(GameChangeReason::mc_deserialize_complete(raw.as_slice()).is_err());
+        assert!(GameChangeReason::mc_deserialize_complete(&[0x0C, 0, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_packet_fingerprints() {
+        let expected = crate::protocol::ProtocolFingerprint::from_fixture(include_str!("../fixtures/v1_15_2.fingerprints"))
+            .expect("fixture parses");
+        let actual = Packet578::fingerprint();
+        let changes = actual.diff(&expected);
+        assert!(
+            changes.is_empty(),
+            "wire format changed:\n{}\n\nif this is intended, replace fixtures/v1_15_2.fingerprints with:\n{}",
+            changes.join("\n"),
+            actual.to_fixture()
+        );
+        assert_eq!(actual, Packet578::fingerprint());
+
+        // the first SplitMix64 output for seed 0, the fixtures are only valid while this holds
+        use rand::RngCore;
+        assert_eq!(crate::protocol::SeededRng::new(0).next_u64(), 0xe220a8397b1dcdaf);
+    }
+
+    #[test]
+    fn test_test_seed_restored_after_panic() {
+        use crate::protocol::{test_random, with_test_seed};
+        use rand::RngCore;
+        let panicked = std::panic::catch_unwind(move || with_test_seed(0, move || {
+            assert_eq!(test_random::<u64>(), 0xe220a8397b1dcdaf);
+            panic!("generating failed");
+        }));
+        assert!(panicked.is_err());
+
+        let mut seeded = crate::protocol::SeededRng::new(0);
+        seeded.next_u64();
+        assert_ne!(test_random::<u64>(), seeded.next_u64());
+    }
+
     // trust me, this is some cutting edge shit
     // I'm definitely not generating code using a unit test
     #[test]
@@ -3,7 +3,7 @@ use alloc::{string::{String, ToString}, vec::Vec, borrow::ToOwned, boxed::Box};
 use alloc::fmt;
 use fmt::Debug;
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 use crate::protocol::TestRandom;
 
 define_protocol!(753, Packet753, RawPacket753, RawPacket753Body, Packet753Kind => {
@@ -38,7 +38,7 @@ define_protocol!(753, Packet753, RawPacket753, RawPacket753Body, Packet753Kind =
     },
     LoginSuccess, 0x02, Login, ClientBound => LoginSuccessSpec {
         uuid: UUID4,
-        username: String
+        username: LimitedString<USERNAME_MAX_LENGTH>
     },
     LoginSetCompression, 0x03, Login, ClientBound => LoginSetCompressionSpec {
         threshold: VarInt
@@ -49,7 +49,7 @@ define_protocol!(753, Packet753, RawPacket753, RawPacket753Body, Packet753Kind =
         data: RemainingBytes
     },
     LoginStart, 0x00, Login, ServerBound => LoginStartSpec {
-        name: String
+        name: LimitedString<USERNAME_MAX_LENGTH>
     },
     LoginEncryptionResponse, 0x01, Login, ServerBound => LoginEncryptionResponseSpec {
         shared_secret: CountedArray<u8, VarInt>,
@@ -525,7 +525,7 @@ define_protocol!(753, Packet753, RawPacket753, RawPacket753Body, Packet753Kind =
         new_difficulty: Difficulty
     },
     PlayClientChatMessage, 0x03, Play, ServerBound => PlayClientChatMessageSpec {
-        message: String
+        message: LimitedString<CHAT_MESSAGE_MAX_LENGTH>
     },
     PlayClientStatus, 0x04, Play, ServerBound => PlayClientStatusSpec {
         action: ClientStatusAction
@@ -852,6 +852,7 @@ proto_byte_enum!(ChatPosition,
 );
 
 #[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BlockChangeHorizontalPosition {
     pub rel_x: u8,
     pub rel_z: u8,
@@ -874,17 +875,18 @@ impl Deserialize for BlockChangeHorizontalPosition {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for BlockChangeHorizontalPosition {
     fn test_gen_random() -> Self {
         BlockChangeHorizontalPosition {
-            rel_x: rand::random::<u8>() % 16,
-            rel_z: rand::random::<u8>() % 16,
+            rel_x: crate::protocol::test_random::<u8>() % 16,
+            rel_z: crate::protocol::test_random::<u8>() % 16,
         }
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ChunkSectionPosition {
     pub x: i32,
     pub y: i32,
@@ -911,7 +913,7 @@ impl Deserialize for ChunkSectionPosition {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for ChunkSectionPosition {
     fn test_gen_random() -> Self {
         Self {
@@ -923,6 +925,7 @@ impl TestRandom for ChunkSectionPosition {
 }
 
 #[derive(Debug, Clone, PartialEq, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MultiBlockChangeRecord {
     pub block_id: u64,
     pub rel_position: Vec3<i8>,
@@ -952,7 +955,7 @@ impl Deserialize for MultiBlockChangeRecord {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for MultiBlockChangeRecord {
     fn test_gen_random() -> Self {
         let mut rel_position = <Vec3<i8>>::test_gen_random();
@@ -996,9 +999,9 @@ proto_varint_enum!(BossBarDivision,
 );
 
 proto_byte_flag!(BossBarFlags,
-    0x01 :: is_darken_sky set_darken_sky,
-    0x02 :: is_dragon_bar set_dragon_bar,
-    0x04 :: is_create_fog set_create_fog
+    0x01 :: is_darken_sky set_darken_sky with_darken_sky,
+    0x02 :: is_dragon_bar set_dragon_bar with_dragon_bar,
+    0x04 :: is_create_fog set_create_fog with_create_fog
 );
 
 proto_struct!(BossBarAddSpec {
@@ -1028,6 +1031,7 @@ proto_struct!(TabCompleteMatch {
 });
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CommandNodeSpec {
     pub children_indices: CountedArray<VarInt, VarInt>,
     pub redirect_node: Option<VarInt>,
@@ -1036,6 +1040,7 @@ pub struct CommandNodeSpec {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CommandNode {
     Root,
     Argument(CommandArgumentNodeSpec),
@@ -1111,13 +1116,13 @@ impl Deserialize for CommandNodeSpec {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for CommandNodeSpec {
     fn test_gen_random() -> Self {
         let children_indices = <CountedArray<VarInt, VarInt>>::test_gen_random();
         let redirect_node = <Option<VarInt>>::test_gen_random();
-        let is_executable = rand::random::<bool>();
-        let idx = rand::random::<usize>() % 3;
+        let is_executable = crate::protocol::test_random::<bool>();
+        let idx = (crate::protocol::test_random::<u32>() % 3) as usize;
         let node = match idx {
             0 => CommandNode::Root,
             1 => CommandNode::Argument(CommandArgumentNodeSpec::test_gen_random()),
@@ -1135,6 +1140,7 @@ impl TestRandom for CommandNodeSpec {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CommandArgumentNodeSpec {
     pub name: String,
     pub parser: CommandParserSpec,
@@ -1170,7 +1176,7 @@ impl CommandArgumentNodeSpec {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for CommandArgumentNodeSpec {
     fn test_gen_random() -> Self {
         let name = String::test_gen_random();
@@ -1245,6 +1251,7 @@ proto_str_enum!(CommandParserSpec,
     "minecraft:time" :: Time
 );
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NumParserProps<T> {
     pub min: Option<T>,
     pub max: Option<T>,
@@ -1328,17 +1335,17 @@ impl<T> Deserialize for NumParserProps<T> where T: Deserialize {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl<T> TestRandom for NumParserProps<T> where
     T: TestRandom + std::cmp::PartialOrd,
     rand::distributions::Standard: rand::distributions::Distribution<T>,
 {
     fn test_gen_random() -> Self {
-        let has_min = rand::random::<bool>();
-        let has_max = rand::random::<bool>();
+        let has_min = crate::protocol::test_random::<bool>();
+        let has_max = crate::protocol::test_random::<bool>();
         let (min, max) = if has_min && has_max {
-            let a = rand::random::<T>();
-            let b = rand::random::<T>();
+            let a = crate::protocol::test_random::<T>();
+            let b = crate::protocol::test_random::<T>();
             if a < b {
                 (Some(a), Some(b))
             } else {
@@ -1347,7 +1354,7 @@ impl<T> TestRandom for NumParserProps<T> where
         } else if !has_min && !has_max {
             (None, None)
         } else {
-            let v = rand::random::<T>();
+            let v = crate::protocol::test_random::<T>();
             if has_min {
                 (Some(v), None)
             } else {
@@ -1369,12 +1376,12 @@ proto_varint_enum!(StringParserMode,
 );
 
 proto_byte_flag!(EntityParserFlags,
-    0x01 :: is_single_target set_single_target,
-    0x02 :: is_players_only set_players_only
+    0x01 :: is_single_target set_single_target with_single_target,
+    0x02 :: is_players_only set_players_only with_players_only
 );
 
 proto_byte_flag!(ScoreHolderFlags,
-    0x01 :: is_multiple set_multiple
+    0x01 :: is_multiple set_multiple with_multiple
 );
 
 proto_struct!(RangeParserProps {
@@ -1390,6 +1397,7 @@ proto_byte_enum!(TeamAction,
 );
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TeamMember {
     Player(String),
     Entity(UUID4),
@@ -1410,7 +1418,8 @@ impl Deserialize for TeamMember {
         use TeamMember::*;
 
         Ok(String::mc_deserialize(data)?.map(move |raw| {
-            if let Some(entity_id) = UUID4::parse(raw.as_str()) {
+            // a player name can be 32 hex characters, entity uuids are always sent with dashes
+            if let Some(entity_id) = UUID4::parse_hyphenated(raw.as_str()) {
                 Entity(entity_id)
             } else {
                 Player(raw)
@@ -1419,16 +1428,16 @@ impl Deserialize for TeamMember {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for TeamMember {
     fn test_gen_random() -> Self {
         use TeamMember::*;
 
-        let rand_bool: bool = rand::random();
+        let rand_bool: bool = crate::protocol::test_random();
         if rand_bool {
             Player(String::test_gen_random())
         } else {
-            Entity(UUID4::random())
+            Entity(UUID4::test_gen_random())
         }
     }
 }
@@ -1473,8 +1482,8 @@ proto_struct!(TeamActionUpdateInfoSpec {
 });
 
 proto_byte_flag!(TeamFriendlyFlags,
-    0x01 :: allow_friendly_fire set_friendly_fire,
-    0x02 :: show_invisible_teammates set_show_invisible_teammates
+    0x01 :: allow_friendly_fire set_friendly_fire with_friendly_fire,
+    0x02 :: show_invisible_teammates set_show_invisible_teammates with_show_invisible_teammates
 );
 
 proto_byte_enum!(UpdateScoreAction,
@@ -1483,6 +1492,7 @@ proto_byte_enum!(UpdateScoreAction,
 );
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UpdateScoreSpec {
     pub objective_name: String,
     pub action: UpdateScoreAction,
@@ -1512,7 +1522,7 @@ impl Deserialize for UpdateScoreSpec {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for UpdateScoreSpec {
     fn test_gen_random() -> Self {
         Self {
@@ -1551,6 +1561,7 @@ proto_varint_enum!(SoundCategory,
 );
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StopSoundSpec {
     pub source: Option<SoundCategory>,
     pub sound: Option<String>,
@@ -1609,16 +1620,16 @@ impl Deserialize for StopSoundSpec {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for StopSoundSpec {
     fn test_gen_random() -> Self {
-        let source = if rand::random::<bool>() {
+        let source = if crate::protocol::test_random::<bool>() {
             Some(SoundCategory::test_gen_random())
         } else {
             None
         };
 
-        let sound = if source.is_none() || rand::random::<bool>() {
+        let sound = if source.is_none() || crate::protocol::test_random::<bool>() {
             Some(String::test_gen_random())
         } else {
             None
@@ -1639,6 +1650,7 @@ proto_byte_enum!(GameMode,
 );
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PreviousGameMode {
     NoPrevious,
     Previous(GameMode)
@@ -1682,7 +1694,7 @@ impl Into<Option<GameMode>> for PreviousGameMode {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for PreviousGameMode {
     fn test_gen_random() -> Self {
         use PreviousGameMode::*;
@@ -1712,6 +1724,7 @@ proto_byte_enum!(RespawnRequestType,
 );
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GameChangeReason {
     NoRespawnAvailable,
     EndRaining,
@@ -1787,7 +1800,7 @@ impl Deserialize for GameChangeReason {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for GameChangeReason {
     fn test_gen_random() -> Self {
         // todo
@@ -1832,6 +1845,7 @@ proto_struct!(MapIconSpec {
 });
 
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MapColumns {
     NoUpdates,
     Updated(MapColumnsSpec),
@@ -1891,7 +1905,7 @@ impl From<Option<MapColumnsSpec>> for MapColumns {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for MapColumns {
     fn test_gen_random() -> Self {
         <Option<MapColumnsSpec>>::test_gen_random().into()
@@ -1943,10 +1957,10 @@ proto_varint_enum!(WindowType,
 );
 
 proto_byte_flag!(PlayerAbilityFlags,
-    0x01 :: is_invulnerable set_invulnerable,
-    0x02 :: is_flying set_flying,
-    0x04 :: is_flight_allowed set_flight_allowed,
-    0x08 :: is_instant_break set_instant_break
+    0x01 :: is_invulnerable set_invulnerable with_invulnerable,
+    0x02 :: is_flying set_flying with_flying,
+    0x04 :: is_flight_allowed set_flight_allowed with_flight_allowed,
+    0x08 :: is_instant_break set_instant_break with_instant_break
 );
 
 proto_varint_enum!(CombatEvent,
@@ -2004,11 +2018,11 @@ proto_struct!(FacePlayerEntityTarget {
 });
 
 proto_byte_flag!(PositionAndLookFlags,
-    0x01 :: is_x_rel set_x_rel,
-    0x02 :: is_y_rel set_y_rel,
-    0x04 :: is_z_rel set_z_rel,
-    0x08 :: is_y_rotation_rel set_y_rotation_rel,
-    0x10 :: is_x_rotation_rel set_x_rotation_rel
+    0x01 :: is_x_rel set_x_rel with_x_rel,
+    0x02 :: is_y_rel set_y_rel with_y_rel,
+    0x04 :: is_z_rel set_z_rel with_z_rel,
+    0x08 :: is_y_rotation_rel set_y_rotation_rel with_y_rotation_rel,
+    0x10 :: is_x_rotation_rel set_x_rotation_rel with_x_rotation_rel
 );
 
 proto_byte_enum!(EntityEffectKind,
@@ -2091,12 +2105,14 @@ proto_byte_enum!(ScoreboardPosition,
 );
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EntityEquipmentEntry {
     pub slot: EquipmentSlot,
     pub item: Slot
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EntityEquipmentArray {
     data: Vec<EntityEquipmentEntry>
 }
@@ -2172,7 +2188,7 @@ impl From<EntityEquipmentArray> for Vec<EntityEquipmentEntry> {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for EntityEquipmentArray {
     fn test_gen_random() -> Self {
         let mut out = Vec::new();
@@ -2242,6 +2258,7 @@ proto_struct!(AdvancementDisplaySpec {
 });
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AdvancementDisplayFlags {
     pub background_texture: Option<String>,
     pub show_toast: bool,
@@ -2291,16 +2308,16 @@ impl Deserialize for AdvancementDisplayFlags {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for AdvancementDisplayFlags {
     fn test_gen_random() -> Self {
-        let background_texture = if rand::random::<bool>() {
+        let background_texture = if crate::protocol::test_random::<bool>() {
             Some(String::test_gen_random())
         } else {
             None
         };
-        let show_toast = rand::random::<bool>();
-        let hidden = rand::random::<bool>();
+        let show_toast = crate::protocol::test_random::<bool>();
+        let hidden = crate::protocol::test_random::<bool>();
 
         Self {
             background_texture,
@@ -2353,9 +2370,9 @@ proto_byte_enum!(EntityPropertyModifierOperation,
 );
 
 proto_byte_flag!(EntityEffectFlags,
-    0x01 :: is_ambient set_ambient,
-    0x02 :: is_show_particles set_show_particles,
-    0x04 :: is_show_icon set_show_icon
+    0x01 :: is_ambient set_ambient with_ambient,
+    0x02 :: is_show_particles set_show_particles with_show_particles,
+    0x04 :: is_show_icon set_show_icon with_show_icon
 );
 
 proto_struct!(TagSpec {
@@ -2380,13 +2397,13 @@ proto_varint_enum!(ClientMainHand,
 );
 
 proto_byte_flag!(ClientDisplayedSkinParts,
-    0x01 :: is_cape_enabled set_cape_enabled,
-    0x02 :: is_jacket_enabled set_jacket_enabled,
-    0x04 :: is_left_sleeve_enabled set_left_sleeve_enabled,
-    0x08 :: is_right_sleeve_enabled set_right_sleeve_enabled,
-    0x10 :: is_left_pants_leg_enabled set_left_pants_leg_enabled,
-    0x20 :: is_right_pant_legs_enabled set_right_pant_legs_enabled,
-    0x40 :: is_hat_enabled set_hat_enabled
+    0x01 :: is_cape_enabled set_cape_enabled with_cape_enabled,
+    0x02 :: is_jacket_enabled set_jacket_enabled with_jacket_enabled,
+    0x04 :: is_left_sleeve_enabled set_left_sleeve_enabled with_left_sleeve_enabled,
+    0x08 :: is_right_sleeve_enabled set_right_sleeve_enabled with_right_sleeve_enabled,
+    0x10 :: is_left_pants_leg_enabled set_left_pants_leg_enabled with_left_pants_leg_enabled,
+    0x20 :: is_right_pant_legs_enabled set_right_pant_legs_enabled with_right_pant_legs_enabled,
+    0x40 :: is_hat_enabled set_hat_enabled with_hat_enabled
 );
 
 proto_varint_enum!(InventoryOperationMode,
@@ -2411,10 +2428,10 @@ proto_varint_enum!(InteractKind,
 );
 
 proto_byte_flag!(ClientPlayerAbilities,
-    0x01 :: is_creative set_creative,
-    0x02 :: is_flying set_flying,
-    0x04 :: is_fly_enabled set_fly_enabled,
-    0x08 :: is_damaged_disabled set_damaged_disabled
+    0x01 :: is_creative set_creative with_creative,
+    0x02 :: is_flying set_flying with_flying,
+    0x04 :: is_fly_enabled set_fly_enabled with_fly_enabled,
+    0x08 :: is_damaged_disabled set_damaged_disabled with_damaged_disabled
 );
 
 proto_varint_enum!(PlayerDiggingStatus,
@@ -2449,8 +2466,8 @@ proto_varint_enum!(EntityActionKind,
 );
 
 proto_byte_flag!(SteerVehicleFlags,
-    0x01 :: is_jump set_jump,
-    0x02 :: is_unmount set_unmount
+    0x01 :: is_jump set_jump with_jump,
+    0x02 :: is_unmount set_unmount with_unmount
 );
 
 proto_varint_enum!(RecipeBookType,
@@ -2479,9 +2496,9 @@ proto_varint_enum!(CommandBlockMode,
 );
 
 proto_byte_flag!(CommandBlockFlags,
-    0x01 :: is_track_output set_track_output,
-    0x02 :: is_conditional set_conditional,
-    0x04 :: is_automatic set_automatic
+    0x01 :: is_track_output set_track_output with_track_output,
+    0x02 :: is_conditional set_conditional with_conditional,
+    0x04 :: is_automatic set_automatic with_automatic
 );
 
 proto_varint_enum!(UpdateStructureBlockAction,
@@ -2512,12 +2529,13 @@ proto_varint_enum!(UpdateStructureBlockRotation,
 );
 
 proto_byte_flag!(UpdateStructureBlockFlags,
-    0x01 :: is_ignore_entities set_ignore_entities,
-    0x02 :: is_show_air set_show_air,
-    0x04 :: is_show_bounding_box set_show_bounding_box
+    0x01 :: is_ignore_entities set_ignore_entities with_ignore_entities,
+    0x02 :: is_show_air set_show_air with_show_air,
+    0x04 :: is_show_bounding_box set_show_bounding_box with_show_bounding_box
 );
 
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RecipeSpec {
     pub recipe: Recipe,
     pub id: String,
@@ -2574,7 +2592,7 @@ impl Deserialize for RecipeSpec {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for RecipeSpec {
     fn test_gen_random() -> Self {
         RecipeSpec {
@@ -2595,6 +2613,7 @@ proto_struct!(RecipeCraftingShapelessSpec {
 });
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RecipeCraftingShapedSpec {
     pub width: VarInt,
     pub height: VarInt,
@@ -2605,6 +2624,13 @@ pub struct RecipeCraftingShapedSpec {
 
 impl Serialize for RecipeCraftingShapedSpec {
     fn mc_serialize<S: Serializer>(&self, to: &mut S) -> SerializeResult {
+        if self.width.0 < 0 || self.height.0 < 0 || self.width.0 as usize * self.height.0 as usize != self.ingredients.len() {
+            return Err(SerializeErr::CannotSerialize(alloc::format!(
+                "shaped recipe is {}x{} but has {} ingredients",
+                self.width, self.height, self.ingredients.len()
+            )));
+        }
+
         to.serialize_other(&self.width)?;
         to.serialize_other(&self.height)?;
         to.serialize_other(&self.group)?;
@@ -2622,7 +2648,17 @@ impl Deserialize for RecipeCraftingShapedSpec {
         let Deserialized { value: height, data } = <VarInt>::mc_deserialize(data)?;
         let Deserialized { value: group, mut data } = <String>::mc_deserialize(data)?;
 
-        let ingredients_count = width.0 as usize * height.0 as usize;
+        if width.0 < 0 || height.0 < 0 {
+            return Err(DeserializeErr::CannotUnderstandValue(alloc::format!(
+                "shaped recipe has invalid dimensions {}x{}", width, height
+            )));
+        }
+        // every ingredient takes at least one byte, so a grid bigger than the rest of the packet is corrupt
+        let ingredients_count = (width.0 as usize).checked_mul(height.0 as usize)
+            .filter(|count| *count <= data.len())
+            .ok_or_else(|| DeserializeErr::CannotUnderstandValue(alloc::format!(
+                "shaped recipe is {}x{} but only {} bytes are left for its ingredients", width, height, data.len()
+            )))?;
         let mut ingredients: Vec<RecipeIngredient> = Vec::with_capacity(ingredients_count);
         for _ in 0..ingredients_count {
             let Deserialized { value: elem, data: rest } = RecipeIngredient::mc_deserialize(data)?;
@@ -2645,12 +2681,12 @@ impl Deserialize for RecipeCraftingShapedSpec {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for RecipeCraftingShapedSpec {
     fn test_gen_random() -> Self {
         use rand::distributions::Distribution;
         let size_distr = rand::distributions::Uniform::new(1, 9);
-        let mut rng = rand::thread_rng();
+        let mut rng = crate::protocol::test_rng();
 
         let width: VarInt = size_distr.sample(&mut rng).into();
         let height: VarInt = size_distr.sample(&mut rng).into();
@@ -2696,13 +2732,17 @@ proto_varint_enum!(RecipeUnlockAction,
     0x02 :: Remove
 );
 
+// the vanilla client refuses chunk packets with more section data than this
+pub const MAX_CHUNK_DATA_LENGTH: usize = 2097152;
+
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ChunkData {
     pub position: ChunkPosition<i32>,
     pub primary_bit_mask: VarInt,
     pub heightmaps: NamedNbtTag,
     pub biomes: Option<CountedArray<VarInt, VarInt>>,
-    pub data: CountedArray<u8, VarInt>,
+    pub data: CountedArray<u8, VarInt, MAX_CHUNK_DATA_LENGTH>,
     pub block_entities: Vec<NamedNbtTag>,
 }
 
@@ -2742,10 +2782,14 @@ impl Deserialize for ChunkData {
         } else {
             None
         };
-        let Deserialized { value: chunk_data, data } = <CountedArray<u8, VarInt>>::mc_deserialize(data)?;
+        let Deserialized { value: chunk_data, data } = <CountedArray<u8, VarInt, MAX_CHUNK_DATA_LENGTH>>::mc_deserialize(data)?;
         let Deserialized { value: n_block_entities_raw, mut data } = VarInt::mc_deserialize(data)?;
+        if n_block_entities_raw.0 < 0 {
+            return Err(DeserializeErr::NegativeLength(n_block_entities_raw));
+        }
         let n_block_entities = n_block_entities_raw.0 as usize;
-        let mut block_entities = Vec::with_capacity(n_block_entities);
+        // every block entity takes at least one byte
+        let mut block_entities = Vec::with_capacity(core::cmp::min(n_block_entities, data.len()));
         for _ in 0..n_block_entities {
             let Deserialized { value: entity, data: rest } = NamedNbtTag::mc_deserialize(data)?;
             data = rest;
@@ -2763,7 +2807,7 @@ impl Deserialize for ChunkData {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for ChunkData {
     fn test_gen_random() -> Self {
         ChunkData {
@@ -2771,7 +2815,7 @@ impl TestRandom for ChunkData {
             primary_bit_mask: VarInt::test_gen_random(),
             heightmaps: NamedNbtTag::test_gen_random(),
             biomes: None,
-            data: <CountedArray<u8, VarInt>>::test_gen_random(),
+            data: <CountedArray<u8, VarInt, MAX_CHUNK_DATA_LENGTH>>::test_gen_random(),
             block_entities: vec![],
         }
     }
@@ -2862,21 +2906,53 @@ impl fmt::Debug for LightingData {
     }
 }
 
+// in serde formats the sections are a list, with null for sections which aren't present
+#[cfg(feature = "serde")]
+impl serde::Serialize for LightingData {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.data.iter().map(move |section| section.as_ref().map(move |arr| &arr[..])))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for LightingData {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use core::convert::TryInto;
+        use serde::de::Error;
+        let sections = Vec::<Option<Vec<u8>>>::deserialize(deserializer)?;
+        if sections.len() != LIGHT_DATA_SECTIONS {
+            return Err(D::Error::invalid_length(sections.len(), &"18 light sections"));
+        }
+
+        let mut data = Box::new([None; LIGHT_DATA_SECTIONS]);
+        for (target, section) in data.iter_mut().zip(sections) {
+            if let Some(bytes) = section {
+                let len = bytes.len();
+                let bytes: [u8; LIGHT_DATA_LENGTH] = bytes.try_into()
+                    .map_err(move |_| D::Error::invalid_length(len, &"2048 bytes of light data"))?;
+                *target = Some(bytes);
+            }
+        }
+
+        Ok(LightingData { data })
+    }
+}
+
 impl fmt::Display for LightingData {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         <dyn fmt::Debug>::fmt(self, f)
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl LightingData {
     fn gen_random_mask() -> i32 {
-        let rand: u32 = rand::random();
+        let rand: u32 = crate::protocol::test_random();
         (rand & ((1 << 19) - 1)) as i32
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for LightingData {
     fn test_gen_random() -> Self {
         let set_mask = Self::gen_random_mask();
@@ -2885,7 +2961,7 @@ impl TestRandom for LightingData {
             if (set_mask & (1 << i)) != 0 {
                 let mut data_arr = [0u8; LIGHT_DATA_LENGTH];
                 for k in 0..LIGHT_DATA_LENGTH {
-                    data_arr[k] = rand::random();
+                    data_arr[k] = crate::protocol::test_random();
                 }
                 data[i] = Some(data_arr);
             }
@@ -2898,17 +2974,32 @@ impl TestRandom for LightingData {
 }
 
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LightingUpdateSpec {
     pub skylight_data: LightingData,
     pub blocklight_data: LightingData,
+    pub skylight_reset_mask: VarInt,
+    pub blocklight_reset_mask: VarInt,
+}
+
+impl LightingUpdateSpec {
+    // resets every section which has no data sent for it
+    pub fn new(skylight_data: LightingData, blocklight_data: LightingData) -> Self {
+        Self {
+            skylight_reset_mask: skylight_data.reset_mask(),
+            blocklight_reset_mask: blocklight_data.reset_mask(),
+            skylight_data,
+            blocklight_data,
+        }
+    }
 }
 
 impl Serialize for LightingUpdateSpec {
     fn mc_serialize<S: Serializer>(&self, to: &mut S) -> SerializeResult {
         self.skylight_data.update_mask().mc_serialize(to)?;
         self.blocklight_data.update_mask().mc_serialize(to)?;
-        self.skylight_data.reset_mask().mc_serialize(to)?;
-        self.blocklight_data.reset_mask().mc_serialize(to)?;
+        self.skylight_reset_mask.mc_serialize(to)?;
+        self.blocklight_reset_mask.mc_serialize(to)?;
         self.skylight_data.serialize_data(to)?;
         self.blocklight_data.serialize_data(to)
     }
@@ -2918,8 +3009,8 @@ impl Deserialize for LightingUpdateSpec {
     fn mc_deserialize(data: &[u8]) -> DeserializeResult<'_, Self> {
         let Deserialized { value: skylight_update_mask, data } = VarInt::mc_deserialize(data)?;
         let Deserialized { value: blocklight_update_mask, data } = VarInt::mc_deserialize(data)?;
-        let Deserialized { value: _, data } = VarInt::mc_deserialize(data)?;
-        let Deserialized { value: _, data } = VarInt::mc_deserialize(data)?;
+        let Deserialized { value: skylight_reset_mask, data } = VarInt::mc_deserialize(data)?;
+        let Deserialized { value: blocklight_reset_mask, data } = VarInt::mc_deserialize(data)?;
 
         let Deserialized { value: skylight_data, data } = LightingData::deserialize(skylight_update_mask, data)?;
         let Deserialized { value: blocklight_data, data } = LightingData::deserialize(blocklight_update_mask, data)?;
@@ -2927,21 +3018,26 @@ impl Deserialize for LightingUpdateSpec {
         Deserialized::ok(Self {
             skylight_data,
             blocklight_data,
+            skylight_reset_mask,
+            blocklight_reset_mask,
         }, data)
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for LightingUpdateSpec {
     fn test_gen_random() -> Self {
         Self {
             skylight_data: LightingData::test_gen_random(),
             blocklight_data: LightingData::test_gen_random(),
+            skylight_reset_mask: VarInt(LightingData::gen_random_mask()),
+            blocklight_reset_mask: VarInt(LightingData::gen_random_mask()),
         }
     }
 }
 
 #[derive(Clone, Debug, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EntityMetadata {
     pub fields: Vec<EntityMetadataField>
 }
@@ -2980,10 +3076,10 @@ impl Deserialize for EntityMetadata {
     }
 }
 
-#[cfg(all(test, feature = "std"))]
+#[cfg(all(feature = "std", any(test, feature = "fingerprint")))]
 impl TestRandom for EntityMetadata {
     fn test_gen_random() -> Self {
-        let n_fields = rand::random::<usize>() % 10;
+        let n_fields = (crate::protocol::test_random::<u32>() % 10) as usize;
         let mut fields = Vec::with_capacity(n_fields);
         for i in 0..n_fields {
             fields.push(EntityMetadataField {
@@ -2998,55 +3094,7 @@ impl TestRandom for EntityMetadata {
     }
 }
 
-impl EntityMetadata {
-    pub fn set(&mut self, index: u8, data: EntityMetadataFieldData) {
-        for field in &mut self.fields {
-            if field.index == index {
-                field.data = data;
-                return;
-            }
-        }
-
-        self.fields.push(EntityMetadataField {
-            index,
-            data,
-        })
-    }
-
-    pub fn remove(&mut self, index: u8) -> bool {
-        for i in 0..self.fields.len() {
-            let field = self.fields
-                .get(i)
-                .expect("iterating through this vec, definitely have this index");
-            if field.index == index {
-                self.fields.remove(i);
-                return true;
-            }
-        }
-
-        false
-    }
-
-    pub fn get(&self, index: u8) -> Option<&EntityMetadataFieldData> {
-        for field in &self.fields {
-            if field.index == index {
-                return Some(&field.data);
-            }
-        }
-
-        None
-    }
-
-    pub fn get_mut(&mut self, index: u8) -> Option<&mut EntityMetadataFieldData> {
-        for field in &mut self.fields {
-            if field.index == index {
-                return Some(&mut field.data);
-            }
-        }
-
-        None
-    }
-}
+entity_metadata_helpers!();
 
 impl<'a> core::iter::IntoIterator for &'a EntityMetadata {
     type Item = (u8, &'a EntityMetadataFieldData);
@@ -3079,6 +3127,7 @@ impl<'a> core::iter::Iterator for FieldIter<'a> {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EntityMetadataField {
     pub index: u8,
     pub data: EntityMetadataFieldData,
@@ -3183,7 +3232,7 @@ proto_varint_enum!(ParticleSpec,
     0x14 :: EntityEffect,
     0x15 :: ExposionEmitter,
     0x16 :: Explosion,
-    0x17 :: FallingDust(DustParticleData),
+    0x17 :: FallingDust(BlockParticleData),
     0x18 :: Firework,
     0x19 :: Fishing,
     0x1A :: Flame,
@@ -3235,10 +3284,57 @@ proto_struct!(DustParticleData {
     scale: f32
 });
 
+// plugin payloads can be large, proxies which only forward them can split the raw body without
+// copying the data
+fn borrow_field<T: Deserialize>(data: &mut &[u8]) -> Result<T, crate::protocol::PacketErr> {
+    let Deserialized { value, data: rest } = T::mc_deserialize(data)
+        .map_err(crate::protocol::PacketErr::DeserializeFailed)?;
+    *data = rest;
+    Ok(value)
+}
+
+fn borrow_plugin_message(mut data: &[u8]) -> Result<(String, CowRemainingBytes<'_>), crate::protocol::PacketErr> {
+    let channel = borrow_field(&mut data)?;
+    Ok((channel, CowRemainingBytes::borrow_from(data).value))
+}
+
+impl<'a> RawPacket753Body<'a, PlayServerPluginMessageSpec> {
+    pub fn borrow_parts(&self) -> Result<(String, CowRemainingBytes<'a>), crate::protocol::PacketErr> {
+        borrow_plugin_message(self.data)
+    }
+}
+
+impl<'a> RawPacket753Body<'a, PlayClientPluginMessageSpec> {
+    pub fn borrow_parts(&self) -> Result<(String, CowRemainingBytes<'a>), crate::protocol::PacketErr> {
+        borrow_plugin_message(self.data)
+    }
+}
+
+impl<'a> RawPacket753Body<'a, LoginPluginRequestSpec> {
+    pub fn borrow_parts(&self) -> Result<(VarInt, String, CowRemainingBytes<'a>), crate::protocol::PacketErr> {
+        let mut data = self.data;
+        let message_id = borrow_field(&mut data)?;
+        let channel = borrow_field(&mut data)?;
+        Ok((message_id, channel, CowRemainingBytes::borrow_from(data).value))
+    }
+}
+
+impl<'a> RawPacket753Body<'a, LoginPluginResponseSpec> {
+    pub fn borrow_parts(&self) -> Result<(VarInt, bool, CowRemainingBytes<'a>), crate::protocol::PacketErr> {
+        let mut data = self.data;
+        let message_id = borrow_field(&mut data)?;
+        let successful = borrow_field(&mut data)?;
+        Ok((message_id, successful, CowRemainingBytes::borrow_from(data).value))
+    }
+}
+
 #[cfg(all(test, feature = "std"))]
 pub mod tests {
     use super::*;
     use crate::packet_test_cases;
+    use crate::entity_metadata_test_cases;
+
+    entity_metadata_test_cases!();
 
     packet_test_cases!(RawPacket753, Packet753, Handshake, HandshakeSpec,
         test_handshake, bench_write_handshake, bench_read_handshake);
@@ -3699,6 +3795,92 @@ pub mod tests {
     packet_test_cases!(RawPacket753, Packet753, PlayUseItem, PlayUseItemSpec,
         test_play_use_item, bench_write_play_use_item, bench_read_play_use_item);
 
+    #[test]
+    fn test_packet_fingerprints() {
+        let expected = crate::protocol::ProtocolFingerprint::from_fixture(include_str!("../fixtures/v1_16_3.fingerprints"))
+            .expect("fixture parses");
+        let actual = Packet753::fingerprint();
+        let changes = actual.diff(&expected);
+        assert!(
+            changes.is_empty(),
+            "wire format changed:\n{}\n\nif this is intended, replace fixtures/v1_16_3.fingerprints with:\n{}",
+            changes.join("\n"),
+            actual.to_fixture()
+        );
+        assert_eq!(actual, Packet753::fingerprint());
+    }
+
+    #[test]
+    fn test_plugin_message_borrow_parts() {
+        use crate::protocol::{RawPacket, HasPacketBody, HasPacketId};
+        let packet = Packet753::PlayServerPluginMessage(PlayServerPluginMessageSpec {
+            channel: "minecraft:brand".to_owned(),
+            data: alloc::vec![7u8; 4096].into(),
+        });
+        let mut out = BytesSerializer::default();
+        packet.mc_serialize_body(&mut out).expect("serialize succeeds");
+        let bytes = out.into_bytes();
+
+        let raw = RawPacket753::create(packet.id(), bytes.as_slice()).expect("valid id");
+        let (channel, data) = match &raw {
+            RawPacket753::PlayServerPluginMessage(body) => body.borrow_parts().expect("borrows"),
+            other => panic!("unexpected packet {:?}", other),
+        };
+        assert_eq!(channel, "minecraft:brand");
+        assert!(data.is_borrowed());
+        assert_eq!(data.as_ptr(), bytes[bytes.len() - 4096..].as_ptr());
+
+        let mut out = BytesSerializer::default();
+        channel.mc_serialize(&mut out).expect("serialize succeeds");
+        data.mc_serialize(&mut out).expect("serialize succeeds");
+        assert_eq!(out.into_bytes(), bytes);
+
+        let owned: CowRemainingBytes<'static> = data.into_owned();
+        assert!(!owned.is_borrowed());
+        assert_eq!(RemainingBytes::from(owned), RemainingBytes::from(alloc::vec![7u8; 4096]));
+    }
+
+    #[test]
+    fn test_login_plugin_borrow_parts() {
+        use crate::protocol::{RawPacket, HasPacketBody, HasPacketId};
+        let request = Packet753::LoginPluginRequest(LoginPluginRequestSpec {
+            message_id: VarInt(3),
+            channel: "velocity:player_info".to_owned(),
+            data: alloc::vec![1u8, 2, 3].into(),
+        });
+        let mut out = BytesSerializer::default();
+        request.mc_serialize_body(&mut out).expect("serialize succeeds");
+        let bytes = out.into_bytes();
+        let (message_id, channel, data) = match RawPacket753::create(request.id(), bytes.as_slice()).expect("valid id") {
+            RawPacket753::LoginPluginRequest(body) => body.borrow_parts().expect("borrows"),
+            other => panic!("unexpected packet {:?}", other),
+        };
+        assert_eq!(message_id, VarInt(3));
+        assert_eq!(channel, "velocity:player_info");
+        assert!(data.is_borrowed());
+        assert_eq!(data.as_ptr(), bytes[bytes.len() - 3..].as_ptr());
+
+        let response = Packet753::LoginPluginResponse(LoginPluginResponseSpec {
+            message_id: VarInt(3),
+            successful: true,
+            data: alloc::vec![4u8, 5].into(),
+        });
+        let mut out = BytesSerializer::default();
+        response.mc_serialize_body(&mut out).expect("serialize succeeds");
+        let bytes = out.into_bytes();
+        let (message_id, successful, data) = match RawPacket753::create(response.id(), bytes.as_slice()).expect("valid id") {
+            RawPacket753::LoginPluginResponse(body) => body.borrow_parts().expect("borrows"),
+            other => panic!("unexpected packet {:?}", other),
+        };
+        assert_eq!(message_id, VarInt(3));
+        assert!(successful);
+        assert!(data.is_borrowed());
+        assert_eq!(&*data, &[4u8, 5][..]);
+
+        let truncated = RawPacket753Body::<LoginPluginResponseSpec> { data: &[], _typ: core::marker::PhantomData };
+        assert!(truncated.borrow_parts().is_err());
+    }
+
     // trust me, this is some cutting edge shit
     // I'm definitely not generating code using a unit test
     #[test]
@@ -965,8 +965,8 @@ impl Deserialize for BlockChangeHorizontalPosition {
 impl TestRandom for BlockChangeHorizontalPosition {
     fn test_gen_random() -> Self {
         BlockChangeHorizontalPosition {
-            rel_x: rand::random::<u8>() % 16,
-            rel_z: rand::random::<u8>() % 16,
+            rel_x: crate::protocol::test_random::<u8>() % 16,
+            rel_z: crate::protocol::test_random::<u8>() % 16,
         }
     }
 }
@@ -1089,9 +1089,9 @@ proto_varint_enum!(BossBarDivision,
 );
 
 proto_byte_flag!(BossBarFlags,
-    0x01 :: is_darken_sky set_darken_sky,
-    0x02 :: is_dragon_bar set_dragon_bar,
-    0x04 :: is_create_fog set_create_fog
+    0x01 :: is_darken_sky set_darken_sky with_darken_sky,
+    0x02 :: is_dragon_bar set_dragon_bar with_dragon_bar,
+    0x04 :: is_create_fog set_create_fog with_create_fog
 );
 
 proto_struct!(BossBarAddSpec {
@@ -1229,8 +1229,8 @@ impl TestRandom for CommandNodeSpec {
     fn test_gen_random() -> Self {
         let children_indices = <CountedArray<VarInt, VarInt>>::test_gen_random();
         let redirect_node = <Option<VarInt>>::test_gen_random();
-        let is_executable = rand::random::<bool>();
-        let idx = rand::random::<usize>() % 3;
+        let is_executable = crate::protocol::test_random::<bool>();
+        let idx = crate::protocol::test_random::<usize>() % 3;
         let node = match idx {
             0 => CommandNode::Root,
             1 => CommandNode::Argument(CommandArgumentNodeSpec::test_gen_random()),
@@ -1467,11 +1467,11 @@ where
     rand::distributions::Standard: rand::distributions::Distribution<T>,
 {
     fn test_gen_random() -> Self {
-        let has_min = rand::random::<bool>();
-        let has_max = rand::random::<bool>();
+        let has_min = crate::protocol::test_random::<bool>();
+        let has_max = crate::protocol::test_random::<bool>();
         let (min, max) = if has_min && has_max {
-            let a = rand::random::<T>();
-            let b = rand::random::<T>();
+            let a = crate::protocol::test_random::<T>();
+            let b = crate::protocol::test_random::<T>();
             if a < b {
                 (Some(a), Some(b))
             } else {
@@ -1480,7 +1480,7 @@ where
         } else if !has_min && !has_max {
             (None, None)
         } else {
-            let v = rand::random::<T>();
+            let v = crate::protocol::test_random::<T>();
             if has_min {
                 (Some(v), None)
             } else {
@@ -1499,12 +1499,12 @@ proto_varint_enum!(StringParserMode,
 );
 
 proto_byte_flag!(EntityParserFlags,
-    0x01 :: is_single_target set_single_target,
-    0x02 :: is_players_only set_players_only
+    0x01 :: is_single_target set_single_target with_single_target,
+    0x02 :: is_players_only set_players_only with_players_only
 );
 
 proto_byte_flag!(ScoreHolderFlags,
-    0x01 :: is_multiple set_multiple
+    0x01 :: is_multiple set_multiple with_multiple
 );
 
 proto_struct!(RangeParserProps { decimal: bool });
@@ -1552,11 +1552,11 @@ impl TestRandom for TeamMember {
     fn test_gen_random() -> Self {
         use TeamMember::*;
 
-        let rand_bool: bool = rand::random();
+        let rand_bool: bool = crate::protocol::test_random();
         if rand_bool {
             Player(String::test_gen_random())
         } else {
-            Entity(UUID4::random())
+            Entity(UUID4::test_gen_random())
         }
     }
 }
@@ -1601,8 +1601,8 @@ proto_struct!(TeamActionUpdateInfoSpec {
 });
 
 proto_byte_flag!(TeamFriendlyFlags,
-    0x01 :: allow_friendly_fire set_friendly_fire,
-    0x02 :: show_invisible_teammates set_show_invisible_teammates
+    0x01 :: allow_friendly_fire set_friendly_fire with_friendly_fire,
+    0x02 :: show_invisible_teammates set_show_invisible_teammates with_show_invisible_teammates
 );
 
 proto_byte_enum!(UpdateScoreAction,
@@ -1746,13 +1746,13 @@ impl Deserialize for StopSoundSpec {
 #[cfg(all(test, feature = "std"))]
 impl TestRandom for StopSoundSpec {
     fn test_gen_random() -> Self {
-        let source = if rand::random::<bool>() {
+        let source = if crate::protocol::test_random::<bool>() {
             Some(SoundCategory::test_gen_random())
         } else {
             None
         };
 
-        let sound = if source.is_none() || rand::random::<bool>() {
+        let sound = if source.is_none() || crate::protocol::test_random::<bool>() {
             Some(String::test_gen_random())
         } else {
             None
@@ -2084,10 +2084,10 @@ proto_varint_enum!(WindowType,
 );
 
 proto_byte_flag!(PlayerAbilityFlags,
-    0x01 :: is_invulnerable set_invulnerable,
-    0x02 :: is_flying set_flying,
-    0x04 :: is_flight_allowed set_flight_allowed,
-    0x08 :: is_instant_break set_instant_break
+    0x01 :: is_invulnerable set_invulnerable with_invulnerable,
+    0x02 :: is_flying set_flying with_flying,
+    0x04 :: is_flight_allowed set_flight_allowed with_flight_allowed,
+    0x08 :: is_instant_break set_instant_break with_instant_break
 );
 
 proto_varint_enum!(CombatEvent,
@@ -2145,11 +2145,11 @@ proto_struct!(FacePlayerEntityTarget {
 });
 
 proto_byte_flag!(PositionAndLookFlags,
-    0x01 :: is_x_rel set_x_rel,
-    0x02 :: is_y_rel set_y_rel,
-    0x04 :: is_z_rel set_z_rel,
-    0x08 :: is_y_rotation_rel set_y_rotation_rel,
-    0x10 :: is_x_rotation_rel set_x_rotation_rel
+    0x01 :: is_x_rel set_x_rel with_x_rel,
+    0x02 :: is_y_rel set_y_rel with_y_rel,
+    0x04 :: is_z_rel set_z_rel with_z_rel,
+    0x08 :: is_y_rotation_rel set_y_rotation_rel with_y_rotation_rel,
+    0x10 :: is_x_rotation_rel set_x_rotation_rel with_x_rotation_rel
 );
 
 proto_byte_enum!(EntityEffectKind,
@@ -2446,13 +2446,13 @@ impl Deserialize for AdvancementDisplayFlags {
 #[cfg(all(test, feature = "std"))]
 impl TestRandom for AdvancementDisplayFlags {
     fn test_gen_random() -> Self {
-        let background_texture = if rand::random::<bool>() {
+        let background_texture = if crate::protocol::test_random::<bool>() {
             Some(String::test_gen_random())
         } else {
             None
         };
-        let show_toast = rand::random::<bool>();
-        let hidden = rand::random::<bool>();
+        let show_toast = crate::protocol::test_random::<bool>();
+        let hidden = crate::protocol::test_random::<bool>();
 
         Self {
             background_texture,
@@ -2505,9 +2505,9 @@ proto_byte_enum!(EntityPropertyModifierOperation,
 );
 
 proto_byte_flag!(EntityEffectFlags,
-    0x01 :: is_ambient set_ambient,
-    0x02 :: is_show_particles set_show_particles,
-    0x04 :: is_show_icon set_show_icon
+    0x01 :: is_ambient set_ambient with_ambient,
+    0x02 :: is_show_particles set_show_particles with_show_particles,
+    0x04 :: is_show_icon set_show_icon with_show_icon
 );
 
 proto_struct!(TagSpec {
@@ -2532,13 +2532,13 @@ proto_varint_enum!(ClientMainHand,
 );
 
 proto_byte_flag!(ClientDisplayedSkinParts,
-    0x01 :: is_cape_enabled set_cape_enabled,
-    0x02 :: is_jacket_enabled set_jacket_enabled,
-    0x04 :: is_left_sleeve_enabled set_left_sleeve_enabled,
-    0x08 :: is_right_sleeve_enabled set_right_sleeve_enabled,
-    0x10 :: is_left_pants_leg_enabled set_left_pants_leg_enabled,
-    0x20 :: is_right_pant_legs_enabled set_right_pant_legs_enabled,
-    0x40 :: is_hat_enabled set_hat_enabled
+    0x01 :: is_cape_enabled set_cape_enabled with_cape_enabled,
+    0x02 :: is_jacket_enabled set_jacket_enabled with_jacket_enabled,
+    0x04 :: is_left_sleeve_enabled set_left_sleeve_enabled with_left_sleeve_enabled,
+    0x08 :: is_right_sleeve_enabled set_right_sleeve_enabled with_right_sleeve_enabled,
+    0x10 :: is_left_pants_leg_enabled set_left_pants_leg_enabled with_left_pants_leg_enabled,
+    0x20 :: is_right_pant_legs_enabled set_right_pant_legs_enabled with_right_pant_legs_enabled,
+    0x40 :: is_hat_enabled set_hat_enabled with_hat_enabled
 );
 
 proto_varint_enum!(InventoryOperationMode,
@@ -2563,10 +2563,10 @@ proto_varint_enum!(InteractKind,
 );
 
 proto_byte_flag!(ClientPlayerAbilities,
-    0x01 :: is_creative set_creative,
-    0x02 :: is_flying set_flying,
-    0x04 :: is_fly_enabled set_fly_enabled,
-    0x08 :: is_damaged_disabled set_damaged_disabled
+    0x01 :: is_creative set_creative with_creative,
+    0x02 :: is_flying set_flying with_flying,
+    0x04 :: is_fly_enabled set_fly_enabled with_fly_enabled,
+    0x08 :: is_damaged_disabled set_damaged_disabled with_damaged_disabled
 );
 
 proto_varint_enum!(PlayerDiggingStatus,
@@ -2601,8 +2601,8 @@ proto_varint_enum!(EntityActionKind,
 );
 
 proto_byte_flag!(SteerVehicleFlags,
-    0x01 :: is_jump set_jump,
-    0x02 :: is_unmount set_unmount
+    0x01 :: is_jump set_jump with_jump,
+    0x02 :: is_unmount set_unmount with_unmount
 );
 
 proto_varint_enum!(RecipeBookType,
@@ -2631,9 +2631,9 @@ proto_varint_enum!(CommandBlockMode,
 );
 
 proto_byte_flag!(CommandBlockFlags,
-    0x01 :: is_track_output set_track_output,
-    0x02 :: is_conditional set_conditional,
-    0x04 :: is_automatic set_automatic
+    0x01 :: is_track_output set_track_output with_track_output,
+    0x02 :: is_conditional set_conditional with_conditional,
+    0x04 :: is_automatic set_automatic with_automatic
 );
 
 proto_varint_enum!(UpdateStructureBlockAction,
@@ -2664,9 +2664,9 @@ proto_varint_enum!(UpdateStructureBlockRotation,
 );
 
 proto_byte_flag!(UpdateStructureBlockFlags,
-    0x01 :: is_ignore_entities set_ignore_entities,
-    0x02 :: is_show_air set_show_air,
-    0x04 :: is_show_bounding_box set_show_bounding_box
+    0x01 :: is_ignore_entities set_ignore_entities with_ignore_entities,
+    0x02 :: is_show_air set_show_air with_show_air,
+    0x04 :: is_show_bounding_box set_show_bounding_box with_show_bounding_box
 );
 
 #[derive(Clone, PartialEq, Debug)]
@@ -2814,7 +2814,7 @@ impl TestRandom for RecipeCraftingShapedSpec {
     fn test_gen_random() -> Self {
         use rand::distributions::Distribution;
         let size_distr = rand::distributions::Uniform::new(1, 9);
-        let mut rng = rand::thread_rng();
+        let mut rng = crate::protocol::test_rng();
 
         let width: VarInt = size_distr.sample(&mut rng).into();
         let height: VarInt = size_distr.sample(&mut rng).into();
@@ -2905,7 +2905,7 @@ impl Deserialize for EntityMetadata {
 #[cfg(all(test, feature = "std"))]
 impl TestRandom for EntityMetadata {
     fn test_gen_random() -> Self {
-        let n_fields = rand::random::<usize>() % 10;
+        let n_fields = crate::protocol::test_random::<usize>() % 10;
         let mut fields = Vec::with_capacity(n_fields);
         for i in 0..n_fields {
             fields.push(EntityMetadataField {